@@ -0,0 +1,30 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let crt_static = std::env::var("CARGO_CFG_TARGET_FEATURE")
+        .is_ok_and(|features| features.split(',').any(|feature| feature == "crt-static"));
+
+    if std::env::var_os("CARGO_FEATURE_REQUIRE_CRT_STATIC").is_some() && !crt_static {
+        panic!(
+            "the `require-crt-static` feature requires the static CRT, \
+            build with `-C target-feature=+crt-static`"
+        );
+    }
+
+    if std::env::var_os("CARGO_FEATURE_REQUIRE_CRT_DYNAMIC").is_some() && crt_static {
+        panic!(
+            "the `require-crt-dynamic` feature requires the dynamic CRT, \
+            build with `-C target-feature=-crt-static`"
+        );
+    }
+
+    #[cfg(feature = "seh")]
+    if std::env::var("CARGO_CFG_TARGET_ENV").is_ok_and(|env| env == "msvc") {
+        println!("cargo:rerun-if-changed=src/ffi/shield.c");
+
+        cc::Build::new()
+            .file("src/ffi/shield.c")
+            .static_crt(crt_static)
+            .compile("cxx_stl_shield");
+    }
+}
@@ -0,0 +1,336 @@
+//! Containers with the layouts of the C++ toolset selected with a feature.
+//!
+//! Visual Studio 2015 through 2022 ship binary compatible STLs, so the `msvc2015`,
+//! `msvc2017`, `msvc2019` and `msvc2022` features all select the layouts of the
+//! containers at the crate root, which store the allocator before the container value.
+//! The `msvc2012` feature selects the layouts of `vec::msvc2012`
+//! and `string::msvc2012`, which store it after the value.
+//! The `msvc2010` feature selects the layouts of `vec::msvc2010` and `string::msvc2010`,
+//! which also store it after the value, as a member taking up a padded byte even
+//! when the allocator is stateless. Only release layouts are implemented for Visual
+//! Studio 2010, and the `_SECURE_SCL` layouts of Visual Studio 2008 aren't implemented.
+//! Without any of them, the 2015-2022 layouts are used.
+//!
+//! The `gnu-abi` feature selects libstdc++ layouts. Its `std::vector` is laid out
+//! like MSVC's, so the vectors at the crate root are used. Its `std::string` points
+//! into its own small string buffer, so it can't be moved like a Rust value,
+//! and CSTL doesn't implement it. The strings of [`string::gnu`] are implemented
+//! in Rust instead, and are only modified through pinned references.
+//!
+//! The `libcxx-abi` feature selects libc++ layouts. Its `std::vector` stores the
+//! allocator after the pointers like MSVC 2012, so the `vec::msvc2012` vectors
+//! are used. Its `std::string` packs a long mode flag into its size and capacity,
+//! which CSTL doesn't implement, so the strings of [`string::libcxx`] are
+//! implemented in Rust instead.
+//!
+//! [`string::gnu`]: crate::string::gnu
+//! [`string::libcxx`]: crate::string::libcxx
+//!
+//! Selecting toolsets with different layouts is a compile-time error.
+//!
+//! The CSTL C code is built against the CRT selected by the `crt-static` target feature,
+//! like the rest of the program. A mod built against another CRT flavor than the
+//! process it's injected into can't free memory allocated by it, which corrupts
+//! both heaps, so the `require-crt-static` and `require-crt-dynamic` features
+//! fail the build if it links the other flavor, see [`CRT_STATIC`].
+//!
+//! Features are unified across all crates in a build, so libraries should name
+//! the toolset they need with an [`Abi`] marker instead, e.g. [`CxxVecFor<T, Msvc2012>`],
+//! which lets containers of different toolsets coexist in one program.
+
+use std::alloc::System as SysAlloc;
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::RustSemantics,
+    string::{
+        narrow::{CxxNarrowStringLayout, RawString},
+        utf16::{CxxUtf16StringLayout, RawUtf16String},
+        utf32::{CxxUtf32StringLayout, RawUtf32String},
+        utf8::{CxxUtf8StringLayout, RawUtf8String},
+        wide::{CxxWideStringLayout, RawWideString},
+    },
+    vec::{CxxVecLayout, RawVec},
+};
+
+#[cfg(any(
+    all(
+        feature = "msvc2010",
+        any(
+            feature = "msvc2012",
+            feature = "msvc2015",
+            feature = "msvc2017",
+            feature = "msvc2019",
+            feature = "msvc2022"
+        )
+    ),
+    all(
+        feature = "msvc2012",
+        any(
+            feature = "msvc2015",
+            feature = "msvc2017",
+            feature = "msvc2019",
+            feature = "msvc2022"
+        )
+    ),
+    all(
+        feature = "gnu-abi",
+        any(
+            feature = "msvc2010",
+            feature = "msvc2012",
+            feature = "msvc2015",
+            feature = "msvc2017",
+            feature = "msvc2019",
+            feature = "msvc2022",
+            feature = "libcxx-abi"
+        )
+    ),
+    all(
+        feature = "libcxx-abi",
+        any(
+            feature = "msvc2010",
+            feature = "msvc2012",
+            feature = "msvc2015",
+            feature = "msvc2017",
+            feature = "msvc2019",
+            feature = "msvc2022"
+        )
+    )
+))]
+compile_error!(
+    "`msvc2010`, `msvc2012`, `msvc2015` to `msvc2022`, `gnu-abi` and `libcxx-abi` select \
+    incompatible layouts, enable only one of these toolsets"
+);
+
+#[cfg(not(any(
+    feature = "msvc2010",
+    feature = "msvc2012",
+    feature = "gnu-abi",
+    feature = "libcxx-abi"
+)))]
+pub use crate::{
+    string::{CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString},
+    vec::{CxxCustomVec, CxxTrivialVec, CxxVec},
+};
+
+#[cfg(all(
+    feature = "msvc2010",
+    not(any(feature = "msvc2012", feature = "gnu-abi", feature = "libcxx-abi"))
+))]
+pub use crate::{
+    string::msvc2010::{
+        CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString,
+    },
+    vec::msvc2010::{CxxCustomVec, CxxTrivialVec, CxxVec},
+};
+
+#[cfg(all(
+    feature = "msvc2012",
+    not(any(feature = "gnu-abi", feature = "libcxx-abi"))
+))]
+pub use crate::{
+    string::msvc2012::{
+        CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString,
+    },
+    vec::msvc2012::{CxxCustomVec, CxxTrivialVec, CxxVec},
+};
+
+#[cfg(feature = "gnu-abi")]
+pub use crate::{
+    string::gnu::{CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString},
+    vec::{CxxCustomVec, CxxTrivialVec, CxxVec},
+};
+
+#[cfg(all(feature = "libcxx-abi", not(feature = "gnu-abi")))]
+pub use crate::{
+    string::libcxx::{
+        CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString,
+    },
+    vec::msvc2012::{CxxCustomVec, CxxTrivialVec, CxxVec},
+};
+
+/// Toolset the containers in this module are laid out for.
+#[cfg(all(
+    feature = "msvc2010",
+    not(any(feature = "msvc2012", feature = "gnu-abi", feature = "libcxx-abi"))
+))]
+pub const TOOLSET: &str = "msvc2010";
+
+/// Toolset the containers in this module are laid out for.
+#[cfg(all(
+    feature = "msvc2012",
+    not(any(feature = "gnu-abi", feature = "libcxx-abi"))
+))]
+pub const TOOLSET: &str = "msvc2012";
+
+/// Toolset the containers in this module are laid out for.
+#[cfg(feature = "gnu-abi")]
+pub const TOOLSET: &str = "libstdc++";
+
+/// Toolset the containers in this module are laid out for.
+#[cfg(all(feature = "libcxx-abi", not(feature = "gnu-abi")))]
+pub const TOOLSET: &str = "libc++";
+
+/// Toolset the containers in this module are laid out for.
+#[cfg(not(any(
+    feature = "msvc2010",
+    feature = "msvc2012",
+    feature = "gnu-abi",
+    feature = "libcxx-abi"
+)))]
+pub const TOOLSET: &str = "msvc2015-2022";
+
+#[cfg(all(feature = "require-crt-static", feature = "require-crt-dynamic"))]
+compile_error!(
+    "`require-crt-static` and `require-crt-dynamic` require different CRTs, enable only one of them"
+);
+
+/// Whether the CSTL C code and the rest of the program link the static CRT.
+///
+/// The flavor is selected with `-C target-feature=+crt-static` or `-crt-static`
+/// in `RUSTFLAGS` or `.cargo/config.toml`, which the `cc` builds of CSTL and of the
+/// `seh` shield follow. The `require-crt-static` and `require-crt-dynamic` features
+/// only check it, as features can't change the flags of other crates. Which MSVC
+/// toolset compiles the C code is selected by the environment, e.g. the
+/// `VCToolsVersion` variable of a Developer Command Prompt.
+pub const CRT_STATIC: bool = cfg!(target_feature = "crt-static");
+
+/// Marker for a C++ toolset, selecting the layouts of its vectors.
+pub trait Abi {
+    /// Layout of `std::vector`.
+    type Vec<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawVec>;
+}
+
+/// Extends [`Abi`] with the layouts of the toolset's strings.
+pub trait StringAbi: Abi {
+    /// Layout of `std::string`.
+    type NarrowString<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawString>;
+    /// Layout of `std::wstring`.
+    type WideString<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawWideString>;
+    /// Layout of `std::u8string`.
+    type Utf8String<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawUtf8String>;
+    /// Layout of `std::u16string`.
+    type Utf16String<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawUtf16String>;
+    /// Layout of `std::u32string`.
+    type Utf32String<A: CxxProxy>: WithCxxProxy<Alloc = A, Value = RawUtf32String>;
+}
+
+/// Visual Studio 2010 without iterator debugging, which stores
+/// the allocator after the container value as a padded member.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Msvc2010;
+
+/// Visual Studio 2012, which stores the allocator after the container value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Msvc2012;
+
+/// Visual Studio 2015 to 2022, the layouts of the containers at the crate root.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Msvc2015;
+
+/// Visual Studio 2017, binary compatible with [`Msvc2015`].
+pub type Msvc2017 = Msvc2015;
+
+/// Visual Studio 2019, binary compatible with [`Msvc2015`].
+pub type Msvc2019 = Msvc2015;
+
+/// Visual Studio 2022, binary compatible with [`Msvc2015`].
+pub type Msvc2022 = Msvc2015;
+
+/// Visual Studio 2015 to 2022 with an `_ITERATOR_DEBUG_LEVEL` of 1 or 2,
+/// see [`iterator_debug`](crate::iterator_debug).
+#[cfg(feature = "iterator-debug")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Msvc2015Debug;
+
+/// libstdc++, whose strings are implemented in Rust rather than by CSTL,
+/// see [`string::gnu`](crate::string::gnu).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Gnu;
+
+/// libc++, whose strings are implemented in Rust rather than by CSTL,
+/// see [`string::libcxx`](crate::string::libcxx).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Libcxx;
+
+impl Abi for Msvc2010 {
+    type Vec<A: CxxProxy> = crate::vec::msvc2010::Layout<A>;
+}
+
+impl StringAbi for Msvc2010 {
+    type NarrowString<A: CxxProxy> = crate::string::narrow::msvc2010::Layout<A>;
+    type WideString<A: CxxProxy> = crate::string::wide::msvc2010::Layout<A>;
+    type Utf8String<A: CxxProxy> = crate::string::utf8::msvc2010::Layout<A>;
+    type Utf16String<A: CxxProxy> = crate::string::utf16::msvc2010::Layout<A>;
+    type Utf32String<A: CxxProxy> = crate::string::utf32::msvc2010::Layout<A>;
+}
+
+impl Abi for Msvc2012 {
+    type Vec<A: CxxProxy> = crate::vec::msvc2012::Layout<A>;
+}
+
+impl StringAbi for Msvc2012 {
+    type NarrowString<A: CxxProxy> = crate::string::narrow::msvc2012::Layout<A>;
+    type WideString<A: CxxProxy> = crate::string::wide::msvc2012::Layout<A>;
+    type Utf8String<A: CxxProxy> = crate::string::utf8::msvc2012::Layout<A>;
+    type Utf16String<A: CxxProxy> = crate::string::utf16::msvc2012::Layout<A>;
+    type Utf32String<A: CxxProxy> = crate::string::utf32::msvc2012::Layout<A>;
+}
+
+impl Abi for Msvc2015 {
+    type Vec<A: CxxProxy> = crate::vec::Layout<A>;
+}
+
+impl StringAbi for Msvc2015 {
+    type NarrowString<A: CxxProxy> = crate::string::narrow::Layout<A>;
+    type WideString<A: CxxProxy> = crate::string::wide::Layout<A>;
+    type Utf8String<A: CxxProxy> = crate::string::utf8::Layout<A>;
+    type Utf16String<A: CxxProxy> = crate::string::utf16::Layout<A>;
+    type Utf32String<A: CxxProxy> = crate::string::utf32::Layout<A>;
+}
+
+#[cfg(feature = "iterator-debug")]
+impl Abi for Msvc2015Debug {
+    type Vec<A: CxxProxy> = crate::vec::iterator_debug::Layout<A>;
+}
+
+#[cfg(feature = "iterator-debug")]
+impl StringAbi for Msvc2015Debug {
+    type NarrowString<A: CxxProxy> = crate::string::narrow::iterator_debug::Layout<A>;
+    type WideString<A: CxxProxy> = crate::string::wide::iterator_debug::Layout<A>;
+    type Utf8String<A: CxxProxy> = crate::string::utf8::iterator_debug::Layout<A>;
+    type Utf16String<A: CxxProxy> = crate::string::utf16::iterator_debug::Layout<A>;
+    type Utf32String<A: CxxProxy> = crate::string::utf32::iterator_debug::Layout<A>;
+}
+
+impl Abi for Gnu {
+    type Vec<A: CxxProxy> = crate::vec::Layout<A>;
+}
+
+impl Abi for Libcxx {
+    type Vec<A: CxxProxy> = crate::vec::msvc2012::Layout<A>;
+}
+
+/// Vector with the layout of toolset `B`.
+pub type CxxVecFor<T, B = Msvc2015, A = SysAlloc, S = RustSemantics> =
+    CxxVecLayout<T, A, <B as Abi>::Vec<A>, S>;
+
+/// `std::string` with the layout of toolset `B`.
+pub type CxxNarrowStringFor<B = Msvc2015, A = SysAlloc> =
+    CxxNarrowStringLayout<A, <B as StringAbi>::NarrowString<A>>;
+
+/// `std::wstring` with the layout of toolset `B`.
+pub type CxxWideStringFor<B = Msvc2015, A = SysAlloc> =
+    CxxWideStringLayout<A, <B as StringAbi>::WideString<A>>;
+
+/// `std::u8string` with the layout of toolset `B`.
+pub type CxxUtf8StringFor<B = Msvc2015, A = SysAlloc> =
+    CxxUtf8StringLayout<A, <B as StringAbi>::Utf8String<A>>;
+
+/// `std::u16string` with the layout of toolset `B`.
+pub type CxxUtf16StringFor<B = Msvc2015, A = SysAlloc> =
+    CxxUtf16StringLayout<A, <B as StringAbi>::Utf16String<A>>;
+
+/// `std::u32string` with the layout of toolset `B`.
+pub type CxxUtf32StringFor<B = Msvc2015, A = SysAlloc> =
+    CxxUtf32StringLayout<A, <B as StringAbi>::Utf32String<A>>;
@@ -0,0 +1,93 @@
+//! Allocation of over-aligned types.
+//!
+//! MSVC allocators (`malloc`, `HeapAlloc`, most game heaps) only guarantee
+//! [`MEMORY_ALLOCATION_ALIGNMENT`], but containers of SIMD types like
+//! `DirectX::XMVECTOR` require more. [`OverAligned`] adapts any such allocator
+//! to satisfy the alignment CSTL requests.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    mem, ptr,
+};
+
+/// Alignment guaranteed by the default Windows heap allocation functions.
+pub const MEMORY_ALLOCATION_ALIGNMENT: usize = 2 * mem::size_of::<usize>();
+
+/// Allocator adapter that manually aligns blocks requiring an alignment over
+/// [`MEMORY_ALLOCATION_ALIGNMENT`], in which case the inner allocator is only
+/// ever asked for [`MEMORY_ALLOCATION_ALIGNMENT`]-aligned memory.
+///
+/// A pointer to the start of the underlying block is stored right before the aligned
+/// pointer, similarly to how MSVC's `std::allocator` handles large allocations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OverAligned<A>(pub A);
+
+impl<A: GlobalAlloc> OverAligned<A> {
+    /// Allocates a block of memory as described by `layout`,
+    /// regardless of what alignment the inner allocator guarantees.
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::alloc`].
+    pub unsafe fn alloc_aligned(&self, layout: Layout) -> *mut u8 {
+        let Some(block) = Self::block_layout(layout) else {
+            return ptr::null_mut();
+        };
+
+        unsafe {
+            let base = self.0.alloc(block);
+
+            if base.is_null() {
+                return base;
+            }
+
+            let unaligned = base.add(mem::size_of::<*mut u8>());
+            let data = unaligned.add(unaligned.align_offset(layout.align()));
+            (data as *mut *mut u8).sub(1).write(base);
+
+            data
+        }
+    }
+
+    /// Deallocates a block of memory returned by [`OverAligned::alloc_aligned`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`OverAligned::alloc_aligned`] with the same `layout`.
+    pub unsafe fn dealloc_aligned(&self, ptr: *mut u8, layout: Layout) {
+        unsafe {
+            let base = (ptr as *mut *mut u8).sub(1).read();
+            let block = Self::block_layout(layout).expect("bad layout passed to `dealloc_aligned`");
+
+            self.0.dealloc(base, block);
+        }
+    }
+
+    /// Layout of the underlying block, with room for the base pointer and alignment padding.
+    fn block_layout(layout: Layout) -> Option<Layout> {
+        let size = layout.size().checked_add(layout.align())?;
+        Layout::from_size_align(size, MEMORY_ALLOCATION_ALIGNMENT).ok()
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for OverAligned<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe {
+            if layout.align() > MEMORY_ALLOCATION_ALIGNMENT {
+                self.alloc_aligned(layout)
+            } else {
+                self.0.alloc(layout)
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe {
+            if layout.align() > MEMORY_ALLOCATION_ALIGNMENT {
+                self.dealloc_aligned(ptr, layout)
+            } else {
+                self.0.dealloc(ptr, layout)
+            }
+        }
+    }
+}
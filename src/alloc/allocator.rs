@@ -0,0 +1,28 @@
+//! Adapter for implementors of the unstable [`Allocator`] trait.
+
+use std::{
+    alloc::{Allocator, GlobalAlloc, Layout},
+    ptr::{self, NonNull},
+};
+
+/// Adapts an [`Allocator`] to be used as a C++ compatible allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AllocatorProxy<A>(pub A);
+
+unsafe impl<A: Allocator> GlobalAlloc for AllocatorProxy<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0
+            .allocate(layout)
+            .map_or(ptr::null_mut(), |block| block.as_ptr() as *mut u8)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0
+            .allocate_zeroed(layout)
+            .map_or(ptr::null_mut(), |block| block.as_ptr() as *mut u8)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { self.0.deallocate(NonNull::new_unchecked(ptr), layout) }
+    }
+}
@@ -0,0 +1,126 @@
+//! Bump allocation over a caller-supplied memory region.
+//!
+//! An [`Arena`] hands out memory from a fixed region and never frees
+//! individual blocks, instead all of them are released at once by [`Arena::reset`].
+//! Useful for batches of short-lived containers, e.g. ones created every frame.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+};
+
+/// Bump allocator over a borrowed memory region.
+///
+/// Containers allocate from it through an [`ArenaAlloc`] handle obtained with [`Arena::proxy`].
+pub struct Arena<'a> {
+    start: NonNull<u8>,
+    capacity: usize,
+    offset: Cell<usize>,
+    _marker: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+/// [`CxxProxy`](super::CxxProxy) handle to an [`Arena`].
+///
+/// Deallocation is a no-op, except for the most recent allocation,
+/// which is rolled back so that a growing container can reuse its space.
+#[derive(Clone, Copy)]
+pub struct ArenaAlloc<'a> {
+    arena: &'a Arena<'a>,
+}
+
+impl<'a> Arena<'a> {
+    /// Creates an empty arena that allocates from `region`.
+    pub fn new(region: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            start: NonNull::new(region.as_mut_ptr() as *mut u8).unwrap_or(NonNull::dangling()),
+            capacity: region.len(),
+            offset: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns an allocator handle that can be passed to containers.
+    pub fn proxy(&self) -> ArenaAlloc<'_> {
+        ArenaAlloc { arena: self }
+    }
+
+    /// Size of the memory region in bytes.
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of bytes allocated since creation or the last [`Arena::reset`].
+    pub fn used(&self) -> usize {
+        self.offset.get()
+    }
+
+    /// Number of bytes left in the memory region, not accounting for alignment.
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.offset.get()
+    }
+
+    /// Releases all allocations in O(1).
+    ///
+    /// Taking `&mut self` guarantees that no [`ArenaAlloc`] handles,
+    /// and thus no containers allocated from the arena, are still alive.
+    pub fn reset(&mut self) {
+        self.offset.set(0);
+    }
+}
+
+impl fmt::Debug for Arena<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("start", &self.start)
+            .field("capacity", &self.capacity)
+            .field("used", &self.offset.get())
+            .finish()
+    }
+}
+
+impl fmt::Debug for ArenaAlloc<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ArenaAlloc").field(self.arena).finish()
+    }
+}
+
+unsafe impl GlobalAlloc for ArenaAlloc<'_> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let arena = self.arena;
+        let offset = arena.offset.get();
+
+        unsafe {
+            let unaligned = arena.start.as_ptr().add(offset);
+            let padding = unaligned.align_offset(layout.align());
+
+            match offset
+                .checked_add(padding)
+                .and_then(|o| o.checked_add(layout.size()))
+            {
+                Some(end) if end <= arena.capacity => {
+                    arena.offset.set(end);
+                    unaligned.add(padding)
+                }
+                _ => ptr::null_mut(),
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let arena = self.arena;
+
+        unsafe {
+            let top = arena.start.as_ptr().add(arena.offset.get());
+
+            if ptr.add(layout.size()) == top {
+                arena
+                    .offset
+                    .set(ptr.offset_from(arena.start.as_ptr()) as usize);
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Proxy to the Rust global allocator.
+
+use std::{
+    alloc::{self, GlobalAlloc, Layout},
+    ptr,
+};
+
+/// Allocator that routes all allocations through the `#[global_allocator]`.
+///
+/// Every block is prefixed with a header holding its requested size,
+/// so it is released with the exact layout it was allocated with,
+/// regardless of the size the caller reports when freeing it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RustGlobalAlloc;
+
+impl RustGlobalAlloc {
+    /// Layout of a block including its header, and the offset of the user data in it.
+    fn block_layout(layout: Layout) -> Option<(Layout, usize)> {
+        Layout::new::<usize>().extend(layout).ok()
+    }
+}
+
+unsafe impl GlobalAlloc for RustGlobalAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((block, offset)) = Self::block_layout(layout) else {
+            return ptr::null_mut();
+        };
+
+        unsafe {
+            let base = alloc::alloc(block);
+
+            if base.is_null() {
+                return base;
+            }
+
+            let data = base.add(offset);
+            (data as *mut usize).sub(1).write(layout.size());
+
+            data
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe {
+            let size = (ptr as *mut usize).sub(1).read();
+            let layout = Layout::from_size_align_unchecked(size, layout.align());
+            let (block, offset) = Self::block_layout(layout).expect("corrupted block header");
+
+            alloc::dealloc(ptr.sub(offset), block);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Proxy to [jemalloc](https://github.com/jemalloc/jemalloc).
+//!
+//! The jemalloc symbols are not linked by this crate, they must be provided
+//! by the final binary, e.g. through the `tikv-jemallocator` or `tikv-jemalloc-sys` crates.
+//! The `_rjem_` symbol prefix those crates use by default is expected.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::{c_int, c_void},
+};
+
+extern "C" {
+    #[link_name = "_rjem_mallocx"]
+    fn mallocx(size: usize, flags: c_int) -> *mut c_void;
+    #[link_name = "_rjem_sdallocx"]
+    fn sdallocx(ptr: *mut c_void, size: usize, flags: c_int);
+}
+
+/// Allocator that uses the jemalloc heap shared with the rest of the binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Jemalloc;
+
+impl Jemalloc {
+    /// Equivalent of the `MALLOCX_ALIGN` macro.
+    fn flags(layout: Layout) -> c_int {
+        layout.align().trailing_zeros() as c_int
+    }
+}
+
+unsafe impl GlobalAlloc for Jemalloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { mallocx(layout.size(), Self::flags(layout)) as _ }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { sdallocx(ptr as _, layout.size(), Self::flags(layout)) }
+    }
+}
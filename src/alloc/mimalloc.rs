@@ -0,0 +1,28 @@
+//! Proxy to [mimalloc](https://github.com/microsoft/mimalloc).
+//!
+//! The mimalloc symbols are not linked by this crate, they must be provided
+//! by the final binary, e.g. through the `mimalloc` or `libmimalloc-sys` crates.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::c_void,
+};
+
+extern "C" {
+    fn mi_malloc_aligned(size: usize, alignment: usize) -> *mut c_void;
+    fn mi_free_size_aligned(p: *mut c_void, size: usize, alignment: usize);
+}
+
+/// Allocator that uses the mimalloc heap shared with the rest of the binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MiMalloc;
+
+unsafe impl GlobalAlloc for MiMalloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { mi_malloc_aligned(layout.size(), layout.align()) as _ }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { mi_free_size_aligned(ptr as _, layout.size(), layout.align()) }
+    }
+}
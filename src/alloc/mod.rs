@@ -0,0 +1,378 @@
+//! C++ allocation interface.
+//!
+//! Types that implement either [`CxxProxy`] or [`GlobalAlloc`] + [`Clone`]
+//! can be used as C++ compatible allocators. With the `nightly` feature,
+//! implementors of [`std::alloc::Allocator`] can be used through `AllocatorProxy`.
+
+use std::{
+    alloc::{self, GlobalAlloc, Layout},
+    cell::Cell,
+    ffi::c_void,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+};
+
+use cstl_sys::CSTL_Alloc;
+
+use crate::semantics;
+
+pub mod aligned;
+pub use aligned::{OverAligned, MEMORY_ALLOCATION_ALIGNMENT};
+
+#[cfg(feature = "nightly")]
+pub mod allocator;
+#[cfg(feature = "nightly")]
+pub use allocator::AllocatorProxy;
+
+pub mod arena;
+pub use arena::{Arena, ArenaAlloc};
+
+pub mod global;
+pub use global::RustGlobalAlloc;
+
+#[cfg(feature = "jemalloc")]
+pub mod jemalloc;
+#[cfg(feature = "jemalloc")]
+pub use jemalloc::Jemalloc;
+
+#[cfg(feature = "mimalloc")]
+pub mod mimalloc;
+#[cfg(feature = "mimalloc")]
+pub use mimalloc::MiMalloc;
+
+#[cfg(all(windows, any(target_arch = "x86_64", target_arch = "x86")))]
+pub mod module;
+#[cfg(all(windows, any(target_arch = "x86_64", target_arch = "x86")))]
+pub use module::ModuleNewDelete;
+
+pub mod pmr;
+pub use pmr::{CxxMonotonicBufferResource, CxxUnsynchronizedPoolResource, PolymorphicAllocator};
+
+pub mod policy;
+pub use policy::{
+    alloc_error_policy, growth_policy, set_alloc_error_policy, set_growth_policy, AllocErrorPolicy,
+    GrowthPolicy, TryReserveError,
+};
+
+pub mod presets;
+
+/// Trait for types that can spawn an opaque allocator instance from itself
+/// via [`CxxProxy::proxy`].
+///
+/// Types that implement [`CxxProxy`] can be used as C++ compatible allocators.
+///
+/// The proxy must honor the alignment of every [`Layout`] it is asked for.
+/// Containers allocate storage for over-aligned element types, with alignments over
+/// [`MEMORY_ALLOCATION_ALIGNMENT`], through [`CxxProxy::alloc_aligned`] instead,
+/// which uses the proxy unless overridden. Allocators that don't honor such
+/// alignments can be wrapped in [`OverAligned`].
+pub trait CxxProxy {
+    fn proxy<'a>(&self) -> impl GlobalAlloc + 'a
+    where
+        Self: 'a;
+
+    /// Allocates memory as described by `layout`, whose alignment is over
+    /// [`MEMORY_ALLOCATION_ALIGNMENT`], for the storage of over-aligned elements.
+    ///
+    /// Receives the proxy spawned for the container operation, as the allocator
+    /// itself may be borrowed along with the container. Allocates from `proxy`
+    /// by default. Allocators whose proxies only honor the default alignment
+    /// can align the memory themselves here, e.g. like [`OverAligned`].
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::alloc`].
+    unsafe fn alloc_aligned(proxy: &impl GlobalAlloc, layout: Layout) -> *mut u8 {
+        unsafe { proxy.alloc(layout) }
+    }
+
+    /// Deallocates memory returned by [`CxxProxy::alloc_aligned`] with a proxy
+    /// compatible with `proxy`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`CxxProxy::alloc_aligned`] with the same `layout`.
+    unsafe fn dealloc_aligned(proxy: &impl GlobalAlloc, ptr: *mut u8, layout: Layout) {
+        unsafe { proxy.dealloc(ptr, layout) }
+    }
+
+    /// Borrows this allocator as a proxy that can be shared between containers.
+    ///
+    /// Allocators carry no element type, so unlike `std::allocator_traits::rebind_alloc`
+    /// rebinding doesn't create a new allocator, and all containers created
+    /// with the returned [`ProxyRef`] use this very instance.
+    fn rebind(&self) -> ProxyRef<'_, Self>
+    where
+        Self: Sized,
+    {
+        ProxyRef(self)
+    }
+}
+
+impl<A: GlobalAlloc + Clone> CxxProxy for A {
+    fn proxy<'a>(&self) -> impl GlobalAlloc + 'a
+    where
+        Self: 'a,
+    {
+        self.clone()
+    }
+}
+
+/// Proxy to a borrowed allocator instance, see [`CxxProxy::rebind`].
+#[derive(Debug)]
+pub struct ProxyRef<'a, A: CxxProxy>(pub &'a A);
+
+impl<A: CxxProxy> Clone for ProxyRef<'_, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A: CxxProxy> Copy for ProxyRef<'_, A> {}
+
+impl<A: CxxProxy> CxxProxy for ProxyRef<'_, A> {
+    fn proxy<'a>(&self) -> impl GlobalAlloc + 'a
+    where
+        Self: 'a,
+    {
+        self.0.proxy()
+    }
+
+    unsafe fn alloc_aligned(proxy: &impl GlobalAlloc, layout: Layout) -> *mut u8 {
+        unsafe { A::alloc_aligned(proxy, layout) }
+    }
+
+    unsafe fn dealloc_aligned(proxy: &impl GlobalAlloc, ptr: *mut u8, layout: Layout) {
+        unsafe { A::dealloc_aligned(proxy, ptr, layout) }
+    }
+}
+
+pub trait WithCxxProxy: Sized {
+    type Value;
+    type Alloc: CxxProxy;
+
+    /// Offset in bytes of the value in the layout.
+    const VALUE_OFFSET: usize;
+
+    /// Offset in bytes of the pointer to the `std::_Container_proxy` in the layout,
+    /// if the layout has one, see `iterator_debug`.
+    const PROXY_OFFSET: Option<usize> = None;
+
+    fn value_as_ref(&self) -> &Self::Value;
+
+    fn value_as_mut(&mut self) -> &mut Self::Value;
+
+    fn alloc_as_ref(&self) -> &Self::Alloc;
+
+    fn new_in(alloc: Self::Alloc) -> Self;
+
+    /// Splits the layout into its allocator and value without dropping them.
+    ///
+    /// Layouts with other members release them here.
+    fn into_parts(self) -> (Self::Alloc, Self::Value) {
+        let this = mem::ManuallyDrop::new(self);
+        unsafe {
+            (
+                ptr::read(this.alloc_as_ref()),
+                ptr::read(this.value_as_ref()),
+            )
+        }
+    }
+
+    /// Whether CSTL leaves the value intact when an allocation fails.
+    ///
+    /// If it doesn't, a failed allocation always aborts.
+    const RECOVERS_FROM_ALLOC_ERROR: bool = true;
+
+    /// Size in bytes of the units in which CSTL reports the size of freed memory.
+    ///
+    /// CSTL strings report it in characters rather than bytes.
+    const FREE_SIZE_UNIT: usize = 1;
+
+    #[inline]
+    fn with_proxy<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&Self::Value, &mut CSTL_Alloc) -> R,
+    {
+        let raw_alloc = RawAlloc::new::<Self>(self.alloc_as_ref().proxy(), policy_for::<Self>());
+        let result = f(self.value_as_ref(), &mut raw_alloc.as_cstl());
+        // Before `check` can panic, so the caught panic isn't left pending:
+        semantics::resume_pending_panic();
+        raw_alloc.check();
+        result
+    }
+
+    #[inline]
+    fn with_proxy_mut<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Value, &mut CSTL_Alloc) -> R,
+    {
+        let raw_alloc = RawAlloc::new::<Self>(self.alloc_as_ref().proxy(), policy_for::<Self>());
+        let result = f(self.value_as_mut(), &mut raw_alloc.as_cstl());
+        // Before `check` can panic, so the caught panic isn't left pending:
+        semantics::resume_pending_panic();
+        raw_alloc.check();
+        result
+    }
+
+    /// Like [`WithCxxProxy::with_proxy_mut`], but reports allocation failures
+    /// regardless of the [`AllocErrorPolicy`].
+    ///
+    /// If [`WithCxxProxy::RECOVERS_FROM_ALLOC_ERROR`] is `false`,
+    /// `f` must check for failed allocations itself.
+    #[inline]
+    fn try_with_proxy_mut<R, F>(&mut self, f: F) -> Result<R, TryReserveError>
+    where
+        F: FnOnce(&mut Self::Value, &mut CSTL_Alloc) -> R,
+    {
+        let raw_alloc =
+            RawAlloc::new::<Self>(self.alloc_as_ref().proxy(), AllocErrorPolicy::ReturnError);
+        let result = f(self.value_as_mut(), &mut raw_alloc.as_cstl());
+        semantics::resume_pending_panic();
+
+        match raw_alloc.failed.get() {
+            Some(layout) => Err(TryReserveError::AllocError { layout }),
+            None => Ok(result),
+        }
+    }
+}
+
+fn policy_for<P: WithCxxProxy>() -> AllocErrorPolicy {
+    if P::RECOVERS_FROM_ALLOC_ERROR {
+        alloc_error_policy()
+    } else {
+        AllocErrorPolicy::Abort
+    }
+}
+
+/// Allocator stored as a data member, as by Visual Studio 2010 containers.
+///
+/// Like a C++ member of an empty class, it takes at least one byte,
+/// even when the allocator is a zero-sized type.
+#[repr(C)]
+pub(crate) union AllocMember<A> {
+    alloc: mem::ManuallyDrop<A>,
+    _empty: u8,
+}
+
+impl<A> AllocMember<A> {
+    pub const fn new(alloc: A) -> Self {
+        Self {
+            alloc: mem::ManuallyDrop::new(alloc),
+        }
+    }
+
+    pub const fn get(&self) -> &A {
+        // `ManuallyDrop` is transparent, and the allocator is always initialized:
+        unsafe { &*ptr::addr_of!(self.alloc).cast::<A>() }
+    }
+}
+
+impl<A> Drop for AllocMember<A> {
+    fn drop(&mut self) {
+        unsafe { mem::ManuallyDrop::drop(&mut self.alloc) }
+    }
+}
+
+/// CSTL allocator interface to a proxy of the allocator `P`.
+struct RawAlloc<P: CxxProxy, A: GlobalAlloc> {
+    alloc: A,
+    policy: AllocErrorPolicy,
+    failed: Cell<Option<Layout>>,
+    free_size_unit: usize,
+    _marker: PhantomData<fn(&P)>,
+}
+
+impl<P: CxxProxy, A: GlobalAlloc> RawAlloc<P, A> {
+    #[inline]
+    fn new<L: WithCxxProxy<Alloc = P>>(alloc: A, policy: AllocErrorPolicy) -> Self {
+        Self {
+            alloc,
+            policy,
+            failed: Cell::new(None),
+            free_size_unit: L::FREE_SIZE_UNIT,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The returned interface must not outlive `self`.
+    #[inline]
+    fn as_cstl(&self) -> CSTL_Alloc {
+        CSTL_Alloc {
+            opaque: self as *const Self as _,
+            aligned_alloc: Some(Self::RAW_ALLOC_PTR),
+            aligned_free: Some(Self::RAW_FREE_PTR),
+        }
+    }
+
+    #[inline]
+    /// Panics if an allocation failed under [`AllocErrorPolicy::Panic`].
+    ///
+    /// Must run after resuming the panics caught by the table functions,
+    /// which would otherwise stay pending for an unrelated later call.
+    fn check(&self) {
+        if let (Some(layout), AllocErrorPolicy::Panic) = (self.failed.get(), self.policy) {
+            panic!("memory allocation of {} bytes failed", layout.size());
+        }
+    }
+
+    const RAW_ALLOC_PTR: unsafe extern "C" fn(*mut c_void, usize, usize) -> *mut c_void =
+        unsafe { mem::transmute(Self::raw_alloc as *const ()) };
+
+    const RAW_FREE_PTR: unsafe extern "C" fn(*mut c_void, *mut c_void, usize, usize) =
+        unsafe { mem::transmute(Self::raw_free as *const ()) };
+
+    unsafe extern "C" fn raw_alloc(
+        opaque: NonNull<Self>,
+        size: usize,
+        alignment: usize,
+    ) -> *mut c_void {
+        unsafe {
+            let raw_alloc = opaque.as_ref();
+            let layout =
+                Layout::from_size_align(size, alignment).expect("bad layout passed from CSTL");
+            let ptr = if alignment > MEMORY_ALLOCATION_ALIGNMENT {
+                P::alloc_aligned(&raw_alloc.alloc, layout)
+            } else {
+                raw_alloc.alloc.alloc(layout)
+            };
+
+            debug_assert!(
+                ptr.is_null() || ptr.align_offset(alignment) == 0,
+                "allocator returned a pointer that isn't aligned to {alignment}"
+            );
+
+            if ptr.is_null() {
+                raw_alloc.failed.set(Some(layout));
+
+                if raw_alloc.policy == AllocErrorPolicy::Abort {
+                    alloc::handle_alloc_error(layout);
+                }
+            }
+
+            ptr as _
+        }
+    }
+
+    unsafe extern "C" fn raw_free(
+        opaque: NonNull<Self>,
+        ptr: *mut u8,
+        size: usize,
+        alignment: usize,
+    ) {
+        unsafe {
+            if !ptr.is_null() {
+                let raw_alloc = opaque.as_ref();
+                let layout = Layout::from_size_align(size * raw_alloc.free_size_unit, alignment)
+                    .expect("bad layout passed from CSTL");
+
+                if alignment > MEMORY_ALLOCATION_ALIGNMENT {
+                    P::dealloc_aligned(&raw_alloc.alloc, ptr, layout);
+                } else {
+                    raw_alloc.alloc.dealloc(ptr, layout);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Proxy to the `operator new` and `operator delete` of a loaded module.
+//!
+//! Containers handed to a module that frees them with its own `std::allocator`
+//! must be allocated from the same heap and in the same way.
+//! [`ModuleNewDelete`] replicates what MSVC's `std::allocator` does on top of
+//! the operators exported by (or located at known offsets in) the module.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::{c_char, c_void, CStr},
+    mem, ptr,
+};
+
+use super::MEMORY_ALLOCATION_ALIGNMENT;
+
+type NewFn = unsafe extern "C" fn(usize) -> *mut c_void;
+type DeleteFn = unsafe extern "C" fn(*mut c_void);
+type AlignedNewFn = unsafe extern "C" fn(usize, usize) -> *mut c_void;
+type AlignedDeleteFn = unsafe extern "C" fn(*mut c_void, usize);
+
+/// `void* operator new(size_t)`.
+#[cfg(target_arch = "x86_64")]
+const NEW_SYMBOL: &CStr = c"??2@YAPEAX_K@Z";
+/// `void operator delete(void*)`.
+#[cfg(target_arch = "x86_64")]
+const DELETE_SYMBOL: &CStr = c"??3@YAXPEAX@Z";
+/// `void* operator new(size_t, std::align_val_t)`.
+#[cfg(target_arch = "x86_64")]
+const ALIGNED_NEW_SYMBOL: &CStr = c"??2@YAPEAX_KW4align_val_t@std@@@Z";
+/// `void operator delete(void*, std::align_val_t)`.
+#[cfg(target_arch = "x86_64")]
+const ALIGNED_DELETE_SYMBOL: &CStr = c"??3@YAXPEAXW4align_val_t@std@@@Z";
+
+/// `void* operator new(size_t)`.
+#[cfg(target_arch = "x86")]
+const NEW_SYMBOL: &CStr = c"??2@YAPAXI@Z";
+/// `void operator delete(void*)`.
+#[cfg(target_arch = "x86")]
+const DELETE_SYMBOL: &CStr = c"??3@YAXPAX@Z";
+/// `void* operator new(size_t, std::align_val_t)`.
+#[cfg(target_arch = "x86")]
+const ALIGNED_NEW_SYMBOL: &CStr = c"??2@YAPAXIW4align_val_t@std@@@Z";
+/// `void operator delete(void*, std::align_val_t)`.
+#[cfg(target_arch = "x86")]
+const ALIGNED_DELETE_SYMBOL: &CStr = c"??3@YAXPAXW4align_val_t@std@@@Z";
+
+/// Size from which `std::allocator` aligns blocks to [`BIG_ALLOCATION_ALIGNMENT`].
+const BIG_ALLOCATION_THRESHOLD: usize = 4096;
+
+/// Alignment of large blocks allocated by `std::allocator`.
+const BIG_ALLOCATION_ALIGNMENT: usize = 32;
+
+/// Bytes `std::allocator` reserves in front of large blocks for alignment
+/// and the pointer to the start of the underlying allocation.
+const NON_USER_SIZE: usize = mem::size_of::<*mut c_void>() + BIG_ALLOCATION_ALIGNMENT - 1;
+
+extern "system" {
+    fn GetModuleHandleW(module_name: *const u16) -> *mut c_void;
+    fn GetProcAddress(module: *mut c_void, proc_name: *const c_char) -> *mut c_void;
+}
+
+/// Allocator that uses the `operator new` and `operator delete` of a module,
+/// exactly like its `std::allocator` does.
+///
+/// Over-aligned allocations require the module to also provide the
+/// `std::align_val_t` overloads, and fail otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct ModuleNewDelete {
+    new: NewFn,
+    delete: DeleteFn,
+    aligned: Option<(AlignedNewFn, AlignedDeleteFn)>,
+}
+
+impl ModuleNewDelete {
+    /// Resolves the operators exported by the module with the handle `module`.
+    ///
+    /// Returns `None` if the module doesn't export `operator new` and `operator delete`.
+    ///
+    /// # Safety
+    ///
+    /// `module` must be a valid module handle, and the module must stay loaded
+    /// for as long as the returned allocator or memory allocated by it is in use.
+    pub unsafe fn from_module(module: *mut c_void) -> Option<Self> {
+        unsafe {
+            let new = Self::resolve(module, NEW_SYMBOL)?;
+            let delete = Self::resolve(module, DELETE_SYMBOL)?;
+            let aligned = Self::resolve(module, ALIGNED_NEW_SYMBOL)
+                .zip(Self::resolve(module, ALIGNED_DELETE_SYMBOL));
+
+            Some(Self {
+                new: mem::transmute::<*mut c_void, NewFn>(new),
+                delete: mem::transmute::<*mut c_void, DeleteFn>(delete),
+                aligned: aligned.map(|(new, delete)| {
+                    (
+                        mem::transmute::<*mut c_void, AlignedNewFn>(new),
+                        mem::transmute::<*mut c_void, AlignedDeleteFn>(delete),
+                    )
+                }),
+            })
+        }
+    }
+
+    /// Resolves the operators exported by the already loaded module `name`.
+    ///
+    /// Returns `None` if the module isn't loaded or doesn't export
+    /// `operator new` and `operator delete`.
+    ///
+    /// # Safety
+    ///
+    /// The module must stay loaded for as long as the returned allocator
+    /// or memory allocated by it is in use.
+    pub unsafe fn from_module_name(name: &str) -> Option<Self> {
+        let name: Vec<u16> = name.encode_utf16().chain([0]).collect();
+
+        unsafe {
+            let module = GetModuleHandleW(name.as_ptr());
+
+            if module.is_null() {
+                return None;
+            }
+
+            Self::from_module(module)
+        }
+    }
+
+    /// Uses the operators located at the given offsets from the base of `module`,
+    /// for modules that don't export them.
+    ///
+    /// # Safety
+    ///
+    /// `new_rva` and `delete_rva` must be the offsets of `operator new(size_t)` and
+    /// `operator delete(void*)` in the module. The module must stay loaded for as long
+    /// as the returned allocator or memory allocated by it is in use.
+    pub unsafe fn from_rvas(module: *mut c_void, new_rva: usize, delete_rva: usize) -> Self {
+        unsafe {
+            Self {
+                new: mem::transmute::<*mut u8, NewFn>(module.cast::<u8>().add(new_rva)),
+                delete: mem::transmute::<*mut u8, DeleteFn>(module.cast::<u8>().add(delete_rva)),
+                aligned: None,
+            }
+        }
+    }
+
+    unsafe fn resolve(module: *mut c_void, symbol: &CStr) -> Option<*mut c_void> {
+        let proc = unsafe { GetProcAddress(module, symbol.as_ptr()) };
+        (!proc.is_null()).then_some(proc)
+    }
+}
+
+unsafe impl GlobalAlloc for ModuleNewDelete {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe {
+            if layout.align() > MEMORY_ALLOCATION_ALIGNMENT {
+                return match self.aligned {
+                    Some((new, _)) => new(layout.size(), layout.align()) as _,
+                    None => ptr::null_mut(),
+                };
+            }
+
+            if layout.size() < BIG_ALLOCATION_THRESHOLD {
+                return (self.new)(layout.size()) as _;
+            }
+
+            let Some(block_size) = layout.size().checked_add(NON_USER_SIZE) else {
+                return ptr::null_mut();
+            };
+
+            let base = (self.new)(block_size) as *mut u8;
+
+            if base.is_null() {
+                return base;
+            }
+
+            let unaligned = base.add(mem::size_of::<*mut u8>());
+            let data = unaligned.add(unaligned.align_offset(BIG_ALLOCATION_ALIGNMENT));
+            (data as *mut *mut u8).sub(1).write(base);
+
+            data
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe {
+            if layout.align() > MEMORY_ALLOCATION_ALIGNMENT {
+                if let Some((_, delete)) = self.aligned {
+                    delete(ptr as _, layout.align());
+                }
+            } else if layout.size() < BIG_ALLOCATION_THRESHOLD {
+                (self.delete)(ptr as _);
+            } else {
+                let base = (ptr as *mut *mut u8).sub(1).read();
+                (self.delete)(base as _);
+            }
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! `std::pmr` memory resources.
+//!
+//! Containers of the `std::pmr` namespace, e.g. `std::pmr::vector<T>`, store a
+//! `std::pmr::polymorphic_allocator<T>`, which is a pointer to a `std::pmr::memory_resource`.
+//! [`PolymorphicAllocator`] has the same layout, so such containers can be used
+//! as e.g. `CxxVec<T, PolymorphicAllocator>`, allocating through the resource's vtable.
+//!
+//! [`CxxMonotonicBufferResource`] and [`CxxUnsynchronizedPoolResource`] are Rust resources
+//! that start with a `std::pmr::memory_resource` vtable, so C++ can allocate from them
+//! through a pointer obtained with their `polymorphic_allocator` methods.
+//! They draw memory from a Rust-owned buffer first, and from an upstream allocator
+//! once it's exhausted. As C++ can't handle a resource returning null, they abort
+//! with [`handle_alloc_error`] if the upstream allocator fails.
+
+use std::{
+    alloc::{handle_alloc_error, GlobalAlloc, Layout, System},
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+use super::Arena;
+
+/// Declares virtual member functions and their pointer types with the calling
+/// convention of MSVC, which passes `this` in ECX with `__thiscall` on x86.
+#[cfg(all(windows, target_arch = "x86"))]
+macro_rules! virtual_fn {
+    (unsafe fn($($args:tt)*) $(-> $ret:ty)?) => {
+        unsafe extern "thiscall" fn($($args)*) $(-> $ret)?
+    };
+    ($(#[$attr:meta])* unsafe fn $name:ident $(<$gen:ident: $bound:path>)?
+        ($($args:tt)*) $(-> $ret:ty)? $body:block) => {
+        $(#[$attr])*
+        unsafe extern "thiscall" fn $name $(<$gen: $bound>)? ($($args)*) $(-> $ret)? $body
+    };
+}
+
+/// Declares virtual member functions and their pointer types with the calling
+/// convention of the C++ ABI, which passes `this` as the first argument.
+#[cfg(not(all(windows, target_arch = "x86")))]
+macro_rules! virtual_fn {
+    (unsafe fn($($args:tt)*) $(-> $ret:ty)?) => {
+        unsafe extern "C" fn($($args)*) $(-> $ret)?
+    };
+    ($(#[$attr:meta])* unsafe fn $name:ident $(<$gen:ident: $bound:path>)?
+        ($($args:tt)*) $(-> $ret:ty)? $body:block) => {
+        $(#[$attr])*
+        unsafe extern "C" fn $name $(<$gen: $bound>)? ($($args)*) $(-> $ret)? $body
+    };
+}
+
+/// Virtual function table of `std::pmr::memory_resource`.
+#[repr(C)]
+struct MemoryResourceVtable {
+    destructor: virtual_fn!(unsafe fn(this: *mut c_void, flags: u32) -> *mut c_void),
+    do_allocate:
+        virtual_fn!(unsafe fn(this: *mut c_void, bytes: usize, align: usize) -> *mut c_void),
+    do_deallocate:
+        virtual_fn!(unsafe fn(this: *mut c_void, ptr: *mut c_void, bytes: usize, align: usize)),
+    do_is_equal: virtual_fn!(unsafe fn(this: *const c_void, that: *const c_void) -> bool),
+}
+
+/// `std::pmr::polymorphic_allocator`, a proxy to a `std::pmr::memory_resource`.
+///
+/// Not [`Send`] or [`Sync`], as the resource may be unsynchronized.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PolymorphicAllocator<'a> {
+    resource: NonNull<c_void>,
+    _marker: PhantomData<&'a c_void>,
+}
+
+impl PolymorphicAllocator<'_> {
+    /// Creates a proxy to the `std::pmr::memory_resource` at `resource`,
+    /// e.g. the allocator of a pmr container owned by C++.
+    ///
+    /// Returns `None` if `resource` is null.
+    ///
+    /// # Safety
+    ///
+    /// `resource` must point to a `std::pmr::memory_resource` that outlives
+    /// the returned proxy and the memory allocated by it. Its `do_allocate` must not throw
+    /// when called from Rust, e.g. because it's backed by a resource of this module.
+    pub unsafe fn from_raw(resource: *mut c_void) -> Option<Self> {
+        NonNull::new(resource).map(|resource| Self {
+            resource,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the pointer to the resource.
+    pub fn resource(&self) -> *mut c_void {
+        self.resource.as_ptr()
+    }
+
+    fn vtable(&self) -> &MemoryResourceVtable {
+        unsafe { *self.resource.cast::<&MemoryResourceVtable>().as_ptr() }
+    }
+}
+
+unsafe impl GlobalAlloc for PolymorphicAllocator<'_> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let this = self.resource.as_ptr();
+        unsafe { (self.vtable().do_allocate)(this, layout.size(), layout.align()) as _ }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let this = self.resource.as_ptr();
+        unsafe { (self.vtable().do_deallocate)(this, ptr as _, layout.size(), layout.align()) }
+    }
+}
+
+/// Memory resource implemented in Rust, laid out behind a vtable pointer.
+trait RustResource: Sized {
+    const VTABLE: MemoryResourceVtable = MemoryResourceVtable {
+        destructor: resource_destructor,
+        do_allocate: resource_allocate::<Self>,
+        do_deallocate: resource_deallocate::<Self>,
+        do_is_equal: resource_is_equal,
+    };
+
+    fn allocate(&self, layout: Layout) -> NonNull<u8>;
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+virtual_fn! {
+    /// The resource is owned by Rust, so C++ must never delete it.
+    unsafe fn resource_destructor(this: *mut c_void, _flags: u32) -> *mut c_void {
+        this
+    }
+}
+
+virtual_fn! {
+    unsafe fn resource_allocate<R: RustResource>(
+        this: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) -> *mut c_void {
+        let layout = Layout::from_size_align(bytes, align).expect("bad layout passed from C++");
+        unsafe { (*(this as *const R)).allocate(layout).as_ptr() as _ }
+    }
+}
+
+virtual_fn! {
+    unsafe fn resource_deallocate<R: RustResource>(
+        this: *mut c_void,
+        ptr: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) {
+        let layout = Layout::from_size_align(bytes, align).expect("bad layout passed from C++");
+
+        if let Some(ptr) = NonNull::new(ptr as *mut u8) {
+            unsafe { (*(this as *const R)).deallocate(ptr, layout) }
+        }
+    }
+}
+
+virtual_fn! {
+    unsafe fn resource_is_equal(this: *const c_void, that: *const c_void) -> bool {
+        ptr::eq(this, that)
+    }
+}
+
+/// Block of upstream memory, preceded by its header.
+struct Chunk {
+    next: Option<NonNull<Chunk>>,
+    layout: Layout,
+}
+
+/// Size of the first upstream chunk, doubled for each one after it.
+const INITIAL_CHUNK_SIZE: usize = 4096;
+
+/// `std::pmr::monotonic_buffer_resource` over a Rust-owned buffer.
+///
+/// Memory is only released all at once, by [`CxxMonotonicBufferResource::release`]
+/// or when the resource is dropped. Once the buffer is exhausted, chunks of
+/// geometrically growing size are allocated from the upstream allocator `U`.
+#[repr(C)]
+pub struct CxxMonotonicBufferResource<'a, U: GlobalAlloc = System> {
+    vtable: &'static MemoryResourceVtable,
+    buffer: Arena<'a>,
+    upstream: U,
+    chunks: Cell<Option<NonNull<Chunk>>>,
+    cursor: Cell<*mut u8>,
+    end: Cell<*mut u8>,
+    next_chunk_size: Cell<usize>,
+}
+
+impl<'a> CxxMonotonicBufferResource<'a> {
+    /// Creates a resource that allocates from `buffer`, then from [`System`].
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self::new_in(buffer, System)
+    }
+}
+
+impl<'a, U: GlobalAlloc> CxxMonotonicBufferResource<'a, U> {
+    /// Creates a resource that allocates from `buffer`, then from `upstream`.
+    pub fn new_in(buffer: &'a mut [MaybeUninit<u8>], upstream: U) -> Self {
+        Self {
+            vtable: &Self::VTABLE,
+            buffer: Arena::new(buffer),
+            upstream,
+            chunks: Cell::new(None),
+            cursor: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+            next_chunk_size: Cell::new(INITIAL_CHUNK_SIZE),
+        }
+    }
+
+    /// Returns an allocator that C++ and Rust containers can allocate from the resource with.
+    pub fn polymorphic_allocator(&self) -> PolymorphicAllocator<'_> {
+        PolymorphicAllocator {
+            resource: NonNull::from(self).cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the upstream allocator.
+    pub fn upstream(&self) -> &U {
+        &self.upstream
+    }
+
+    /// Number of bytes allocated from the buffer.
+    pub fn buffer_used(&self) -> usize {
+        self.buffer.used()
+    }
+
+    /// Releases all memory, freeing the upstream chunks.
+    ///
+    /// Taking `&mut self` guarantees that no allocators, and thus no containers
+    /// allocated from the resource, are still alive.
+    pub fn release(&mut self) {
+        let mut chunk = self.chunks.take();
+
+        while let Some(current) = chunk {
+            unsafe {
+                let Chunk { next, layout } = current.read();
+                self.upstream.dealloc(current.as_ptr() as *mut u8, layout);
+                chunk = next;
+            }
+        }
+
+        self.buffer.reset();
+        self.cursor.set(ptr::null_mut());
+        self.end.set(ptr::null_mut());
+        self.next_chunk_size.set(INITIAL_CHUNK_SIZE);
+    }
+
+    /// Bumps the cursor of the current upstream chunk.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let cursor = self.cursor.get();
+        let padding = cursor.align_offset(layout.align());
+        let available = self.end.get() as usize - cursor as usize;
+
+        if cursor.is_null() || padding.checked_add(layout.size())? > available {
+            return None;
+        }
+
+        unsafe {
+            let block = cursor.add(padding);
+            self.cursor.set(block.add(layout.size()));
+            Some(NonNull::new_unchecked(block))
+        }
+    }
+
+    /// Allocates a new upstream chunk large enough for `layout`.
+    fn grow(&self, layout: Layout) {
+        let header = Layout::new::<Chunk>();
+        let Some(size) = layout
+            .size()
+            .checked_add(layout.align())
+            .and_then(|size| size.checked_add(header.size()))
+        else {
+            handle_alloc_error(layout);
+        };
+
+        let size = size.max(self.next_chunk_size.get());
+        let Ok(chunk_layout) = Layout::from_size_align(size, header.align()) else {
+            handle_alloc_error(layout);
+        };
+
+        unsafe {
+            let Some(chunk) = NonNull::new(self.upstream.alloc(chunk_layout) as *mut Chunk) else {
+                handle_alloc_error(chunk_layout);
+            };
+
+            chunk.write(Chunk {
+                next: self.chunks.get(),
+                layout: chunk_layout,
+            });
+
+            self.chunks.set(Some(chunk));
+            self.cursor.set(chunk.as_ptr().add(1) as *mut u8);
+            self.end.set((chunk.as_ptr() as *mut u8).add(size));
+        }
+
+        self.next_chunk_size.set(size.saturating_mul(2));
+    }
+}
+
+impl<U: GlobalAlloc> RustResource for CxxMonotonicBufferResource<'_, U> {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        if let Some(block) = NonNull::new(unsafe { self.buffer.proxy().alloc(layout) }) {
+            return block;
+        }
+
+        self.bump(layout).unwrap_or_else(|| {
+            self.grow(layout);
+            self.bump(layout).expect("upstream chunk too small")
+        })
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl<U: GlobalAlloc> Drop for CxxMonotonicBufferResource<'_, U> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl<U: GlobalAlloc> fmt::Debug for CxxMonotonicBufferResource<'_, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CxxMonotonicBufferResource")
+            .field("buffer", &self.buffer)
+            .field("has_chunks", &self.chunks.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Largest block size served from the pools of a [`CxxUnsynchronizedPoolResource`].
+pub const LARGEST_POOL_BLOCK: usize = 4096;
+
+/// Smallest block size of the pools, large enough to link free blocks.
+const SMALLEST_POOL_BLOCK: usize = mem::size_of::<usize>();
+
+/// Number of pools, one for each power of two block size.
+const POOL_COUNT: usize =
+    (LARGEST_POOL_BLOCK.trailing_zeros() - SMALLEST_POOL_BLOCK.trailing_zeros()) as usize + 1;
+
+/// Free block in a pool, linked to the next one.
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// `std::pmr::unsynchronized_pool_resource` over a Rust-owned buffer.
+///
+/// Requests up to [`LARGEST_POOL_BLOCK`] bytes are served from pools of power of two
+/// block sizes, and freed blocks are reused by later requests of the same size class.
+/// Pool blocks are carved from a [`CxxMonotonicBufferResource`] over the buffer,
+/// and larger requests go to the upstream allocator `U` directly.
+///
+/// Like the C++ resource it isn't thread-safe, so it isn't [`Sync`].
+#[repr(C)]
+pub struct CxxUnsynchronizedPoolResource<'a, U: GlobalAlloc = System> {
+    vtable: &'static MemoryResourceVtable,
+    pools: [Cell<Option<NonNull<FreeBlock>>>; POOL_COUNT],
+    blocks: CxxMonotonicBufferResource<'a, U>,
+    large: RefCell<Vec<(NonNull<u8>, Layout)>>,
+}
+
+impl<'a> CxxUnsynchronizedPoolResource<'a> {
+    /// Creates a resource that allocates from `buffer`, then from [`System`].
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self::new_in(buffer, System)
+    }
+}
+
+impl<'a, U: GlobalAlloc> CxxUnsynchronizedPoolResource<'a, U> {
+    /// Creates a resource that allocates from `buffer`, then from `upstream`.
+    pub fn new_in(buffer: &'a mut [MaybeUninit<u8>], upstream: U) -> Self {
+        Self {
+            vtable: &Self::VTABLE,
+            pools: [const { Cell::new(None) }; POOL_COUNT],
+            blocks: CxxMonotonicBufferResource::new_in(buffer, upstream),
+            large: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns an allocator that C++ and Rust containers can allocate from the resource with.
+    pub fn polymorphic_allocator(&self) -> PolymorphicAllocator<'_> {
+        PolymorphicAllocator {
+            resource: NonNull::from(self).cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the upstream allocator.
+    pub fn upstream(&self) -> &U {
+        self.blocks.upstream()
+    }
+
+    /// Releases all memory, including blocks that were never deallocated.
+    ///
+    /// Taking `&mut self` guarantees that no allocators, and thus no containers
+    /// allocated from the resource, are still alive.
+    pub fn release(&mut self) {
+        for (ptr, layout) in self.large.get_mut().drain(..) {
+            unsafe { self.blocks.upstream().dealloc(ptr.as_ptr(), layout) }
+        }
+
+        for pool in &self.pools {
+            pool.set(None);
+        }
+
+        self.blocks.release();
+    }
+
+    /// Returns the index of the pool serving `layout`, if it isn't too large.
+    fn pool_index(layout: Layout) -> Option<usize> {
+        let block_size = layout
+            .size()
+            .max(layout.align())
+            .max(SMALLEST_POOL_BLOCK)
+            .next_power_of_two();
+
+        (block_size <= LARGEST_POOL_BLOCK)
+            .then(|| (block_size.trailing_zeros() - SMALLEST_POOL_BLOCK.trailing_zeros()) as usize)
+    }
+}
+
+impl<U: GlobalAlloc> RustResource for CxxUnsynchronizedPoolResource<'_, U> {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        let Some(index) = Self::pool_index(layout) else {
+            let Some(ptr) = NonNull::new(unsafe { self.blocks.upstream().alloc(layout) }) else {
+                handle_alloc_error(layout);
+            };
+
+            self.large.borrow_mut().push((ptr, layout));
+            return ptr;
+        };
+
+        let pool = &self.pools[index];
+
+        if let Some(block) = pool.get() {
+            pool.set(unsafe { block.read().next });
+            return block.cast();
+        }
+
+        let block_size = SMALLEST_POOL_BLOCK << index;
+        let block_layout = unsafe { Layout::from_size_align_unchecked(block_size, block_size) };
+        self.blocks.allocate(block_layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let Some(index) = Self::pool_index(layout) else {
+            let mut large = self.large.borrow_mut();
+
+            if let Some(position) = large.iter().position(|&(block, _)| block == ptr) {
+                large.swap_remove(position);
+                unsafe { self.blocks.upstream().dealloc(ptr.as_ptr(), layout) }
+            }
+
+            return;
+        };
+
+        let pool = &self.pools[index];
+        let block = ptr.cast::<FreeBlock>();
+
+        unsafe { block.write(FreeBlock { next: pool.get() }) };
+        pool.set(Some(block));
+    }
+}
+
+impl<U: GlobalAlloc> Drop for CxxUnsynchronizedPoolResource<'_, U> {
+    fn drop(&mut self) {
+        for (ptr, layout) in self.large.get_mut().drain(..) {
+            unsafe { self.blocks.upstream().dealloc(ptr.as_ptr(), layout) }
+        }
+    }
+}
+
+impl<U: GlobalAlloc> fmt::Debug for CxxUnsynchronizedPoolResource<'_, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CxxUnsynchronizedPoolResource")
+            .field("blocks", &self.blocks)
+            .field("large", &self.large.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
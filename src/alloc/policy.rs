@@ -0,0 +1,121 @@
+//! Allocation failure handling.
+//!
+//! What a failed allocation does to an infallible container operation is decided
+//! by the crate-wide [`AllocErrorPolicy`]. Operations prefixed with `try_`
+//! always report failures as a [`TryReserveError`] instead.
+
+use std::{
+    alloc::Layout,
+    error::Error,
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Behavior of infallible container operations when an allocation fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum AllocErrorPolicy {
+    /// Call [`std::alloc::handle_alloc_error`], which aborts the process by default.
+    #[default]
+    Abort,
+    /// Panic once the failed operation has returned from CSTL.
+    Panic,
+    /// Leave the container as it was before the failed operation.
+    ///
+    /// Infallible operations can't report the failure, so a value they take
+    /// by value, e.g. the element given to `push`, is dropped. Their `try_`
+    /// variants report it instead.
+    ReturnError,
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(AllocErrorPolicy::Abort as u8);
+
+/// Sets the crate-wide [`AllocErrorPolicy`].
+///
+/// Strings can't recover from a failed allocation inside CSTL,
+/// so their infallible operations always behave as with [`AllocErrorPolicy::Abort`].
+pub fn set_alloc_error_policy(policy: AllocErrorPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the crate-wide [`AllocErrorPolicy`].
+pub fn alloc_error_policy() -> AllocErrorPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        1 => AllocErrorPolicy::Panic,
+        2 => AllocErrorPolicy::ReturnError,
+        _ => AllocErrorPolicy::Abort,
+    }
+}
+
+/// Capacity containers grow to when they are full.
+///
+/// Strings are grown by CSTL, which like MSVC grows them by at least half of their
+/// capacity, so [`GrowthPolicy::Exact`] only applies to vectors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum GrowthPolicy {
+    /// Double the capacity of vectors, and grow strings like MSVC.
+    #[default]
+    Native,
+    /// Double the capacity.
+    Double,
+    /// Grow by half of the capacity, like MSVC's `_Calculate_growth`.
+    Msvc,
+    /// Grow to the required capacity only.
+    Exact,
+}
+
+impl GrowthPolicy {
+    /// Returns the capacity to grow a container of `capacity` to, so that it holds
+    /// at least `required` elements, where [`GrowthPolicy::Native`] stands for `native`.
+    pub(crate) fn grown_capacity(self, native: Self, capacity: usize, required: usize) -> usize {
+        let policy = if self == Self::Native { native } else { self };
+
+        let grown = match policy {
+            Self::Native | Self::Double => capacity.saturating_mul(2),
+            Self::Msvc => capacity.saturating_add(capacity / 2),
+            Self::Exact => required,
+        };
+
+        grown.max(required).min(isize::MAX as usize)
+    }
+}
+
+static GROWTH_POLICY: AtomicU8 = AtomicU8::new(GrowthPolicy::Native as u8);
+
+/// Sets the crate-wide [`GrowthPolicy`].
+pub fn set_growth_policy(policy: GrowthPolicy) {
+    GROWTH_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the crate-wide [`GrowthPolicy`].
+pub fn growth_policy() -> GrowthPolicy {
+    match GROWTH_POLICY.load(Ordering::Relaxed) {
+        1 => GrowthPolicy::Double,
+        2 => GrowthPolicy::Msvc,
+        3 => GrowthPolicy::Exact,
+        _ => GrowthPolicy::Native,
+    }
+}
+
+/// The error type for `try_reserve` and other fallible growth methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// The requested capacity exceeded the maximum size of the container.
+    CapacityOverflow,
+    /// The allocator failed to allocate a block of the given layout.
+    AllocError { layout: Layout },
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CapacityOverflow => f.write_str("requested capacity exceeded the maximum size"),
+            Self::AllocError { layout } => {
+                write!(f, "memory allocation of {} bytes failed", layout.size())
+            }
+        }
+    }
+}
+
+impl Error for TryReserveError {}
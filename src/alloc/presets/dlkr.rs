@@ -0,0 +1,97 @@
+//! `DLKR::DLAllocator`, the polymorphic allocator interface of FromSoftware's engine.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::c_void,
+    ptr::NonNull,
+};
+
+/// Virtual function table of `DLKR::DLAllocator`.
+#[repr(C)]
+struct DlAllocatorVtable {
+    destructor: unsafe extern "C" fn(this: NonNull<c_void>, flags: u32),
+    allocator_id: unsafe extern "C" fn(this: NonNull<c_void>) -> u32,
+    _unk10: usize,
+    heap_flags: unsafe extern "C" fn(this: NonNull<c_void>, out: *mut u64) -> *mut u64,
+    heap_capacity: unsafe extern "C" fn(this: NonNull<c_void>) -> usize,
+    heap_size: unsafe extern "C" fn(this: NonNull<c_void>) -> usize,
+    backing_heap_capacity: unsafe extern "C" fn(this: NonNull<c_void>) -> usize,
+    allocation_count: unsafe extern "C" fn(this: NonNull<c_void>) -> usize,
+    size_of_allocation: unsafe extern "C" fn(this: NonNull<c_void>, ptr: *mut c_void) -> usize,
+    allocate: unsafe extern "C" fn(this: NonNull<c_void>, size: usize) -> *mut c_void,
+    allocate_aligned:
+        unsafe extern "C" fn(this: NonNull<c_void>, size: usize, align: usize) -> *mut c_void,
+    reallocate:
+        unsafe extern "C" fn(this: NonNull<c_void>, ptr: *mut c_void, size: usize) -> *mut c_void,
+    reallocate_aligned: unsafe extern "C" fn(
+        this: NonNull<c_void>,
+        ptr: *mut c_void,
+        size: usize,
+        align: usize,
+    ) -> *mut c_void,
+    deallocate: unsafe extern "C" fn(this: NonNull<c_void>, ptr: *mut c_void),
+}
+
+/// Allocator that forwards to a `DLKR::DLAllocator` instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DlkrAllocator {
+    this: NonNull<c_void>,
+}
+
+impl DlkrAllocator {
+    /// Creates a proxy to the `DLKR::DLAllocator` at `this`.
+    ///
+    /// Returns `None` if `this` is null.
+    ///
+    /// # Safety
+    ///
+    /// `this` must point to a `DLKR::DLAllocator` that outlives the returned proxy
+    /// and the memory allocated by it, and that is safe to use from any thread
+    /// the proxy is used on.
+    pub unsafe fn from_raw(this: *mut c_void) -> Option<Self> {
+        NonNull::new(this).map(|this| Self { this })
+    }
+
+    /// Returns the pointer to the allocator instance.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.this.as_ptr()
+    }
+
+    /// Returns the identifier the game assigned to the allocator.
+    pub fn allocator_id(&self) -> u32 {
+        unsafe { (self.vtable().allocator_id)(self.this) }
+    }
+
+    /// Returns the size in bytes of the memory currently allocated from the heap.
+    pub fn heap_size(&self) -> usize {
+        unsafe { (self.vtable().heap_size)(self.this) }
+    }
+
+    /// Returns the total capacity in bytes of the heap.
+    pub fn heap_capacity(&self) -> usize {
+        unsafe { (self.vtable().heap_capacity)(self.this) }
+    }
+
+    /// Returns the number of live allocations.
+    pub fn allocation_count(&self) -> usize {
+        unsafe { (self.vtable().allocation_count)(self.this) }
+    }
+
+    fn vtable(&self) -> &DlAllocatorVtable {
+        unsafe { *self.this.cast::<&DlAllocatorVtable>().as_ptr() }
+    }
+}
+
+// SAFETY: guaranteed by the contract of `DlkrAllocator::from_raw`.
+unsafe impl Send for DlkrAllocator {}
+unsafe impl Sync for DlkrAllocator {}
+
+unsafe impl GlobalAlloc for DlkrAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { (self.vtable().allocate_aligned)(self.this, layout.size(), layout.align()) as _ }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        unsafe { (self.vtable().deallocate)(self.this, ptr as _) }
+    }
+}
@@ -0,0 +1,11 @@
+//! Ready-made proxies for allocators of well-known game engines.
+//!
+//! Each preset is constructed from a pointer to an allocator object
+//! owned by the game, and is enabled by its own feature:
+//!
+//! - `dlkr`: `DlkrAllocator`, for `DLKR::DLAllocator` in FromSoftware titles.
+
+#[cfg(feature = "dlkr")]
+pub mod dlkr;
+#[cfg(feature = "dlkr")]
+pub use dlkr::DlkrAllocator;
@@ -0,0 +1,81 @@
+//! Integration with the [arbitrary](https://docs.rs/arbitrary) crate, for fuzzing.
+//!
+//! Vectors get arbitrary elements and spare capacity. Strings get arbitrary
+//! characters, and half of the time a length right around the small string
+//! capacity, to exercise the switch between small and large mode.
+
+use std::mem;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::Semantics,
+    string::{
+        narrow::{CxxNarrowStringLayout, RawString},
+        utf16::{CxxUtf16StringLayout, RawUtf16String},
+        utf32::{CxxUtf32StringLayout, RawUtf32String},
+        utf8::{CxxUtf8StringLayout, RawUtf8String},
+        wide::{CxxWideStringLayout, RawWideString},
+    },
+    vec::{CxxVecLayout, RawVec},
+};
+
+impl<'a, T, A, L, S> Arbitrary<'a> for CxxVecLayout<T, A, L, S>
+where
+    T: Arbitrary<'a>,
+    A: CxxProxy + Default,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut vec = Self::default();
+
+        for element in u.arbitrary_iter()? {
+            vec.push(element?);
+        }
+
+        vec.reserve(u8::arbitrary(u)? as usize);
+        Ok(vec)
+    }
+}
+
+/// Returns an arbitrary length for a string of `C` characters.
+fn arbitrary_string_len<'a, C: Arbitrary<'a>>(u: &mut Unstructured<'a>) -> Result<usize> {
+    let small_capacity = 16 / mem::size_of::<C>() - 1;
+
+    if bool::arbitrary(u)? {
+        u.int_in_range(small_capacity - 1..=small_capacity + 1)
+    } else {
+        u.arbitrary_len::<C>()
+    }
+}
+
+macro_rules! string_arbitrary {
+    ($($string:ident, $raw:ty, $char:ty;)*) => {
+        $(
+            impl<'a, A, L> Arbitrary<'a> for $string<A, L>
+            where
+                A: CxxProxy + Default,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+            {
+                fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                    let len = arbitrary_string_len::<$char>(u)?;
+                    let chars = (0..len)
+                        .map(|_| <$char>::arbitrary(u))
+                        .collect::<Result<Vec<_>>>()?;
+
+                    Ok(Self::from_bytes_in(chars, A::default()))
+                }
+            }
+        )*
+    };
+}
+
+string_arbitrary! {
+    CxxNarrowStringLayout, RawString, u8;
+    CxxWideStringLayout, RawWideString, u16;
+    CxxUtf8StringLayout, RawUtf8String, u8;
+    CxxUtf16StringLayout, RawUtf16String, u16;
+    CxxUtf32StringLayout, RawUtf32String, u32;
+}
@@ -0,0 +1,202 @@
+//! Configuration for [bindgen](https://github.com/rust-lang/rust-bindgen) to use the
+//! containers of this crate in generated bindings, instead of opaque blobs.
+//!
+//! [`BindgenConfig`] blocklists the C++ containers and provides raw lines aliasing
+//! them to the containers of [`abi`](crate::abi), either as bindgen command line
+//! arguments with [`BindgenConfig::args`], or for a `bindgen::Builder` in a build script:
+//!
+//! ```ignore
+//! let config = cxx_stl::bindgen::BindgenConfig::default();
+//!
+//! for ty in config.blocklist() {
+//!     builder = builder.blocklist_type(ty);
+//! }
+//!
+//! for raw in config.raw_lines() {
+//!     builder = match raw.module {
+//!         Some(module) => builder.module_raw_line(module, raw.line),
+//!         None => builder.raw_line(raw.line),
+//!     };
+//! }
+//! ```
+//!
+//! References to blocklisted templates keep all of their template arguments, including
+//! the allocator and character traits, which the aliases discard through [`Discard`].
+//! Strings are only aliased for MSVC layouts.
+
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+use crate::abi::{CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String};
+
+/// Projects to `T`, ignoring the implementing type, so that aliases can
+/// take template arguments they don't use.
+pub trait Discard<T> {
+    type Output;
+}
+
+impl<U, T> Discard<T> for U {
+    type Output = T;
+}
+
+/// Character types bindgen emits for the characters of `std::basic_string`.
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+pub trait CharType {
+    /// String of these characters.
+    type String;
+}
+
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+impl CharType for i8 {
+    type String = CxxNarrowString;
+}
+
+/// `char8_t`.
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+impl CharType for u8 {
+    type String = CxxUtf8String;
+}
+
+/// `char16_t`, and `wchar_t` on Windows.
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+impl CharType for u16 {
+    type String = CxxUtf16String;
+}
+
+/// `char32_t`.
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+impl CharType for u32 {
+    type String = CxxUtf32String;
+}
+
+/// C++ type replaced by a container, under its name in namespace `std`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Replacement {
+    pub name: &'static str,
+    /// Template parameters of the alias, including the angle brackets.
+    pub params: &'static str,
+    /// Rust type the alias refers to.
+    pub rust: &'static str,
+}
+
+/// Replacements for the container templates and their typedefs.
+pub const REPLACEMENTS: &[Replacement] = &[
+    Replacement {
+        name: "vector",
+        params: "<T, A>",
+        rust: "<A as ::cxx_stl::bindgen::Discard<::cxx_stl::abi::CxxVec<T>>>::Output",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "basic_string",
+        params: "<C, Tr, A>",
+        rust: "<(Tr, A) as ::cxx_stl::bindgen::Discard<\
+            <C as ::cxx_stl::bindgen::CharType>::String>>::Output",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "string",
+        params: "",
+        rust: "::cxx_stl::abi::CxxNarrowString",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "wstring",
+        params: "",
+        rust: "::cxx_stl::abi::CxxWideString",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "u8string",
+        params: "",
+        rust: "::cxx_stl::abi::CxxUtf8String",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "u16string",
+        params: "",
+        rust: "::cxx_stl::abi::CxxUtf16String",
+    },
+    #[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+    Replacement {
+        name: "u32string",
+        params: "",
+        rust: "::cxx_stl::abi::CxxUtf32String",
+    },
+];
+
+/// Raw line to add to the bindings, in `module` if any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawLine {
+    pub module: Option<&'static str>,
+    pub line: String,
+}
+
+/// Bindgen configuration replacing the C++ containers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BindgenConfig {
+    /// Whether the bindings are generated with `--enable-cxx-namespaces`,
+    /// which places the aliases in `root::std` rather than prefixing them with `std_`.
+    pub cxx_namespaces: bool,
+    /// Whether to derive `Default`, which all containers implement.
+    pub derive_default: bool,
+}
+
+impl BindgenConfig {
+    /// Returns the patterns of the types to blocklist.
+    pub fn blocklist(&self) -> Vec<String> {
+        REPLACEMENTS
+            .iter()
+            .map(|replacement| format!("std::{}", replacement.name))
+            .collect()
+    }
+
+    /// Returns the raw lines aliasing the blocklisted types.
+    pub fn raw_lines(&self) -> Vec<RawLine> {
+        REPLACEMENTS
+            .iter()
+            .map(|Replacement { name, params, rust }| {
+                if self.cxx_namespaces {
+                    RawLine {
+                        module: Some("root::std"),
+                        line: format!("pub type {name}{params} = {rust};"),
+                    }
+                } else {
+                    RawLine {
+                        module: None,
+                        line: format!("pub type std_{name}{params} = {rust};"),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the bindgen command line arguments of the configuration.
+    ///
+    /// Containers can't be copied, so `Copy` isn't derived. Only the derives all
+    /// containers implement can be enabled, which excludes `PartialEq` and `Hash`.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["--no-derive-copy".to_owned()];
+
+        if self.derive_default {
+            args.push("--with-derive-default".to_owned());
+        }
+
+        if self.cxx_namespaces {
+            args.push("--enable-cxx-namespaces".to_owned());
+        }
+
+        for ty in self.blocklist() {
+            args.extend(["--blocklist-type".to_owned(), ty]);
+        }
+
+        for RawLine { module, line } in self.raw_lines() {
+            match module {
+                Some(module) => {
+                    args.extend(["--module-raw-line".to_owned(), module.to_owned(), line])
+                }
+                None => args.extend(["--raw-line".to_owned(), line]),
+            }
+        }
+
+        args
+    }
+}
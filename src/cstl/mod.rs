@@ -0,0 +1,36 @@
+//! CSTL functions called by the containers.
+//!
+//! With the `rust-cstl` feature, or under Miri, which can't call into the C library,
+//! they are replaced by Rust implementations that follow the C code step by step,
+//! calling the same semantics tables and allocator functions in the same order.
+//! This lets code using the containers be tested under Miri and sanitizers.
+//!
+//! The Rust string functions report failed allocations instead of writing through
+//! a null pointer, but are otherwise identical.
+
+#[cfg(not(any(miri, feature = "rust-cstl")))]
+pub(crate) use cstl_sys::{
+    CSTL_string_append_n, CSTL_string_assign_n, CSTL_string_destroy, CSTL_string_reserve,
+    CSTL_string_shrink_to_fit, CSTL_string_swap, CSTL_u16string_append_n, CSTL_u16string_assign_n,
+    CSTL_u16string_destroy, CSTL_u16string_reserve, CSTL_u16string_shrink_to_fit,
+    CSTL_u16string_swap, CSTL_u32string_append_n, CSTL_u32string_assign_n, CSTL_u32string_destroy,
+    CSTL_u32string_reserve, CSTL_u32string_shrink_to_fit, CSTL_u32string_swap,
+    CSTL_u8string_append_n, CSTL_u8string_assign_n, CSTL_u8string_destroy, CSTL_u8string_reserve,
+    CSTL_u8string_shrink_to_fit, CSTL_u8string_swap, CSTL_vector_begin, CSTL_vector_clear,
+    CSTL_vector_copy_assign, CSTL_vector_copy_assign_range, CSTL_vector_copy_insert_range,
+    CSTL_vector_copy_push_back, CSTL_vector_destroy, CSTL_vector_end, CSTL_vector_erase,
+    CSTL_vector_iterator_add, CSTL_vector_move_assign, CSTL_vector_move_assign_range,
+    CSTL_vector_move_push_back, CSTL_vector_reserve, CSTL_vector_resize, CSTL_vector_shrink_to_fit,
+    CSTL_vector_swap, CSTL_vector_truncate, CSTL_wstring_append_n, CSTL_wstring_assign_n,
+    CSTL_wstring_destroy, CSTL_wstring_reserve, CSTL_wstring_shrink_to_fit, CSTL_wstring_swap,
+};
+
+#[cfg(any(miri, feature = "rust-cstl"))]
+mod string;
+#[cfg(any(miri, feature = "rust-cstl"))]
+pub(crate) use string::*;
+
+#[cfg(any(miri, feature = "rust-cstl"))]
+mod vector;
+#[cfg(any(miri, feature = "rust-cstl"))]
+pub(crate) use vector::*;
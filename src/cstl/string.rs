@@ -0,0 +1,345 @@
+//! `basic_string_def.inl` in Rust, for every character width.
+
+// Named like the C functions they stand in for:
+#![allow(non_snake_case)]
+
+use std::{mem, ptr};
+
+use cstl_sys::{
+    char16_t, char32_t, char8_t, wchar_t, CSTL_Alloc, CSTL_StringVal, CSTL_UTF16StringVal,
+    CSTL_UTF32StringVal, CSTL_UTF8StringVal, CSTL_WideStringVal,
+};
+
+use super::vector::{allocate, free};
+use crate::string::{chars_ptr_raw, small_capacity, try_reserve_raw};
+
+/// Pointers to the fields of a CSTL string of `C` characters.
+///
+/// The fields are accessed through raw pointers only, since the characters
+/// passed to CSTL may point into the string itself.
+struct Fields<C> {
+    bx: *mut C,
+    size: *mut usize,
+    res: *mut usize,
+}
+
+const fn max_size<C>() -> usize {
+    if mem::size_of::<C>() == 1 {
+        isize::MAX as usize - 1
+    } else {
+        isize::MAX as usize / mem::size_of::<C>()
+    }
+}
+
+fn calculate_growth<C>(requested: usize, old: usize) -> usize {
+    let max = max_size::<C>();
+    let masked = requested | small_capacity::<C>();
+
+    if masked > max || old > max - old / 2 {
+        return max;
+    }
+
+    masked.max(old + old / 2)
+}
+
+unsafe fn allocate_for_capacity<C>(capacity: usize, alloc: *mut CSTL_Alloc) -> *mut C {
+    unsafe {
+        allocate(
+            (capacity + 1) * mem::size_of::<C>(),
+            mem::align_of::<C>(),
+            alloc,
+        ) as _
+    }
+}
+
+unsafe fn deallocate_for_capacity<C>(old_ptr: *mut C, capacity: usize, alloc: *mut CSTL_Alloc) {
+    // In characters, as freed by CSTL, see `WithCxxProxy::FREE_SIZE_UNIT`:
+    unsafe { free(old_ptr as _, capacity + 1, mem::align_of::<C>(), alloc) }
+}
+
+unsafe fn tidy_deallocate<C: Default>(s: Fields<C>, alloc: *mut CSTL_Alloc) {
+    unsafe {
+        if *s.res > small_capacity::<C>() {
+            deallocate_for_capacity(*s.bx.cast::<*mut C>(), *s.res, alloc);
+            s.bx.cast::<*mut C>().write(ptr::null_mut());
+        }
+
+        *s.size = 0;
+        *s.res = small_capacity::<C>();
+        s.bx.write(C::default());
+    }
+}
+
+unsafe fn assign_n<C: Default>(
+    s: Fields<C>,
+    chars: *const C,
+    count: usize,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    unsafe {
+        if count <= *s.res {
+            *s.size = count;
+
+            let old_ptr = chars_ptr_raw(s.bx, *s.res);
+            ptr::copy(chars, old_ptr, count);
+            old_ptr.add(count).write(C::default());
+
+            return true;
+        }
+
+        if count > max_size::<C>() {
+            return false;
+        }
+
+        let old_capacity = *s.res;
+        let new_capacity = calculate_growth::<C>(count, old_capacity);
+        let new_ptr = allocate_for_capacity::<C>(new_capacity, alloc);
+
+        if new_ptr.is_null() {
+            return false;
+        }
+
+        *s.size = count;
+        *s.res = new_capacity;
+
+        ptr::copy_nonoverlapping(chars, new_ptr, count);
+        new_ptr.add(count).write(C::default());
+
+        if old_capacity > small_capacity::<C>() {
+            deallocate_for_capacity(*s.bx.cast::<*mut C>(), old_capacity, alloc);
+        }
+
+        s.bx.cast::<*mut C>().write(new_ptr);
+    }
+
+    true
+}
+
+unsafe fn append_n<C: Default>(
+    s: Fields<C>,
+    chars: *const C,
+    count: usize,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    unsafe {
+        let old_size = *s.size;
+        let new_size = old_size.wrapping_add(count);
+
+        if count <= *s.res - old_size {
+            *s.size = new_size;
+
+            let old_ptr = chars_ptr_raw(s.bx, *s.res);
+            ptr::copy(chars, old_ptr.add(old_size), count);
+            old_ptr.add(new_size).write(C::default());
+
+            return true;
+        }
+
+        if max_size::<C>() - old_size < count {
+            return false;
+        }
+
+        let old_capacity = *s.res;
+        let new_capacity = calculate_growth::<C>(new_size, old_capacity);
+        let new_ptr = allocate_for_capacity::<C>(new_capacity, alloc);
+
+        if new_ptr.is_null() {
+            return false;
+        }
+
+        *s.size = new_size;
+        *s.res = new_capacity;
+
+        ptr::copy_nonoverlapping(chars, new_ptr.add(old_size), count);
+
+        if old_capacity > small_capacity::<C>() {
+            let old_ptr = *s.bx.cast::<*mut C>();
+            ptr::copy_nonoverlapping(old_ptr, new_ptr, old_size);
+            deallocate_for_capacity(old_ptr, old_capacity, alloc);
+        } else {
+            ptr::copy_nonoverlapping(s.bx, new_ptr, old_size);
+        }
+
+        new_ptr.add(new_size).write(C::default());
+        s.bx.cast::<*mut C>().write(new_ptr);
+    }
+
+    true
+}
+
+unsafe fn reserve<C: Copy>(s: Fields<C>, new_capacity: usize, alloc: *mut CSTL_Alloc) -> bool {
+    unsafe { try_reserve_raw(s.bx, *s.size, &mut *s.res, new_capacity, &mut *alloc).is_ok() }
+}
+
+unsafe fn shrink_to_fit<C>(s: Fields<C>, alloc: *mut CSTL_Alloc) {
+    unsafe {
+        if *s.res <= small_capacity::<C>() {
+            return;
+        }
+
+        let old_ptr = *s.bx.cast::<*mut C>();
+
+        // Become small:
+        if *s.size < small_capacity::<C>() {
+            ptr::copy_nonoverlapping(old_ptr, s.bx, *s.size + 1);
+            deallocate_for_capacity(old_ptr, *s.res, alloc);
+            *s.res = small_capacity::<C>();
+            return;
+        }
+
+        let target_capacity = (*s.size | small_capacity::<C>()).min(max_size::<C>());
+
+        if target_capacity < *s.res {
+            let new_ptr = allocate_for_capacity::<C>(target_capacity, alloc);
+
+            if new_ptr.is_null() {
+                return;
+            }
+
+            ptr::copy_nonoverlapping(old_ptr, new_ptr, *s.size + 1);
+            deallocate_for_capacity(old_ptr, *s.res, alloc);
+
+            s.bx.cast::<*mut C>().write(new_ptr);
+            *s.res = target_capacity;
+        }
+    }
+}
+
+macro_rules! string_functions {
+    (
+        $val:ty, $char:ty,
+        $append_n:ident, $assign_n:ident, $destroy:ident,
+        $reserve:ident, $shrink_to_fit:ident, $swap:ident $(,)?
+    ) => {
+        unsafe fn fields(instance: *mut $val) -> Fields<$char> {
+            unsafe {
+                Fields {
+                    bx: ptr::addr_of_mut!((*instance).bx).cast(),
+                    size: ptr::addr_of_mut!((*instance).size),
+                    res: ptr::addr_of_mut!((*instance).res),
+                }
+            }
+        }
+
+        pub(crate) unsafe fn $append_n(
+            instance: *mut $val,
+            ptr: *const $char,
+            count: usize,
+            alloc: *mut CSTL_Alloc,
+        ) -> bool {
+            unsafe { append_n(fields(instance), ptr, count, alloc) }
+        }
+
+        pub(crate) unsafe fn $assign_n(
+            instance: *mut $val,
+            ptr: *const $char,
+            count: usize,
+            alloc: *mut CSTL_Alloc,
+        ) -> bool {
+            unsafe { assign_n(fields(instance), ptr, count, alloc) }
+        }
+
+        pub(crate) unsafe fn $destroy(instance: *mut $val, alloc: *mut CSTL_Alloc) {
+            unsafe { tidy_deallocate(fields(instance), alloc) }
+        }
+
+        pub(crate) unsafe fn $reserve(
+            instance: *mut $val,
+            new_capacity: usize,
+            alloc: *mut CSTL_Alloc,
+        ) -> bool {
+            unsafe { reserve(fields(instance), new_capacity, alloc) }
+        }
+
+        pub(crate) unsafe fn $shrink_to_fit(instance: *mut $val, alloc: *mut CSTL_Alloc) {
+            unsafe { shrink_to_fit(fields(instance), alloc) }
+        }
+
+        pub(crate) unsafe fn $swap(instance: *mut $val, other_instance: *mut $val) {
+            if instance != other_instance {
+                unsafe { ptr::swap(instance, other_instance) }
+            }
+        }
+    };
+}
+
+mod narrow {
+    use super::*;
+
+    string_functions!(
+        CSTL_StringVal,
+        std::ffi::c_char,
+        CSTL_string_append_n,
+        CSTL_string_assign_n,
+        CSTL_string_destroy,
+        CSTL_string_reserve,
+        CSTL_string_shrink_to_fit,
+        CSTL_string_swap,
+    );
+}
+
+mod wide {
+    use super::*;
+
+    string_functions!(
+        CSTL_WideStringVal,
+        wchar_t,
+        CSTL_wstring_append_n,
+        CSTL_wstring_assign_n,
+        CSTL_wstring_destroy,
+        CSTL_wstring_reserve,
+        CSTL_wstring_shrink_to_fit,
+        CSTL_wstring_swap,
+    );
+}
+
+mod utf8 {
+    use super::*;
+
+    string_functions!(
+        CSTL_UTF8StringVal,
+        char8_t,
+        CSTL_u8string_append_n,
+        CSTL_u8string_assign_n,
+        CSTL_u8string_destroy,
+        CSTL_u8string_reserve,
+        CSTL_u8string_shrink_to_fit,
+        CSTL_u8string_swap,
+    );
+}
+
+mod utf16 {
+    use super::*;
+
+    string_functions!(
+        CSTL_UTF16StringVal,
+        char16_t,
+        CSTL_u16string_append_n,
+        CSTL_u16string_assign_n,
+        CSTL_u16string_destroy,
+        CSTL_u16string_reserve,
+        CSTL_u16string_shrink_to_fit,
+        CSTL_u16string_swap,
+    );
+}
+
+mod utf32 {
+    use super::*;
+
+    string_functions!(
+        CSTL_UTF32StringVal,
+        char32_t,
+        CSTL_u32string_append_n,
+        CSTL_u32string_assign_n,
+        CSTL_u32string_destroy,
+        CSTL_u32string_reserve,
+        CSTL_u32string_shrink_to_fit,
+        CSTL_u32string_swap,
+    );
+}
+
+pub(crate) use narrow::*;
+pub(crate) use utf16::*;
+pub(crate) use utf32::*;
+pub(crate) use utf8::*;
+pub(crate) use wide::*;
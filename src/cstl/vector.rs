@@ -0,0 +1,1003 @@
+//! `vector.c` in Rust.
+
+// Named like the C functions they stand in for:
+#![allow(non_snake_case)]
+
+use std::{
+    alloc::{self, Layout},
+    ffi::c_void,
+    ptr,
+};
+
+use cstl_sys::{
+    CSTL_Alloc, CSTL_CopyTypeCRef, CSTL_DropTypeCRef, CSTL_MoveTypeCRef, CSTL_Type,
+    CSTL_VectorCRef, CSTL_VectorIter, CSTL_VectorRef, CSTL_VectorVal,
+};
+
+/// Alignment packed into a CSTL type handle, see `CSTL_type_alignment`.
+fn type_alignment(type_: CSTL_Type) -> usize {
+    let packed = type_ as isize;
+    (packed & packed.wrapping_neg()) as usize
+}
+
+/// Size packed into a CSTL type handle, see `CSTL_type_size`.
+fn type_size(type_: CSTL_Type) -> usize {
+    let alignment = type_alignment(type_);
+    let packed = type_ as isize;
+
+    if packed < 0 {
+        packed.wrapping_neg() as usize ^ alignment
+    } else {
+        packed as usize
+    }
+}
+
+/// Alignment CSTL derives from the element size where it has no type handle.
+fn size_alignment(type_size: usize) -> usize {
+    type_size & type_size.wrapping_neg()
+}
+
+pub(super) unsafe fn allocate(size: usize, alignment: usize, alloc: *mut CSTL_Alloc) -> *mut u8 {
+    unsafe {
+        let alloc = &*alloc;
+        let aligned_alloc = alloc.aligned_alloc.expect("null allocation function");
+        aligned_alloc(alloc.opaque, size, alignment) as _
+    }
+}
+
+pub(super) unsafe fn free(memory: *mut u8, size: usize, alignment: usize, alloc: *mut CSTL_Alloc) {
+    unsafe {
+        let alloc = &*alloc;
+        let aligned_free = alloc.aligned_free.expect("null deallocation function");
+        aligned_free(alloc.opaque, memory as _, size, alignment)
+    }
+}
+
+unsafe fn drop_range(drop: CSTL_DropTypeCRef, first: *mut u8, last: *mut u8) {
+    if let Some(drop) = unsafe { (*drop).drop } {
+        unsafe { drop(first as _, last as _) }
+    }
+}
+
+unsafe fn move_range(move_: CSTL_MoveTypeCRef, first: *mut u8, last: *mut u8, dest: *mut u8) {
+    unsafe {
+        let move_ = (*move_).move_.expect("null move function");
+        move_(first as _, last as _, dest as _)
+    }
+}
+
+unsafe fn copy_range(copy: CSTL_CopyTypeCRef, first: *const u8, last: *const u8, dest: *mut u8) {
+    unsafe {
+        let copy = (*copy).copy.expect("null copy function");
+        copy(first as _, last as _, dest as _)
+    }
+}
+
+unsafe fn fill_range(copy: CSTL_CopyTypeCRef, first: *mut u8, last: *mut u8, value: *const u8) {
+    unsafe {
+        let fill = (*copy).fill.expect("null fill function");
+        fill(first as _, last as _, value as _)
+    }
+}
+
+/// Copies or moves a range into a temporary buffer, for ranges aliasing the vector.
+///
+/// Unlike CSTL, which uses a stack buffer for small ranges, the buffer is always
+/// allocated with the global allocator, so the vector's allocator isn't involved.
+struct Temp {
+    first: *mut u8,
+    layout: Layout,
+}
+
+impl Temp {
+    fn new(bytes: usize, alignment: usize) -> Self {
+        let layout = Layout::from_size_align(bytes, alignment).expect("bad temporary layout");
+        let first = unsafe { alloc::alloc(layout) };
+
+        if first.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        Self { first, layout }
+    }
+
+    fn last(&self) -> *mut u8 {
+        unsafe { self.first.add(self.layout.size()) }
+    }
+}
+
+impl Drop for Temp {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.first, self.layout) }
+    }
+}
+
+unsafe fn size_bytes(instance: CSTL_VectorCRef) -> usize {
+    unsafe { (*instance).last as usize - (*instance).first as usize }
+}
+
+unsafe fn capacity_bytes(instance: CSTL_VectorCRef) -> usize {
+    unsafe { (*instance).end as usize - (*instance).first as usize }
+}
+
+fn bytes_max(size: usize) -> usize {
+    (isize::MAX as usize - 1) / size * size
+}
+
+fn checked_mul(type_size: usize, n: usize) -> Option<usize> {
+    n.checked_mul(type_size)
+        .filter(|&bytes| bytes < bytes_max(type_size))
+}
+
+unsafe fn growth_bytes(instance: CSTL_VectorCRef, type_size: usize, new_bytes: usize) -> usize {
+    let old_bytes = unsafe { capacity_bytes(instance) };
+    let max_bytes = bytes_max(type_size);
+
+    if old_bytes > max_bytes - old_bytes {
+        return max_bytes;
+    }
+
+    (old_bytes + old_bytes).max(new_bytes)
+}
+
+unsafe fn new_with_bytes(bytes: usize, alignment: usize, alloc: *mut CSTL_Alloc) -> CSTL_VectorVal {
+    let first = unsafe { allocate(bytes, alignment, alloc) };
+    let end = if first.is_null() {
+        ptr::null_mut()
+    } else {
+        unsafe { first.add(bytes) }
+    };
+
+    CSTL_VectorVal {
+        first: first as _,
+        last: first as _,
+        end: end as _,
+    }
+}
+
+unsafe fn tidy(
+    instance: CSTL_VectorRef,
+    alignment: usize,
+    drop: CSTL_DropTypeCRef,
+    alloc: *mut CSTL_Alloc,
+) {
+    unsafe {
+        let val = &mut *instance;
+
+        if !val.first.is_null() {
+            drop_range(drop, val.first as _, val.last as _);
+            free(val.first as _, capacity_bytes(val), alignment, alloc);
+
+            val.first = ptr::null_mut();
+            val.last = ptr::null_mut();
+            val.end = ptr::null_mut();
+        }
+    }
+}
+
+unsafe fn replace(
+    instance: CSTL_VectorRef,
+    alignment: usize,
+    drop: CSTL_DropTypeCRef,
+    alloc: *mut CSTL_Alloc,
+    val: CSTL_VectorVal,
+) {
+    unsafe {
+        tidy(instance, alignment, drop, alloc);
+        *instance = val;
+    }
+}
+
+unsafe fn reallocate_bytes(
+    instance: CSTL_VectorRef,
+    alignment: usize,
+    move_: CSTL_MoveTypeCRef,
+    new_bytes: usize,
+    old_alloc: *mut CSTL_Alloc,
+    new_alloc: *mut CSTL_Alloc,
+) -> bool {
+    unsafe {
+        let mut tmp = new_with_bytes(new_bytes, alignment, new_alloc);
+
+        if tmp.first.is_null() {
+            return false;
+        }
+
+        tmp.last = (tmp.first as *mut u8).add(size_bytes(instance)) as _;
+        move_range(
+            move_,
+            (*instance).first as _,
+            (*instance).last as _,
+            tmp.first as _,
+        );
+
+        replace(instance, alignment, &(*move_).drop_type, old_alloc, tmp);
+    }
+
+    true
+}
+
+/// Moves `[first, last)` to `dest` one element at a time, dropping each destination first.
+unsafe fn sized_move(
+    type_size: usize,
+    move_: CSTL_MoveTypeCRef,
+    mut first: *mut u8,
+    last: *mut u8,
+    mut dest: *mut u8,
+) -> *mut u8 {
+    unsafe {
+        while first < last {
+            let dest_next = dest.add(type_size);
+            drop_range(&(*move_).drop_type, dest, dest_next);
+            let first_next = first.add(type_size);
+            move_range(move_, first, first_next, dest);
+            dest = dest_next;
+            first = first_next;
+        }
+    }
+
+    dest
+}
+
+/// Moves `[first, last)` to end at `dest` one element at a time, starting from the back.
+unsafe fn sized_move_backwards(
+    type_size: usize,
+    move_: CSTL_MoveTypeCRef,
+    first: *mut u8,
+    mut last: *mut u8,
+    mut dest: *mut u8,
+) -> *mut u8 {
+    unsafe {
+        while first < last {
+            let dest_prev = dest;
+            dest = dest.sub(type_size);
+            let last_prev = last;
+            last = last.sub(type_size);
+            drop_range(&(*move_).drop_type, dest, dest_prev);
+            move_range(move_, last, last_prev, dest);
+        }
+    }
+
+    dest
+}
+
+pub(crate) unsafe fn CSTL_vector_destroy(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    drop: CSTL_DropTypeCRef,
+    alloc: *mut CSTL_Alloc,
+) {
+    unsafe { tidy(instance, type_alignment(type_), drop, alloc) }
+}
+
+pub(crate) unsafe fn CSTL_vector_copy_assign(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    copy: CSTL_CopyTypeCRef,
+    other_instance: CSTL_VectorCRef,
+    mut alloc: *mut CSTL_Alloc,
+    other_alloc: *mut CSTL_Alloc,
+    propagate_alloc: bool,
+) -> bool {
+    if ptr::eq(instance, other_instance) {
+        return true;
+    }
+
+    unsafe {
+        if propagate_alloc && alloc != other_alloc {
+            let new_bytes = size_bytes(other_instance);
+
+            if !reallocate_bytes(
+                instance,
+                type_alignment(type_),
+                &(*copy).move_type,
+                new_bytes,
+                alloc,
+                other_alloc,
+            ) {
+                return false;
+            }
+
+            alloc = other_alloc;
+        }
+
+        CSTL_vector_copy_assign_range(
+            instance,
+            type_,
+            copy,
+            (*other_instance).first,
+            (*other_instance).last,
+            alloc,
+        )
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_move_assign(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    move_: CSTL_MoveTypeCRef,
+    other_instance: CSTL_VectorRef,
+    alloc: *mut CSTL_Alloc,
+    other_alloc: *mut CSTL_Alloc,
+    propagate_alloc: bool,
+) -> bool {
+    let alignment = type_alignment(type_);
+
+    if instance == other_instance {
+        return true;
+    }
+
+    unsafe {
+        if !propagate_alloc && alloc != other_alloc {
+            let new_bytes = size_bytes(other_instance);
+
+            if !reallocate_bytes(instance, alignment, move_, new_bytes, alloc, other_alloc) {
+                return false;
+            }
+
+            let val = &mut *instance;
+            drop_range(&(*move_).drop_type, val.first as _, val.last as _);
+            move_range(
+                move_,
+                (*other_instance).first as _,
+                (*other_instance).last as _,
+                val.first as _,
+            );
+
+            val.last = (val.first as *mut u8).add(new_bytes) as _;
+
+            return true;
+        }
+
+        replace(
+            instance,
+            alignment,
+            &(*move_).drop_type,
+            alloc,
+            *other_instance,
+        );
+
+        let other = &mut *other_instance;
+        other.first = ptr::null_mut();
+        other.last = ptr::null_mut();
+        other.end = ptr::null_mut();
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_copy_assign_range(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    copy: CSTL_CopyTypeCRef,
+    range_first: *const c_void,
+    range_last: *const c_void,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    let alignment = type_alignment(type_);
+    let type_size = type_size(type_);
+    let (range_first, range_last) = (range_first as *const u8, range_last as *const u8);
+    let new_bytes = range_last as usize - range_first as usize;
+
+    unsafe {
+        let first = (*instance).first as *mut u8;
+        let last = (*instance).last as *mut u8;
+
+        if new_bytes > capacity_bytes(instance) {
+            let new_capacity = growth_bytes(instance, type_size, new_bytes);
+            let mut tmp = new_with_bytes(new_capacity, alignment, alloc);
+
+            if tmp.first.is_null() {
+                return false;
+            }
+
+            tmp.last = (tmp.first as *mut u8).add(new_bytes) as _;
+            copy_range(copy, range_first, range_last, tmp.first as _);
+
+            replace(
+                instance,
+                alignment,
+                &(*copy).move_type.drop_type,
+                alloc,
+                tmp,
+            );
+
+            return true;
+        }
+
+        let is_aliased = range_last >= first && range_first < last && new_bytes != 0;
+        let temp = is_aliased.then(|| Temp::new(new_bytes, alignment));
+        let (tmp_first, tmp_last) = match &temp {
+            Some(temp) => {
+                copy_range(copy, range_first, range_last, temp.first);
+                (temp.first as *const u8, temp.last() as *const u8)
+            }
+            None => (range_first, range_last),
+        };
+
+        drop_range(&(*copy).move_type.drop_type, first, last);
+        copy_range(copy, tmp_first, tmp_last, first);
+
+        (*instance).last = first.add(new_bytes) as _;
+
+        if let Some(temp) = temp {
+            drop_range(&(*copy).move_type.drop_type, temp.first, temp.last());
+        }
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_move_assign_range(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    move_: CSTL_MoveTypeCRef,
+    range_first: *mut c_void,
+    range_last: *mut c_void,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    let alignment = type_alignment(type_);
+    let type_size = type_size(type_);
+    let (range_first, range_last) = (range_first as *mut u8, range_last as *mut u8);
+    let new_bytes = range_last as usize - range_first as usize;
+
+    unsafe {
+        let first = (*instance).first as *mut u8;
+        let last = (*instance).last as *mut u8;
+
+        if new_bytes > capacity_bytes(instance) {
+            let new_capacity = growth_bytes(instance, type_size, new_bytes);
+            let mut tmp = new_with_bytes(new_capacity, alignment, alloc);
+
+            if tmp.first.is_null() {
+                return false;
+            }
+
+            tmp.last = (tmp.first as *mut u8).add(new_bytes) as _;
+            move_range(move_, range_first, range_last, tmp.first as _);
+
+            replace(instance, alignment, &(*move_).drop_type, alloc, tmp);
+
+            return true;
+        }
+
+        let is_aliased = range_last >= first && range_first < last && new_bytes != 0;
+        let temp = is_aliased.then(|| Temp::new(new_bytes, alignment));
+        let (tmp_first, tmp_last) = match &temp {
+            Some(temp) => {
+                move_range(move_, range_first, range_last, temp.first);
+                (temp.first, temp.last())
+            }
+            None => (range_first, range_last),
+        };
+
+        drop_range(&(*move_).drop_type, first, last);
+        move_range(move_, tmp_first, tmp_last, first);
+
+        (*instance).last = first.add(new_bytes) as _;
+
+        if let Some(temp) = temp {
+            drop_range(&(*move_).drop_type, temp.first, temp.last());
+        }
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_swap(instance: CSTL_VectorRef, other_instance: CSTL_VectorRef) {
+    unsafe { ptr::swap(instance, other_instance) }
+}
+
+pub(crate) unsafe fn CSTL_vector_begin(
+    instance: CSTL_VectorCRef,
+    type_: CSTL_Type,
+) -> CSTL_VectorIter {
+    CSTL_VectorIter {
+        pointer: unsafe { (*instance).first },
+        size: type_size(type_),
+        owner: instance,
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_end(
+    instance: CSTL_VectorCRef,
+    type_: CSTL_Type,
+) -> CSTL_VectorIter {
+    CSTL_VectorIter {
+        pointer: unsafe { (*instance).last },
+        size: type_size(type_),
+        owner: instance,
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_iterator_add(
+    iterator: CSTL_VectorIter,
+    n: isize,
+) -> CSTL_VectorIter {
+    if n == 0 {
+        return iterator;
+    }
+
+    debug_assert!(!iterator.pointer.is_null());
+
+    CSTL_VectorIter {
+        pointer: unsafe { iterator.pointer.byte_offset(n * iterator.size as isize) },
+        ..iterator
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_resize(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    copy: CSTL_CopyTypeCRef,
+    new_size: usize,
+    value: *const c_void,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    let alignment = type_alignment(type_);
+    let type_size = type_size(type_);
+
+    let Some(new_bytes) = checked_mul(type_size, new_size) else {
+        return false;
+    };
+
+    unsafe {
+        let old_bytes = size_bytes(instance);
+
+        if new_bytes == old_bytes {
+            return true;
+        }
+
+        let mut old_last = (*instance).last as *mut u8;
+        let mut new_last = ((*instance).first as *mut u8).wrapping_add(new_bytes);
+
+        if new_bytes > old_bytes {
+            if new_bytes > capacity_bytes(instance) {
+                let new_capacity = growth_bytes(instance, type_size, new_bytes);
+                let tmp = new_with_bytes(new_capacity, alignment, alloc);
+
+                if tmp.first.is_null() {
+                    return false;
+                }
+
+                old_last = (tmp.first as *mut u8).add(old_bytes);
+                new_last = (tmp.first as *mut u8).add(new_bytes);
+                copy_range(
+                    copy,
+                    (*instance).first as _,
+                    (*instance).last as _,
+                    tmp.first as _,
+                );
+
+                replace(
+                    instance,
+                    alignment,
+                    &(*copy).move_type.drop_type,
+                    alloc,
+                    tmp,
+                );
+            }
+
+            fill_range(copy, old_last, new_last, value as _);
+        } else {
+            drop_range(&(*copy).move_type.drop_type, new_last, old_last);
+        }
+
+        (*instance).last = new_last as _;
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_truncate(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    drop: CSTL_DropTypeCRef,
+    new_size: usize,
+) {
+    let Some(new_bytes) = checked_mul(type_size(type_), new_size) else {
+        return;
+    };
+
+    unsafe {
+        if new_bytes >= size_bytes(instance) {
+            return;
+        }
+
+        let old_last = (*instance).last as *mut u8;
+        let new_last = ((*instance).first as *mut u8).add(new_bytes);
+        (*instance).last = new_last as _;
+        drop_range(drop, new_last, old_last);
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_reserve(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    move_: CSTL_MoveTypeCRef,
+    new_capacity: usize,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    let Some(new_bytes) = checked_mul(type_size(type_), new_capacity) else {
+        return false;
+    };
+
+    unsafe {
+        if new_bytes > capacity_bytes(instance) {
+            return reallocate_bytes(
+                instance,
+                type_alignment(type_),
+                move_,
+                new_bytes,
+                alloc,
+                alloc,
+            );
+        }
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_shrink_to_fit(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    move_: CSTL_MoveTypeCRef,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    let alignment = type_alignment(type_);
+
+    unsafe {
+        let val = &*instance;
+
+        if val.last != val.end {
+            if val.first == val.last {
+                tidy(instance, alignment, &(*move_).drop_type, alloc);
+            } else {
+                let new_capacity = size_bytes(instance);
+                return reallocate_bytes(instance, alignment, move_, new_capacity, alloc, alloc);
+            }
+        }
+    }
+
+    true
+}
+
+pub(crate) unsafe fn CSTL_vector_clear(instance: CSTL_VectorRef, drop: CSTL_DropTypeCRef) {
+    unsafe {
+        let val = &mut *instance;
+
+        if val.first == val.last {
+            return;
+        }
+
+        drop_range(drop, val.first as _, val.last as _);
+        val.last = val.first;
+    }
+}
+
+unsafe fn copy_insert_reallocate(
+    instance: CSTL_VectorRef,
+    copy: CSTL_CopyTypeCRef,
+    type_size: usize,
+    where_: *mut u8,
+    value: *const u8,
+    alloc: *mut CSTL_Alloc,
+) -> *mut u8 {
+    let fake_alignment = size_alignment(type_size);
+
+    unsafe {
+        let first = (*instance).first as *mut u8;
+        let last = (*instance).last as *mut u8;
+        let where_bytes = where_ as usize - first as usize;
+        let old_bytes = size_bytes(instance);
+        let new_bytes = growth_bytes(instance, type_size, old_bytes + type_size);
+        let mut tmp = new_with_bytes(new_bytes, fake_alignment, alloc);
+
+        if tmp.first.is_null() {
+            return last;
+        }
+
+        let tmp_first = tmp.first as *mut u8;
+        tmp.last = tmp_first.add(old_bytes + type_size) as _;
+        let constructed_first = tmp_first.add(where_bytes);
+        let constructed_last = constructed_first.add(type_size);
+
+        copy_range(copy, value, value.add(type_size), constructed_first);
+
+        if where_ != last {
+            move_range(&(*copy).move_type, where_, last, constructed_last);
+        }
+
+        move_range(&(*copy).move_type, first, where_, tmp_first);
+        replace(
+            instance,
+            fake_alignment,
+            &(*copy).move_type.drop_type,
+            alloc,
+            tmp,
+        );
+
+        constructed_first
+    }
+}
+
+unsafe fn move_insert_reallocate(
+    instance: CSTL_VectorRef,
+    move_: CSTL_MoveTypeCRef,
+    type_size: usize,
+    where_: *mut u8,
+    value: *mut u8,
+    alloc: *mut CSTL_Alloc,
+) -> *mut u8 {
+    let fake_alignment = size_alignment(type_size);
+
+    unsafe {
+        let first = (*instance).first as *mut u8;
+        let last = (*instance).last as *mut u8;
+        let where_bytes = where_ as usize - first as usize;
+        let old_bytes = size_bytes(instance);
+        let new_bytes = growth_bytes(instance, type_size, old_bytes + type_size);
+        let mut tmp = new_with_bytes(new_bytes, fake_alignment, alloc);
+
+        if tmp.first.is_null() {
+            return last;
+        }
+
+        let tmp_first = tmp.first as *mut u8;
+        tmp.last = tmp_first.add(old_bytes + type_size) as _;
+        let constructed_first = tmp_first.add(where_bytes);
+        let constructed_last = constructed_first.add(type_size);
+
+        move_range(move_, value, value.add(type_size), constructed_first);
+
+        if where_ != last {
+            move_range(move_, where_, last, constructed_last);
+        }
+
+        move_range(move_, first, where_, tmp_first);
+        replace(instance, fake_alignment, &(*move_).drop_type, alloc, tmp);
+
+        constructed_first
+    }
+}
+
+unsafe fn copy_insert(
+    instance: CSTL_VectorRef,
+    copy: CSTL_CopyTypeCRef,
+    mut where_: CSTL_VectorIter,
+    value: *const u8,
+    alloc: *mut CSTL_Alloc,
+) -> CSTL_VectorIter {
+    let type_size = where_.size;
+    let where_pointer = where_.pointer as *mut u8;
+
+    unsafe {
+        let old_last = (*instance).last as *mut u8;
+
+        if size_bytes(instance) >= bytes_max(type_size) {
+            where_.pointer = old_last as _;
+            return where_;
+        }
+
+        if old_last != (*instance).end as *mut u8 {
+            (*instance).last = old_last.add(type_size) as _;
+
+            if where_pointer != old_last {
+                sized_move_backwards(
+                    type_size,
+                    &(*copy).move_type,
+                    where_pointer,
+                    old_last,
+                    old_last,
+                );
+                drop_range(
+                    &(*copy).move_type.drop_type,
+                    where_pointer,
+                    where_pointer.add(type_size),
+                );
+            }
+
+            copy_range(copy, value, value.add(type_size), where_pointer);
+        } else {
+            where_.pointer =
+                copy_insert_reallocate(instance, copy, type_size, where_pointer, value, alloc) as _;
+        }
+    }
+
+    where_
+}
+
+unsafe fn move_insert(
+    instance: CSTL_VectorRef,
+    move_: CSTL_MoveTypeCRef,
+    mut where_: CSTL_VectorIter,
+    value: *mut u8,
+    alloc: *mut CSTL_Alloc,
+) -> CSTL_VectorIter {
+    let type_size = where_.size;
+    let where_pointer = where_.pointer as *mut u8;
+
+    unsafe {
+        let old_last = (*instance).last as *mut u8;
+
+        if size_bytes(instance) >= bytes_max(type_size) {
+            where_.pointer = old_last as _;
+            return where_;
+        }
+
+        if old_last != (*instance).end as *mut u8 {
+            (*instance).last = old_last.add(type_size) as _;
+
+            if where_pointer != old_last {
+                let old_back = old_last.sub(type_size);
+                move_range(move_, old_back, old_last, old_last);
+                sized_move_backwards(type_size, move_, where_pointer, old_back, old_last);
+                drop_range(
+                    &(*move_).drop_type,
+                    where_pointer,
+                    where_pointer.add(type_size),
+                );
+            }
+
+            move_range(move_, value, value.add(type_size), where_pointer);
+        } else {
+            where_.pointer =
+                move_insert_reallocate(instance, move_, type_size, where_pointer, value, alloc)
+                    as _;
+        }
+    }
+
+    where_
+}
+
+pub(crate) unsafe fn CSTL_vector_copy_insert_range(
+    instance: CSTL_VectorRef,
+    copy: CSTL_CopyTypeCRef,
+    mut where_: CSTL_VectorIter,
+    range_first: *const c_void,
+    range_last: *const c_void,
+    alloc: *mut CSTL_Alloc,
+) -> CSTL_VectorIter {
+    if range_first == range_last {
+        return where_;
+    }
+
+    let type_size = where_.size;
+    let alignment = size_alignment(type_size);
+    let (range_first, range_last) = (range_first as *const u8, range_last as *const u8);
+    let new_bytes = range_last as usize - range_first as usize;
+    let mut where_pointer = where_.pointer as *mut u8;
+
+    unsafe {
+        let first = (*instance).first as *mut u8;
+        let old_last = (*instance).last as *mut u8;
+        let where_bytes = where_pointer as usize - first as usize;
+        let unused_bytes = (*instance).end as usize - old_last as usize;
+
+        if new_bytes > unused_bytes {
+            let old_bytes = size_bytes(instance);
+
+            if new_bytes > bytes_max(type_size) - old_bytes {
+                where_.pointer = old_last as _;
+                return where_;
+            }
+
+            let new_capacity = growth_bytes(instance, type_size, old_bytes + new_bytes);
+            let mut tmp = new_with_bytes(new_capacity, alignment, alloc);
+
+            if tmp.first.is_null() {
+                where_.pointer = old_last as _;
+                return where_;
+            }
+
+            let tmp_first = tmp.first as *mut u8;
+            tmp.last = tmp_first.add(old_bytes + new_bytes) as _;
+            let constructed_first = tmp_first.add(where_bytes);
+            let constructed_last = constructed_first.add(new_bytes);
+
+            copy_range(copy, range_first, range_last, constructed_first);
+
+            if where_pointer != old_last {
+                move_range(
+                    &(*copy).move_type,
+                    where_pointer,
+                    old_last,
+                    constructed_last,
+                );
+            }
+
+            move_range(&(*copy).move_type, first, where_pointer, tmp_first);
+            replace(
+                instance,
+                alignment,
+                &(*copy).move_type.drop_type,
+                alloc,
+                tmp,
+            );
+
+            where_pointer = constructed_first;
+        } else {
+            let affected_bytes = old_last as usize - where_pointer as usize;
+
+            if new_bytes > affected_bytes {
+                let new_mid = old_last.add(new_bytes - affected_bytes);
+                move_range(&(*copy).move_type, where_pointer, old_last, new_mid);
+                drop_range(&(*copy).move_type.drop_type, where_pointer, old_last);
+            } else {
+                let new_mid = old_last.sub(new_bytes);
+                move_range(&(*copy).move_type, new_mid, old_last, old_last);
+                sized_move_backwards(
+                    type_size,
+                    &(*copy).move_type,
+                    where_pointer,
+                    new_mid,
+                    old_last,
+                );
+                drop_range(
+                    &(*copy).move_type.drop_type,
+                    where_pointer,
+                    where_pointer.add(new_bytes),
+                );
+            }
+
+            copy_range(copy, range_first, range_last, where_pointer);
+            (*instance).last = old_last.add(new_bytes) as _;
+        }
+    }
+
+    where_.pointer = where_pointer as _;
+    where_
+}
+
+pub(crate) unsafe fn CSTL_vector_copy_push_back(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    copy: CSTL_CopyTypeCRef,
+    value: *const c_void,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    unsafe {
+        let end = CSTL_vector_end(instance, type_);
+        let inserted = copy_insert(instance, copy, end, value as _, alloc);
+        !ptr::eq(inserted.pointer, (*instance).last)
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_move_push_back(
+    instance: CSTL_VectorRef,
+    type_: CSTL_Type,
+    move_: CSTL_MoveTypeCRef,
+    value: *mut c_void,
+    alloc: *mut CSTL_Alloc,
+) -> bool {
+    unsafe {
+        let end = CSTL_vector_end(instance, type_);
+        let inserted = move_insert(instance, move_, end, value as _, alloc);
+        !ptr::eq(inserted.pointer, (*instance).last)
+    }
+}
+
+pub(crate) unsafe fn CSTL_vector_erase(
+    instance: CSTL_VectorRef,
+    move_: CSTL_MoveTypeCRef,
+    where_: CSTL_VectorIter,
+) -> CSTL_VectorIter {
+    unsafe {
+        let last = CSTL_vector_iterator_add(where_, 1);
+
+        if where_.pointer != last.pointer {
+            let new_last = sized_move(
+                last.size,
+                move_,
+                last.pointer as _,
+                (*instance).last as _,
+                where_.pointer as _,
+            );
+
+            drop_range(&(*move_).drop_type, new_last, (*instance).last as _);
+            (*instance).last = new_last as _;
+        }
+    }
+
+    where_
+}
@@ -0,0 +1,123 @@
+//! Integration with the [cxx](https://cxx.rs) crate.
+//!
+//! The strings and vectors of [`abi`](crate::abi) with the default allocator implement
+//! [`ExternType`], so they can be passed by value in `#[cxx::bridge]` signatures:
+//!
+//! ```ignore
+//! #[cxx::bridge]
+//! mod ffi {
+//!     #[namespace = "std"]
+//!     unsafe extern "C++" {
+//!         include!("cxx_stl.h");
+//!
+//!         type u16string = cxx_stl::abi::CxxUtf16String;
+//!     }
+//!
+//!     #[namespace = "cxx_stl"]
+//!     unsafe extern "C++" {
+//!         type vector_i32 = cxx_stl::abi::CxxVec<i32>;
+//!     }
+//! }
+//! ```
+//!
+//! A type id can't name a template instantiation, so vectors are identified by the
+//! `cxx_stl::vector_*` aliases declared in [`HEADER`], one per primitive element type
+//! with the names cxx gives them. The header also opts the containers out of cxx's
+//! relocatability check, as MSVC containers can be moved like Rust values.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ::cxx::{kind::Trivial, type_id, ExternType};
+
+use crate::abi::CxxVec;
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+use crate::abi::{CxxNarrowString, CxxUtf16String, CxxUtf32String, CxxUtf8String, CxxWideString};
+
+/// C++ header declaring the types the containers are bridged to.
+pub const HEADER: &str = r#"#pragma once
+#include <cstddef>
+#include <cstdint>
+#include <string>
+#include <type_traits>
+#include <vector>
+
+#include "rust/cxx.h"
+
+namespace cxx_stl {
+using vector_u8 = std::vector<std::uint8_t>;
+using vector_u16 = std::vector<std::uint16_t>;
+using vector_u32 = std::vector<std::uint32_t>;
+using vector_u64 = std::vector<std::uint64_t>;
+using vector_usize = std::vector<std::size_t>;
+using vector_i8 = std::vector<std::int8_t>;
+using vector_i16 = std::vector<std::int16_t>;
+using vector_i32 = std::vector<std::int32_t>;
+using vector_i64 = std::vector<std::int64_t>;
+using vector_isize = std::vector<std::ptrdiff_t>;
+using vector_f32 = std::vector<float>;
+using vector_f64 = std::vector<double>;
+} // namespace cxx_stl
+
+template <typename T>
+struct rust::IsRelocatable<std::vector<T>> : std::true_type {};
+template <>
+struct rust::IsRelocatable<std::string> : std::true_type {};
+template <>
+struct rust::IsRelocatable<std::wstring> : std::true_type {};
+#ifdef __cpp_char8_t
+template <>
+struct rust::IsRelocatable<std::u8string> : std::true_type {};
+#endif
+template <>
+struct rust::IsRelocatable<std::u16string> : std::true_type {};
+template <>
+struct rust::IsRelocatable<std::u32string> : std::true_type {};
+"#;
+
+/// Writes [`HEADER`] to `cxx_stl.h` in `dir`, returning the path of the file.
+///
+/// `dir` must be in the include path of the bridge, e.g. [`cxx_build`'s](https://docs.rs/cxx-build)
+/// `CFG.exported_header_dirs`.
+pub fn write_header(dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let path = dir.as_ref().join("cxx_stl.h");
+    fs::write(&path, HEADER)?;
+    Ok(path)
+}
+
+macro_rules! extern_type {
+    ($($ty:ty => $id:literal),* $(,)?) => {
+        $(
+            unsafe impl ExternType for $ty {
+                type Id = type_id!($id);
+                type Kind = Trivial;
+            }
+        )*
+    };
+}
+
+#[cfg(not(any(feature = "gnu-abi", feature = "libcxx-abi")))]
+extern_type! {
+    CxxNarrowString => "std::string",
+    CxxWideString => "std::wstring",
+    CxxUtf8String => "std::u8string",
+    CxxUtf16String => "std::u16string",
+    CxxUtf32String => "std::u32string",
+}
+
+extern_type! {
+    CxxVec<u8> => "cxx_stl::vector_u8",
+    CxxVec<u16> => "cxx_stl::vector_u16",
+    CxxVec<u32> => "cxx_stl::vector_u32",
+    CxxVec<u64> => "cxx_stl::vector_u64",
+    CxxVec<usize> => "cxx_stl::vector_usize",
+    CxxVec<i8> => "cxx_stl::vector_i8",
+    CxxVec<i16> => "cxx_stl::vector_i16",
+    CxxVec<i32> => "cxx_stl::vector_i32",
+    CxxVec<i64> => "cxx_stl::vector_i64",
+    CxxVec<isize> => "cxx_stl::vector_isize",
+    CxxVec<f32> => "cxx_stl::vector_f32",
+    CxxVec<f64> => "cxx_stl::vector_f64",
+}
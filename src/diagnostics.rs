@@ -0,0 +1,253 @@
+//! Diagnostics for the internals of containers, e.g. ones that look corrupted.
+//!
+//! [`dump`] describes the mode, pointers, length and capacity of a container, and
+//! the invariants it violates. Checks never dereference the pointers of a container,
+//! so they are safe to run on corrupted ones.
+//!
+//! [`MemoryFootprint`] reports the heap bytes owned by a container, e.g. to budget
+//! and log the memory a mod contributes to a game process.
+
+use std::{ffi::c_void, fmt, mem};
+
+use crate::alloc::WithCxxProxy;
+
+/// Containers that can describe their internals, see [`dump`].
+pub trait Diagnose {
+    fn report(&self) -> ContainerReport;
+}
+
+/// Describes the internals of `container`.
+pub fn dump<C: Diagnose + ?Sized>(container: &C) -> ContainerReport {
+    container.report()
+}
+
+/// Containers that can report the heap memory they own.
+pub trait MemoryFootprint {
+    /// Bytes currently allocated by the container, including the overhead of the ABI,
+    /// such as the `std::_Container_proxy` of containers with iterator debugging.
+    ///
+    /// Counts the requested sizes of allocations, not the bookkeeping of the allocator.
+    fn allocated_bytes(&self) -> usize;
+}
+
+/// Mode of a string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StringMode {
+    /// The characters are stored in the string itself.
+    Small,
+    /// The characters are stored in an allocation.
+    Large,
+}
+
+/// Invariant of a container that doesn't hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InvariantViolation {
+    /// Some, but not all, of the pointers of a vector are null.
+    PartiallyNull,
+    /// The pointers of a vector aren't in order.
+    Misordered,
+    /// A pointer isn't aligned for the elements.
+    Misaligned { pointer: &'static str, align: usize },
+    /// The storage of a vector doesn't hold a whole number of elements.
+    PartialElement,
+    /// The length of a string exceeds its capacity.
+    LengthExceedsCapacity,
+    /// The capacity of a string is below the capacity of the small string buffer.
+    CapacityBelowSmall,
+    /// A string in large mode has a null pointer to its characters.
+    NullCharacters,
+    /// A string in small mode isn't null-terminated.
+    MissingTerminator,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PartiallyNull => f.write_str("only some of the pointers are null"),
+            Self::Misordered => f.write_str("pointers are out of order"),
+            Self::Misaligned { pointer, align } => {
+                write!(f, "`{pointer}` isn't aligned to {align}")
+            }
+            Self::PartialElement => f.write_str("storage holds a partial element"),
+            Self::LengthExceedsCapacity => f.write_str("length exceeds capacity"),
+            Self::CapacityBelowSmall => f.write_str("capacity is below the small capacity"),
+            Self::NullCharacters => f.write_str("large mode pointer is null"),
+            Self::MissingTerminator => f.write_str("small mode buffer isn't null-terminated"),
+        }
+    }
+}
+
+/// Description of the internals of a container, see [`dump`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerReport {
+    /// Name of the container type.
+    pub container: &'static str,
+    /// Mode of a string, `None` for other containers.
+    pub mode: Option<StringMode>,
+    /// Names and addresses of the pointers in the container.
+    pub pointers: Vec<(&'static str, usize)>,
+    /// Length in elements, zero if it can't be determined.
+    pub length: usize,
+    /// Capacity in elements, zero if it can't be determined.
+    pub capacity: usize,
+    pub violations: Vec<InvariantViolation>,
+}
+
+impl ContainerReport {
+    /// Whether the container upholds all checked invariants.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ContainerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.container)?;
+
+        if let Some(mode) = self.mode {
+            write!(f, " ({mode:?} mode)")?;
+        }
+
+        writeln!(f, ": length {}, capacity {}", self.length, self.capacity)?;
+
+        for (name, address) in &self.pointers {
+            writeln!(f, "  {name} = {address:#x}")?;
+        }
+
+        if self.violations.is_empty() {
+            writeln!(f, "  no invariant violations")
+        } else {
+            self.violations
+                .iter()
+                .try_for_each(|violation| writeln!(f, "  violation: {violation}"))
+        }
+    }
+}
+
+/// Reports on a vector of `T` with the pointers `first`, `last` and `end`.
+pub(crate) fn vector_report<T>(
+    container: &'static str,
+    first: *const c_void,
+    last: *const c_void,
+    end: *const c_void,
+) -> ContainerReport {
+    let pointers = vec![
+        ("first", first as usize),
+        ("last", last as usize),
+        ("end", end as usize),
+    ];
+
+    let mut violations = Vec::new();
+    let (size, align) = (mem::size_of::<T>(), mem::align_of::<T>());
+
+    if first.is_null() != last.is_null() || first.is_null() != end.is_null() {
+        violations.push(InvariantViolation::PartiallyNull);
+    }
+
+    let ordered = first <= last && last <= end;
+
+    if !ordered {
+        violations.push(InvariantViolation::Misordered);
+    }
+
+    for &(pointer, address) in &pointers {
+        if !address.is_multiple_of(align) {
+            violations.push(InvariantViolation::Misaligned { pointer, align });
+        }
+    }
+
+    let (bytes, capacity_bytes) = if ordered {
+        (
+            last as usize - first as usize,
+            end as usize - first as usize,
+        )
+    } else {
+        (0, 0)
+    };
+
+    if size != 0 && !(bytes.is_multiple_of(size) && capacity_bytes.is_multiple_of(size)) {
+        violations.push(InvariantViolation::PartialElement);
+    }
+
+    ContainerReport {
+        container,
+        mode: None,
+        pointers,
+        length: bytes.checked_div(size).unwrap_or(0),
+        capacity: capacity_bytes.checked_div(size).unwrap_or(0),
+        violations,
+    }
+}
+
+/// Reports on a string of `C` characters with the small string buffer `buf`,
+/// which is also the pointer `ptr` in large mode.
+pub(crate) fn string_report<C: Copy + Default + PartialEq>(
+    container: &'static str,
+    buf: &[C],
+    ptr: *const C,
+    size: usize,
+    res: usize,
+) -> ContainerReport {
+    let small_capacity = buf.len() - 1;
+    let mut violations = Vec::new();
+
+    if res < small_capacity {
+        violations.push(InvariantViolation::CapacityBelowSmall);
+    }
+
+    if size > res {
+        violations.push(InvariantViolation::LengthExceedsCapacity);
+    }
+
+    let (mode, pointers) = if res > small_capacity {
+        let align = mem::align_of::<C>();
+
+        if ptr.is_null() {
+            violations.push(InvariantViolation::NullCharacters);
+        } else if !ptr.is_aligned() {
+            violations.push(InvariantViolation::Misaligned {
+                pointer: "ptr",
+                align,
+            });
+        }
+
+        (StringMode::Large, vec![("ptr", ptr as usize)])
+    } else {
+        if buf.get(size).is_some_and(|&c| c != C::default()) {
+            violations.push(InvariantViolation::MissingTerminator);
+        }
+
+        (StringMode::Small, Vec::new())
+    };
+
+    ContainerReport {
+        container,
+        mode: Some(mode),
+        pointers,
+        length: size,
+        capacity: res,
+        violations,
+    }
+}
+
+#[cfg(feature = "iterator-debug")]
+const PROXY_SIZE: usize = mem::size_of::<crate::iterator_debug::ContainerProxy>();
+// Only layouts from `iterator_debug` modules have a proxy:
+#[cfg(not(feature = "iterator-debug"))]
+const PROXY_SIZE: usize = 0;
+
+/// Bytes allocated for the `std::_Container_proxy` of the layout `L`, if any.
+pub(crate) fn proxy_bytes<L: WithCxxProxy>() -> usize {
+    L::PROXY_OFFSET.map_or(0, |_| PROXY_SIZE)
+}
+
+/// Bytes allocated for the characters of a string of `C` characters
+/// with the small string buffer `buf` and the capacity `res`.
+pub(crate) fn string_bytes<C>(buf: &[C], res: usize) -> usize {
+    if res > buf.len() - 1 {
+        (res + 1) * mem::size_of::<C>()
+    } else {
+        0
+    }
+}
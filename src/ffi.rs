@@ -0,0 +1,410 @@
+//! Passing containers and their elements to and from C++ functions in the MSVC x64 ABI.
+//!
+//! MSVC passes arguments other than those of 1, 2, 4 or 8 bytes, which includes all
+//! containers, as a pointer to a temporary copy made by the caller and destroyed by
+//! the callee. Classes with constructors are always returned through a pointer to
+//! uninitialized storage provided by the caller, which comes first among the
+//! arguments of free functions, and right after `this` for member functions.
+//!
+//! [`PassByValue`] and [`ReturnSlot`] declare these pointers in the signatures of
+//! raw function pointers, and [`pass_by_value`] and [`return_by_value`] manage the
+//! storage behind them:
+//!
+//! ```ignore
+//! type SetName = unsafe extern "C" fn(this: *mut Player, name: PassByValue<CxxUtf16String>);
+//! type GetName = unsafe extern "C" fn(
+//!     this: *mut Player,
+//!     ret: ReturnSlot<CxxUtf16String>,
+//! ) -> *mut CxxUtf16String;
+//!
+//! pass_by_value(name, |name| unsafe { set_name(player, name) });
+//! let name = unsafe { return_by_value(|ret| get_name(player, ret)) };
+//! ```
+//!
+//! [`ByRef`] and [`ByMut`] stand for `const T&` and `T&` parameters,
+//! e.g. `const std::u16string&` in hook signatures:
+//!
+//! ```ignore
+//! unsafe extern "C" fn on_chat(this: *mut Chat, message: ByRef<CxxUtf16String>) {
+//!     println!("{:?}", String::from_utf16_lossy(&message));
+//! }
+//! ```
+//!
+//! [`with_iter_range`] and [`iter_range`] pass elements to functions taking
+//! a range of contiguous iterators, which are plain pointers in release builds,
+//! as two separate `first` and `last` arguments:
+//!
+//! ```ignore
+//! type AddItems = unsafe extern "C" fn(this: *mut Inventory, first: *const Item, last: *const Item);
+//!
+//! with_iter_range(items.filter(Item::is_stackable), |first, last| unsafe {
+//!     add_items(inventory, first, last)
+//! });
+//! ```
+//!
+//! With the `seh` feature on MSVC targets, `shield` catches the C++ exceptions
+//! and SEH exceptions of the C++ functions called in a closure.
+//!
+//! [`CxxIter`] is a single iterator of a vector or string passed by value,
+//! as returned by their `begin`, `end` and `iter_at` methods.
+//!
+//! [`MaybeOwned`] holds either a container owned by C++ or one created in Rust,
+//! e.g. for hooks that only sometimes replace the container they pass on.
+//!
+//! [`Pinned`] keeps a container in place while C++ code holds raw pointers
+//! into its storage, and checks in debug builds that it isn't reallocated meanwhile.
+
+use std::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use crate::vec::CxxVec;
+
+mod pinned;
+pub use pinned::{Pinned, RawStorage};
+
+#[cfg(all(feature = "seh", target_env = "msvc"))]
+mod shield;
+#[cfg(all(feature = "seh", target_env = "msvc"))]
+pub use shield::{shield, ForeignException, CXX_EXCEPTION_CODE};
+
+/// Whether MSVC passes arguments of type `T` by value as a pointer to a copy on x64.
+pub const fn is_passed_by_pointer<T>() -> bool {
+    !matches!(mem::size_of::<T>(), 1 | 2 | 4 | 8)
+}
+
+/// Argument of type `T` passed by value, as a pointer to a temporary
+/// which the callee takes ownership of and destroys.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct PassByValue<'a, T> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T> PassByValue<'_, T> {
+    /// Returns the pointer to the temporary.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+/// Storage for a return value of type `T`, passed as a pointer
+/// to uninitialized memory which the callee constructs the value in.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct ReturnSlot<'a, T> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<&'a mut MaybeUninit<T>>,
+}
+
+impl<T> ReturnSlot<'_, T> {
+    /// Returns the pointer to the storage.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+/// Moves `value` into a temporary and calls `f` with a [`PassByValue`] to it.
+///
+/// `value` is never dropped by Rust, as `f` must pass it to a C++ function
+/// taking it by value, which destroys it. It is leaked if `f` doesn't.
+pub fn pass_by_value<T, R>(value: T, f: impl FnOnce(PassByValue<'_, T>) -> R) -> R {
+    let mut value = ManuallyDrop::new(value);
+
+    f(PassByValue {
+        ptr: NonNull::from(&mut *value),
+        _marker: PhantomData,
+    })
+}
+
+/// Calls `f` with a [`ReturnSlot`] and returns the value constructed in it.
+///
+/// `f` returns the pointer the callee returns, which is ignored.
+///
+/// # Safety
+///
+/// `f` must pass the slot to a C++ function returning a `T`,
+/// which constructs a valid value of it in the slot.
+pub unsafe fn return_by_value<T, P>(f: impl FnOnce(ReturnSlot<'_, T>) -> P) -> T {
+    let mut slot = MaybeUninit::<T>::uninit();
+
+    f(ReturnSlot {
+        ptr: NonNull::from(&mut slot).cast(),
+        _marker: PhantomData,
+    });
+
+    unsafe { slot.assume_init() }
+}
+
+/// C++ `const T&` parameter, dereferencing to the referenced `T`.
+///
+/// In debug builds, dereferencing asserts that the reference isn't null and is aligned.
+#[repr(transparent)]
+pub struct ByRef<'a, T> {
+    ptr: *const T,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<T> ByRef<'_, T> {
+    /// Returns the pointer the reference is passed as.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+}
+
+impl<T> Clone for ByRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ByRef<'_, T> {}
+
+impl<T> Deref for ByRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        debug_assert_reference(self.ptr);
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<'a, T> From<&'a T> for ByRef<'a, T> {
+    fn from(value: &'a T) -> Self {
+        Self {
+            ptr: value,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ByRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// C++ `T&` parameter, dereferencing to the referenced `T`.
+///
+/// In debug builds, dereferencing asserts that the reference isn't null and is aligned.
+#[repr(transparent)]
+pub struct ByMut<'a, T> {
+    ptr: *mut T,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T> ByMut<'_, T> {
+    /// Returns the pointer the reference is passed as.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> Deref for ByMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        debug_assert_reference(self.ptr);
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> DerefMut for ByMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        debug_assert_reference(self.ptr);
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<'a, T> From<&'a mut T> for ByMut<'a, T> {
+    fn from(value: &'a mut T) -> Self {
+        Self {
+            ptr: value,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ByMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[inline]
+#[track_caller]
+fn debug_assert_reference<T>(ptr: *const T) {
+    debug_assert!(!ptr.is_null(), "C++ reference is null");
+    debug_assert!(ptr.is_aligned(), "C++ reference (is {ptr:p}) is misaligned");
+}
+
+/// C++ iterator into a vector or string, e.g. `std::_Vector_iterator` or
+/// `std::_String_iterator` without iterator debugging, which only hold a pointer
+/// to the element.
+///
+/// Iterators are 8 bytes, so MSVC passes them by value in a register, and C++ functions
+/// taking one can be declared to take a `CxxIter<T>`. They borrow the container
+/// immutably, so C++ code must not modify it through them, declare functions that
+/// do to take `*mut T` instead, see [`iter_range_mut`]. With the `iterator-debug` feature,
+/// `iterator_debug::CheckedIter` stands for the iterators of code built with
+/// an `_ITERATOR_DEBUG_LEVEL` of 1 or 2.
+#[repr(transparent)]
+pub struct CxxIter<'a, T> {
+    ptr: *mut T,
+    _marker: PhantomData<&'a [T]>,
+}
+
+impl<T> CxxIter<'_, T> {
+    /// Creates an iterator to `ptr`, null for iterators of an empty container.
+    pub(crate) fn new(ptr: *mut T) -> Self {
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the pointer to the element the iterator points to.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> Clone for CxxIter<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CxxIter<'_, T> {}
+
+// The iterator borrows the container immutably, like `&[T]`:
+unsafe impl<T: Sync> Send for CxxIter<'_, T> {}
+
+unsafe impl<T: Sync> Sync for CxxIter<'_, T> {}
+
+impl<T> fmt::Debug for CxxIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CxxIter").field(&self.ptr).finish()
+    }
+}
+
+/// Returns the `[first, last)` iterator range of the elements of `slice`.
+pub fn iter_range<T>(slice: &[T]) -> (*const T, *const T) {
+    let range = slice.as_ptr_range();
+    (range.start, range.end)
+}
+
+/// Returns the `[first, last)` iterator range of the elements of `slice`,
+/// which C++ code may modify through it.
+pub fn iter_range_mut<T>(slice: &mut [T]) -> (*mut T, *mut T) {
+    let range = slice.as_mut_ptr_range();
+    (range.start, range.end)
+}
+
+/// Collects `iter` into a temporary vector and calls `f` with the
+/// `[first, last)` iterator range of its elements.
+///
+/// The elements are dropped once `f` returns, so C++ code must not keep the iterators.
+pub fn with_iter_range<I, R>(iter: I, f: impl FnOnce(*mut I::Item, *mut I::Item) -> R) -> R
+where
+    I: IntoIterator,
+{
+    let mut temp = CxxVec::new();
+    temp.extend(iter);
+
+    let (first, last) = iter_range_mut(&mut temp);
+    f(first, last)
+}
+
+/// Container borrowed from C++ code, or owned by Rust.
+///
+/// Only an owned container is dropped with the wrapper.
+pub enum MaybeOwned<'a, C> {
+    Borrowed(&'a mut C),
+    Owned(C),
+}
+
+impl<'a, C> MaybeOwned<'a, C> {
+    /// Borrows the container at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid container, which isn't accessed
+    /// through other pointers for `'a`.
+    pub unsafe fn from_raw(ptr: *mut C) -> Self {
+        Self::Borrowed(unsafe { &mut *ptr })
+    }
+
+    pub fn is_owned(&self) -> bool {
+        matches!(self, Self::Owned(_))
+    }
+
+    /// Returns the pointer to the container, to pass it on to C++ code.
+    pub fn as_mut_ptr(&mut self) -> *mut C {
+        &mut **self
+    }
+
+    /// Replaces the container with an owned `container`,
+    /// dropping the previous one if it was owned.
+    pub fn set_owned(&mut self, container: C) -> &mut C {
+        *self = Self::Owned(container);
+        self
+    }
+
+    /// Returns the owned container, cloning a borrowed one.
+    pub fn into_owned(self) -> C
+    where
+        C: Clone,
+    {
+        match self {
+            Self::Borrowed(borrowed) => borrowed.clone(),
+            Self::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<C> Deref for MaybeOwned<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Borrowed(borrowed) => borrowed,
+            Self::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<C> DerefMut for MaybeOwned<'_, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Borrowed(borrowed) => borrowed,
+            Self::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<'a, C> From<&'a mut C> for MaybeOwned<'a, C> {
+    fn from(borrowed: &'a mut C) -> Self {
+        Self::Borrowed(borrowed)
+    }
+}
+
+impl<C> From<C> for MaybeOwned<'_, C> {
+    fn from(owned: C) -> Self {
+        Self::Owned(owned)
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for MaybeOwned<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Borrowed(borrowed) => f.debug_tuple("Borrowed").field(borrowed).finish(),
+            Self::Owned(owned) => f.debug_tuple("Owned").field(owned).finish(),
+        }
+    }
+}
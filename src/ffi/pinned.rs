@@ -0,0 +1,215 @@
+use std::{
+    cell::Cell, fmt, marker::PhantomPinned, mem::ManuallyDrop, ops::Deref, pin::Pin, ptr, thread,
+};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::Semantics,
+    string::{
+        libcxx::CxxBasicString, narrow::RawString, utf16::RawUtf16String, utf32::RawUtf32String,
+        utf8::RawUtf8String, wide::RawWideString, CharUnit,
+    },
+    vec::{CxxVecDyn, CxxVecLayout, RawVec},
+};
+
+/// Containers with contiguous storage that C++ code can keep raw pointers into,
+/// like the results of `data()` and `c_str()`.
+pub trait RawStorage {
+    type Elem;
+
+    /// Returns the pointer to the storage, which is dangling if nothing is allocated.
+    fn storage_ptr(&self) -> *const Self::Elem;
+
+    /// Capacity of the storage in elements.
+    fn storage_capacity(&self) -> usize;
+}
+
+/// Container that stays in place while C++ code holds raw pointers into its storage.
+///
+/// Handles to the storage are only given out through a pinned reference, so the
+/// container doesn't move, which would move the characters of small strings.
+/// In debug builds, [`Pinned::modify`] panics if the storage is reallocated while
+/// handles are outstanding, as does dropping the container.
+pub struct Pinned<C: RawStorage> {
+    container: C,
+    handles: Cell<usize>,
+    _pin: PhantomPinned,
+}
+
+impl<C: RawStorage> Pinned<C> {
+    pub const fn new(container: C) -> Self {
+        Self {
+            container,
+            handles: Cell::new(0),
+            _pin: PhantomPinned,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        // Handles are only given out once pinned, so there are none:
+        let this = ManuallyDrop::new(self);
+        unsafe { ptr::read(&this.container) }
+    }
+
+    /// Returns the pointer to the storage for C++ code to keep,
+    /// counting it as an outstanding handle until [`Pinned::release_handle`].
+    pub fn as_pinned_handle(self: Pin<&Self>) -> *const C::Elem {
+        self.handles.set(self.handles.get() + 1);
+        self.container.storage_ptr()
+    }
+
+    /// Releases a handle returned by [`Pinned::as_pinned_handle`]
+    /// once C++ code no longer uses it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no outstanding handles.
+    pub fn release_handle(self: Pin<&Self>) {
+        let handles = self.handles.get();
+
+        assert!(
+            handles > 0,
+            "released a handle of a container without handles"
+        );
+        self.handles.set(handles - 1);
+    }
+
+    /// Releases all handles, e.g. once the C++ object keeping them is destroyed.
+    pub fn release_all(self: Pin<&Self>) {
+        self.handles.set(0);
+    }
+
+    /// Number of outstanding handles.
+    pub fn handles(&self) -> usize {
+        self.handles.get()
+    }
+
+    /// Calls `f` with the container, which it may mutate as long as the storage
+    /// isn't reallocated while handles are outstanding.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `f` reallocates the storage
+    /// while there are outstanding handles.
+    pub fn modify<R>(self: Pin<&mut Self>, f: impl FnOnce(&mut C) -> R) -> R {
+        // The container itself is not structurally pinned, only its storage is checked:
+        let this = unsafe { self.get_unchecked_mut() };
+        let before = (
+            this.container.storage_ptr(),
+            this.container.storage_capacity(),
+        );
+
+        let result = f(&mut this.container);
+
+        if cfg!(debug_assertions) && this.handles.get() > 0 {
+            let after = (
+                this.container.storage_ptr(),
+                this.container.storage_capacity(),
+            );
+
+            assert!(
+                before == after,
+                "reallocated the storage of a container with {} outstanding handles",
+                this.handles.get()
+            );
+        }
+
+        result
+    }
+}
+
+impl<C: RawStorage> Deref for Pinned<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.container
+    }
+}
+
+impl<C: RawStorage> Drop for Pinned<C> {
+    fn drop(&mut self) {
+        let handles = self.handles.get();
+
+        if cfg!(debug_assertions) && handles > 0 && !thread::panicking() {
+            panic!("dropped a container with {handles} outstanding handles");
+        }
+    }
+}
+
+impl<C: RawStorage + fmt::Debug> fmt::Debug for Pinned<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pinned")
+            .field("container", &self.container)
+            .field("handles", &self.handles.get())
+            .finish()
+    }
+}
+
+impl<T, A, L, S> RawStorage for CxxVecLayout<T, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    type Elem = T;
+
+    fn storage_ptr(&self) -> *const T {
+        self.as_ptr()
+    }
+
+    fn storage_capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<A: CxxProxy> RawStorage for CxxVecDyn<A> {
+    type Elem = u8;
+
+    fn storage_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn storage_capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> RawStorage for CxxBasicString<C, A> {
+    type Elem = C;
+
+    fn storage_ptr(&self) -> *const C {
+        self.as_ptr()
+    }
+
+    fn storage_capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+macro_rules! string_storage {
+    ($($module:ident::$string:ident($raw:ty, $char:ty)),* $(,)?) => {$(
+        impl<A, L> RawStorage for crate::string::$module::$string<A, L>
+        where
+            A: CxxProxy,
+            L: WithCxxProxy<Alloc = A, Value = $raw>,
+        {
+            type Elem = $char;
+
+            fn storage_ptr(&self) -> *const $char {
+                self.as_ptr()
+            }
+
+            fn storage_capacity(&self) -> usize {
+                self.capacity()
+            }
+        }
+    )*};
+}
+
+string_storage!(
+    narrow::CxxNarrowStringLayout(RawString, u8),
+    wide::CxxWideStringLayout(RawWideString, u16),
+    utf8::CxxUtf8StringLayout(RawUtf8String, u8),
+    utf16::CxxUtf16StringLayout(RawUtf16String, u16),
+    utf32::CxxUtf32StringLayout(RawUtf32String, u32),
+);
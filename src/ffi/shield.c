@@ -0,0 +1,25 @@
+#include <windows.h>
+
+typedef void (*cxx_stl_shield_fn)(void *data);
+typedef int (*cxx_stl_is_panicking_fn)(void);
+
+static int filter(EXCEPTION_POINTERS *pointers, cxx_stl_is_panicking_fn is_panicking,
+                  DWORD *code, ULONG_PTR *address) {
+    // Rust panics are C++ exceptions and keep unwinding to their `catch_unwind`.
+    if (is_panicking())
+        return EXCEPTION_CONTINUE_SEARCH;
+
+    *code = pointers->ExceptionRecord->ExceptionCode;
+    *address = (ULONG_PTR)pointers->ExceptionRecord->ExceptionAddress;
+    return EXCEPTION_EXECUTE_HANDLER;
+}
+
+int cxx_stl_shield(cxx_stl_shield_fn f, void *data, cxx_stl_is_panicking_fn is_panicking,
+                   DWORD *code, ULONG_PTR *address) {
+    __try {
+        f(data);
+        return 0;
+    } __except (filter(GetExceptionInformation(), is_panicking, code, address)) {
+        return 1;
+    }
+}
@@ -0,0 +1,100 @@
+//! Catching exceptions thrown by C++ code, with a C shim using `__try`/`__except`.
+
+use std::{error::Error, ffi::c_void, fmt};
+
+/// SEH exception code of C++ exceptions thrown by MSVC.
+pub const CXX_EXCEPTION_CODE: u32 = 0xE06D7363;
+
+/// C++ exception or SEH exception caught by [`shield`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ForeignException {
+    code: u32,
+    address: usize,
+}
+
+impl ForeignException {
+    /// Returns the SEH exception code, e.g. `0xC0000005` for an access violation.
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    /// Returns the address the exception was raised at.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Whether the exception is a C++ exception.
+    pub fn is_cxx(&self) -> bool {
+        self.code == CXX_EXCEPTION_CODE
+    }
+}
+
+impl fmt::Display for ForeignException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_cxx() {
+            write!(f, "C++ exception thrown at {:#x}", self.address)
+        } else {
+            write!(
+                f,
+                "SEH exception {:#010x} raised at {:#x}",
+                self.code, self.address
+            )
+        }
+    }
+}
+
+impl Error for ForeignException {}
+
+extern "C-unwind" {
+    fn cxx_stl_shield(
+        f: unsafe extern "C-unwind" fn(*mut c_void),
+        data: *mut c_void,
+        is_panicking: extern "C" fn() -> i32,
+        code: *mut u32,
+        address: *mut usize,
+    ) -> i32;
+}
+
+/// Calls `f`, returning the C++ exception or SEH exception that escapes it as an error.
+///
+/// The functions `f` calls that may throw must be declared `extern "C-unwind"`,
+/// so that the Rust frames in between are unwound and drop their values.
+/// Panics in `f` aren't caught, and propagate to the caller. While the thread
+/// is panicking, which MSVC can't tell apart from a C++ exception, nothing is caught.
+///
+/// The frames in between are unwound, destroying their C++ objects, but a caught
+/// C++ exception object is leaked. Code faulting, e.g. with an access violation,
+/// may have left its objects and containers half-updated, so they are best
+/// left alone afterwards.
+pub fn shield<F: FnOnce() -> R, R>(f: F) -> Result<R, ForeignException> {
+    unsafe extern "C-unwind" fn call<F: FnOnce() -> R, R>(data: *mut c_void) {
+        let (f, result) = unsafe { &mut *data.cast::<(Option<F>, Option<R>)>() };
+        *result = f.take().map(|f| f());
+    }
+
+    extern "C" fn is_panicking() -> i32 {
+        std::thread::panicking() as i32
+    }
+
+    let mut state: (Option<F>, Option<R>) = (Some(f), None);
+    let mut exception = ForeignException {
+        code: 0,
+        address: 0,
+    };
+
+    let caught = unsafe {
+        cxx_stl_shield(
+            call::<F, R>,
+            &mut state as *mut _ as *mut c_void,
+            is_panicking,
+            &mut exception.code,
+            &mut exception.address,
+        )
+    };
+
+    if caught == 0 {
+        Ok(state.1.expect("shielded function returned"))
+    } else {
+        Err(exception)
+    }
+}
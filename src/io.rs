@@ -0,0 +1,136 @@
+//! [`std::io`] traits for containers of bytes, e.g. to feed saves or packets held
+//! in C++ containers to Rust parsers and decompressors.
+//!
+//! [`CxxCursor`] reads from a [`CxxVec<u8>`](crate::vec::CxxVec), a
+//! [`CxxNarrowString`](crate::string::CxxNarrowString) or any other container
+//! of bytes, either borrowed or owned. [`CxxVec<u8>`](crate::vec::CxxVec) also implements
+//! [`Write`](io::Write), so encoders can write straight into a vector passed on to C++ code.
+
+use std::{
+    cmp,
+    io::{self, BufRead, Read, Seek, SeekFrom},
+};
+
+/// Cursor over the bytes of a container, like [`io::Cursor`].
+///
+/// The position may be past the end of the container, in which case reads return no bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CxxCursor<C> {
+    inner: C,
+    pos: u64,
+}
+
+impl<C> CxxCursor<C> {
+    /// Creates a cursor at the start of `inner`.
+    pub const fn new(inner: C) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    pub const fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the container, which may be shrunk below the position of the cursor.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    pub const fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+}
+
+impl<C: AsRef<[u8]>> CxxCursor<C> {
+    /// Returns the bytes after the position of the cursor.
+    pub fn remaining(&self) -> &[u8] {
+        let bytes = self.inner.as_ref();
+        let start = cmp::min(self.pos, bytes.len() as u64) as usize;
+
+        &bytes[start..]
+    }
+
+    /// Whether the cursor is at or past the end of the container.
+    pub fn is_empty(&self) -> bool {
+        self.remaining().is_empty()
+    }
+}
+
+impl<C: AsRef<[u8]>> Read for CxxCursor<C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = Read::read(&mut self.remaining(), buf)?;
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let remaining = self.remaining();
+
+        let Some(bytes) = remaining.get(..buf.len()) else {
+            self.pos += remaining.len() as u64;
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+
+        buf.copy_from_slice(bytes);
+        self.pos += buf.len() as u64;
+
+        Ok(())
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let remaining = self.remaining();
+        let n = remaining.len();
+
+        buf.try_reserve(n)?;
+        buf.extend_from_slice(remaining);
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl<C: AsRef<[u8]>> BufRead for CxxCursor<C> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<C: AsRef<[u8]>> Seek for CxxCursor<C> {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.inner.as_ref().len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
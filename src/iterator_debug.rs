@@ -0,0 +1,255 @@
+//! Support for containers of C++ code built with `_ITERATOR_DEBUG_LEVEL` 1 or 2.
+//!
+//! With iterator debugging, every MSVC container value starts with a pointer to a
+//! `std::_Container_proxy`, allocated with the container's allocator. Checked iterators
+//! point to the proxy instead of the container, so containers can be moved, and
+//! the proxy points back to the container to validate them.
+//!
+//! The containers in the `iterator_debug` modules of [`vec`](crate::vec) and
+//! [`string`](crate::string) include the proxy pointer, and allocate and free
+//! the proxy through their allocator. The back pointer is updated whenever
+//! the container value is accessed, so it follows the container when it is moved.
+//! C++ iterators are orphaned, i.e. invalidated, when the container is dropped,
+//! and follow the contents of containers swapped with `swap_contents` like in C++,
+//! but are not orphaned by other operations done from Rust.
+//! As they allocate the proxy when created, their constructors aren't `const fn`,
+//! unlike those of the other containers.
+//!
+//! [`CheckedIter`] reproduces the layout of checked iterators, so that Rust can
+//! create iterator arguments for C++ functions taking them by value.
+
+use std::{
+    alloc::{handle_alloc_error, GlobalAlloc, Layout},
+    ffi::c_void,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+use crate::alloc::{CxxProxy, WithCxxProxy};
+
+/// `std::_Container_proxy`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ContainerProxy {
+    /// Container the iterators belong to, `_Mycont`.
+    pub cont: *const c_void,
+    /// First iterator in the chain of registered iterators, `_Myfirstiter`.
+    pub first_iter: *mut IteratorBase,
+}
+
+/// `std::_Iterator_base12`, the base of checked iterators.
+#[repr(C)]
+#[derive(Debug)]
+pub struct IteratorBase {
+    /// Proxy of the container the iterator belongs to, `_Myproxy`.
+    pub proxy: *mut ContainerProxy,
+    /// Next iterator in the chain, `_Mynextiter`.
+    pub next_iter: *mut IteratorBase,
+}
+
+/// Owning pointer to the [`ContainerProxy`] of a container, `std::_Container_base12`.
+///
+/// It must be stored right before the container value, which it points back to.
+#[repr(transparent)]
+#[derive(Debug)]
+pub(crate) struct ProxyPtr(NonNull<ContainerProxy>);
+
+impl ProxyPtr {
+    /// Allocates a proxy with `alloc`.
+    pub fn new_in<A: CxxProxy>(alloc: &A) -> Self {
+        let layout = Layout::new::<ContainerProxy>();
+
+        unsafe {
+            let Some(proxy) = NonNull::new(alloc.proxy().alloc(layout) as *mut ContainerProxy)
+            else {
+                handle_alloc_error(layout);
+            };
+
+            proxy.write(ContainerProxy {
+                cont: ptr::null(),
+                first_iter: ptr::null_mut(),
+            });
+
+            Self(proxy)
+        }
+    }
+
+    /// Points the proxy back to the container this pointer is stored in.
+    pub fn attach(&self) {
+        let cont = self as *const Self as *mut c_void;
+
+        // `&self` may be shared between threads, but they all store the same address:
+        let field = unsafe { AtomicPtr::from_ptr(ptr::addr_of_mut!((*self.0.as_ptr()).cont) as _) };
+
+        if field.load(Ordering::Relaxed) != cont {
+            field.store(cont, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the pointer to the proxy.
+    pub fn as_ptr(&self) -> *mut ContainerProxy {
+        self.0.as_ptr()
+    }
+
+    /// Orphans the registered iterators, like `_Orphan_all`.
+    pub fn orphan_all(&self) {
+        unsafe {
+            let proxy = self.0.as_ptr();
+            let mut iter = (*proxy).first_iter;
+
+            while !iter.is_null() {
+                (*iter).proxy = ptr::null_mut();
+                iter = (*iter).next_iter;
+            }
+
+            (*proxy).first_iter = ptr::null_mut();
+        }
+    }
+
+    /// Orphans the registered iterators and frees the proxy, like `_Orphan_all`
+    /// followed by `_Free_proxy`.
+    ///
+    /// # Safety
+    ///
+    /// The proxy must have been allocated with an allocator compatible with `alloc`,
+    /// and must not be used afterwards.
+    pub unsafe fn free_in<A: CxxProxy>(&self, alloc: &A) {
+        self.orphan_all();
+
+        unsafe {
+            let proxy = self.0.as_ptr();
+
+            alloc
+                .proxy()
+                .dealloc(proxy as *mut u8, Layout::new::<ContainerProxy>());
+        }
+    }
+}
+
+// The proxy is owned, and only written to through `&self` atomically:
+unsafe impl Send for ProxyPtr {}
+unsafe impl Sync for ProxyPtr {}
+
+/// Returns the proxy pointer of `layout`, if it has one.
+pub(crate) fn proxy_of<L: WithCxxProxy>(layout: &L) -> Option<&ProxyPtr> {
+    L::PROXY_OFFSET.map(|offset| unsafe { &*(layout as *const L).byte_add(offset).cast() })
+}
+
+/// Exchanges the proxies of two containers along with their iterators,
+/// like `_Swap_proxy_and_iterators`, so the iterators follow the swapped values.
+///
+/// Does nothing unless both layouts have a proxy. The back pointers are updated
+/// the next time the values are accessed.
+///
+/// # Safety
+///
+/// Each allocator must be able to free the proxy allocated by the other.
+pub(crate) unsafe fn swap_proxies<L: WithCxxProxy, L2: WithCxxProxy>(a: &mut L, b: &mut L2) {
+    if let (Some(a_offset), Some(b_offset)) = (L::PROXY_OFFSET, L2::PROXY_OFFSET) {
+        unsafe {
+            ptr::swap::<ProxyPtr>(
+                (a as *mut L).byte_add(a_offset).cast(),
+                (b as *mut L2).byte_add(b_offset).cast(),
+            );
+        }
+    }
+}
+
+/// Checked iterator into a container with iterator debugging,
+/// e.g. `std::_Vector_iterator`, an [`IteratorBase`] followed by the element pointer.
+///
+/// With an `_ITERATOR_DEBUG_LEVEL` of 2, C++ expects every iterator to be registered
+/// with the proxy of its container, see [`CheckedIter::register`].
+///
+/// MSVC passes iterators by value as a pointer to a temporary, which the callee
+/// destroys, so C++ functions taking one should be declared to take a
+/// `*mut CheckedIter<T>`, see [`CheckedIter::as_mut_ptr`]. Dropping the iterator
+/// afterwards is fine, as the callee leaves it unregistered.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CheckedIter<'a, T> {
+    base: IteratorBase,
+    ptr: *mut T,
+    _marker: PhantomData<(&'a mut [T], PhantomPinned)>,
+}
+
+impl<T> CheckedIter<'_, T> {
+    /// Creates an unregistered iterator to `ptr` through the attached `proxy`.
+    pub(crate) fn new(proxy: &ProxyPtr, ptr: *mut T) -> Self {
+        Self {
+            base: IteratorBase {
+                proxy: proxy.as_ptr(),
+                next_iter: ptr::null_mut(),
+            },
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the pointer to the element the iterator points to.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Whether the iterator has been orphaned by C++, which leaves it invalid.
+    pub fn is_orphaned(&self) -> bool {
+        self.base.proxy.is_null()
+    }
+
+    /// Registers the iterator with the proxy of its container, like `_Adopt`
+    /// with an `_ITERATOR_DEBUG_LEVEL` of 2. It is unregistered when dropped.
+    ///
+    /// Does nothing if the iterator is already registered or orphaned.
+    pub fn register(self: Pin<&mut Self>) {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if this.is_orphaned() || this.find_link().is_some() {
+            return;
+        }
+
+        unsafe {
+            let proxy = this.base.proxy;
+            this.base.next_iter = (*proxy).first_iter;
+            (*proxy).first_iter = &mut this.base;
+        }
+    }
+
+    /// Returns the pointer to pass the iterator by value to C++.
+    pub fn as_mut_ptr(self: Pin<&mut Self>) -> *mut Self {
+        unsafe { self.get_unchecked_mut() }
+    }
+
+    /// Finds the link pointing to this iterator in the chain of its proxy.
+    fn find_link(&mut self) -> Option<*mut *mut IteratorBase> {
+        let this = &mut self.base as *mut IteratorBase;
+
+        unsafe {
+            let mut link = ptr::addr_of_mut!((*self.base.proxy).first_iter);
+
+            while !(*link).is_null() {
+                if *link == this {
+                    return Some(link);
+                }
+
+                link = ptr::addr_of_mut!((**link).next_iter);
+            }
+        }
+
+        None
+    }
+}
+
+impl<T> Drop for CheckedIter<'_, T> {
+    fn drop(&mut self) {
+        if self.is_orphaned() {
+            return;
+        }
+
+        // Like `_Orphan_me`, but the iterator may never have been registered:
+        if let Some(link) = self.find_link() {
+            unsafe { *link = self.base.next_iter }
+        }
+    }
+}
@@ -1,7 +1,27 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 
+pub mod abi;
 pub mod alloc;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+pub mod bindgen;
+mod cstl;
+#[cfg(feature = "cxx")]
+pub mod cxx;
+pub mod diagnostics;
+pub mod ffi;
+pub mod io;
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug;
+pub mod probe;
+pub mod remote;
+#[cfg(feature = "rkyv")]
+mod rkyv;
 pub mod semantics;
+#[cfg(feature = "serde")]
+mod serde;
 
 pub mod string;
+pub mod testing;
 pub mod vec;
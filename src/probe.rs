@@ -0,0 +1,335 @@
+//! Heuristics for finding out which C++ layouts a program uses.
+//!
+//! Reading a container with the wrong layout corrupts memory, so the features to
+//! build this crate with can be checked against a live object of known content
+//! first, e.g. a `std::string` global holding a short literal.
+//!
+//! Containers only differ in where they store their allocator before Visual Studio
+//! 2015, so MSVC 2010, 2012 and 2015-2022 containers can't be told apart from
+//! their contents. Visual Studio 2010 containers are larger though, as their
+//! allocator is a data member even when it's stateless.
+
+use std::{mem, ptr};
+
+/// C++ toolset whose container layouts are in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Toolset {
+    /// Visual Studio 2010.
+    Msvc2010,
+    /// Visual Studio 2012.
+    Msvc2012,
+    /// Visual Studio 2015 to 2022.
+    Msvc2015,
+    /// libstdc++.
+    Gnu,
+    /// libc++.
+    Libcxx,
+}
+
+/// Layout of C++ containers found by a probe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AbiDescriptor {
+    /// Toolset the layout belongs to, or `None` if the probed object
+    /// is laid out the same by all of them.
+    pub toolset: Option<Toolset>,
+    /// Whether the containers start with a `std::_Container_proxy` pointer,
+    /// as with an `_ITERATOR_DEBUG_LEVEL` of 1 or 2.
+    pub iterator_debug: bool,
+}
+
+impl AbiDescriptor {
+    /// Features of this crate that select the layout.
+    ///
+    /// Without a toolset, no toolset feature is returned, so a probe that can't
+    /// tell MSVC toolsets apart still needs the toolset checked another way,
+    /// e.g. with `probe_loaded_runtime` on Windows.
+    pub fn features(&self) -> Vec<&'static str> {
+        let toolset = match self.toolset {
+            Some(Toolset::Msvc2010) => Some("msvc2010"),
+            Some(Toolset::Msvc2012) => Some("msvc2012"),
+            Some(Toolset::Gnu) => Some("gnu-abi"),
+            Some(Toolset::Libcxx) => Some("libcxx-abi"),
+            Some(Toolset::Msvc2015) | None => None,
+        };
+
+        toolset
+            .into_iter()
+            .chain(self.iterator_debug.then_some("iterator-debug"))
+            .collect()
+    }
+}
+
+/// Longest content [`probe_string`] accepts, which fits in the small string buffer
+/// of every toolset, so no pointers have to be followed to compare it.
+pub const MAX_PROBE_LEN: usize = 15;
+
+/// Size of the largest `std::string` layout, with iterator debugging.
+const MAX_STRING_SIZE: usize = 16 + 3 * mem::size_of::<usize>();
+
+/// Finds the layout of the `std::string` at `object` holding `content`.
+///
+/// Returns `None` if no known layout matches.
+///
+/// # Panics
+///
+/// Panics if `content` is longer than [`MAX_PROBE_LEN`] bytes.
+///
+/// # Safety
+///
+/// `object` must be valid for reads of `16 + 3 * size_of::<usize>()` bytes.
+pub unsafe fn probe_string(object: *const u8, content: &[u8]) -> Option<AbiDescriptor> {
+    let len = content.len();
+
+    if len > MAX_PROBE_LEN {
+        panic!("probe content length (is {len}) should be <= {MAX_PROBE_LEN}");
+    }
+
+    let bytes = unsafe { object.cast::<[u8; MAX_STRING_SIZE]>().read_unaligned() };
+    let word = |offset: usize| {
+        let mut buf = [0; mem::size_of::<usize>()];
+        buf.copy_from_slice(&bytes[offset..offset + mem::size_of::<usize>()]);
+        usize::from_ne_bytes(buf)
+    };
+    let holds = |offset: usize| bytes[offset..offset + len] == *content && bytes[offset + len] == 0;
+
+    // `_Bx._Buf`, `_Mysize` and `_Myres`, preceded by `_Myproxy` with iterator debugging:
+    let msvc = |base: usize| {
+        word(base + 16) == len && word(base + 16 + mem::size_of::<usize>()) == 15 && holds(base)
+    };
+
+    let descriptor = |toolset, iterator_debug| {
+        Some(AbiDescriptor {
+            toolset,
+            iterator_debug,
+        })
+    };
+
+    // Visual Studio 2010 and 2012 strings store their allocator after these
+    // members, so every MSVC toolset matches:
+    if msvc(0) {
+        return descriptor(None, false);
+    }
+
+    if word(0) != 0 && msvc(mem::size_of::<usize>()) {
+        return descriptor(None, true);
+    }
+
+    // `_M_p` pointing to `_M_local_buf`, after `_M_string_length`:
+    let local_buf = 2 * mem::size_of::<usize>();
+
+    if word(0) == object as usize + local_buf
+        && word(mem::size_of::<usize>()) == len
+        && holds(local_buf)
+    {
+        return descriptor(Some(Toolset::Gnu), false);
+    }
+
+    // Short mode `__size_` shifted left past the `__is_long_` bit, then `__data_`:
+    if bytes[0] as usize == len << 1 && holds(1) {
+        return descriptor(Some(Toolset::Libcxx), false);
+    }
+
+    None
+}
+
+/// Finds the layout of the `std::vector<T>` at `object` holding `content`.
+///
+/// All toolsets lay out vectors as three pointers, so only iterator debugging
+/// can be detected. The element pointer is only followed once the other pointers
+/// are consistent with `content`.
+///
+/// Returns `None` if no known layout matches, which includes
+/// empty vectors that never allocated.
+///
+/// # Safety
+///
+/// `object` must be valid for reads of four pointers, and if it is a vector,
+/// its elements must be valid for reads.
+pub unsafe fn probe_vector<T: PartialEq>(
+    object: *const u8,
+    content: &[T],
+) -> Option<AbiDescriptor> {
+    let words = unsafe { object.cast::<[usize; 4]>().read_unaligned() };
+
+    let matches = |first: usize, last: usize, end: usize| {
+        let size = mem::size_of_val(content);
+
+        first != 0
+            && first.is_multiple_of(mem::align_of::<T>())
+            && last.checked_sub(first) == Some(size)
+            && end >= last
+            && unsafe {
+                content.iter().eq(&*ptr::slice_from_raw_parts(
+                    first as *const T,
+                    content.len(),
+                ))
+            }
+    };
+
+    let descriptor = |iterator_debug| {
+        Some(AbiDescriptor {
+            toolset: None,
+            iterator_debug,
+        })
+    };
+
+    if matches(words[0], words[1], words[2]) {
+        return descriptor(false);
+    }
+
+    if words[0] != 0 && matches(words[1], words[2], words[3]) {
+        return descriptor(true);
+    }
+
+    None
+}
+
+/// Finds the MSVC toolset of the C++ runtime loaded in the process.
+///
+/// The debug runtime implies iterator debugging, as it is the default
+/// for debug builds. Returns `None` if no known runtime is loaded.
+#[cfg(windows)]
+pub fn probe_loaded_runtime() -> Option<AbiDescriptor> {
+    extern "system" {
+        fn GetModuleHandleW(module_name: *const u16) -> *mut std::ffi::c_void;
+    }
+
+    const RUNTIMES: &[(&str, Toolset, bool)] = &[
+        ("msvcp140.dll", Toolset::Msvc2015, false),
+        ("msvcp140d.dll", Toolset::Msvc2015, true),
+        ("msvcp110.dll", Toolset::Msvc2012, false),
+        ("msvcp110d.dll", Toolset::Msvc2012, true),
+        ("msvcp100.dll", Toolset::Msvc2010, false),
+        ("msvcp100d.dll", Toolset::Msvc2010, true),
+    ];
+
+    RUNTIMES
+        .iter()
+        .find_map(|&(name, toolset, iterator_debug)| {
+            let name: Vec<u16> = name.encode_utf16().chain([0]).collect();
+            let module = unsafe { GetModuleHandleW(name.as_ptr()) };
+
+            (!module.is_null()).then_some(AbiDescriptor {
+                toolset: Some(toolset),
+                iterator_debug,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{probe_string, probe_vector, AbiDescriptor, Toolset, MAX_STRING_SIZE};
+
+    const WORD: usize = mem::size_of::<usize>();
+
+    /// Probes a `std::string` laid out in a buffer by `init`, which is passed
+    /// the buffer and its address.
+    fn probe(content: &[u8], init: impl FnOnce(&mut [u8], usize)) -> Option<AbiDescriptor> {
+        let mut bytes = [0xcc; MAX_STRING_SIZE];
+        let address = bytes.as_ptr() as usize;
+        init(&mut bytes, address);
+
+        unsafe { probe_string(bytes.as_ptr(), content) }
+    }
+
+    fn write_word(bytes: &mut [u8], offset: usize, word: usize) {
+        bytes[offset..offset + WORD].copy_from_slice(&word.to_ne_bytes());
+    }
+
+    fn write_str(bytes: &mut [u8], offset: usize, content: &[u8]) {
+        bytes[offset..offset + content.len()].copy_from_slice(content);
+        bytes[offset + content.len()] = 0;
+    }
+
+    fn msvc(bytes: &mut [u8], base: usize, content: &[u8]) {
+        write_str(bytes, base, content);
+        write_word(bytes, base + 16, content.len());
+        write_word(bytes, base + 16 + WORD, 15);
+    }
+
+    #[test]
+    fn msvc_string_has_no_toolset() {
+        let descriptor = probe(b"probe", |bytes, _| msvc(bytes, 0, b"probe"));
+
+        assert_eq!(
+            descriptor,
+            Some(AbiDescriptor {
+                toolset: None,
+                iterator_debug: false,
+            })
+        );
+        assert!(descriptor.unwrap().features().is_empty());
+    }
+
+    #[test]
+    fn msvc_string_with_proxy() {
+        let descriptor = probe(b"probe", |bytes, _| {
+            write_word(bytes, 0, 0x1000);
+            msvc(bytes, WORD, b"probe");
+        });
+
+        assert_eq!(
+            descriptor,
+            Some(AbiDescriptor {
+                toolset: None,
+                iterator_debug: true,
+            })
+        );
+        assert_eq!(descriptor.unwrap().features(), ["iterator-debug"]);
+    }
+
+    #[test]
+    fn gnu_string_points_to_local_buf() {
+        let descriptor = probe(b"probe", |bytes, address| {
+            write_word(bytes, 0, address + 2 * WORD);
+            write_word(bytes, WORD, 5);
+            write_str(bytes, 2 * WORD, b"probe");
+        });
+
+        assert_eq!(descriptor.unwrap().toolset, Some(Toolset::Gnu));
+        assert_eq!(descriptor.unwrap().features(), ["gnu-abi"]);
+    }
+
+    #[test]
+    fn libcxx_short_string() {
+        let descriptor = probe(b"probe", |bytes, _| {
+            bytes[0] = 5 << 1;
+            write_str(bytes, 1, b"probe");
+        });
+
+        assert_eq!(descriptor.unwrap().toolset, Some(Toolset::Libcxx));
+        assert_eq!(descriptor.unwrap().features(), ["libcxx-abi"]);
+    }
+
+    #[test]
+    fn unknown_layout() {
+        assert_eq!(probe(b"probe", |_, _| {}), None);
+        assert_eq!(probe(b"probe", |bytes, _| msvc(bytes, 0, b"other")), None);
+    }
+
+    #[test]
+    fn vector_with_and_without_proxy() {
+        let elements = [1u32, 2, 3];
+        let first = elements.as_ptr() as usize;
+        let last = first + mem::size_of_val(&elements);
+
+        let plain = [first, last, last, 0];
+        let descriptor = unsafe { probe_vector(plain.as_ptr().cast(), &elements) };
+        assert_eq!(
+            descriptor,
+            Some(AbiDescriptor {
+                toolset: None,
+                iterator_debug: false,
+            })
+        );
+
+        let debug = [0x1000, first, last, last];
+        let descriptor = unsafe { probe_vector(debug.as_ptr().cast(), &elements) };
+        assert!(descriptor.unwrap().iterator_debug);
+
+        let wrong = unsafe { probe_vector(plain.as_ptr().cast(), &[1u32, 2, 4]) };
+        assert_eq!(wrong, None);
+    }
+}
@@ -0,0 +1,914 @@
+//! Container values of another process, laid out for its pointer width.
+//!
+//! The values in [`cstl_sys`] use the `usize` of the host, so a 64-bit tool can't
+//! use them to interpret containers copied out of a 32-bit process. The values here
+//! are parameterized over a [`RemotePtr`] instead, `u32` for 32-bit processes
+//! and `u64` for 64-bit ones. Pointers in them are addresses in the other
+//! process, which must be read from it separately.
+//!
+//! With iterator debugging, containers start with a `std::_Container_proxy`
+//! pointer of the same width before the value.
+//!
+//! [`RemoteReader`] reads containers through a [`ReadMemory`] implementation,
+//! e.g. `ProcessMemory` on Windows, and copies their contents into Rust collections.
+//! [`RemoteReader::validate_map`] checks the trees of maps for corruption, reporting
+//! the addresses of the offending nodes.
+//!
+//! With the `bytemuck` or `zerocopy` features, the values implement their traits
+//! for reinterpreting captured bytes, e.g. `bytemuck::Pod` and `zerocopy::FromBytes`.
+
+use std::{cmp::Ordering, fmt, io, marker::PhantomData, mem, ptr, slice};
+
+/// Pointer of another process, stored as an integer of its width.
+pub trait RemotePtr: Pod + Default + Eq + fmt::Debug + Into<u64> {}
+
+impl RemotePtr for u32 {}
+
+impl RemotePtr for u64 {}
+
+/// Reads a value of `T` from the start of `bytes`,
+/// or returns `None` if there are too few of them.
+///
+/// `T` must be valid for any bit pattern.
+unsafe fn read_from<T>(bytes: &[u8]) -> Option<T> {
+    (bytes.len() >= mem::size_of::<T>())
+        .then(|| unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+/// `std::map` value of another process, `std::_Tree_val` with a stateless
+/// allocator and comparator.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(zerocopy::FromBytes, zerocopy::KnownLayout, zerocopy::Immutable)
+)]
+pub struct RemoteMapVal<P: RemotePtr> {
+    /// Sentinel node, whose parent is the root of the tree, `_Myhead`.
+    pub head: P,
+    /// Number of elements, `_Mysize`.
+    pub size: P,
+}
+
+impl<P: RemotePtr> RemoteMapVal<P> {
+    /// Reads the value from the start of `bytes`, copied out of the other process.
+    ///
+    /// Returns `None` if `bytes` is too short.
+    pub fn read_from(bytes: &[u8]) -> Option<Self> {
+        unsafe { read_from(bytes) }
+    }
+}
+
+/// `CSTL_VectorVal` of another process.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(zerocopy::FromBytes, zerocopy::KnownLayout, zerocopy::Immutable)
+)]
+pub struct RemoteVectorVal<P: RemotePtr> {
+    pub first: P,
+    pub last: P,
+    pub end: P,
+}
+
+impl<P: RemotePtr> RemoteVectorVal<P> {
+    /// Reads the value from the start of `bytes`, copied out of the other process.
+    ///
+    /// Returns `None` if `bytes` is too short.
+    pub fn read_from(bytes: &[u8]) -> Option<Self> {
+        unsafe { read_from(bytes) }
+    }
+
+    /// Returns the number of `T` elements in the vector, where `T` must have
+    /// the same size in both processes.
+    pub fn len<T>(&self) -> u64 {
+        byte_len(self.first, self.last) / elem_size::<T>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.first == self.last
+    }
+
+    /// Returns the number of `T` elements the vector can hold, where `T` must have
+    /// the same size in both processes.
+    pub fn capacity<T>(&self) -> u64 {
+        byte_len(self.first, self.end) / elem_size::<T>()
+    }
+}
+
+/// `CSTL_StringUnion` of another process, the small string buffer
+/// or the pointer to the characters of a large string.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(zerocopy::FromBytes, zerocopy::KnownLayout, zerocopy::Immutable)
+)]
+pub union RemoteStringUnion<P: RemotePtr> {
+    pub buf: [u8; 16],
+    pub ptr: P,
+}
+
+/// `CSTL_StringVal` of another process.
+///
+/// All string values share this layout, the methods taking the character type `C`,
+/// e.g. `u16` for `std::wstring`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(zerocopy::FromBytes, zerocopy::KnownLayout, zerocopy::Immutable)
+)]
+pub struct RemoteStringVal<P: RemotePtr> {
+    pub bx: RemoteStringUnion<P>,
+    pub size: P,
+    pub res: P,
+}
+
+impl<P: RemotePtr> RemoteStringVal<P> {
+    /// Reads the value from the start of `bytes`, copied out of the other process.
+    ///
+    /// Returns `None` if `bytes` is too short.
+    pub fn read_from(bytes: &[u8]) -> Option<Self> {
+        unsafe { read_from(bytes) }
+    }
+
+    /// Returns the length of the string in characters.
+    pub fn len(&self) -> u64 {
+        self.size.into()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the string in characters.
+    pub fn capacity(&self) -> u64 {
+        self.res.into()
+    }
+
+    /// Whether the characters of a string of `C` characters are stored
+    /// out of line, rather than in the small string buffer.
+    pub fn is_large<C>(&self) -> bool {
+        self.capacity() > (16 / mem::size_of::<C>() - 1) as u64
+    }
+
+    /// Returns the bytes of the characters of a small string of `C` characters,
+    /// or `None` if it is large, or its length exceeds the buffer.
+    pub fn inline_bytes<C>(&self) -> Option<&[u8]> {
+        let len = usize::try_from(self.len())
+            .ok()?
+            .checked_mul(mem::size_of::<C>())?;
+        let buf = unsafe { &self.bx.buf };
+
+        if self.is_large::<C>() {
+            return None;
+        }
+
+        buf.get(..len)
+    }
+
+    /// Returns the address of the characters of a large string of `C` characters
+    /// in the other process, or `None` if it is small.
+    pub fn remote_ptr<C>(&self) -> Option<P> {
+        self.is_large::<C>().then_some(unsafe { self.bx.ptr })
+    }
+}
+
+impl<P: RemotePtr> fmt::Debug for RemoteStringVal<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteStringVal")
+            .field("bx", unsafe { &self.bx.buf })
+            .field("size", &self.size)
+            .field("res", &self.res)
+            .finish()
+    }
+}
+
+/// Types valid for any bit pattern, which can be read from another process.
+///
+/// # Safety
+///
+/// Every bit pattern of the size of the type must be a valid value of it.
+pub unsafe trait Pod: Copy {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(
+    (),
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64
+);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+unsafe impl<P: RemotePtr> Pod for RemoteMapVal<P> {}
+
+unsafe impl<P: RemotePtr> Pod for RemoteVectorVal<P> {}
+
+unsafe impl<P: RemotePtr> Pod for RemoteStringVal<P> {}
+
+// None of the values have padding, as the union is at least as aligned as `P`,
+// and its size is a multiple of the alignment of `P`:
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Zeroable> bytemuck::Zeroable for RemoteMapVal<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Pod> bytemuck::Pod for RemoteMapVal<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Zeroable> bytemuck::Zeroable for RemoteVectorVal<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Pod> bytemuck::Pod for RemoteVectorVal<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Zeroable> bytemuck::Zeroable for RemoteStringUnion<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Pod> bytemuck::Pod for RemoteStringUnion<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Zeroable> bytemuck::Zeroable for RemoteStringVal<P> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<P: RemotePtr + bytemuck::Pod> bytemuck::Pod for RemoteStringVal<P> {}
+
+/// Memory of another process.
+pub trait ReadMemory {
+    /// Fills `buf` with the bytes at `address`, failing if any of them can't be read.
+    fn read(&self, address: u64, buf: &mut [u8]) -> io::Result<()>;
+}
+
+impl<M: ReadMemory + ?Sized> ReadMemory for &M {
+    fn read(&self, address: u64, buf: &mut [u8]) -> io::Result<()> {
+        (**self).read(address, buf)
+    }
+}
+
+/// Memory of a process read with `ReadProcessMemory`.
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct ProcessMemory {
+    handle: *mut std::ffi::c_void,
+}
+
+#[cfg(windows)]
+impl ProcessMemory {
+    /// Reads the memory of the process of `handle`, which isn't closed when dropped.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a process handle with `PROCESS_VM_READ` access,
+    /// and must stay open while it is used.
+    pub unsafe fn from_raw_handle(handle: *mut std::ffi::c_void) -> Self {
+        Self { handle }
+    }
+}
+
+#[cfg(windows)]
+impl ReadMemory for ProcessMemory {
+    fn read(&self, address: u64, buf: &mut [u8]) -> io::Result<()> {
+        extern "system" {
+            fn ReadProcessMemory(
+                process: *mut std::ffi::c_void,
+                base_address: *const std::ffi::c_void,
+                buffer: *mut std::ffi::c_void,
+                size: usize,
+                number_of_bytes_read: *mut usize,
+            ) -> i32;
+        }
+
+        let address = usize::try_from(address)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "address out of range"))?;
+        let mut read = 0;
+
+        let ok = unsafe {
+            ReadProcessMemory(
+                self.handle,
+                address as *const std::ffi::c_void,
+                buf.as_mut_ptr() as *mut std::ffi::c_void,
+                buf.len(),
+                &mut read,
+            )
+        };
+
+        if ok == 0 {
+            Err(io::Error::last_os_error())
+        } else if read != buf.len() {
+            Err(io::ErrorKind::UnexpectedEof.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reader of the containers of another process with pointers of type `P`.
+///
+/// Addresses passed to it are those of the container values, after the
+/// `std::_Container_proxy` pointer with iterator debugging. Element types
+/// must be laid out the same in both processes.
+///
+/// Corrupted or concurrently modified containers are reported as
+/// [`io::ErrorKind::InvalidData`] errors, as are containers longer
+/// than the limit set with [`RemoteReader::with_max_len`].
+#[derive(Debug)]
+pub struct RemoteReader<M: ReadMemory, P: RemotePtr> {
+    memory: M,
+    max_len: u64,
+    _marker: PhantomData<P>,
+}
+
+impl<M: ReadMemory, P: RemotePtr> RemoteReader<M, P> {
+    /// Default limit of [`RemoteReader::with_max_len`].
+    pub const DEFAULT_MAX_LEN: u64 = 1 << 24;
+
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            max_len: Self::DEFAULT_MAX_LEN,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Limits the number of elements of the containers read, to avoid allocating
+    /// memory for the contents of corrupted containers.
+    pub fn with_max_len(self, max_len: u64) -> Self {
+        Self { max_len, ..self }
+    }
+
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    /// Reads a value of `T` at `address`.
+    pub fn read_value<T: Pod>(&self, address: u64) -> io::Result<T> {
+        let mut value = mem::MaybeUninit::<T>::zeroed();
+
+        unsafe {
+            let bytes =
+                slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+            self.memory.read(address, bytes)?;
+            Ok(value.assume_init())
+        }
+    }
+
+    /// Reads `len` values of `T` at `address`.
+    pub fn read_array<T: Pod>(&self, address: u64, len: u64) -> io::Result<Vec<T>> {
+        let len = self.check_len(len)?;
+        let mut values = Vec::<T>::with_capacity(len);
+
+        unsafe {
+            ptr::write_bytes(values.as_mut_ptr(), 0, len);
+            values.set_len(len);
+
+            let bytes = slice::from_raw_parts_mut(
+                values.as_mut_ptr() as *mut u8,
+                len * mem::size_of::<T>(),
+            );
+            self.memory.read(address, bytes)?;
+        }
+
+        Ok(values)
+    }
+
+    /// Reads the `std::vector<T>` value at `address`.
+    pub fn read_vector_val(&self, address: u64) -> io::Result<RemoteVectorVal<P>> {
+        self.read_value(address)
+    }
+
+    /// Reads the elements of the `std::vector<T>` at `address`.
+    pub fn read_vector<T: Pod>(&self, address: u64) -> io::Result<Vec<T>> {
+        let val = self.read_vector_val(address)?;
+        let (first, last) = (val.first.into(), val.last.into());
+
+        if last < first || (last - first) % elem_size::<T>() != 0 {
+            return Err(invalid_data("vector pointers are inconsistent"));
+        }
+
+        self.read_array(first, val.len::<T>())
+    }
+
+    /// Reads the `std::basic_string` value at `address`.
+    pub fn read_string_val(&self, address: u64) -> io::Result<RemoteStringVal<P>> {
+        self.read_value(address)
+    }
+
+    /// Reads the characters of the `std::basic_string` of `C` characters at `address`.
+    pub fn read_string<C: Pod>(&self, address: u64) -> io::Result<Vec<C>> {
+        let val = self.read_string_val(address)?;
+
+        if val.len() > val.capacity() {
+            return Err(invalid_data("string length exceeds its capacity"));
+        }
+
+        match val.remote_ptr::<C>() {
+            Some(ptr) => self.read_array(ptr.into(), val.len()),
+            None => {
+                let bytes = val
+                    .inline_bytes::<C>()
+                    .ok_or_else(|| invalid_data("string length exceeds its buffer"))?;
+
+                Ok(bytes
+                    .chunks_exact(mem::size_of::<C>())
+                    .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr() as *const C) })
+                    .collect())
+            }
+        }
+    }
+
+    /// Reads the `std::u16string` or Windows `std::wstring` at `address`.
+    pub fn read_u16string(&self, address: u64) -> io::Result<String> {
+        String::from_utf16(&self.read_string::<u16>(address)?)
+            .map_err(|_| invalid_data("string isn't valid UTF-16"))
+    }
+
+    /// Reads the `std::map` value at `address`.
+    pub fn read_map_val(&self, address: u64) -> io::Result<RemoteMapVal<P>> {
+        self.read_value(address)
+    }
+
+    /// Reads the elements of the `std::map<K, V>` at `address`, in order.
+    ///
+    /// `std::set<K>` can be read as a map with `()` values.
+    pub fn read_map<K: Pod, V: Pod>(&self, address: u64) -> io::Result<Vec<(K, V)>> {
+        let val = self.read_map_val(address)?;
+        let len = self.check_len(val.size.into())?;
+
+        let ptr_size = mem::size_of::<P>() as u64;
+        let (key_offset, value_offset) = pair_offsets::<P, K, V>();
+
+        let left = |node: u64| self.read_value::<P>(node).map(Into::into);
+        let right = |node: u64| {
+            self.read_value::<P>(field_address(node, 2 * ptr_size)?)
+                .map(Into::into)
+        };
+        let is_nil = |node: u64| {
+            self.read_value::<u8>(field_address(node, 3 * ptr_size + 1)?)
+                .map(|nil| nil != 0)
+        };
+
+        let head: u64 = val.head.into();
+        let root = self.read_value::<P>(field_address(head, ptr_size)?)?.into();
+
+        let mut elements = Vec::with_capacity(len);
+        let mut stack = Vec::new();
+        let mut node = root;
+
+        // In-order traversal, with every leaf pointing to the sentinel:
+        loop {
+            while node != head && !is_nil(node)? {
+                if stack.len() >= len {
+                    return Err(invalid_data("map tree is deeper than its size"));
+                }
+
+                stack.push(node);
+                node = left(node)?;
+            }
+
+            let Some(parent) = stack.pop() else {
+                break;
+            };
+
+            if elements.len() == len {
+                return Err(invalid_data("map has more elements than its size"));
+            }
+
+            elements.push((
+                self.read_value::<K>(field_address(parent, key_offset)?)?,
+                self.read_value::<V>(field_address(parent, value_offset)?)?,
+            ));
+
+            node = right(parent)?;
+        }
+
+        if elements.len() != len {
+            return Err(invalid_data("map has fewer elements than its size"));
+        }
+
+        Ok(elements)
+    }
+
+    /// Checks the red-black tree of a `std::map<K, V>` for corruption, returning the
+    /// first invariant it violates, or `None` if it's intact.
+    ///
+    /// The links between the nodes, their colors and the ordering of the keys are
+    /// checked, as well as the size of the map and the links of the sentinel node.
+    /// A `std::set<K>` can be checked as a map with `()` values, while duplicate keys
+    /// of multimaps are reported as misordered.
+    pub fn validate_map<K: Pod + PartialOrd, V: Pod>(
+        &self,
+        address: u64,
+    ) -> io::Result<Option<TreeViolation>> {
+        let val = self.read_map_val(address)?;
+        let size: u64 = val.size.into();
+        self.check_len(size)?;
+
+        let ptr_size = mem::size_of::<P>() as u64;
+        let (key_offset, _) = pair_offsets::<P, K, V>();
+
+        let link = |node: u64, index: u64| {
+            self.read_value::<P>(field_address(node, index * ptr_size)?)
+                .map(Into::into)
+        };
+        let is_red = |node: u64| {
+            self.read_value::<u8>(field_address(node, 3 * ptr_size)?)
+                .map(|color| color == RED)
+        };
+
+        let head: u64 = val.head.into();
+        let is_leaf = |node: u64| -> io::Result<bool> {
+            Ok(node == head || self.read_value::<u8>(field_address(node, 3 * ptr_size + 1)?)? != 0)
+        };
+
+        let check_link = |node: u64, index: u64, expected: u64| {
+            let found = link(node, index)?;
+
+            Ok::<_, io::Error>((found != expected).then_some(TreeViolation::Link {
+                node,
+                field: ["_Left", "_Parent", "_Right"][index as usize],
+                found,
+                expected,
+            }))
+        };
+
+        let root = link(head, 1)?;
+
+        if is_leaf(root)? {
+            if size != 0 {
+                return Ok(Some(TreeViolation::Size { size, nodes: 0 }));
+            }
+
+            for index in [0, 2] {
+                if let Some(violation) = check_link(head, index, head)? {
+                    return Ok(Some(violation));
+                }
+            }
+
+            return Ok(None);
+        }
+
+        if let Some(violation) = check_link(root, 1, head)? {
+            return Ok(Some(violation));
+        }
+
+        if is_red(root)? {
+            return Ok(Some(TreeViolation::RedRoot { node: root }));
+        }
+
+        // Pre-order traversal checking the links and colors, along with the number
+        // of black nodes on the path to each leaf:
+        let mut stack = vec![(root, 1)];
+        let mut nodes = 0;
+        let mut black_height = None;
+
+        while let Some((node, blacks)) = stack.pop() {
+            nodes += 1;
+
+            if nodes > size {
+                return Ok(Some(TreeViolation::Size { size, nodes }));
+            }
+
+            let red = is_red(node)?;
+
+            for index in [2, 0] {
+                let child = link(node, index)?;
+
+                if is_leaf(child)? {
+                    match black_height {
+                        None => black_height = Some(blacks),
+                        Some(height) if height != blacks => {
+                            return Ok(Some(TreeViolation::BlackHeight { node }));
+                        }
+                        Some(_) => {}
+                    }
+
+                    continue;
+                }
+
+                if let Some(violation) = check_link(child, 1, node)? {
+                    return Ok(Some(violation));
+                }
+
+                let child_red = is_red(child)?;
+
+                if red && child_red {
+                    return Ok(Some(TreeViolation::RedChild { node, child }));
+                }
+
+                stack.push((child, blacks + u64::from(!child_red)));
+            }
+        }
+
+        if nodes != size {
+            return Ok(Some(TreeViolation::Size { size, nodes }));
+        }
+
+        // In-order traversal of the now acyclic tree checking the keys:
+        let mut previous: Option<(u64, K)> = None;
+        let mut first = None;
+        let mut node = root;
+
+        loop {
+            while !is_leaf(node)? {
+                stack.push((node, 0));
+                node = link(node, 0)?;
+            }
+
+            let Some((parent, _)) = stack.pop() else {
+                break;
+            };
+
+            let key = self.read_value::<K>(field_address(parent, key_offset)?)?;
+
+            match previous {
+                Some((last, ref last_key))
+                    if last_key.partial_cmp(&key) != Some(Ordering::Less) =>
+                {
+                    return Ok(Some(TreeViolation::Misordered {
+                        node: parent,
+                        previous: last,
+                    }));
+                }
+                None => first = Some(parent),
+                Some(_) => {}
+            }
+
+            previous = Some((parent, key));
+            node = link(parent, 2)?;
+        }
+
+        let last = previous.map(|(last, _)| last);
+
+        for (index, expected) in [(0, first), (2, last)] {
+            if let Some(violation) = check_link(head, index, expected.unwrap_or(head))? {
+                return Ok(Some(violation));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn check_len(&self, len: u64) -> io::Result<usize> {
+        if len > self.max_len {
+            return Err(invalid_data("container length exceeds the limit"));
+        }
+
+        usize::try_from(len).map_err(|_| invalid_data("container length exceeds the limit"))
+    }
+}
+
+/// Invariant of a `std::map` red-black tree found violated by [`RemoteReader::validate_map`],
+/// with the addresses of the nodes involved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TreeViolation {
+    /// A link of `node`, named after its MSVC member, points to `found` instead of `expected`.
+    Link {
+        node: u64,
+        field: &'static str,
+        found: u64,
+        expected: u64,
+    },
+    /// The root is red.
+    RedRoot { node: u64 },
+    /// The red `node` has a red `child`.
+    RedChild { node: u64, child: u64 },
+    /// The path to a leaf below `node` has a different number of black nodes than
+    /// the paths to the leaves before it.
+    BlackHeight { node: u64 },
+    /// The key of `node` isn't greater than the key of the node before it.
+    Misordered { node: u64, previous: u64 },
+    /// The tree has more or fewer than `size` nodes, counting up to `nodes`.
+    Size { size: u64, nodes: u64 },
+}
+
+impl fmt::Display for TreeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Link {
+                node,
+                field,
+                found,
+                expected,
+            } => write!(
+                f,
+                "`{field}` of node {node:#x} is {found:#x} instead of {expected:#x}"
+            ),
+            Self::RedRoot { node } => write!(f, "root node {node:#x} is red"),
+            Self::RedChild { node, child } => {
+                write!(f, "red node {node:#x} has red child {child:#x}")
+            }
+            Self::BlackHeight { node } => {
+                write!(f, "black height below node {node:#x} differs")
+            }
+            Self::Misordered { node, previous } => {
+                write!(f, "node {node:#x} isn't ordered after node {previous:#x}")
+            }
+            Self::Size { size, nodes } => {
+                write!(f, "tree has {nodes} nodes instead of its size {size}")
+            }
+        }
+    }
+}
+
+/// `_Color` of red nodes, with black ones being 1.
+const RED: u8 = 0;
+
+/// Offsets of the key and value in a `std::_Tree_node`, after `_Left`, `_Parent`
+/// and `_Right`, followed by `_Color`, `_Isnil` and a `std::pair<const K, V>`.
+fn pair_offsets<P: RemotePtr, K, V>() -> (u64, u64) {
+    let ptr_size = mem::size_of::<P>() as u64;
+    let pair_align = mem::align_of::<K>().max(mem::align_of::<V>()) as u64;
+    let key_offset = (3 * ptr_size + 2).next_multiple_of(pair_align);
+    let value_offset =
+        key_offset + mem::size_of::<K>().next_multiple_of(mem::align_of::<V>()) as u64;
+
+    (key_offset, value_offset)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Returns the address of the field at `offset` in the node at `node`,
+/// failing if the address of a corrupted node overflows.
+fn field_address(node: u64, offset: u64) -> io::Result<u64> {
+    node.checked_add(offset)
+        .ok_or_else(|| invalid_data("node address overflows"))
+}
+
+fn byte_len<P: RemotePtr>(first: P, last: P) -> u64 {
+    last.into().wrapping_sub(first.into())
+}
+
+fn elem_size<T>() -> u64 {
+    mem::size_of::<T>().max(1) as u64
+}
+
+// The layouts of a 32 and 64-bit process, regardless of the host:
+const _: () = {
+    assert!(mem::size_of::<RemoteVectorVal<u32>>() == 12);
+    assert!(mem::size_of::<RemoteVectorVal<u64>>() == 24);
+    assert!(mem::size_of::<RemoteStringVal<u32>>() == 24);
+    assert!(mem::size_of::<RemoteStringVal<u64>>() == 32);
+    assert!(mem::size_of::<RemoteMapVal<u32>>() == 8);
+    assert!(mem::align_of::<RemoteStringVal<u32>>() == 4);
+};
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{pair_offsets, ReadMemory, RemoteReader, TreeViolation};
+
+    /// Memory of another process, `bytes` copied from `BASE`.
+    struct FakeMemory {
+        bytes: Vec<u8>,
+    }
+
+    const BASE: u64 = 0x1000;
+    const MAP: u64 = BASE;
+    const HEAD: u64 = BASE + 0x20;
+    const BLACK: u8 = 1;
+    const RED: u8 = 0;
+
+    impl ReadMemory for FakeMemory {
+        fn read(&self, address: u64, buf: &mut [u8]) -> io::Result<()> {
+            let start = address
+                .checked_sub(BASE)
+                .and_then(|start| usize::try_from(start).ok())
+                .filter(|&start| start + buf.len() <= self.bytes.len())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+            buf.copy_from_slice(&self.bytes[start..start + buf.len()]);
+            Ok(())
+        }
+    }
+
+    impl FakeMemory {
+        /// A `std::map<u32, ()>` of a 64-bit process at `MAP` with `size` elements.
+        fn map(head: u64, size: u64) -> Self {
+            let mut memory = Self {
+                bytes: vec![0; 0x200],
+            };
+            memory.write(MAP, &head.to_le_bytes());
+            memory.write(MAP + 8, &size.to_le_bytes());
+            memory
+        }
+
+        fn write(&mut self, address: u64, bytes: &[u8]) {
+            let start = (address - BASE) as usize;
+            self.bytes[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+
+        /// Writes the `_Left`, `_Parent` and `_Right` links, `_Color` and `_Isnil`
+        /// and the key of the node at `node`.
+        fn node(&mut self, node: u64, links: [u64; 3], color: u8, key: u32) {
+            for (index, link) in links.into_iter().enumerate() {
+                self.write(node + 8 * index as u64, &link.to_le_bytes());
+            }
+
+            self.write(node + 24, &[color, u8::from(node == HEAD)]);
+            self.write(node + pair_offsets::<u64, u32, ()>().0, &key.to_le_bytes());
+        }
+    }
+
+    // The nodes of a tree of the keys 1, 2 and 3 under a black root:
+    const LEFT: u64 = BASE + 0x40;
+    const ROOT: u64 = BASE + 0x60;
+    const RIGHT: u64 = BASE + 0x80;
+
+    fn tree() -> FakeMemory {
+        let mut memory = FakeMemory::map(HEAD, 3);
+        memory.node(HEAD, [LEFT, ROOT, RIGHT], BLACK, 0);
+        memory.node(ROOT, [LEFT, HEAD, RIGHT], BLACK, 2);
+        memory.node(LEFT, [HEAD, ROOT, HEAD], RED, 1);
+        memory.node(RIGHT, [HEAD, ROOT, HEAD], RED, 3);
+        memory
+    }
+
+    fn read(memory: FakeMemory) -> io::Result<Vec<(u32, ())>> {
+        RemoteReader::<_, u64>::new(memory).read_map::<u32, ()>(MAP)
+    }
+
+    fn validate(memory: FakeMemory) -> io::Result<Option<TreeViolation>> {
+        RemoteReader::<_, u64>::new(memory).validate_map::<u32, ()>(MAP)
+    }
+
+    #[test]
+    fn valid_tree() {
+        assert_eq!(validate(tree()).unwrap(), None);
+        assert_eq!(read(tree()).unwrap(), [(1, ()), (2, ()), (3, ())]);
+    }
+
+    #[test]
+    fn red_child_of_red_node() {
+        let child = BASE + 0xa0;
+        let mut memory = tree();
+        memory.write(MAP + 8, &4u64.to_le_bytes());
+        memory.node(HEAD, [child, ROOT, RIGHT], BLACK, 0);
+        memory.node(LEFT, [child, ROOT, HEAD], RED, 1);
+        memory.node(child, [HEAD, LEFT, HEAD], RED, 0);
+
+        assert_eq!(
+            validate(memory).unwrap(),
+            Some(TreeViolation::RedChild { node: LEFT, child })
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let mut memory = tree();
+        memory.node(RIGHT, [HEAD, ROOT, ROOT], RED, 3);
+
+        // The root is reached again, but its parent is the sentinel:
+        assert_eq!(
+            validate(memory).unwrap(),
+            Some(TreeViolation::Link {
+                node: ROOT,
+                field: "_Parent",
+                found: HEAD,
+                expected: RIGHT,
+            })
+        );
+    }
+
+    #[test]
+    fn overflowing_node_address() {
+        let mut memory = tree();
+        memory.node(ROOT, [LEFT, HEAD, u64::MAX - 8], BLACK, 2);
+
+        let err = validate(memory).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn overflowing_node_address_of_read_map() {
+        let mut memory = tree();
+        memory.node(ROOT, [LEFT, HEAD, u64::MAX - 8], BLACK, 2);
+        assert_eq!(read(memory).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut memory = tree();
+        memory.write(MAP, &(u64::MAX - 4).to_le_bytes());
+        assert_eq!(read(memory).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
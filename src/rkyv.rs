@@ -0,0 +1,123 @@
+//! Integration with the [rkyv](https://rkyv.org) crate.
+//!
+//! Vectors archive as an [`ArchivedVec`] of their archived elements, and strings as
+//! an [`ArchivedVec`] of their archived characters, which can be accessed in place.
+
+use ::rkyv::{
+    rancor::Fallible,
+    ser::{Allocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    Archive, Archived, Deserialize, Place, Serialize,
+};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::Semantics,
+    string::{
+        narrow::{CxxNarrowStringLayout, RawString},
+        utf16::{CxxUtf16StringLayout, RawUtf16String},
+        utf32::{CxxUtf32StringLayout, RawUtf32String},
+        utf8::{CxxUtf8StringLayout, RawUtf8String},
+        wide::{CxxWideStringLayout, RawWideString},
+    },
+    vec::{CxxVecLayout, RawVec},
+};
+
+impl<T, A, L, S> Archive for CxxVecLayout<T, A, L, S>
+where
+    T: Archive,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_slice(self.as_slice(), resolver, out);
+    }
+}
+
+impl<T, A, L, S, Z> Serialize<Z> for CxxVecLayout<T, A, L, S>
+where
+    T: Serialize<Z>,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+    Z: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut Z) -> Result<Self::Resolver, Z::Error> {
+        ArchivedVec::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+impl<T, A, L, S, D> Deserialize<CxxVecLayout<T, A, L, S>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    A: CxxProxy + Default,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<CxxVecLayout<T, A, L, S>, D::Error> {
+        let mut vec = CxxVecLayout::default();
+        vec.reserve(self.len());
+
+        for element in self.iter() {
+            vec.push(element.deserialize(deserializer)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+macro_rules! string_rkyv {
+    ($($string:ident, $raw:ty, $char:ty;)*) => {
+        $(
+            impl<A, L> Archive for $string<A, L>
+            where
+                A: CxxProxy,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+            {
+                type Archived = ArchivedVec<Archived<$char>>;
+                type Resolver = VecResolver;
+
+                fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+                    ArchivedVec::resolve_from_slice(self.as_bytes(), resolver, out);
+                }
+            }
+
+            impl<A, L, Z> Serialize<Z> for $string<A, L>
+            where
+                A: CxxProxy,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+                Z: Fallible + Allocator + Writer + ?Sized,
+            {
+                fn serialize(&self, serializer: &mut Z) -> Result<Self::Resolver, Z::Error> {
+                    ArchivedVec::serialize_from_slice(self.as_bytes(), serializer)
+                }
+            }
+
+            impl<A, L, D> Deserialize<$string<A, L>, D> for ArchivedVec<Archived<$char>>
+            where
+                A: CxxProxy + Default,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+                D: Fallible + ?Sized,
+            {
+                fn deserialize(&self, _: &mut D) -> Result<$string<A, L>, D::Error> {
+                    let chars: Vec<$char> = self.iter().map(|&c| c.into()).collect();
+                    Ok($string::from_bytes_in(chars, A::default()))
+                }
+            }
+        )*
+    };
+}
+
+string_rkyv! {
+    CxxNarrowStringLayout, RawString, u8;
+    CxxWideStringLayout, RawWideString, u16;
+    CxxUtf8StringLayout, RawUtf8String, u8;
+    CxxUtf16StringLayout, RawUtf16String, u16;
+    CxxUtf32StringLayout, RawUtf32String, u32;
+}
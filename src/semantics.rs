@@ -1,32 +1,49 @@
-//! Set of traits describing C++ move and copy semantics.
+//! Set of traits describing C++ move, copy, comparison and hash semantics.
 //!
-//! They provide the "glue" for CSTL in the form of drop, copy and move function tables.
+//! They provide the "glue" for CSTL in the form of drop, copy, move,
+//! comparison and hash function tables.
+//!
+//! Panics can't unwind through CSTL, so the table functions catch them.
+//! What happens next is decided by the crate-wide [`UnwindPolicy`].
 
 use std::{
+    alloc::Layout,
+    any::Any,
+    cell::Cell,
+    ffi::c_void,
+    hash::{DefaultHasher, Hash, Hasher},
     mem::{self, MaybeUninit},
+    panic::{self, AssertUnwindSafe},
+    process,
     ptr::{self, NonNull},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use cstl_sys::{
+    CSTL_Copy, CSTL_Drop, CSTL_Fill, CSTL_Hash, CSTL_IsEq, CSTL_IsLt, CSTL_Move, CSTL_Type,
 };
 
-use cstl_sys::{CSTL_CopyType, CSTL_DropType, CSTL_MoveType, CSTL_Type};
+// Table types, so the custom semantics traits can be implemented without depending on
+// `cstl-sys`, as by the `CxxSemantics` derive:
+pub use cstl_sys::{CSTL_CompType, CSTL_CopyType, CSTL_DropType, CSTL_HashType, CSTL_MoveType};
+#[cfg(feature = "derive")]
+pub use stl_rs_derive::CxxSemantics;
 
 /// Trait for sized types.
 ///
 /// Besides the size and alignment, also provides a [`CSTL_DropType`] table.
+/// Types that don't need dropping share a single no-op drop function.
 pub trait BaseType: Sized {
-    /// CSTL type handle.
-    const TYPE: CSTL_Type = if Self::SIZE & Self::ALIGN == 0 {
-        usize::wrapping_neg(Self::SIZE | Self::ALIGN)
-    } else {
-        Self::SIZE
-    } as CSTL_Type;
+    /// CSTL type handle, packing [`BaseType::SIZE`] and [`BaseType::ALIGN`].
+    const TYPE: CSTL_Type = define_type(Self::SIZE, Self::ALIGN);
 
     /// Size of type.
     ///
-    /// For ZSTs it is equal to 1.
+    /// For ZSTs it is equal to the alignment, as CSTL types can't be zero-sized.
     const SIZE: usize = if mem::size_of::<Self>() != 0 {
         mem::size_of::<Self>()
     } else {
-        1
+        mem::align_of::<Self>()
     };
 
     /// Alignment of type.
@@ -34,18 +51,23 @@ pub trait BaseType: Sized {
 
     /// CSTL destructible type table.
     const DROP: CSTL_DropType = CSTL_DropType {
-        drop: unsafe { Some(mem::transmute(Self::raw_drop as *const ())) },
+        drop: if mem::needs_drop::<Self>() {
+            unsafe { mem::transmute::<*const (), CSTL_Drop>(Self::raw_drop as *const ()) }
+        } else {
+            Some(raw_forget)
+        },
     };
 
+    /// Drops the objects in the range `[first, last)`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`.
     unsafe extern "C" fn raw_drop(first: NonNull<Self>, last: NonNull<Self>) {
-        unsafe {
-            let len = last
-                .offset_from(first)
-                .try_into()
-                .expect("`first` > `last`");
-
+        guard_drop(|| unsafe {
+            let len = range_len(first, last);
             ptr::slice_from_raw_parts_mut(first.as_ptr(), len).drop_in_place();
-        }
+        })
     }
 }
 
@@ -61,20 +83,68 @@ pub trait MoveType: Default + Sized {
     /// CSTL movable type table.
     const MOVE: CSTL_MoveType = CSTL_MoveType {
         drop_type: <Self as BaseType>::DROP,
-        move_: unsafe { Some(mem::transmute(Self::raw_move as *const ())) },
+        move_: unsafe { mem::transmute::<*const (), CSTL_Move>(Self::raw_move as *const ()) },
     };
 
+    /// Moves the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects.
     unsafe extern "C" fn raw_move(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
-        unsafe {
-            for i in 0..last.offset_from(first) {
-                dest.offset(i).write(mem::take(first.offset(i).as_mut()));
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                dest.add(i).write(mem::take(first.add(i).as_mut()));
             }
-        }
+        })
     }
 }
 
 impl<T: Default> MoveType for T {}
 
+/// Trait for types that define their own C++ move.
+///
+/// Unlike [`MoveType`] it doesn't require [`Default`], which move-only handles
+/// often can't implement: the type itself decides what its moved-from state is.
+/// It is implemented for all [`Default`] types by leaving the default value behind.
+///
+/// Containers relocate their elements rather than moving them with C++ semantics
+/// whenever the moved-from value is never observed, so neither trait is needed
+/// to store a type in them.
+///
+/// Provides a [`CSTL_MoveType`] table.
+pub trait CxxMove: Sized {
+    /// CSTL movable type table.
+    const MOVE: CSTL_MoveType = CSTL_MoveType {
+        drop_type: <Self as BaseType>::DROP,
+        move_: unsafe { mem::transmute::<*const (), CSTL_Move>(Self::raw_move as *const ()) },
+    };
+
+    /// Moves the value out of `self`, leaving a valid moved-from value in its place.
+    fn cxx_move(&mut self) -> Self;
+
+    /// Moves the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects.
+    unsafe extern "C" fn raw_move(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                dest.add(i).write(first.add(i).as_mut().cxx_move());
+            }
+        })
+    }
+}
+
+impl<T: Default> CxxMove for T {
+    fn cxx_move(&mut self) -> Self {
+        mem::take(self)
+    }
+}
+
 /// Trait for types that can be copied and moved with C++ semantics.
 ///
 /// Requires [`Clone`], but also [`Default`], see [`MoveType`] for the justification.
@@ -84,24 +154,36 @@ pub trait CopyMoveType: Clone + Default + Sized {
     /// CSTL copyable type table.
     const COPY: CSTL_CopyType = CSTL_CopyType {
         move_type: <Self as MoveType>::MOVE,
-        copy: unsafe { Some(mem::transmute(Self::raw_copy as *const ())) },
-        fill: unsafe { Some(mem::transmute(Self::raw_fill as *const ())) },
+        copy: unsafe { mem::transmute::<*const (), CSTL_Copy>(Self::raw_copy as *const ()) },
+        fill: unsafe { mem::transmute::<*const (), CSTL_Fill>(Self::raw_fill as *const ()) },
     };
 
+    /// Copies the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects.
     unsafe extern "C" fn raw_copy(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
-        unsafe {
-            for i in 0..last.offset_from(first) {
-                dest.offset(i).write(first.offset(i).as_ref().clone());
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                dest.add(i).write(first.add(i).as_ref().clone());
             }
-        }
+        })
     }
 
+    /// Fills the uninitialized range `[first, last)` with copies of `value`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be valid for writes and `value` must point
+    /// to an initialized object of type `Self`.
     unsafe extern "C" fn raw_fill(first: NonNull<Self>, last: NonNull<Self>, value: NonNull<Self>) {
-        unsafe {
-            for i in 0..last.offset_from(first) {
-                first.offset(i).write(value.as_ref().clone());
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                first.add(i).write(value.as_ref().clone());
             }
-        }
+        })
     }
 }
 
@@ -121,38 +203,704 @@ pub trait CopyOnlyType: Clone + Sized {
     const COPY: CSTL_CopyType = CSTL_CopyType {
         move_type: CSTL_MoveType {
             drop_type: <Self as BaseType>::DROP,
-            move_: unsafe { Some(mem::transmute(Self::raw_move as *const ())) },
+            move_: unsafe { mem::transmute::<*const (), CSTL_Move>(Self::raw_move as *const ()) },
         },
-        copy: unsafe { Some(mem::transmute(Self::raw_copy as *const ())) },
-        fill: unsafe { Some(mem::transmute(Self::raw_fill as *const ())) },
+        copy: unsafe { mem::transmute::<*const (), CSTL_Copy>(Self::raw_copy as *const ()) },
+        fill: unsafe { mem::transmute::<*const (), CSTL_Fill>(Self::raw_fill as *const ()) },
     };
 
+    /// Moves the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects.
     unsafe extern "C" fn raw_move(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
-        unsafe {
-            for i in 0..last.offset_from(first) {
-                dest.offset(i).write(first.offset(i).as_ref().clone());
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                dest.add(i).write(first.add(i).as_ref().clone());
             }
-        }
+        })
     }
 
+    /// Copies the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects.
     unsafe extern "C" fn raw_copy(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
-        unsafe {
-            for i in 0..last.offset_from(first) {
-                dest.offset(i).write(first.offset(i).as_ref().clone());
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                dest.add(i).write(first.add(i).as_ref().clone());
+            }
+        })
+    }
+
+    /// Fills the uninitialized range `[first, last)` with copies of `value`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be valid for writes and `value` must point
+    /// to an initialized object of type `Self`.
+    unsafe extern "C" fn raw_fill(first: NonNull<Self>, last: NonNull<Self>, value: NonNull<Self>) {
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                first.add(i).write(value.as_ref().clone());
+            }
+        })
+    }
+}
+
+impl<T: Clone> CopyOnlyType for T {}
+
+/// Trait for types that can be value-initialized, like `T()` in C++.
+///
+/// Provides a [`CSTL_CopyType`] table for growing vectors with `CSTL_vector_resize`,
+/// whose fill function writes [`Default::default`] values and ignores the value
+/// it is passed. The table has no copy function, as `Self` may not be copyable,
+/// so it may only grow vectors within their capacity, where CSTL doesn't copy.
+pub trait DefaultConstructType: Default + Sized {
+    /// CSTL value-initializing type table.
+    const DEFAULT: CSTL_CopyType = CSTL_CopyType {
+        move_type: <Self as TrivialRelocate>::RELOCATE,
+        copy: None,
+        fill: unsafe {
+            mem::transmute::<*const (), CSTL_Fill>(Self::raw_fill_default as *const ())
+        },
+    };
+
+    /// Fills the uninitialized range `[first, last)` with default values.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be valid for writes. `_value` is never read and may be null.
+    unsafe extern "C" fn raw_fill_default(
+        first: NonNull<Self>,
+        last: NonNull<Self>,
+        _value: *const Self,
+    ) {
+        guard_abort(|| unsafe {
+            for i in 0..range_len(first, last) {
+                first.add(i).write(Self::default());
             }
+        })
+    }
+}
+
+impl<T: Default> DefaultConstructType for T {}
+
+/// Trait for types with a total order.
+///
+/// Provides a [`CSTL_CompType`] table for ordered containers.
+pub trait CompareType: Ord + Sized {
+    /// CSTL comparable type table.
+    const COMPARE: CSTL_CompType = CSTL_CompType {
+        is_eq: unsafe { mem::transmute::<*const (), CSTL_IsEq>(Self::raw_is_eq as *const ()) },
+        is_lt: unsafe { mem::transmute::<*const (), CSTL_IsLt>(Self::raw_is_lt as *const ()) },
+    };
+
+    /// Compares `lhs` and `rhs` for equality.
+    ///
+    /// # Safety
+    ///
+    /// `lhs` and `rhs` must point to initialized objects of type `Self`.
+    unsafe extern "C" fn raw_is_eq(lhs: NonNull<Self>, rhs: NonNull<Self>) -> bool {
+        guard_abort(|| unsafe { lhs.as_ref() == rhs.as_ref() })
+    }
+
+    /// Compares `lhs` and `rhs` for less than.
+    ///
+    /// # Safety
+    ///
+    /// `lhs` and `rhs` must point to initialized objects of type `Self`.
+    unsafe extern "C" fn raw_is_lt(lhs: NonNull<Self>, rhs: NonNull<Self>) -> bool {
+        guard_abort(|| unsafe { lhs.as_ref() < rhs.as_ref() })
+    }
+}
+
+impl<T: Ord> CompareType for T {}
+
+/// Trait for types that can be hashed and compared for equality.
+///
+/// Hashes are computed with [`DefaultHasher`] and don't match any C++ `std::hash`.
+///
+/// Provides a [`CSTL_HashType`] table for unordered containers.
+pub trait HashType: Hash + Eq + Sized {
+    /// CSTL hashable type table.
+    const HASH: CSTL_HashType = CSTL_HashType {
+        is_eq: unsafe { mem::transmute::<*const (), CSTL_IsEq>(Self::raw_is_eq as *const ()) },
+        hash: unsafe { mem::transmute::<*const (), CSTL_Hash>(Self::raw_hash as *const ()) },
+    };
+
+    /// Compares `lhs` and `rhs` for equality.
+    ///
+    /// # Safety
+    ///
+    /// `lhs` and `rhs` must point to initialized objects of type `Self`.
+    unsafe extern "C" fn raw_is_eq(lhs: NonNull<Self>, rhs: NonNull<Self>) -> bool {
+        guard_abort(|| unsafe { lhs.as_ref() == rhs.as_ref() })
+    }
+
+    /// Hashes the object at `instance`.
+    ///
+    /// # Safety
+    ///
+    /// `instance` must point to an initialized object of type `Self`.
+    unsafe extern "C" fn raw_hash(instance: NonNull<Self>) -> usize {
+        guard_abort(|| {
+            let mut hasher = DefaultHasher::new();
+            unsafe { instance.as_ref() }.hash(&mut hasher);
+            hasher.finish() as usize
+        })
+    }
+}
+
+impl<T: Hash + Eq> HashType for T {}
+
+/// Escape hatch for types that provide their own CSTL tables,
+/// e.g. to call a C++ copy constructor through a function pointer.
+///
+/// Containers only use these tables when their semantics parameter is [`Custom`],
+/// see [`Semantics`].
+///
+/// # Safety
+///
+/// The tables must implement the operations documented on the CSTL table types
+/// for objects of type `Self`.
+pub unsafe trait CustomSemantics: Sized {
+    /// CSTL destructible type table.
+    const DROP: CSTL_DropType = <Self as BaseType>::DROP;
+
+    /// CSTL movable type table used when a container relocates its storage.
+    ///
+    /// Its drop table is called on the moved-from objects afterwards.
+    const MOVE: CSTL_MoveType = <Self as TrivialRelocate>::RELOCATE;
+}
+
+/// Extends [`CustomSemantics`] with a copy table.
+///
+/// # Safety
+///
+/// See [`CustomSemantics`].
+pub unsafe trait CustomCopySemantics: CustomSemantics {
+    /// CSTL copyable type table.
+    const COPY: CSTL_CopyType;
+}
+
+/// Extends [`CustomSemantics`] with a comparison table,
+/// e.g. to match the comparator of a game's `std::map`.
+///
+/// # Safety
+///
+/// See [`CustomSemantics`]. The table must establish a strict weak ordering.
+pub unsafe trait CustomCompareSemantics: CustomSemantics {
+    /// CSTL comparable type table.
+    const COMPARE: CSTL_CompType;
+}
+
+/// Extends [`CustomSemantics`] with a hash table,
+/// e.g. to match the hasher of a game's `std::unordered_map`.
+///
+/// # Safety
+///
+/// See [`CustomSemantics`]. Equal objects must have equal hashes.
+pub unsafe trait CustomHashSemantics: CustomSemantics {
+    /// CSTL hashable type table.
+    const HASH: CSTL_HashType;
+}
+
+/// Selects the CSTL tables a container uses for its elements of type `T`.
+///
+/// Values are moved into and out of containers bitwise like any Rust value,
+/// so the tables are used for dropping, copying and reallocating elements in place.
+/// Elements are swapped bitwise too, which is what `std::swap` amounts to
+/// for relocatable types.
+pub trait Semantics<T> {
+    /// Table used to drop elements.
+    const DROP: CSTL_DropType;
+
+    /// Table used to relocate the elements when reallocating storage
+    /// or shifting them within it.
+    const MOVE: CSTL_MoveType;
+
+    /// Whether [`Semantics::MOVE`] relocates elements by copying their bytes,
+    /// so containers can shift whole ranges with [`ptr::copy`] instead.
+    const RELOCATE_BITWISE: bool = false;
+}
+
+/// Extends [`Semantics`] for containers of copyable elements.
+pub trait CopySemantics<T>: Semantics<T> {
+    /// Table used to copy elements.
+    const COPY: CSTL_CopyType;
+
+    /// Function cloning an element on the Rust side, if the copy table clones with it.
+    ///
+    /// Containers then clone elements one at a time instead of copying them with
+    /// [`CopySemantics::COPY`], so a panicking clone unwinds normally and leaves them
+    /// holding the elements cloned so far, rather than aborting inside CSTL.
+    const CLONE: Option<fn(&T) -> T> = None;
+}
+
+/// Extends [`Semantics`] for containers ordered by their elements or keys.
+pub trait CompareSemantics<T>: Semantics<T> {
+    /// Table used to order elements.
+    const COMPARE: CSTL_CompType;
+}
+
+/// Extends [`Semantics`] for containers hashing their elements or keys.
+pub trait HashSemantics<T>: Semantics<T> {
+    /// Table used to hash and compare elements.
+    const HASH: CSTL_HashType;
+}
+
+/// Default [`Semantics`]: elements are dropped with [`Drop`], relocated
+/// by copying their bytes, copied with [`Clone`], ordered with [`Ord`]
+/// and hashed with [`Hash`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RustSemantics;
+
+impl<T> Semantics<T> for RustSemantics {
+    const DROP: CSTL_DropType = <T as BaseType>::DROP;
+    const MOVE: CSTL_MoveType = <T as TrivialRelocate>::RELOCATE;
+
+    const RELOCATE_BITWISE: bool = true;
+}
+
+impl<T: Clone> CopySemantics<T> for RustSemantics {
+    const COPY: CSTL_CopyType = DefaultUninit::<T>::RELOCATE_CLONE;
+
+    const CLONE: Option<fn(&T) -> T> = Some(T::clone);
+}
+
+impl<T: Ord> CompareSemantics<T> for RustSemantics {
+    const COMPARE: CSTL_CompType = <T as CompareType>::COMPARE;
+}
+
+impl<T: Hash + Eq> HashSemantics<T> for RustSemantics {
+    const HASH: CSTL_HashType = <T as HashType>::HASH;
+}
+
+/// [`Semantics`] that use the tables of the element's [`CustomSemantics`] impl.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Custom;
+
+impl<T: CustomSemantics> Semantics<T> for Custom {
+    const DROP: CSTL_DropType = <T as CustomSemantics>::DROP;
+    const MOVE: CSTL_MoveType = <T as CustomSemantics>::MOVE;
+}
+
+impl<T: CustomCopySemantics> CopySemantics<T> for Custom {
+    const COPY: CSTL_CopyType = <T as CustomCopySemantics>::COPY;
+}
+
+impl<T: CustomCompareSemantics> CompareSemantics<T> for Custom {
+    const COMPARE: CSTL_CompType = <T as CustomCompareSemantics>::COMPARE;
+}
+
+impl<T: CustomHashSemantics> HashSemantics<T> for Custom {
+    const HASH: CSTL_HashType = <T as CustomHashSemantics>::HASH;
+}
+
+/// [`Semantics`] for trivially copyable elements, which are moved and copied
+/// in whole ranges with the tables of their [`TrivialType`] impl.
+///
+/// Unlike with [`RustSemantics`], copies aren't made with [`Clone`] one element
+/// at a time, so e.g. cloning a `CxxTrivialVec<u32>` is a single `memcpy`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Trivial;
+
+impl<T: TrivialType> Semantics<T> for Trivial {
+    const DROP: CSTL_DropType = <T as TrivialType>::TRIVIAL_MOVE.drop_type;
+    const MOVE: CSTL_MoveType = <T as TrivialType>::TRIVIAL_MOVE;
+
+    const RELOCATE_BITWISE: bool = true;
+}
+
+impl<T: TrivialType> CopySemantics<T> for Trivial {
+    const COPY: CSTL_CopyType = <T as TrivialType>::TRIVIAL_COPY;
+}
+
+impl<T: TrivialType + Ord> CompareSemantics<T> for Trivial {
+    const COMPARE: CSTL_CompType = <T as CompareType>::COMPARE;
+}
+
+impl<T: TrivialType + Hash + Eq> HashSemantics<T> for Trivial {
+    const HASH: CSTL_HashType = <T as HashType>::HASH;
+}
+
+// Pointers to C++ objects, like the elements of `std::vector<T*>`, are trivial,
+// with `None` as the null pointer thanks to the niche of `NonNull`:
+unsafe impl<T> CustomSemantics for NonNull<T> {
+    const MOVE: CSTL_MoveType = <Self as TrivialType>::TRIVIAL_MOVE;
+}
+
+unsafe impl<T> CustomCopySemantics for NonNull<T> {
+    const COPY: CSTL_CopyType = <Self as TrivialType>::TRIVIAL_COPY;
+}
+
+unsafe impl<T> CustomSemantics for Option<NonNull<T>> {
+    const MOVE: CSTL_MoveType = <Self as TrivialType>::TRIVIAL_MOVE;
+}
+
+unsafe impl<T> CustomCopySemantics for Option<NonNull<T>> {
+    const COPY: CSTL_CopyType = <Self as TrivialType>::TRIVIAL_COPY;
+}
+
+const _: () = assert!(mem::size_of::<Option<NonNull<u8>>>() == mem::size_of::<*mut u8>());
+
+/// Trait for types that can be moved by copying their bytes, leaving the source uninitialized.
+///
+/// Every Rust type can be moved like this, including ones that are neither [`Clone`]
+/// nor [`Default`] such as [`Box`], [`String`] or [`Vec`], so containers relocate
+/// their elements with this table unless their [`Semantics`] say otherwise.
+///
+/// Provides a [`CSTL_MoveType`] table with a drop function that does nothing,
+/// as CSTL drops the moved-from objects, which are left without an owner.
+/// The table doesn't depend on the type, so all instantiations share its functions.
+pub trait TrivialRelocate: Sized {
+    /// CSTL relocatable type table.
+    const RELOCATE: CSTL_MoveType = CSTL_MoveType {
+        drop_type: CSTL_DropType {
+            drop: Some(raw_forget),
+        },
+        move_: Some(raw_relocate_bytes),
+    };
+}
+
+impl<T> TrivialRelocate for T {}
+
+/// Trait for types that can be copied and moved by copying their bytes,
+/// like trivially copyable C++ types.
+///
+/// Provides [`CSTL_MoveType`] and [`CSTL_CopyType`] tables that copy whole ranges
+/// with `memmove` and `memcpy`, instead of moving or cloning elements one by one.
+/// Their functions only depend on the layout of the type, so they are shared
+/// between all trivial types, and fill functions between types of common sizes.
+///
+/// Containers with [`RustSemantics`] don't use these tables, as they clone elements
+/// with [`Clone`]. [`Trivial`] semantics use them for all moves and copies, and
+/// [`CxxVecLayout::extend_from_copied_slice`](crate::vec::CxxVecLayout::extend_from_copied_slice)
+/// with any semantics.
+///
+/// # Safety
+///
+/// A bitwise copy of a value must be a valid copy of it, and the type must not need dropping.
+/// This holds for all [`Copy`] types, which implement this trait automatically.
+pub unsafe trait TrivialType: Copy {
+    /// CSTL trivially movable type table.
+    const TRIVIAL_MOVE: CSTL_MoveType = CSTL_MoveType {
+        drop_type: CSTL_DropType {
+            drop: Some(raw_forget),
+        },
+        move_: Some(raw_relocate_bytes),
+    };
+
+    /// CSTL trivially copyable type table.
+    const TRIVIAL_COPY: CSTL_CopyType = CSTL_CopyType {
+        move_type: Self::TRIVIAL_MOVE,
+        copy: Some(raw_copy_bytes),
+        fill: match Self::SIZE {
+            1 => Some(raw_fill_bytes::<1>),
+            2 => Some(raw_fill_bytes::<2>),
+            4 => Some(raw_fill_bytes::<4>),
+            8 => Some(raw_fill_bytes::<8>),
+            12 => Some(raw_fill_bytes::<12>),
+            16 => Some(raw_fill_bytes::<16>),
+            24 => Some(raw_fill_bytes::<24>),
+            32 => Some(raw_fill_bytes::<32>),
+            _ => unsafe { mem::transmute::<*const (), CSTL_Fill>(Self::raw_fill as *const ()) },
+        },
+    };
+
+    /// Copies the objects in the range `[first, last)` to uninitialized memory at `dest`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be a valid range of initialized objects of type `Self`,
+    /// and `dest` must be valid for writes of `last - first` objects that don't overlap it.
+    unsafe extern "C" fn raw_copy(first: NonNull<Self>, last: NonNull<Self>, dest: NonNull<Self>) {
+        unsafe {
+            let len = range_len(first, last);
+            ptr::copy_nonoverlapping(first.as_ptr(), dest.as_ptr(), len);
         }
     }
 
+    /// Fills the uninitialized range `[first, last)` with copies of `value`.
+    ///
+    /// # Safety
+    ///
+    /// `[first, last)` must be valid for writes and `value` must point
+    /// to an initialized object of type `Self`.
     unsafe extern "C" fn raw_fill(first: NonNull<Self>, last: NonNull<Self>, value: NonNull<Self>) {
         unsafe {
-            for i in 0..last.offset_from(first) {
-                first.offset(i).write(value.as_ref().clone());
+            let len = range_len(first, last);
+            let value = value.read();
+
+            ptr::slice_from_raw_parts_mut(first.as_ptr() as *mut MaybeUninit<Self>, len)
+                .as_mut()
+                .unwrap_unchecked()
+                .fill(MaybeUninit::new(value));
+        }
+    }
+}
+
+unsafe impl<T: Copy> TrivialType for T {}
+
+/// Size, alignment and tables of a type that is only known at runtime,
+/// e.g. a reverse-engineered struct described by a config file.
+///
+/// Objects are relocated by copying their bytes, like Rust values.
+/// CSTL operations that fill ranges with copies of a value aren't supported,
+/// as a fill function has no way of knowing the size of the value.
+#[derive(Clone, Copy, Debug)]
+pub struct DynType {
+    layout: Layout,
+    drop: CSTL_DropType,
+    copy: CSTL_CopyType,
+}
+
+impl DynType {
+    /// Describes a trivially copyable type with `layout`, padded to its alignment.
+    ///
+    /// Returns `None` if the layout is zero-sized.
+    pub const fn trivial(layout: Layout) -> Option<Self> {
+        unsafe {
+            Self::from_tables(
+                layout,
+                <DefaultUninit<u8> as BaseType>::DROP.drop,
+                <u8 as TrivialType>::TRIVIAL_COPY.copy,
+            )
+        }
+    }
+
+    /// Describes a type with `layout`, padded to its alignment,
+    /// that is dropped with `drop` and copied with `copy`.
+    ///
+    /// Returns `None` if the layout is zero-sized or a function is missing.
+    ///
+    /// # Safety
+    ///
+    /// `drop` and `copy` must implement [`CSTL_Drop`] and [`CSTL_Copy`] for objects
+    /// of the described type, which must be valid for any initialized bytes
+    /// written to them through a container.
+    pub const unsafe fn from_tables(
+        layout: Layout,
+        drop: CSTL_Drop,
+        copy: CSTL_Copy,
+    ) -> Option<Self> {
+        let layout = layout.pad_to_align();
+
+        if layout.size() == 0 || drop.is_none() || copy.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            layout,
+            drop: CSTL_DropType { drop },
+            copy: CSTL_CopyType {
+                move_type: <u8 as TrivialRelocate>::RELOCATE,
+                copy,
+                fill: None,
+            },
+        })
+    }
+
+    /// Layout of the type.
+    pub const fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Size of the type.
+    pub const fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// CSTL type handle.
+    pub const fn cstl_type(&self) -> CSTL_Type {
+        define_type(self.layout.size(), self.layout.align())
+    }
+
+    /// CSTL destructible type table.
+    pub const fn drop_type(&self) -> &CSTL_DropType {
+        &self.drop
+    }
+
+    /// CSTL movable type table that relocates objects.
+    pub const fn move_type(&self) -> &CSTL_MoveType {
+        &self.copy.move_type
+    }
+
+    /// CSTL copyable type table without a fill function.
+    pub const fn copy_type(&self) -> &CSTL_CopyType {
+        &self.copy
+    }
+}
+
+/// Moves the objects in the range `[first, last)` to `dest` by copying their bytes,
+/// leaving the source range logically uninitialized.
+///
+/// # Safety
+///
+/// `[first, last)` must be a valid range of initialized objects,
+/// and `dest` must be valid for writes of `last - first` bytes. The ranges may overlap.
+unsafe extern "C" fn raw_relocate_bytes(first: *mut c_void, last: *mut c_void, dest: *mut c_void) {
+    unsafe {
+        let len = byte_len(first, last);
+        ptr::copy(first as *const u8, dest as *mut u8, len);
+    }
+}
+
+/// Copies the objects in the range `[first, last)` to `dest` by copying their bytes.
+///
+/// # Safety
+///
+/// `[first, last)` must be a valid range of initialized objects, and `dest` must be
+/// valid for writes of `last - first` bytes that don't overlap it.
+unsafe extern "C" fn raw_copy_bytes(first: *const c_void, last: *const c_void, dest: *mut c_void) {
+    unsafe {
+        let len = byte_len(first, last);
+        ptr::copy_nonoverlapping(first as *const u8, dest as *mut u8, len);
+    }
+}
+
+/// Fills the uninitialized range `[first, last)` with copies of the `N` bytes at `value`.
+///
+/// # Safety
+///
+/// `[first, last)` must be valid for writes of a multiple of `N` bytes,
+/// and `value` must point to an initialized object of `N` bytes.
+unsafe extern "C" fn raw_fill_bytes<const N: usize>(
+    first: *mut c_void,
+    last: *mut c_void,
+    value: *const c_void,
+) {
+    unsafe {
+        let len = byte_len(first, last) / N;
+        let value = (value as *const [MaybeUninit<u8>; N]).read_unaligned();
+
+        for i in 0..len {
+            (first as *mut [MaybeUninit<u8>; N])
+                .add(i)
+                .write_unaligned(value);
+        }
+    }
+}
+
+/// Packs `size` and `alignment` into a CSTL type handle, like `CSTL_define_type`.
+///
+/// Sizes with the alignment bit set are stored as is, others are stored negated with
+/// the alignment bit set, so every alignment the size is a multiple of is representable.
+///
+/// # Panics
+///
+/// Panics if `size` is zero or over `isize::MAX`, if `alignment` isn't a power of two,
+/// or if `size` isn't a multiple of `alignment`. For [`BaseType::TYPE`] this is
+/// a compile-time error.
+const fn define_type(size: usize, alignment: usize) -> CSTL_Type {
+    assert!(
+        size != 0 && size <= isize::MAX as usize,
+        "CSTL types must have a non-zero size of at most `isize::MAX`"
+    );
+    assert!(
+        alignment.is_power_of_two(),
+        "CSTL type alignment must be a power of two"
+    );
+    assert!(
+        size.is_multiple_of(alignment),
+        "CSTL type size must be a multiple of its alignment"
+    );
+
+    let (size, alignment) = (size as isize, alignment as isize);
+
+    if size & alignment != 0 {
+        size as CSTL_Type
+    } else {
+        -(size | alignment) as CSTL_Type
+    }
+}
+
+/// Leaves the objects in the range `[first, last)` as they are,
+/// for relocated objects and types that don't need dropping.
+unsafe extern "C" fn raw_forget(_first: *mut c_void, _last: *mut c_void) {}
+
+/// Number of bytes in the range `[first, last)`.
+fn byte_len(first: *const c_void, last: *const c_void) -> usize {
+    (last as usize)
+        .checked_sub(first as usize)
+        .expect("`first` > `last`")
+}
+
+/// Number of objects in the range `[first, last)`.
+///
+/// CSTL lays out zero-sized types [`BaseType::SIZE`] bytes apart.
+fn range_len<T>(first: NonNull<T>, last: NonNull<T>) -> usize {
+    let bytes = (last.as_ptr() as usize)
+        .checked_sub(first.as_ptr() as usize)
+        .expect("`first` > `last`");
+
+    bytes / <T as BaseType>::SIZE
+}
+
+/// Behavior of the table functions when a [`Drop`], [`Clone`] or [`Default`] impl panics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum UnwindPolicy {
+    /// Abort the process.
+    #[default]
+    Abort,
+    /// Finish dropping the remaining objects and resume the panic once CSTL returns.
+    ///
+    /// CSTL can't be told that constructing an object failed, so panics while
+    /// moving or copying objects still abort. Containers clone elements with
+    /// [`CopySemantics::CLONE`] outside of CSTL, where panics unwind regardless.
+    Rethrow,
+}
+
+static UNWIND_POLICY: AtomicU8 = AtomicU8::new(UnwindPolicy::Abort as u8);
+
+thread_local! {
+    static PENDING_PANIC: Cell<Option<Box<dyn Any + Send>>> = const { Cell::new(None) };
+}
+
+/// Sets the crate-wide [`UnwindPolicy`].
+pub fn set_unwind_policy(policy: UnwindPolicy) {
+    UNWIND_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the crate-wide [`UnwindPolicy`].
+pub fn unwind_policy() -> UnwindPolicy {
+    match UNWIND_POLICY.load(Ordering::Relaxed) {
+        1 => UnwindPolicy::Rethrow,
+        _ => UnwindPolicy::Abort,
+    }
+}
+
+/// Resumes a panic caught by a table function, if there is one.
+///
+/// Must be called after every CSTL call that may drop objects.
+pub(crate) fn resume_pending_panic() {
+    if let Some(payload) = PENDING_PANIC.take() {
+        panic::resume_unwind(payload);
+    }
+}
+
+fn guard_drop(f: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        match unwind_policy() {
+            UnwindPolicy::Abort => process::abort(),
+            UnwindPolicy::Rethrow => {
+                // Only the first panic is resumed, like when unwinding out of a drop:
+                let pending = PENDING_PANIC.take().unwrap_or(payload);
+                PENDING_PANIC.set(Some(pending));
             }
         }
     }
 }
 
-impl<T: Clone> CopyOnlyType for T {}
+fn guard_abort<R>(f: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| process::abort())
+}
 
 pub(crate) struct DefaultUninit<T>(MaybeUninit<T>);
 
@@ -170,6 +918,16 @@ impl<T> DefaultUninit<T> {
     }
 }
 
+impl<T: Clone> DefaultUninit<T> {
+    /// Copyable type table that relocates values when moving them,
+    /// and copies `T` values by cloning them.
+    pub const RELOCATE_CLONE: CSTL_CopyType = CSTL_CopyType {
+        move_type: <T as TrivialRelocate>::RELOCATE,
+        copy: <T as CopyOnlyType>::COPY.copy,
+        fill: <T as CopyOnlyType>::COPY.fill,
+    };
+}
+
 impl<T: Clone> Clone for DefaultUninit<T> {
     fn clone(&self) -> Self {
         unsafe { Self((&self.0 as *const MaybeUninit<T>).read()) }
@@ -0,0 +1,162 @@
+//! Integration with the [serde](https://serde.rs) crate.
+//!
+//! Vectors serialize as sequences of their elements, strings of bytes as byte strings
+//! and other strings as sequences of their characters, all without being copied.
+//! Both deserialize from sequences, and strings of bytes also from byte strings.
+//! A `&CxxSlice<u8>` deserializes by borrowing a byte string from the input.
+
+use std::{fmt, marker::PhantomData};
+
+use ::serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::Semantics,
+    string::{
+        narrow::{CxxNarrowStringLayout, RawString},
+        utf16::{CxxUtf16StringLayout, RawUtf16String},
+        utf32::{CxxUtf32StringLayout, RawUtf32String},
+        utf8::{CxxUtf8StringLayout, RawUtf8String},
+        wide::{CxxWideStringLayout, RawWideString},
+    },
+    vec::{CxxSlice, CxxVecLayout, RawVec},
+};
+
+impl<T, A, L, S> Serialize for CxxVecLayout<T, A, L, S>
+where
+    T: Serialize,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+impl<'de, T, A, L, S> Deserialize<'de> for CxxVecLayout<T, A, L, S>
+where
+    T: Deserialize<'de>,
+    A: CxxProxy + Default,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SeqVisitor(PhantomData))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a CxxSlice<u8> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(BorrowedBytesVisitor)
+    }
+}
+
+/// Visitor borrowing a byte string from the deserializer's input.
+struct BorrowedBytesVisitor;
+
+impl<'de> Visitor<'de> for BorrowedBytesVisitor {
+    type Value = &'de CxxSlice<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a borrowed byte string")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(CxxSlice::from_slice(v))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        self.visit_borrowed_bytes(v.as_bytes())
+    }
+}
+
+/// Visitor extending a default constructed container with the elements of a sequence.
+struct SeqVisitor<C, T>(PhantomData<fn() -> (C, T)>);
+
+impl<'de, C, T> Visitor<'de> for SeqVisitor<C, T>
+where
+    C: Default + Extend<T>,
+    T: Deserialize<'de>,
+{
+    type Value = C;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<Q: SeqAccess<'de>>(self, mut seq: Q) -> Result<Self::Value, Q::Error> {
+        let mut container = C::default();
+
+        while let Some(element) = seq.next_element()? {
+            container.extend([element]);
+        }
+
+        Ok(container)
+    }
+}
+
+/// Like [`SeqVisitor`], but also accepting byte strings and strings.
+struct ByteStringVisitor<C>(PhantomData<fn() -> C>);
+
+impl<'de, C> Visitor<'de> for ByteStringVisitor<C>
+where
+    C: Default + Extend<u8>,
+{
+    type Value = C;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte string")
+    }
+
+    fn visit_seq<Q: SeqAccess<'de>>(self, seq: Q) -> Result<Self::Value, Q::Error> {
+        SeqVisitor::<C, u8>(PhantomData).visit_seq(seq)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        let mut container = C::default();
+        container.extend(v.iter().copied());
+        Ok(container)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+}
+
+macro_rules! string_serde {
+    ($($string:ident, $raw:ty, $serialize:ident, $deserialize:ident, $visitor:expr;)*) => {
+        $(
+            impl<A, L> Serialize for $string<A, L>
+            where
+                A: CxxProxy,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+            {
+                fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+                    serializer.$serialize(self.as_bytes())
+                }
+            }
+
+            impl<'de, A, L> Deserialize<'de> for $string<A, L>
+            where
+                A: CxxProxy + Default,
+                L: WithCxxProxy<Alloc = A, Value = $raw>,
+            {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserializer.$deserialize($visitor)
+                }
+            }
+        )*
+    };
+}
+
+string_serde! {
+    CxxNarrowStringLayout, RawString, serialize_bytes, deserialize_byte_buf, ByteStringVisitor(PhantomData);
+    CxxWideStringLayout, RawWideString, collect_seq, deserialize_seq, SeqVisitor::<_, u16>(PhantomData);
+    CxxUtf8StringLayout, RawUtf8String, serialize_bytes, deserialize_byte_buf, ByteStringVisitor(PhantomData);
+    CxxUtf16StringLayout, RawUtf16String, collect_seq, deserialize_seq, SeqVisitor::<_, u16>(PhantomData);
+    CxxUtf32StringLayout, RawUtf32String, collect_seq, deserialize_seq, SeqVisitor::<_, u32>(PhantomData);
+}
@@ -0,0 +1,500 @@
+//! Strings with the layouts of libstdc++, selected by the `gnu-abi` feature.
+//!
+//! CSTL only implements the MSVC strings, so these are implemented in Rust.
+//! A libstdc++ `std::basic_string` holds the pointer to its characters `_M_p`, after
+//! the allocator as an empty base, and their length, followed by a union of a small
+//! string buffer `_M_local_buf` and the capacity of long strings. Short strings
+//! point `_M_p` into their own buffer, so they can't be moved like Rust values.
+//!
+//! Like the containers in [`ffi::Pinned`](crate::ffi::Pinned), the strings are only
+//! modified through pinned references. They are constructed in place, either
+//! in a [`Box`] with e.g. [`CxxBasicString::pin_in`] or in storage provided by C++ code
+//! with [`CxxBasicString::init_in_place_in`], and are never moved afterwards.
+
+use std::{
+    alloc::System as SysAlloc,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, MaybeUninit},
+    ops::Index,
+    pin::Pin,
+    ptr,
+    slice::{self, SliceIndex},
+};
+
+use super::{alloc_units, free_units, handle_reserve_error, CharUnit, RawDebug};
+use crate::alloc::{growth_policy, CxxProxy, GrowthPolicy, TryReserveError};
+
+pub type CxxNarrowString<A = SysAlloc> = CxxBasicString<u8, A>;
+
+/// `std::wstring`, whose `wchar_t` is 16-bit on Windows and 32-bit elsewhere.
+#[cfg(windows)]
+pub type CxxWideString<A = SysAlloc> = CxxBasicString<u16, A>;
+
+/// `std::wstring`, whose `wchar_t` is 16-bit on Windows and 32-bit elsewhere.
+#[cfg(not(windows))]
+pub type CxxWideString<A = SysAlloc> = CxxBasicString<u32, A>;
+
+pub type CxxUtf8String<A = SysAlloc> = CxxBasicString<u8, A>;
+
+pub type CxxUtf16String<A = SysAlloc> = CxxBasicString<u16, A>;
+
+pub type CxxUtf32String<A = SysAlloc> = CxxBasicString<u32, A>;
+
+/// `std::basic_string` of `C` characters with the libstdc++ layout.
+///
+/// Short strings point into themselves, so the string is `!Unpin`
+/// and only modified through [`Pin<&mut Self>`].
+#[repr(C)]
+pub struct CxxBasicString<C: CharUnit, A: CxxProxy = SysAlloc> {
+    alloc: A,
+    /// `_M_p`, pointing to `local` in short mode.
+    p: *mut C,
+    /// `_M_string_length`.
+    len: usize,
+    local: Local,
+    _marker: PhantomData<C>,
+    _pin: PhantomPinned,
+}
+
+/// `_M_local_buf` of short strings, or `_M_allocated_capacity` of long strings.
+#[repr(C)]
+#[derive(Clone, Copy)]
+union Local {
+    buf: [u8; 16],
+    capacity: usize,
+}
+
+/// `max_size()` of a string of `C` characters.
+const fn max_size<C>() -> usize {
+    (isize::MAX as usize / mem::size_of::<C>() - 1) / 2
+}
+
+impl<C: CharUnit> CxxBasicString<C, SysAlloc> {
+    /// Constructs an empty string in a [`Box`].
+    pub fn pin() -> Pin<Box<Self>> {
+        Self::pin_in(SysAlloc)
+    }
+
+    /// Constructs an empty string in the storage at `ptr`,
+    /// see [`CxxBasicString::init_in_place_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxBasicString::init_in_place_in`].
+    pub unsafe fn init_at(ptr: *mut Self) {
+        unsafe { Self::init_in_place_in(ptr, SysAlloc) }
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> CxxBasicString<C, A> {
+    /// Size of the string in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the string in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Capacity in characters of the small string buffer, `_S_local_capacity`.
+    /// Shorter strings are stored in the string itself.
+    pub const SMALL_CAPACITY: usize = 15 / mem::size_of::<C>();
+
+    /// Constructs an empty string with `alloc` in the storage at `ptr`,
+    /// e.g. the `this` pointer passed to a hooked C++ constructor.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned to [`Self::ALIGN`].
+    /// A value previously stored there is overwritten without being dropped.
+    /// The string must not be moved afterwards, as it points to itself.
+    pub unsafe fn init_in_place_in(ptr: *mut Self, alloc: A) {
+        unsafe {
+            ptr.write(Self {
+                alloc,
+                p: ptr::null_mut(),
+                len: 0,
+                local: Local { buf: [0; 16] },
+                _marker: PhantomData,
+                _pin: PhantomPinned,
+            });
+
+            (*ptr).p = ptr::addr_of_mut!((*ptr).local).cast();
+        }
+    }
+
+    /// Constructs an empty string with `alloc` in a [`Box`].
+    pub fn pin_in(alloc: A) -> Pin<Box<Self>> {
+        let slot = Box::into_raw(Box::new(MaybeUninit::<Self>::uninit())).cast::<Self>();
+
+        unsafe {
+            Self::init_in_place_in(slot, alloc);
+            // The string stays at `slot`, as the box is pinned:
+            Pin::new_unchecked(Box::from_raw(slot))
+        }
+    }
+
+    pub fn pin_from_bytes_in<T: AsRef<[C]>>(s: T, alloc: A) -> Pin<Box<Self>> {
+        let mut new = Self::pin_in(alloc);
+        new.as_mut().push(s);
+        new
+    }
+
+    pub fn try_pin_from_bytes_in<T: AsRef<[C]>>(
+        s: T,
+        alloc: A,
+    ) -> Result<Pin<Box<Self>>, TryReserveError> {
+        let mut new = Self::pin_in(alloc);
+        new.as_mut().try_push(s)?;
+        Ok(new)
+    }
+
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub fn as_ptr(&self) -> *const C {
+        if self.is_long() {
+            self.p
+        } else {
+            self.local_ptr()
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[C] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    pub fn as_bytes_with_nul(&self) -> &[C] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len + 1) }
+    }
+
+    /// Returns the character or characters at `index`,
+    /// or `None` if it is out of bounds.
+    pub fn get<I: SliceIndex<[C]>>(&self, index: I) -> Option<&I::Output> {
+        self.as_bytes().get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        if self.is_long() {
+            unsafe { self.local.capacity }
+        } else {
+            Self::SMALL_CAPACITY
+        }
+    }
+
+    pub fn push<T: AsRef<[C]>>(self: Pin<&mut Self>, s: T) {
+        handle_reserve_error(self.try_push(s));
+    }
+
+    pub fn try_push<T: AsRef<[C]>>(self: Pin<&mut Self>, s: T) -> Result<(), TryReserveError> {
+        // The characters are moved within the string, which itself stays in place:
+        let this = unsafe { self.get_unchecked_mut() };
+        let slice = s.as_ref();
+        let len = this.len;
+        let capacity = this.capacity();
+        let required = len
+            .checked_add(slice.len())
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        if required > capacity {
+            // Grows like `_M_create`, to at least twice the capacity:
+            let grown = growth_policy()
+                .grown_capacity(GrowthPolicy::Double, capacity, required)
+                .min(max_size::<C>())
+                .max(required);
+
+            this.reallocate(grown)?;
+        }
+
+        unsafe {
+            let end = this.as_mut_ptr().add(len);
+            ptr::copy_nonoverlapping(slice.as_ptr(), end, slice.len());
+            this.set_len(required);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the characters of the string with `s`, keeping its allocation
+    /// if it fits them.
+    pub fn assign<T: AsRef<[C]>>(mut self: Pin<&mut Self>, s: T) {
+        self.as_mut().clear();
+        self.push(s);
+    }
+
+    pub fn clear(self: Pin<&mut Self>) {
+        unsafe { self.get_unchecked_mut().set_len(0) }
+    }
+
+    pub fn reserve(self: Pin<&mut Self>, additional: usize) {
+        handle_reserve_error(self.try_reserve(additional));
+    }
+
+    pub fn try_reserve(self: Pin<&mut Self>, additional: usize) -> Result<(), TryReserveError> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let required = this
+            .len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        if required > this.capacity() {
+            this.reallocate(required)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shrinks the capacity to the length, moving the characters
+    /// into the small string buffer if they fit.
+    ///
+    /// Like `std::string::shrink_to_fit`, keeps the allocation if a smaller one fails.
+    pub fn shrink_to_fit(self: Pin<&mut Self>) {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if this.is_long() && this.len < this.capacity() {
+            let _ = this.reallocate(this.len);
+        }
+    }
+
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the string,
+    /// its length and capacity and whether it is in large mode.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        RawDebug {
+            name: "CxxBasicString",
+            length: self.len,
+            capacity: self.capacity(),
+            large_mode: self.is_long(),
+        }
+    }
+
+    fn local_ptr(&self) -> *const C {
+        ptr::addr_of!(self.local).cast()
+    }
+
+    /// Whether `_M_p` points past the small string buffer, `!_M_is_local()`.
+    fn is_long(&self) -> bool {
+        !ptr::eq(self.p, self.local_ptr())
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut C {
+        if self.is_long() {
+            self.p
+        } else {
+            ptr::addr_of_mut!(self.local).cast()
+        }
+    }
+
+    /// Sets the length and writes the terminating null after it.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be at most the capacity, and the characters before it initialized.
+    unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+        unsafe { self.as_mut_ptr().add(len).write(C::default()) }
+    }
+
+    /// Moves the characters into storage for `new_capacity` characters,
+    /// which must be at least the length, or into the small string buffer.
+    fn reallocate(&mut self, new_capacity: usize) -> Result<(), TryReserveError> {
+        if new_capacity > max_size::<C>() {
+            return Err(TryReserveError::CapacityOverflow);
+        }
+
+        let old = self.is_long().then_some((self.p, self.capacity()));
+
+        if new_capacity <= Self::SMALL_CAPACITY {
+            let Some((old_ptr, old_capacity)) = old else {
+                return Ok(());
+            };
+
+            unsafe {
+                let local = ptr::addr_of_mut!(self.local).cast::<C>();
+                ptr::copy_nonoverlapping(old_ptr, local, self.len + 1);
+                free_units(&self.alloc, old_ptr, old_capacity + 1);
+                self.p = local;
+            }
+
+            return Ok(());
+        }
+
+        let new_ptr = alloc_units::<C, A>(&self.alloc, new_capacity + 1)?;
+
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr(), new_ptr, self.len + 1);
+
+            if let Some((old_ptr, old_capacity)) = old {
+                free_units(&self.alloc, old_ptr, old_capacity + 1);
+            }
+        }
+
+        self.p = new_ptr;
+        self.local = Local {
+            capacity: new_capacity,
+        };
+
+        Ok(())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> fmt::Debug for CxxBasicString<C, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        C::debug_units(self.as_bytes(), f)
+    }
+}
+
+impl<I, C, A> Index<I> for CxxBasicString<C, A>
+where
+    I: SliceIndex<[C]>,
+    C: CharUnit,
+    A: CxxProxy,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.as_bytes(), index)
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> AsRef<[C]> for CxxBasicString<C, A> {
+    fn as_ref(&self) -> &[C] {
+        self.as_bytes()
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Borrow<[C]> for CxxBasicString<C, A> {
+    fn borrow(&self) -> &[C] {
+        self.as_bytes()
+    }
+}
+
+impl<C, A1, A2> PartialEq<CxxBasicString<C, A2>> for CxxBasicString<C, A1>
+where
+    C: CharUnit,
+    A1: CxxProxy,
+    A2: CxxProxy,
+{
+    fn eq(&self, other: &CxxBasicString<C, A2>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<C, A1, A2> PartialOrd<CxxBasicString<C, A2>> for CxxBasicString<C, A1>
+where
+    C: CharUnit,
+    A1: CxxProxy,
+    A2: CxxProxy,
+{
+    fn partial_cmp(&self, other: &CxxBasicString<C, A2>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Eq for CxxBasicString<C, A> {}
+
+impl<C: CharUnit, A: CxxProxy> Ord for CxxBasicString<C, A> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Ord::cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Hash for CxxBasicString<C, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_bytes(), state)
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Drop for CxxBasicString<C, A> {
+    fn drop(&mut self) {
+        if self.is_long() {
+            unsafe { free_units(&self.alloc, self.p, self.capacity() + 1) }
+        }
+    }
+}
+
+// The string owns its characters, which are only written through `Pin<&mut Self>`:
+unsafe impl<C: CharUnit, A: CxxProxy + Send> Send for CxxBasicString<C, A> {}
+
+unsafe impl<C: CharUnit, A: CxxProxy + Sync> Sync for CxxBasicString<C, A> {}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, pin::Pin};
+
+    use super::{CxxNarrowString, CxxUtf32String};
+    use crate::{
+        probe::{self, Toolset},
+        testing::MockAlloc,
+    };
+
+    const WORD: usize = mem::size_of::<usize>();
+
+    #[test]
+    fn short_strings_point_to_their_buffer() {
+        // Room for the bytes read by the probe past the string:
+        let mut storage = [0usize; 8];
+        let object = storage.as_mut_ptr().cast::<CxxNarrowString>();
+
+        unsafe {
+            CxxNarrowString::init_at(object);
+            Pin::new_unchecked(&mut *object).push(b"hello");
+
+            let s = &*object;
+            assert_eq!(mem::size_of::<CxxNarrowString>(), 2 * WORD + 16);
+            assert_eq!(CxxUtf32String::<MockAlloc>::SMALL_CAPACITY, 3);
+            assert_eq!(s.as_ptr(), object.cast::<u8>().add(2 * WORD));
+            assert_eq!(s.as_bytes_with_nul(), b"hello\0");
+
+            let descriptor = probe::probe_string(object.cast(), b"hello");
+            assert_eq!(descriptor.unwrap().toolset, Some(Toolset::Gnu));
+
+            object.drop_in_place();
+        }
+    }
+
+    #[test]
+    fn long_strings_store_their_capacity_in_the_buffer() {
+        let alloc = MockAlloc::new();
+        let mut s = CxxUtf32String::pin_in(alloc.clone());
+        let chars: Vec<u32> = (0..20).collect();
+
+        s.as_mut().push(&chars[..2]);
+        s.as_mut().push(&chars[2..]);
+        assert_eq!(s.as_bytes(), chars);
+        assert!(s.capacity() >= 20);
+        assert_eq!(s.as_bytes_with_nul()[20], 0);
+
+        s.as_mut().assign(&chars[..3]);
+        s.as_mut().shrink_to_fit();
+        assert_eq!(s.capacity(), CxxUtf32String::<MockAlloc>::SMALL_CAPACITY);
+        assert_eq!(s.as_bytes_with_nul(), [0, 1, 2, 0]);
+
+        alloc.assert_clean();
+    }
+
+    #[test]
+    fn failed_growth_keeps_the_characters() {
+        let alloc = MockAlloc::new();
+        let mut s = CxxNarrowString::pin_from_bytes_in(b"short", alloc.clone());
+
+        alloc.fail_after(0);
+        assert!(s.as_mut().try_push([b'x'; 64]).is_err());
+        assert_eq!(s.as_bytes(), b"short");
+
+        alloc.fail_never();
+        s.as_mut().push([b'x'; 64]);
+        assert_eq!(s.len(), 69);
+
+        drop(s);
+        alloc.assert_clean();
+    }
+}
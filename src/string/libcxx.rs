@@ -0,0 +1,604 @@
+//! Strings with the layouts of libc++, selected by the `libcxx-abi` feature.
+//!
+//! CSTL only implements the MSVC strings, so these are implemented in Rust.
+//! A libc++ `std::basic_string` is three words. In long mode they hold the size
+//! of the allocation in characters, the length and the pointer to the characters.
+//! In short mode the first byte holds the length, and the characters follow in
+//! place after a padding of one character. A flag bit of the first byte selects
+//! the mode, which the allocation sizes leave free by being even on little-endian
+//! targets.
+//!
+//! Short strings don't point into themselves, so unlike libstdc++ strings
+//! these can be moved like Rust values. The allocator is stored after the characters
+//! as the second member of a `__compressed_pair`, taking no room if it's stateless.
+//! Only the default layout is implemented, not `_LIBCPP_ABI_ALTERNATE_STRING_LAYOUT`.
+
+use std::{
+    alloc::System as SysAlloc,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    ops::Index,
+    ptr,
+    slice::{self, SliceIndex},
+};
+
+use super::{alloc_units, free_units, handle_reserve_error, CharUnit, RawDebug};
+use crate::alloc::{growth_policy, CxxProxy, GrowthPolicy, TryReserveError};
+
+pub type CxxNarrowString<A = SysAlloc> = CxxBasicString<u8, A>;
+
+/// `std::wstring`, whose `wchar_t` is 16-bit on Windows and 32-bit elsewhere.
+#[cfg(windows)]
+pub type CxxWideString<A = SysAlloc> = CxxBasicString<u16, A>;
+
+/// `std::wstring`, whose `wchar_t` is 16-bit on Windows and 32-bit elsewhere.
+#[cfg(not(windows))]
+pub type CxxWideString<A = SysAlloc> = CxxBasicString<u32, A>;
+
+pub type CxxUtf8String<A = SysAlloc> = CxxBasicString<u8, A>;
+
+pub type CxxUtf16String<A = SysAlloc> = CxxBasicString<u16, A>;
+
+pub type CxxUtf32String<A = SysAlloc> = CxxBasicString<u32, A>;
+
+/// `std::basic_string` of `C` characters with the libc++ layout.
+#[repr(C)]
+pub struct CxxBasicString<C: CharUnit, A: CxxProxy = SysAlloc> {
+    rep: Rep<C>,
+    alloc: A,
+    _marker: PhantomData<C>,
+}
+
+const REP_SIZE: usize = 3 * mem::size_of::<usize>();
+
+/// `__rep`, the long or short representation of the string.
+#[repr(C)]
+#[derive(Clone, Copy)]
+union Rep<C: Copy> {
+    long: Long<C>,
+    /// The length byte with the flag, a padding character and the characters.
+    short: [u8; REP_SIZE],
+}
+
+/// `__long`, whose first word holds the flag along with the allocation size.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Long<C> {
+    cap: usize,
+    size: usize,
+    data: *mut C,
+}
+
+/// Flag of long mode in the first word, in the first byte of the string.
+const LONG_FLAG: usize = if cfg!(target_endian = "little") {
+    1
+} else {
+    1 << (usize::BITS - 1)
+};
+
+/// Flag of long mode in the first byte of the string.
+const LONG_FLAG_BYTE: u8 = if cfg!(target_endian = "little") {
+    1
+} else {
+    0x80
+};
+
+/// `__endian_factor`, which allocation sizes are multiples of to leave the flag free.
+const ENDIAN_FACTOR: usize = if cfg!(target_endian = "little") { 2 } else { 1 };
+
+/// `__alignment`, in bytes, which allocations are rounded up to.
+const ALIGNMENT: usize = 8;
+
+/// `__min_cap`, the number of characters fitting in place, including the null.
+const fn min_cap<C>() -> usize {
+    let fits = (REP_SIZE - 1) / mem::size_of::<C>();
+
+    if fits > 2 {
+        fits
+    } else {
+        2
+    }
+}
+
+/// `max_size()` of a string of `C` characters.
+const fn max_size<C>() -> usize {
+    isize::MAX as usize / mem::size_of::<C>() - ALIGNMENT
+}
+
+/// `__recommend`, the capacity libc++ allocates to hold `requested` characters.
+fn recommend<C>(requested: usize) -> usize {
+    if requested < min_cap::<C>() {
+        return min_cap::<C>() - 1;
+    }
+
+    let boundary = if mem::size_of::<C>() < ALIGNMENT {
+        ALIGNMENT / mem::size_of::<C>()
+    } else {
+        ENDIAN_FACTOR
+    };
+
+    let guess = (requested + 1).next_multiple_of(boundary) - 1;
+
+    if guess == min_cap::<C>() {
+        guess + ENDIAN_FACTOR
+    } else {
+        guess
+    }
+}
+
+impl<C: CharUnit> CxxBasicString<C, SysAlloc> {
+    pub const fn new() -> Self {
+        Self::new_in(SysAlloc)
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> CxxBasicString<C, A> {
+    /// Size of the string in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the string in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Capacity in characters of short mode, `__min_cap - 1`.
+    /// Shorter strings are stored in the string itself.
+    pub const SMALL_CAPACITY: usize = min_cap::<C>() - 1;
+
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            // An empty short string, whose null is in place:
+            rep: Rep {
+                short: [0; REP_SIZE],
+            },
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxBasicString<C, B> {
+        CxxBasicString::from_bytes_in(&self, alloc)
+    }
+
+    pub fn from_bytes_in<T: AsRef<[C]>>(s: T, alloc: A) -> Self {
+        let mut new = Self::new_in(alloc);
+        new.push(s);
+        new
+    }
+
+    pub fn try_from_bytes_in<T: AsRef<[C]>>(s: T, alloc: A) -> Result<Self, TryReserveError> {
+        let mut new = Self::new_in(alloc);
+        new.try_push(s)?;
+        Ok(new)
+    }
+
+    pub fn as_ptr(&self) -> *const C {
+        if self.is_long() {
+            unsafe { self.rep.long.data }
+        } else {
+            ptr::addr_of!(self.rep).cast::<C>().wrapping_add(1)
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[C] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+
+    pub fn as_bytes_with_nul(&self) -> &[C] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len() + 1) }
+    }
+
+    /// Returns the character or characters at `index`,
+    /// or `None` if it is out of bounds.
+    pub fn get<I: SliceIndex<[C]>>(&self, index: I) -> Option<&I::Output> {
+        self.as_bytes().get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        if self.is_long() {
+            unsafe { self.rep.long.size }
+        } else if cfg!(target_endian = "little") {
+            (self.first_byte() >> 1) as usize
+        } else {
+            self.first_byte() as usize
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        if self.is_long() {
+            unsafe { (self.rep.long.cap & !LONG_FLAG) - 1 }
+        } else {
+            Self::SMALL_CAPACITY
+        }
+    }
+
+    pub fn push<T: AsRef<[C]>>(&mut self, s: T) {
+        handle_reserve_error(self.try_push(s));
+    }
+
+    pub fn try_push<T: AsRef<[C]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let len = self.len();
+        let capacity = self.capacity();
+        let required = len
+            .checked_add(slice.len())
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        if required > capacity {
+            // Grows like `__grow_by`, to at least twice the capacity:
+            let grown = growth_policy()
+                .grown_capacity(GrowthPolicy::Double, capacity, required)
+                .min(max_size::<C>())
+                .max(required);
+
+            self.reallocate(grown)?;
+        }
+
+        unsafe {
+            let end = self.as_mut_ptr().add(len);
+            ptr::copy_nonoverlapping(slice.as_ptr(), end, slice.len());
+            self.set_len(required);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the characters of the string with `s`, keeping its allocation
+    /// if it fits them.
+    pub fn assign<T: AsRef<[C]>>(&mut self, s: T) {
+        self.clear();
+        self.push(s);
+    }
+
+    pub fn clear(&mut self) {
+        unsafe { self.set_len(0) }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        handle_reserve_error(self.try_reserve(additional));
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let required = self
+            .len()
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        if required > self.capacity() {
+            self.reallocate(required)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shrinks the capacity to what libc++ would allocate for the length,
+    /// moving the characters back into the string if they fit.
+    ///
+    /// Like `std::string::shrink_to_fit`, keeps the allocation if a smaller one fails.
+    pub fn shrink_to_fit(&mut self) {
+        if recommend::<C>(self.len()) < self.capacity() {
+            let _ = self.reallocate(self.len());
+        }
+    }
+
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the string,
+    /// its length and capacity and whether it is in long mode.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        RawDebug {
+            name: "CxxBasicString",
+            length: self.len(),
+            capacity: self.capacity(),
+            large_mode: self.is_long(),
+        }
+    }
+
+    fn first_byte(&self) -> u8 {
+        unsafe { self.rep.short[0] }
+    }
+
+    fn is_long(&self) -> bool {
+        self.first_byte() & LONG_FLAG_BYTE != 0
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut C {
+        if self.is_long() {
+            unsafe { self.rep.long.data }
+        } else {
+            ptr::addr_of_mut!(self.rep).cast::<C>().wrapping_add(1)
+        }
+    }
+
+    /// Sets the length and writes the terminating null after it.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be at most the capacity, and the characters before it initialized.
+    unsafe fn set_len(&mut self, len: usize) {
+        unsafe {
+            if self.is_long() {
+                self.rep.long.size = len;
+            } else if cfg!(target_endian = "little") {
+                self.rep.short[0] = (len << 1) as u8;
+            } else {
+                self.rep.short[0] = len as u8;
+            }
+
+            self.as_mut_ptr().add(len).write(C::default());
+        }
+    }
+
+    /// Moves the characters into the storage libc++ would use for `requested`
+    /// characters, which must be at least the length.
+    fn reallocate(&mut self, requested: usize) -> Result<(), TryReserveError> {
+        if requested > max_size::<C>() {
+            return Err(TryReserveError::CapacityOverflow);
+        }
+
+        let new_capacity = recommend::<C>(requested);
+        let len = self.len();
+        let old = self.is_long().then_some(unsafe { self.rep.long });
+
+        if new_capacity == self.capacity() {
+            return Ok(());
+        }
+
+        if new_capacity == Self::SMALL_CAPACITY {
+            let Some(old) = old else {
+                return Ok(());
+            };
+
+            self.rep = Rep {
+                short: [0; REP_SIZE],
+            };
+
+            unsafe {
+                ptr::copy_nonoverlapping(old.data, self.as_mut_ptr(), len);
+                self.set_len(len);
+                free_units(&self.alloc, old.data, old.cap & !LONG_FLAG);
+            }
+
+            return Ok(());
+        }
+
+        let new_ptr = alloc_units::<C, A>(&self.alloc, new_capacity + 1)?;
+
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr(), new_ptr, len + 1);
+
+            if let Some(old) = old {
+                free_units(&self.alloc, old.data, old.cap & !LONG_FLAG);
+            }
+        }
+
+        // The allocation size is a multiple of `ENDIAN_FACTOR`, leaving the flag free:
+        self.rep = Rep {
+            long: Long {
+                cap: (new_capacity + 1) | LONG_FLAG,
+                size: len,
+                data: new_ptr,
+            },
+        };
+
+        Ok(())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> fmt::Debug for CxxBasicString<C, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        C::debug_units(self.as_bytes(), f)
+    }
+}
+
+impl<I, C, A> Index<I> for CxxBasicString<C, A>
+where
+    I: SliceIndex<[C]>,
+    C: CharUnit,
+    A: CxxProxy,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.as_bytes(), index)
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> AsRef<[C]> for CxxBasicString<C, A> {
+    fn as_ref(&self) -> &[C] {
+        self.as_bytes()
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Borrow<[C]> for CxxBasicString<C, A> {
+    fn borrow(&self) -> &[C] {
+        self.as_bytes()
+    }
+}
+
+impl<C, A1, A2> PartialEq<CxxBasicString<C, A2>> for CxxBasicString<C, A1>
+where
+    C: CharUnit,
+    A1: CxxProxy,
+    A2: CxxProxy,
+{
+    fn eq(&self, other: &CxxBasicString<C, A2>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<C, A1, A2> PartialOrd<CxxBasicString<C, A2>> for CxxBasicString<C, A1>
+where
+    C: CharUnit,
+    A1: CxxProxy,
+    A2: CxxProxy,
+{
+    fn partial_cmp(&self, other: &CxxBasicString<C, A2>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Eq for CxxBasicString<C, A> {}
+
+impl<C: CharUnit, A: CxxProxy> Ord for CxxBasicString<C, A> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Ord::cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Hash for CxxBasicString<C, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_bytes(), state)
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy + Default> Default for CxxBasicString<C, A> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Drop for CxxBasicString<C, A> {
+    fn drop(&mut self) {
+        if self.is_long() {
+            unsafe {
+                let Long { cap, data, .. } = self.rep.long;
+                free_units(&self.alloc, data, cap & !LONG_FLAG);
+            }
+        }
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy + Clone> Clone for CxxBasicString<C, A> {
+    fn clone(&self) -> Self {
+        Self::from_bytes_in(self, self.alloc.clone())
+    }
+
+    /// Copies the characters of `source` into the existing buffer if it fits them,
+    /// like C++ copy assignment. The string keeps its own allocator.
+    fn clone_from(&mut self, source: &Self) {
+        self.assign(source);
+    }
+}
+
+impl<C: CharUnit, A: CxxProxy> Extend<C> for CxxBasicString<C, A> {
+    fn extend<I: IntoIterator<Item = C>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for c in iter {
+            self.push([c]);
+        }
+    }
+}
+
+// The string owns its characters, which are only written through `&mut self`:
+unsafe impl<C: CharUnit, A: CxxProxy + Send> Send for CxxBasicString<C, A> {}
+
+unsafe impl<C: CharUnit, A: CxxProxy + Sync> Sync for CxxBasicString<C, A> {}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{CxxNarrowString, CxxUtf16String, CxxUtf32String};
+    use crate::{
+        probe::{self, Toolset},
+        testing::MockAlloc,
+    };
+
+    const WORD: usize = mem::size_of::<usize>();
+
+    fn rep_bytes<T>(s: &T) -> &[u8] {
+        unsafe { std::slice::from_raw_parts((s as *const T).cast::<u8>(), 3 * WORD) }
+    }
+
+    #[test]
+    fn short_strings_are_stored_in_place() {
+        let s = CxxNarrowString::from_bytes_in(b"hello", MockAlloc::new());
+        let bytes = rep_bytes(&s);
+
+        assert_eq!(mem::size_of::<CxxNarrowString>(), 3 * WORD);
+        assert_eq!(CxxNarrowString::<MockAlloc>::SMALL_CAPACITY, 3 * WORD - 2);
+        assert_eq!(
+            CxxUtf16String::<MockAlloc>::SMALL_CAPACITY,
+            (3 * WORD - 1) / 2 - 1
+        );
+        assert_eq!(
+            CxxUtf32String::<MockAlloc>::SMALL_CAPACITY,
+            (3 * WORD - 1) / 4 - 1
+        );
+
+        if cfg!(target_endian = "little") {
+            assert_eq!(bytes[0], 5 << 1);
+        }
+        assert_eq!(&bytes[1..7], b"hello\0");
+        assert_eq!(s.as_bytes_with_nul(), b"hello\0");
+
+        let mut buf = [0; 64];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let descriptor = unsafe { probe::probe_string(buf.as_ptr(), b"hello") };
+        assert_eq!(descriptor.unwrap().toolset, Some(Toolset::Libcxx));
+
+        s.allocator().assert_clean();
+    }
+
+    #[test]
+    fn long_strings_flag_their_allocation_size() {
+        let alloc = MockAlloc::new();
+        let mut s = CxxUtf16String::new_in(alloc.clone());
+        let chars: Vec<u16> = (0..40).collect();
+
+        s.push(&chars[..7]);
+        s.push(&chars[7..]);
+        assert_eq!(s.as_bytes(), chars);
+        assert_eq!(s.as_bytes_with_nul()[40], 0);
+
+        let words = rep_bytes(&s)
+            .chunks(WORD)
+            .map(|word| usize::from_ne_bytes(word.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let flag = if cfg!(target_endian = "little") {
+            1
+        } else {
+            1 << (usize::BITS - 1)
+        };
+
+        // The allocation size with the flag, the length and the pointer:
+        assert_eq!(words[0], (s.capacity() + 1) | flag);
+        assert_eq!((s.capacity() + 1) % 2, 0);
+        assert_eq!(words[1], 40);
+        assert_eq!(words[2], s.as_ptr() as usize);
+
+        s.assign(&chars[..3]);
+        s.shrink_to_fit();
+        assert_eq!(s.capacity(), CxxUtf16String::<MockAlloc>::SMALL_CAPACITY);
+        assert_eq!(s.as_bytes_with_nul(), [0, 1, 2, 0]);
+
+        alloc.assert_clean();
+    }
+
+    #[test]
+    fn failed_growth_keeps_the_characters() {
+        let alloc = MockAlloc::new();
+        let mut s = CxxNarrowString::from_bytes_in(b"short", alloc.clone());
+
+        alloc.fail_after(0);
+        assert!(s.try_push([b'x'; 64]).is_err());
+        assert_eq!(s.as_bytes(), b"short");
+
+        alloc.fail_never();
+        s.push([b'x'; 64]);
+        assert_eq!(s.len(), 69);
+
+        drop(s);
+        alloc.assert_clean();
+    }
+}
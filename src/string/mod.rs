@@ -1,3 +1,15 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    fmt::{self, Write},
+    hash::Hash,
+    marker::PhantomData,
+    mem, ptr,
+};
+
+use cstl_sys::CSTL_Alloc;
+
+use crate::alloc::{CxxProxy, TryReserveError};
+
 pub mod narrow;
 pub use narrow::CxxNarrowString;
 
@@ -8,12 +20,27 @@ pub mod utf8;
 pub use utf8::CxxUtf8String;
 
 pub mod utf16;
-pub use utf16::CxxUtf16String;
+pub use utf16::{CxxCowUtf16String, CxxUtf16Str, CxxUtf16String, ToU16String};
 
 pub mod utf32;
 pub use utf32::CxxUtf32String;
 
-#[cfg(feature = "msvc2012")]
+pub mod gnu;
+
+pub mod libcxx;
+
+pub mod msvc2010 {
+    pub use super::narrow::msvc2010::CxxNarrowString;
+
+    pub use super::wide::msvc2010::CxxWideString;
+
+    pub use super::utf8::msvc2010::CxxUtf8String;
+
+    pub use super::utf16::msvc2010::CxxUtf16String;
+
+    pub use super::utf32::msvc2010::CxxUtf32String;
+}
+
 pub mod msvc2012 {
     pub use super::narrow::msvc2012::CxxNarrowString;
 
@@ -25,3 +52,442 @@ pub mod msvc2012 {
 
     pub use super::utf32::msvc2012::CxxUtf32String;
 }
+
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug {
+    pub use super::narrow::iterator_debug::CxxNarrowString;
+
+    pub use super::wide::iterator_debug::CxxWideString;
+
+    pub use super::utf8::iterator_debug::CxxUtf8String;
+
+    pub use super::utf16::iterator_debug::CxxUtf16String;
+
+    pub use super::utf32::iterator_debug::CxxUtf32String;
+}
+
+// `std::basic_string` is a 16 byte buffer followed by its size and capacity
+// on both 32 and 64-bit targets, preceded by the `std::_Container_proxy` pointer
+// with iterator debugging, and followed by a padded allocator member in Visual Studio 2010:
+const _: () = {
+    const SIZE: usize = 16 + 2 * mem::size_of::<usize>();
+
+    assert!(mem::size_of::<CxxNarrowString>() == SIZE);
+    assert!(mem::size_of::<CxxWideString>() == SIZE);
+    assert!(mem::size_of::<CxxUtf8String>() == SIZE);
+    assert!(mem::size_of::<CxxUtf16String>() == SIZE);
+    assert!(mem::size_of::<CxxUtf32String>() == SIZE);
+    assert!(mem::align_of::<CxxNarrowString>() == mem::align_of::<usize>());
+    assert!(mem::size_of::<msvc2010::CxxNarrowString>() == SIZE + mem::size_of::<usize>());
+    assert!(mem::size_of::<msvc2010::CxxUtf32String>() == SIZE + mem::size_of::<usize>());
+
+    #[cfg(feature = "iterator-debug")]
+    {
+        assert!(
+            mem::size_of::<iterator_debug::CxxNarrowString>() == SIZE + mem::size_of::<usize>()
+        );
+        assert!(mem::size_of::<iterator_debug::CxxUtf16String>() == SIZE + mem::size_of::<usize>());
+    }
+};
+
+/// Internals of a string, see e.g. [`CxxNarrowString::raw_debug`].
+pub(crate) struct RawDebug {
+    pub name: &'static str,
+    pub length: usize,
+    pub capacity: usize,
+    pub large_mode: bool,
+}
+
+impl fmt::Debug for RawDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(self.name)
+            .field("length", &self.length)
+            .field("capacity", &self.capacity)
+            .field("large_mode", &self.large_mode)
+            .finish()
+    }
+}
+
+/// Writes `chars` as a quoted string, escaping them like [`str::escape_debug`]
+/// and the invalid code units with `escape_invalid`.
+fn debug_chars(
+    f: &mut fmt::Formatter<'_>,
+    chars: impl Iterator<Item = Result<char, u32>>,
+    escape_invalid: fn(&mut fmt::Formatter<'_>, u32) -> fmt::Result,
+) -> fmt::Result {
+    f.write_char('"')?;
+
+    for c in chars {
+        match c {
+            Ok(c) => c.escape_debug().try_for_each(|c| f.write_char(c))?,
+            Err(unit) => escape_invalid(f, unit)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+/// Writes UTF-8 code units as a quoted string, escaping invalid bytes as `\xNN`.
+pub(crate) fn debug_utf8(units: &[u8], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let chars = units.utf8_chunks().flat_map(|chunk| {
+        let invalid = chunk.invalid().iter().map(|&byte| Err(byte.into()));
+        chunk.valid().chars().map(Ok).chain(invalid)
+    });
+
+    debug_chars(f, chars, |f, byte| write!(f, "\\x{byte:02x}"))
+}
+
+/// Writes UTF-16 code units as a quoted string, escaping unpaired surrogates as `\u{NNNN}`.
+pub(crate) fn debug_utf16(units: &[u16], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let chars = char::decode_utf16(units.iter().copied())
+        .map(|c| c.map_err(|e| e.unpaired_surrogate().into()));
+
+    debug_chars(f, chars, |f, unit| write!(f, "\\u{{{unit:x}}}"))
+}
+
+/// Writes UTF-32 code units as a quoted string, escaping invalid ones as `\u{NNNN}`.
+pub(crate) fn debug_utf32(units: &[u32], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let chars = units.iter().map(|&unit| char::from_u32(unit).ok_or(unit));
+
+    debug_chars(f, chars, |f, unit| write!(f, "\\u{{{unit:x}}}"))
+}
+
+/// Character type of the strings implemented in Rust rather than by CSTL,
+/// see [`libcxx::CxxBasicString`].
+pub trait CharUnit: Copy + Default + Eq + Ord + Hash + sealed::Sealed + 'static {
+    /// Writes `units` as a quoted string for [`fmt::Debug`].
+    #[doc(hidden)]
+    fn debug_units(units: &[Self], f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl CharUnit for u8 {
+    fn debug_units(units: &[Self], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_utf8(units, f)
+    }
+}
+
+impl CharUnit for u16 {
+    fn debug_units(units: &[Self], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_utf16(units, f)
+    }
+}
+
+impl CharUnit for u32 {
+    fn debug_units(units: &[Self], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_utf32(units, f)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+
+    impl Sealed for u16 {}
+
+    impl Sealed for u32 {}
+}
+
+/// Allocates the storage for `count` characters with `alloc`,
+/// for the strings implemented in Rust.
+pub(crate) fn alloc_units<C, A: CxxProxy>(
+    alloc: &A,
+    count: usize,
+) -> Result<*mut C, TryReserveError> {
+    let layout = Layout::array::<C>(count)
+        .ok()
+        .filter(|layout| layout.size() != 0)
+        .ok_or(TryReserveError::CapacityOverflow)?;
+
+    let ptr = unsafe { alloc.proxy().alloc(layout) };
+
+    if ptr.is_null() {
+        Err(TryReserveError::AllocError { layout })
+    } else {
+        Ok(ptr.cast())
+    }
+}
+
+/// Frees the storage for `count` characters allocated by [`alloc_units`].
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`alloc_units`] for `count` characters
+/// with an allocator compatible with `alloc`.
+pub(crate) unsafe fn free_units<C, A: CxxProxy>(alloc: &A, ptr: *mut C, count: usize) {
+    unsafe {
+        let layout = Layout::array::<C>(count).unwrap_unchecked();
+        alloc.proxy().dealloc(ptr.cast(), layout);
+    }
+}
+
+/// Reports a failure of an infallible operation of a string implemented in Rust,
+/// which like the CSTL strings behaves as with [`AllocErrorPolicy::Abort`].
+///
+/// [`AllocErrorPolicy::Abort`]: crate::alloc::AllocErrorPolicy::Abort
+pub(crate) fn handle_reserve_error(result: Result<(), TryReserveError>) {
+    match result {
+        Ok(()) => {}
+        Err(TryReserveError::CapacityOverflow) => panic!("capacity overflow"),
+        Err(TryReserveError::AllocError { layout }) => std::alloc::handle_alloc_error(layout),
+    }
+}
+
+/// Counted UTF-16 string of the Windows NT API, `UNICODE_STRING`.
+///
+/// Unlike C++ strings, its characters aren't necessarily null-terminated.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct UnicodeString<'a> {
+    /// Length of the string in bytes.
+    length: u16,
+    /// Size of the buffer in bytes.
+    maximum_length: u16,
+    buffer: *const u16,
+    _marker: PhantomData<&'a [u16]>,
+}
+
+impl<'a> UnicodeString<'a> {
+    /// Borrows `chars` as a counted string.
+    ///
+    /// Returns `None` if `chars` is over `u16::MAX / 2` characters long.
+    pub fn new(chars: &'a [u16]) -> Option<Self> {
+        let length = u16::try_from(mem::size_of_val(chars)).ok()?;
+
+        Some(Self {
+            length,
+            maximum_length: length,
+            buffer: chars.as_ptr(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Borrows `chars_with_nul` as a counted string of its characters before
+    /// the terminating null, which is included in the buffer if it fits.
+    pub(crate) fn with_nul(chars_with_nul: &'a [u16]) -> Option<Self> {
+        let mut s = Self::new(&chars_with_nul[..chars_with_nul.len() - 1])?;
+        s.maximum_length = s.length.checked_add(2).unwrap_or(s.length);
+        Some(s)
+    }
+
+    /// Returns the characters of the string.
+    pub fn as_slice(&self) -> &'a [u16] {
+        if self.buffer.is_null() {
+            return &[];
+        }
+
+        unsafe { std::slice::from_raw_parts(self.buffer, self.len()) }
+    }
+
+    /// Returns the length of the string in characters.
+    pub fn len(&self) -> usize {
+        self.length as usize / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// The string borrows its characters immutably, like `&[u16]`:
+unsafe impl Send for UnicodeString<'_> {}
+
+unsafe impl Sync for UnicodeString<'_> {}
+
+/// COM automation string, `BSTR`.
+///
+/// Points to the UTF-16 characters of a length-prefixed allocation made by
+/// `SysAllocStringLen`, and a null `BSTR` is an empty string.
+#[cfg(windows)]
+pub type Bstr = *const u16;
+
+#[cfg(windows)]
+#[link(name = "oleaut32")]
+extern "system" {
+    fn SysAllocStringLen(chars: *const u16, len: u32) -> Bstr;
+    fn SysStringLen(bstr: Bstr) -> u32;
+}
+
+/// Returns the characters of `bstr`, excluding the terminating null.
+///
+/// # Safety
+///
+/// `bstr` must be null or a valid `BSTR` that outlives the returned slice.
+#[cfg(windows)]
+pub(crate) unsafe fn bstr_as_slice<'a>(bstr: Bstr) -> &'a [u16] {
+    if bstr.is_null() {
+        return &[];
+    }
+
+    unsafe { std::slice::from_raw_parts(bstr, SysStringLen(bstr) as usize) }
+}
+
+/// Allocates a `BSTR` holding `chars`.
+#[cfg(windows)]
+pub(crate) fn alloc_bstr(chars: &[u16]) -> Bstr {
+    let len = u32::try_from(chars.len()).expect("string is too long for a BSTR");
+    let bstr = unsafe { SysAllocStringLen(chars.as_ptr(), len) };
+
+    if bstr.is_null() {
+        // The length prefix, characters and terminating null:
+        let size = mem::size_of::<u32>() + (chars.len() + 1) * mem::size_of::<u16>();
+        std::alloc::handle_alloc_error(Layout::from_size_align(size, 4).unwrap());
+    }
+
+    bstr
+}
+
+/// Capacity in characters of the small string buffer of a CSTL string of `C` characters.
+pub(crate) const fn small_capacity<C>() -> usize {
+    16 / mem::size_of::<C>() - 1
+}
+
+/// Returns the pointer to the characters of a CSTL string like `CSTL_*string_c_str`,
+/// without calling into CSTL.
+///
+/// # Safety
+///
+/// `bx` and `res` must be the fields of a valid CSTL string of `C` characters.
+pub(crate) unsafe fn chars_ptr_raw<C>(bx: *mut C, res: usize) -> *mut C {
+    // The union holds the pointer to the characters in large mode:
+    if res > small_capacity::<C>() {
+        unsafe { *bx.cast::<*mut C>() }
+    } else {
+        bx
+    }
+}
+
+/// Appends `chars` and a terminating null to a CSTL string without calling into CSTL,
+/// returning whether they fit in its capacity.
+///
+/// # Safety
+///
+/// `bx`, `size` and `res` must be the fields of a valid CSTL string of `C` characters.
+pub(crate) unsafe fn push_within_capacity_raw<C: Copy + Default>(
+    bx: *mut C,
+    size: &mut usize,
+    res: usize,
+    chars: &[C],
+) -> bool {
+    if chars.len() > res - *size {
+        return false;
+    }
+
+    unsafe {
+        let end = chars_ptr_raw(bx, res).add(*size);
+        ptr::copy_nonoverlapping(chars.as_ptr(), end, chars.len());
+        end.add(chars.len()).write(C::default());
+    }
+
+    *size += chars.len();
+    true
+}
+
+/// Number of characters `Extend` buffers before appending them at once.
+pub(crate) const EXTEND_CHUNK_LEN: usize = 64;
+
+/// Grows a CSTL string to hold at least `new_capacity` characters,
+/// following the growth strategy of the `CSTL_*string_reserve` functions.
+///
+/// Unlike them, fails instead of writing through a null pointer
+/// when the allocation fails.
+///
+/// # Safety
+///
+/// `bx`, `size` and `res` must be the fields of a valid CSTL string of `C` characters
+/// and `alloc` must be an allocator compatible with it.
+pub(crate) unsafe fn try_reserve_raw<C: Copy>(
+    bx: *mut C,
+    size: usize,
+    res: &mut usize,
+    new_capacity: usize,
+    alloc: &mut CSTL_Alloc,
+) -> Result<(), TryReserveError> {
+    let small_capacity = small_capacity::<C>();
+    let max_size = if mem::size_of::<C>() == 1 {
+        isize::MAX as usize - 1
+    } else {
+        isize::MAX as usize / mem::size_of::<C>()
+    };
+
+    if *res >= new_capacity {
+        return Ok(());
+    }
+
+    if new_capacity > max_size {
+        return Err(TryReserveError::CapacityOverflow);
+    }
+
+    let old_capacity = *res;
+    let masked = new_capacity | small_capacity;
+    let new_capacity = if masked > max_size || old_capacity > max_size - old_capacity / 2 {
+        max_size
+    } else {
+        masked.max(old_capacity + old_capacity / 2)
+    };
+
+    let layout =
+        Layout::array::<C>(new_capacity + 1).map_err(|_| TryReserveError::CapacityOverflow)?;
+
+    unsafe {
+        let aligned_alloc = alloc.aligned_alloc.expect("null allocation function");
+        let aligned_free = alloc.aligned_free.expect("null deallocation function");
+
+        let new_ptr = aligned_alloc(alloc.opaque, layout.size(), layout.align()) as *mut C;
+
+        if new_ptr.is_null() {
+            return Err(TryReserveError::AllocError { layout });
+        }
+
+        if old_capacity > small_capacity {
+            let old_ptr = (bx as *mut *mut C).read();
+
+            ptr::copy_nonoverlapping(old_ptr, new_ptr, size + 1);
+
+            // In characters, as freed by CSTL, see `WithCxxProxy::FREE_SIZE_UNIT`:
+            aligned_free(alloc.opaque, old_ptr as _, old_capacity + 1, layout.align());
+        } else {
+            ptr::copy_nonoverlapping(bx, new_ptr, size + 1);
+        }
+
+        (bx as *mut *mut C).write(new_ptr);
+    }
+
+    *res = new_capacity;
+
+    Ok(())
+}
+
+/// Orphans the iterators into the small buffer of a string of `C` characters
+/// with the capacity `res`, which stays in place when the string is swapped.
+#[cfg(feature = "iterator-debug")]
+pub(crate) fn orphan_small_iterators<C, L: crate::alloc::WithCxxProxy>(layout: &L, res: usize) {
+    if res <= small_capacity::<C>() {
+        if let Some(proxy) = crate::iterator_debug::proxy_of(layout) {
+            proxy.orphan_all();
+        }
+    }
+}
+
+/// Swaps the container proxies of two strings of `C` characters with capacities `res`
+/// like `basic_string::swap`, first orphaning the iterators into small buffers,
+/// which stay in place.
+///
+/// # Safety
+///
+/// See [`iterator_debug::swap_proxies`](crate::iterator_debug::swap_proxies).
+#[cfg(feature = "iterator-debug")]
+pub(crate) unsafe fn swap_proxies<C, L, L2>(a: &mut L, b: &mut L2, res: (usize, usize))
+where
+    L: crate::alloc::WithCxxProxy,
+    L2: crate::alloc::WithCxxProxy,
+{
+    use crate::iterator_debug;
+
+    if L::PROXY_OFFSET.is_some() && L2::PROXY_OFFSET.is_some() {
+        orphan_small_iterators::<C, _>(a, res.0);
+        orphan_small_iterators::<C, _>(b, res.1);
+    }
+
+    unsafe { iterator_debug::swap_proxies(a, b) }
+}
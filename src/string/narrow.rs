@@ -1,13 +1,32 @@
-use std::{alloc::System as SysAlloc, borrow::Borrow, fmt, slice};
+use std::{
+    alloc::System as SysAlloc,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    mem::{self, MaybeUninit},
+    ops::Index,
+    pin::Pin,
+    ptr,
+    slice::{self, SliceIndex},
+};
 
 pub use cstl_sys::CSTL_StringVal as RawString;
-use cstl_sys::{
-    CSTL_string_append_char, CSTL_string_append_n, CSTL_string_assign_n,
-    CSTL_string_c_str, CSTL_string_clear, CSTL_string_destroy, CSTL_string_reserve,
-    CSTL_string_shrink_to_fit,
-};
 
-use crate::alloc::{CxxProxy, WithCxxProxy};
+use super::{
+    chars_ptr_raw, debug_utf8, push_within_capacity_raw, small_capacity, try_reserve_raw, RawDebug,
+    EXTEND_CHUNK_LEN,
+};
+use crate::{
+    alloc::{growth_policy, CxxProxy, GrowthPolicy, ProxyRef, TryReserveError, WithCxxProxy},
+    cstl::{
+        CSTL_string_append_n, CSTL_string_assign_n, CSTL_string_destroy, CSTL_string_reserve,
+        CSTL_string_shrink_to_fit, CSTL_string_swap,
+    },
+    diagnostics::{
+        proxy_bytes, string_bytes, string_report, ContainerReport, Diagnose, MemoryFootprint,
+    },
+    ffi::CxxIter,
+};
 
 pub type CxxNarrowString<A = SysAlloc> = CxxNarrowStringLayout<A, Layout<A>>;
 
@@ -53,6 +72,10 @@ impl<A: CxxProxy> CxxNarrowString<A> {
     pub const fn allocator(&self) -> &A {
         &self.inner.alloc
     }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxNarrowString<B> {
+        CxxNarrowString::from_bytes_in(&self, alloc)
+    }
 }
 
 impl<A, L> CxxNarrowStringLayout<A, L>
@@ -60,20 +83,83 @@ where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawString>,
 {
+    /// Size of the string in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the string in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Offset in bytes of the small string buffer, or of the pointer
+    /// to the characters in large mode, `_Bx`.
+    pub const OFFSET_BUF: usize = L::VALUE_OFFSET + mem::offset_of!(RawString, bx);
+
+    /// Offset in bytes of the length in characters, `_Mysize`.
+    pub const OFFSET_SIZE: usize = L::VALUE_OFFSET + mem::offset_of!(RawString, size);
+
+    /// Offset in bytes of the capacity in characters, `_Myres`.
+    pub const OFFSET_CAPACITY: usize = L::VALUE_OFFSET + mem::offset_of!(RawString, res);
+
+    /// Capacity in characters of the small string buffer, `_BUF_SIZE - 1`.
+    /// Shorter strings are stored in the string itself.
+    pub const SMALL_CAPACITY: usize = small_capacity::<u8>();
+
+    /// Constructs an empty string with `alloc` in the storage at `ptr`,
+    /// e.g. the `this` pointer passed to a hooked C++ constructor.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned to [`Self::ALIGN`].
+    /// A value previously stored there is overwritten without being dropped.
+    /// The string must not be moved afterwards, as the container proxy of iterator
+    /// debugging layouts points to it at `ptr` for C++ iterators.
+    pub unsafe fn init_in_place_in(ptr: *mut Self, alloc: A) {
+        unsafe {
+            ptr.write(Self::from_alloc(alloc));
+            // Attaches the container proxy at the final address:
+            (*ptr).inner.value_as_mut();
+        }
+    }
+
+    /// Constructs an empty string with `alloc` in `slot`, pinned as C++ code
+    /// may refer to it there.
+    ///
+    /// `slot` doesn't drop the string, it must be dropped in place,
+    /// e.g. with [`MaybeUninit::assume_init_drop`], to free its characters.
+    /// It must not be moved out of `slot`, see [`Self::init_in_place_in`].
+    pub fn emplace_in(slot: &mut MaybeUninit<Self>, alloc: A) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        let this = slot.write(Self::from_alloc(alloc));
+        // Attaches the container proxy at the final address:
+        this.inner.value_as_mut();
+
+        Pin::new(this)
+    }
+
     pub fn from_bytes_in<T: AsRef<[u8]>>(s: T, alloc: A) -> Self {
         let mut new = Self::from_alloc(alloc);
 
         let slice = s.as_ref();
 
-        new.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
-        });
+        if !new.push_within_capacity(slice) {
+            new.inner.with_proxy_mut(|val, alloc| unsafe {
+                CSTL_string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+            });
+        }
 
         new
     }
 
+    pub fn try_from_bytes_in<T: AsRef<[u8]>>(s: T, alloc: A) -> Result<Self, TryReserveError> {
+        let mut new = Self::from_alloc(alloc);
+        new.try_push(s)?;
+        Ok(new)
+    }
+
     pub fn as_ptr(&self) -> *const u8 {
-        unsafe { CSTL_string_c_str(self.inner.value_as_ref()) as _ }
+        let val = self.inner.value_as_ref();
+        unsafe { chars_ptr_raw(ptr::addr_of!(val.bx).cast_mut().cast(), val.res) }
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -84,6 +170,12 @@ where
         unsafe { slice::from_raw_parts(self.as_ptr(), self.len() + 1) }
     }
 
+    /// Returns the character or characters at `index`,
+    /// or `None` if it is out of bounds.
+    pub fn get<I: SliceIndex<[u8]>>(&self, index: I) -> Option<&I::Output> {
+        self.as_bytes().get(index)
+    }
+
     pub fn len(&self) -> usize {
         self.inner.value_as_ref().size
     }
@@ -99,19 +191,170 @@ where
     pub fn push<T: AsRef<[u8]>>(&mut self, s: T) {
         let slice = s.as_ref();
 
+        if self.push_within_capacity(slice) {
+            return;
+        }
+
+        let capacity = self.capacity();
+        let required = self.len().saturating_add(slice.len());
+        let new_capacity = growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, required);
+        self.reserve(new_capacity - capacity);
+
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_string_append_n(val, slice.as_ptr() as _, slice.len(), alloc);
         });
     }
 
-    pub fn replace<T: AsRef<[u8]>>(&mut self, s: T) {
+    pub fn try_push<T: AsRef<[u8]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let spare = self.capacity() - self.len();
+
+        if slice.len() > spare {
+            self.try_reserve(slice.len() - spare)?;
+        }
+
+        self.push(slice);
+
+        Ok(())
+    }
+
+    /// Replaces the characters with those of `s`, reusing the buffer if it fits them,
+    /// like `std::basic_string::assign`. The old characters aren't copied when growing.
+    pub fn assign<T: AsRef<[u8]>>(&mut self, s: T) {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
+        self.clear();
+
+        if slice.len() > capacity {
+            let new_capacity =
+                growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, slice.len());
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+        });
+    }
+
+    /// Replaces the characters with those of `iter`, reusing the buffer if it fits them.
+    pub fn assign_from_iter<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
         self.clear();
-        self.push(s);
+        self.extend(iter);
     }
 
+    pub fn replace<T: AsRef<[u8]>>(&mut self, s: T) {
+        self.assign(s);
+    }
+
+    pub fn try_replace<T: AsRef<[u8]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
+        if slice.len() > capacity {
+            self.try_reserve(slice.len() - capacity)?;
+        }
+
+        self.replace(slice);
+
+        Ok(())
+    }
+
+    /// Removes all characters, keeping the buffer to reuse it for new characters.
     pub fn clear(&mut self) {
+        let val = self.inner.value_as_mut();
+
         unsafe {
-            CSTL_string_clear(self.inner.value_as_mut());
+            chars_ptr_raw::<u8>(ptr::addr_of_mut!(val.bx).cast(), val.res).write(0);
+        }
+
+        val.size = 0;
+    }
+
+    /// Returns a copy of the string with its allocator, and clears this one while
+    /// keeping its buffer, e.g. to reuse it every frame.
+    pub fn take_reuse(&mut self) -> Self
+    where
+        A: Clone,
+    {
+        let taken = Self::from_bytes_in(self.as_bytes(), self.inner.alloc_as_ref().clone());
+        self.clear();
+
+        taken
+    }
+
+    /// Returns a C++ iterator to the first character, see [`CxxIter`].
+    pub fn begin(&self) -> CxxIter<'_, u8> {
+        self.iter_at(0)
+    }
+
+    /// Returns a C++ iterator past the last character, see [`CxxIter`].
+    pub fn end(&self) -> CxxIter<'_, u8> {
+        self.iter_at(self.len())
+    }
+
+    /// Returns a C++ iterator to the character at `index`,
+    /// or past the last character if `index == len`, see [`CxxIter`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn iter_at(&self, index: usize) -> CxxIter<'_, u8> {
+        let len = self.len();
+
+        if index > len {
+            panic!("iterator index (is {index}) should be <= len (is {len})");
+        }
+
+        let val = self.inner.value_as_ref();
+        CxxIter::new(unsafe {
+            chars_ptr_raw::<u8>(ptr::addr_of!(val.bx).cast_mut().cast(), val.res).add(index)
+        })
+    }
+
+    /// Swaps two strings along with their allocators,
+    /// like `std::swap` with allocators that propagate on container swap.
+    ///
+    /// The string values are exchanged without allocating, copying the small string
+    /// buffers. With iterator debugging, C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    pub fn swap(&mut self, other: &mut Self) {
+        #[cfg(feature = "iterator-debug")]
+        {
+            super::orphan_small_iterators::<u8, _>(&self.inner, self.inner.value_as_ref().res);
+            super::orphan_small_iterators::<u8, _>(&other.inner, other.inner.value_as_ref().res);
+        }
+
+        mem::swap(self, other);
+    }
+
+    /// Swaps the contents of two strings while keeping their allocators,
+    /// like `std::swap` with allocators that don't propagate on container swap.
+    ///
+    /// Use [`mem::swap`] to swap the allocators as well. With iterator debugging,
+    /// C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    ///
+    /// # Safety
+    ///
+    /// Each allocator must be able to free memory allocated by the other,
+    /// as required of C++ allocators that compare equal.
+    pub unsafe fn swap_contents<A2, L2>(&mut self, other: &mut CxxNarrowStringLayout<A2, L2>)
+    where
+        A2: CxxProxy,
+        L2: WithCxxProxy<Alloc = A2, Value = RawString>,
+    {
+        #[cfg(feature = "iterator-debug")]
+        unsafe {
+            let res = (
+                self.inner.value_as_ref().res,
+                other.inner.value_as_ref().res,
+            );
+            super::swap_proxies::<u8, _, _>(&mut self.inner, &mut other.inner, res);
+        }
+
+        unsafe {
+            CSTL_string_swap(self.inner.value_as_mut(), other.inner.value_as_mut());
         }
     }
 
@@ -127,12 +370,50 @@ where
         });
     }
 
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let new_capacity = self
+            .capacity()
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        self.inner.try_with_proxy_mut(|val, alloc| unsafe {
+            try_reserve_raw(
+                &mut val.bx as *mut _ as *mut u8,
+                val.size,
+                &mut val.res,
+                new_capacity,
+                alloc,
+            )
+        })?
+    }
+
     pub fn shrink_to_fit(&mut self) {
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_string_shrink_to_fit(val, alloc);
         });
     }
 
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the string,
+    /// its length and capacity and whether it is in large mode.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        let val = self.inner.value_as_ref();
+
+        RawDebug {
+            name: "CxxNarrowString",
+            length: val.size,
+            capacity: val.res,
+            large_mode: val.res > new_val().res,
+        }
+    }
+
+    /// Appends `s` without calling into CSTL, returning whether it fits in the capacity.
+    fn push_within_capacity(&mut self, s: &[u8]) -> bool {
+        let val = self.inner.value_as_mut();
+        unsafe {
+            push_within_capacity_raw(ptr::addr_of_mut!(val.bx).cast(), &mut val.size, val.res, s)
+        }
+    }
+
     fn from_alloc(alloc: A) -> Self {
         Self {
             inner: L::new_in(alloc),
@@ -140,17 +421,91 @@ where
     }
 }
 
+impl<L> CxxNarrowStringLayout<SysAlloc, L>
+where
+    L: WithCxxProxy<Alloc = SysAlloc, Value = RawString>,
+{
+    /// Constructs an empty string in the storage at `ptr`,
+    /// see [`CxxNarrowStringLayout::init_in_place_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxNarrowStringLayout::init_in_place_in`].
+    pub unsafe fn init_at(ptr: *mut Self) {
+        unsafe { Self::init_in_place_in(ptr, SysAlloc) }
+    }
+
+    /// Constructs an empty string in `slot`, see [`CxxNarrowStringLayout::emplace_in`].
+    pub fn emplace(slot: &mut MaybeUninit<Self>) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        Self::emplace_in(slot, SysAlloc)
+    }
+}
+
+impl<'a, A, L> CxxNarrowStringLayout<ProxyRef<'a, A>, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = ProxyRef<'a, A>, Value = RawString>,
+{
+    pub fn rebound_in(alloc: &'a A) -> Self {
+        Self::from_alloc(alloc.rebind())
+    }
+}
+
 impl<A, L> fmt::Debug for CxxNarrowStringLayout<A, L>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawString>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CxxNarrowString")
-            .field("length", &self.inner.value_as_ref().size)
-            .field("capacity", &self.inner.value_as_ref().res)
-            .field("large_mode", &(self.inner.value_as_ref().res > 15))
-            .finish()
+        debug_utf8(self.as_bytes(), f)
+    }
+}
+
+impl<A, L> Diagnose for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    fn report(&self) -> ContainerReport {
+        let val = self.inner.value_as_ref();
+
+        unsafe {
+            string_report(
+                "CxxNarrowString",
+                &val.bx.buf,
+                val.bx.ptr,
+                val.size,
+                val.res,
+            )
+        }
+    }
+}
+
+impl<A, L> MemoryFootprint for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    fn allocated_bytes(&self) -> usize {
+        let val = self.inner.value_as_ref();
+
+        unsafe { string_bytes(&val.bx.buf, val.res) + proxy_bytes::<L>() }
+    }
+}
+
+impl<I, A, L> Index<I> for CxxNarrowStringLayout<A, L>
+where
+    I: SliceIndex<[u8]>,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.as_bytes(), index)
     }
 }
 
@@ -174,6 +529,57 @@ where
     }
 }
 
+impl<A1, A2, L1, L2> PartialEq<CxxNarrowStringLayout<A2, L2>> for CxxNarrowStringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawString>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawString>,
+{
+    fn eq(&self, other: &CxxNarrowStringLayout<A2, L2>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<A1, A2, L1, L2> PartialOrd<CxxNarrowStringLayout<A2, L2>> for CxxNarrowStringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawString>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawString>,
+{
+    fn partial_cmp(&self, other: &CxxNarrowStringLayout<A2, L2>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Eq for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+}
+
+impl<A, L> Ord for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Ord::cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Hash for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_bytes(), state)
+    }
+}
+
 impl<A, L> Default for CxxNarrowStringLayout<A, L>
 where
     A: CxxProxy + Default,
@@ -204,6 +610,12 @@ where
     fn clone(&self) -> Self {
         Self::from_bytes_in(self, self.inner.alloc_as_ref().clone())
     }
+
+    /// Copies the characters of `source` into the existing buffer if it fits them,
+    /// like C++ copy assignment. The string keeps its own allocator.
+    fn clone_from(&mut self, source: &Self) {
+        self.replace(source);
+    }
 }
 
 impl<A, L> Extend<u8> for CxxNarrowStringLayout<A, L>
@@ -212,16 +624,47 @@ where
     L: WithCxxProxy<Alloc = A, Value = RawString>,
 {
     fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
-        let iter = iter.into_iter();
-        self.reserve(iter.size_hint().0);
-        self.inner.with_proxy_mut(|val, alloc| unsafe {
-            for ch in iter {
-                CSTL_string_append_char(val, 1, ch as i8, alloc);
+        let mut iter = iter.into_iter();
+        let spare = self.capacity() - self.len();
+
+        if iter.size_hint().0 > spare {
+            self.reserve(iter.size_hint().0 - spare);
+        }
+
+        // Appends the characters in chunks, rather than one CSTL call per character:
+        let mut chunk = [0; EXTEND_CHUNK_LEN];
+
+        loop {
+            let len = chunk
+                .iter_mut()
+                .zip(&mut iter)
+                .map(|(c, ch)| *c = ch)
+                .count();
+
+            if len == 0 {
+                break;
             }
-        });
+
+            self.push(&chunk[..len]);
+        }
     }
 }
 
+// The string owns its characters, which are only written through `&mut self`:
+unsafe impl<A, L> Send for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy + Send,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+}
+
+unsafe impl<A, L> Sync for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy + Sync,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+}
+
 const fn new_val() -> RawString {
     RawString {
         bx: cstl_sys::CSTL_StringUnion { buf: [0; 16] },
@@ -230,10 +673,38 @@ const fn new_val() -> RawString {
     }
 }
 
+/// Destroys the string value at `ptr` like `std::string::~basic_string`, freeing
+/// its characters with `alloc`, e.g. to destroy a string constructed by C++
+/// before overwriting it.
+///
+/// An empty value is left at `ptr`. With iterator debugging, the proxy
+/// stored before the value isn't freed.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid string value, allocated with an allocator
+/// compatible with `alloc`.
+pub unsafe fn drop_in_place_with<A: CxxProxy>(ptr: *mut RawString, alloc: &A) {
+    let mut layout = Layout::new_in(alloc.rebind());
+    layout.val = unsafe { ptr.read() };
+
+    layout.with_proxy_mut(|val, alloc| unsafe {
+        CSTL_string_destroy(val, alloc);
+    });
+
+    unsafe { ptr.write(new_val()) }
+}
+
 impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     type Value = RawString;
     type Alloc = A;
 
+    const VALUE_OFFSET: usize = mem::offset_of!(Self, val);
+
+    const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+    const FREE_SIZE_UNIT: usize = mem::size_of::<u8>();
+
     fn value_as_ref(&self) -> &Self::Value {
         &self.val
     }
@@ -254,7 +725,85 @@ impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     }
 }
 
-#[cfg(feature = "msvc2012")]
+pub mod msvc2010 {
+    use cstl_sys::CSTL_StringVal as RawString;
+
+    use crate::alloc::{AllocMember, CxxProxy, WithCxxProxy};
+
+    use super::{new_val, CxxNarrowStringLayout, SysAlloc};
+
+    pub type CxxNarrowString<A = SysAlloc> = CxxNarrowStringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        val: RawString,
+        alloc: AllocMember<A>,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxNarrowString<SysAlloc> {
+        pub const fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxNarrowString<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            self.inner.alloc.get()
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxNarrowString<B> {
+            CxxNarrowString::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawString;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u8>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            self.alloc.get()
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+}
+
 pub mod msvc2012 {
     use cstl_sys::CSTL_StringVal as RawString;
 
@@ -297,12 +846,22 @@ pub mod msvc2012 {
         pub const fn allocator(&self) -> &A {
             &self.inner.alloc
         }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxNarrowString<B> {
+            CxxNarrowString::from_bytes_in(&self, alloc)
+        }
     }
 
     impl<A: CxxProxy> WithCxxProxy for Layout<A> {
         type Value = RawString;
         type Alloc = A;
 
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u8>();
+
         fn value_as_ref(&self) -> &Self::Value {
             &self.val
         }
@@ -323,3 +882,104 @@ pub mod msvc2012 {
         }
     }
 }
+
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug {
+    use cstl_sys::CSTL_StringVal as RawString;
+
+    use crate::{
+        alloc::{CxxProxy, WithCxxProxy},
+        iterator_debug::ProxyPtr,
+    };
+
+    use super::{new_val, CxxNarrowStringLayout, SysAlloc};
+
+    pub type CxxNarrowString<A = SysAlloc> = CxxNarrowStringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        alloc: A,
+        proxy: ProxyPtr,
+        val: RawString,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                proxy: ProxyPtr::new_in(&alloc),
+                alloc,
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxNarrowString<SysAlloc> {
+        pub fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxNarrowString<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            &self.inner.alloc
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxNarrowString<B> {
+            CxxNarrowString::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawString;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const PROXY_OFFSET: Option<usize> = Some(std::mem::offset_of!(Self, proxy));
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u8>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            self.proxy.attach();
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            self.proxy.attach();
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            &self.alloc
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self::new_in(alloc)
+        }
+
+        fn into_parts(self) -> (Self::Alloc, Self::Value) {
+            let this = std::mem::ManuallyDrop::new(self);
+
+            unsafe {
+                this.proxy.free_in(&this.alloc);
+                (std::ptr::read(&this.alloc), std::ptr::read(&this.val))
+            }
+        }
+    }
+
+    impl<A: CxxProxy> Drop for Layout<A> {
+        fn drop(&mut self) {
+            unsafe { self.proxy.free_in(&self.alloc) }
+        }
+    }
+}
@@ -1,13 +1,38 @@
-use std::{alloc::System as SysAlloc, borrow::Borrow, fmt, slice};
+use std::{
+    alloc::System as SysAlloc,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    mem::{self, MaybeUninit},
+    ops::{Deref, Index},
+    pin::Pin,
+    ptr,
+    slice::{self, SliceIndex},
+    str::FromStr,
+};
 
 pub use cstl_sys::CSTL_UTF16StringVal as RawUtf16String;
-use cstl_sys::{
-    CSTL_u16string_append_char, CSTL_u16string_append_n, CSTL_u16string_assign_n,
-    CSTL_u16string_c_str, CSTL_u16string_clear, CSTL_u16string_destroy, CSTL_u16string_reserve,
-    CSTL_u16string_shrink_to_fit,
+
+#[cfg(windows)]
+use super::{alloc_bstr, bstr_as_slice, Bstr};
+use super::{
+    chars_ptr_raw, debug_utf16, push_within_capacity_raw, small_capacity, try_reserve_raw,
+    RawDebug, UnicodeString, EXTEND_CHUNK_LEN,
+};
+use crate::{
+    alloc::{growth_policy, CxxProxy, GrowthPolicy, ProxyRef, TryReserveError, WithCxxProxy},
+    cstl::{
+        CSTL_u16string_append_n, CSTL_u16string_assign_n, CSTL_u16string_destroy,
+        CSTL_u16string_reserve, CSTL_u16string_shrink_to_fit, CSTL_u16string_swap,
+    },
+    diagnostics::{
+        proxy_bytes, string_bytes, string_report, ContainerReport, Diagnose, MemoryFootprint,
+    },
+    ffi::CxxIter,
 };
 
-use crate::alloc::{CxxProxy, WithCxxProxy};
+#[cfg(all(windows, feature = "windows"))]
+use windows_strings::HSTRING;
 
 pub type CxxUtf16String<A = SysAlloc> = CxxUtf16StringLayout<A, Layout<A>>;
 
@@ -41,6 +66,15 @@ impl CxxUtf16String<SysAlloc> {
             inner: Layout::new_in(SysAlloc),
         }
     }
+
+    /// Formats `value` into a new string, like [`ToString`].
+    pub fn from_display(value: impl fmt::Display) -> Self {
+        let mut new = Self::new();
+        fmt::Write::write_fmt(&mut new, format_args!("{value}"))
+            .expect("a Display implementation returned an error unexpectedly");
+
+        new
+    }
 }
 
 impl<A: CxxProxy> CxxUtf16String<A> {
@@ -53,6 +87,10 @@ impl<A: CxxProxy> CxxUtf16String<A> {
     pub const fn allocator(&self) -> &A {
         &self.inner.alloc
     }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxUtf16String<B> {
+        CxxUtf16String::from_bytes_in(&self, alloc)
+    }
 }
 
 impl<A, L> CxxUtf16StringLayout<A, L>
@@ -60,20 +98,126 @@ where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
 {
+    /// Size of the string in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the string in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Offset in bytes of the small string buffer, or of the pointer
+    /// to the characters in large mode, `_Bx`.
+    pub const OFFSET_BUF: usize = L::VALUE_OFFSET + mem::offset_of!(RawUtf16String, bx);
+
+    /// Offset in bytes of the length in characters, `_Mysize`.
+    pub const OFFSET_SIZE: usize = L::VALUE_OFFSET + mem::offset_of!(RawUtf16String, size);
+
+    /// Offset in bytes of the capacity in characters, `_Myres`.
+    pub const OFFSET_CAPACITY: usize = L::VALUE_OFFSET + mem::offset_of!(RawUtf16String, res);
+
+    /// Capacity in characters of the small string buffer, `_BUF_SIZE - 1`.
+    /// Shorter strings are stored in the string itself.
+    pub const SMALL_CAPACITY: usize = small_capacity::<u16>();
+
+    /// Constructs an empty string with `alloc` in the storage at `ptr`,
+    /// e.g. the `this` pointer passed to a hooked C++ constructor.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned to [`Self::ALIGN`].
+    /// A value previously stored there is overwritten without being dropped.
+    /// The string must not be moved afterwards, as the container proxy of iterator
+    /// debugging layouts points to it at `ptr` for C++ iterators.
+    pub unsafe fn init_in_place_in(ptr: *mut Self, alloc: A) {
+        unsafe {
+            ptr.write(Self::from_alloc(alloc));
+            // Attaches the container proxy at the final address:
+            (*ptr).inner.value_as_mut();
+        }
+    }
+
+    /// Constructs an empty string with `alloc` in `slot`, pinned as C++ code
+    /// may refer to it there.
+    ///
+    /// `slot` doesn't drop the string, it must be dropped in place,
+    /// e.g. with [`MaybeUninit::assume_init_drop`], to free its characters.
+    /// It must not be moved out of `slot`, see [`Self::init_in_place_in`].
+    pub fn emplace_in(slot: &mut MaybeUninit<Self>, alloc: A) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        let this = slot.write(Self::from_alloc(alloc));
+        // Attaches the container proxy at the final address:
+        this.inner.value_as_mut();
+
+        Pin::new(this)
+    }
+
     pub fn from_bytes_in<T: AsRef<[u16]>>(s: T, alloc: A) -> Self {
         let mut new = Self::from_alloc(alloc);
 
         let slice = s.as_ref();
 
-        new.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_u16string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
-        });
+        if !new.push_within_capacity(slice) {
+            new.inner.with_proxy_mut(|val, alloc| unsafe {
+                CSTL_u16string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+            });
+        }
 
         new
     }
 
+    pub fn try_from_bytes_in<T: AsRef<[u16]>>(s: T, alloc: A) -> Result<Self, TryReserveError> {
+        let mut new = Self::from_alloc(alloc);
+        new.try_push(s)?;
+        Ok(new)
+    }
+
+    /// Copies the characters of the counted string `s`.
+    pub fn from_unicode_string_in(s: &UnicodeString<'_>, alloc: A) -> Self {
+        Self::from_bytes_in(s.as_slice(), alloc)
+    }
+
+    /// Borrows the string as a counted string, e.g. to pass it to NT functions.
+    ///
+    /// Returns `None` if the string is over `u16::MAX / 2` characters long,
+    /// which counted strings can't represent.
+    pub fn as_unicode_string(&self) -> Option<UnicodeString<'_>> {
+        UnicodeString::with_nul(self.as_bytes_with_nul())
+    }
+
+    /// Copies the characters of the WinRT string `s`.
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn from_hstring_in(s: &HSTRING, alloc: A) -> Self {
+        Self::from_bytes_in(&**s, alloc)
+    }
+
+    /// Creates a WinRT string with the characters of this string.
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn to_hstring(&self) -> HSTRING {
+        HSTRING::from_wide(self.as_bytes())
+    }
+
+    /// Copies the characters of the COM automation string `bstr`.
+    ///
+    /// # Safety
+    ///
+    /// `bstr` must be null or a valid `BSTR`.
+    #[cfg(windows)]
+    pub unsafe fn from_bstr_in(bstr: Bstr, alloc: A) -> Self {
+        Self::from_bytes_in(unsafe { bstr_as_slice(bstr) }, alloc)
+    }
+
+    /// Allocates a COM automation string with the characters of this string.
+    ///
+    /// The caller owns the returned `BSTR`, and frees it with `SysFreeString`.
+    #[cfg(windows)]
+    pub fn to_bstr(&self) -> Bstr {
+        alloc_bstr(self.as_bytes())
+    }
+
     pub fn as_ptr(&self) -> *const u16 {
-        unsafe { CSTL_u16string_c_str(self.inner.value_as_ref()) as _ }
+        let val = self.inner.value_as_ref();
+        unsafe { chars_ptr_raw(ptr::addr_of!(val.bx).cast_mut().cast(), val.res) }
     }
 
     pub fn as_bytes(&self) -> &[u16] {
@@ -84,6 +228,47 @@ where
         unsafe { slice::from_raw_parts(self.as_ptr(), self.len() + 1) }
     }
 
+    /// Borrows the characters of the string, see [`CxxUtf16Str`].
+    pub fn as_str(&self) -> &CxxUtf16Str {
+        CxxUtf16Str::from_bytes(self.as_bytes())
+    }
+
+    /// See [`CxxUtf16Str::to_uppercase`].
+    pub fn to_uppercase(&self) -> CxxUtf16String {
+        self.as_str().to_uppercase()
+    }
+
+    /// See [`CxxUtf16Str::to_lowercase`].
+    pub fn to_lowercase(&self) -> CxxUtf16String {
+        self.as_str().to_lowercase()
+    }
+
+    /// See [`CxxUtf16Str::trim`].
+    pub fn trim(&self) -> &CxxUtf16Str {
+        self.as_str().trim()
+    }
+
+    /// See [`CxxUtf16Str::trim_matches`].
+    pub fn trim_matches(&self, pat: impl FnMut(u16) -> bool) -> &CxxUtf16Str {
+        self.as_str().trim_matches(pat)
+    }
+
+    /// See [`CxxUtf16Str::eq_ignore_ascii_case`].
+    pub fn eq_ignore_ascii_case(&self, other: impl AsRef<[u16]>) -> bool {
+        self.as_str().eq_ignore_ascii_case(other)
+    }
+
+    /// See [`CxxUtf16Str::parse`].
+    pub fn parse<T: FromStr>(&self) -> Result<T, T::Err> {
+        self.as_str().parse()
+    }
+
+    /// Returns the character or characters at `index`,
+    /// or `None` if it is out of bounds.
+    pub fn get<I: SliceIndex<[u16]>>(&self, index: I) -> Option<&I::Output> {
+        self.as_bytes().get(index)
+    }
+
     pub fn len(&self) -> usize {
         self.inner.value_as_ref().size
     }
@@ -99,19 +284,170 @@ where
     pub fn push<T: AsRef<[u16]>>(&mut self, s: T) {
         let slice = s.as_ref();
 
+        if self.push_within_capacity(slice) {
+            return;
+        }
+
+        let capacity = self.capacity();
+        let required = self.len().saturating_add(slice.len());
+        let new_capacity = growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, required);
+        self.reserve(new_capacity - capacity);
+
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_u16string_append_n(val, slice.as_ptr() as _, slice.len(), alloc);
         });
     }
 
-    pub fn replace<T: AsRef<[u16]>>(&mut self, s: T) {
+    pub fn try_push<T: AsRef<[u16]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let spare = self.capacity() - self.len();
+
+        if slice.len() > spare {
+            self.try_reserve(slice.len() - spare)?;
+        }
+
+        self.push(slice);
+
+        Ok(())
+    }
+
+    /// Replaces the characters with those of `s`, reusing the buffer if it fits them,
+    /// like `std::basic_string::assign`. The old characters aren't copied when growing.
+    pub fn assign<T: AsRef<[u16]>>(&mut self, s: T) {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
+        self.clear();
+
+        if slice.len() > capacity {
+            let new_capacity =
+                growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, slice.len());
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_u16string_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+        });
+    }
+
+    /// Replaces the characters with those of `iter`, reusing the buffer if it fits them.
+    pub fn assign_from_iter<I: IntoIterator<Item = u16>>(&mut self, iter: I) {
         self.clear();
-        self.push(s);
+        self.extend(iter);
     }
 
+    pub fn replace<T: AsRef<[u16]>>(&mut self, s: T) {
+        self.assign(s);
+    }
+
+    pub fn try_replace<T: AsRef<[u16]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
+        if slice.len() > capacity {
+            self.try_reserve(slice.len() - capacity)?;
+        }
+
+        self.replace(slice);
+
+        Ok(())
+    }
+
+    /// Removes all characters, keeping the buffer to reuse it for new characters.
     pub fn clear(&mut self) {
+        let val = self.inner.value_as_mut();
+
+        unsafe {
+            chars_ptr_raw::<u16>(ptr::addr_of_mut!(val.bx).cast(), val.res).write(0);
+        }
+
+        val.size = 0;
+    }
+
+    /// Returns a copy of the string with its allocator, and clears this one while
+    /// keeping its buffer, e.g. to reuse it every frame.
+    pub fn take_reuse(&mut self) -> Self
+    where
+        A: Clone,
+    {
+        let taken = Self::from_bytes_in(self.as_bytes(), self.inner.alloc_as_ref().clone());
+        self.clear();
+
+        taken
+    }
+
+    /// Returns a C++ iterator to the first character, see [`CxxIter`].
+    pub fn begin(&self) -> CxxIter<'_, u16> {
+        self.iter_at(0)
+    }
+
+    /// Returns a C++ iterator past the last character, see [`CxxIter`].
+    pub fn end(&self) -> CxxIter<'_, u16> {
+        self.iter_at(self.len())
+    }
+
+    /// Returns a C++ iterator to the character at `index`,
+    /// or past the last character if `index == len`, see [`CxxIter`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn iter_at(&self, index: usize) -> CxxIter<'_, u16> {
+        let len = self.len();
+
+        if index > len {
+            panic!("iterator index (is {index}) should be <= len (is {len})");
+        }
+
+        let val = self.inner.value_as_ref();
+        CxxIter::new(unsafe {
+            chars_ptr_raw::<u16>(ptr::addr_of!(val.bx).cast_mut().cast(), val.res).add(index)
+        })
+    }
+
+    /// Swaps two strings along with their allocators,
+    /// like `std::swap` with allocators that propagate on container swap.
+    ///
+    /// The string values are exchanged without allocating, copying the small string
+    /// buffers. With iterator debugging, C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    pub fn swap(&mut self, other: &mut Self) {
+        #[cfg(feature = "iterator-debug")]
+        {
+            super::orphan_small_iterators::<u16, _>(&self.inner, self.inner.value_as_ref().res);
+            super::orphan_small_iterators::<u16, _>(&other.inner, other.inner.value_as_ref().res);
+        }
+
+        mem::swap(self, other);
+    }
+
+    /// Swaps the contents of two strings while keeping their allocators,
+    /// like `std::swap` with allocators that don't propagate on container swap.
+    ///
+    /// Use [`mem::swap`] to swap the allocators as well. With iterator debugging,
+    /// C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    ///
+    /// # Safety
+    ///
+    /// Each allocator must be able to free memory allocated by the other,
+    /// as required of C++ allocators that compare equal.
+    pub unsafe fn swap_contents<A2, L2>(&mut self, other: &mut CxxUtf16StringLayout<A2, L2>)
+    where
+        A2: CxxProxy,
+        L2: WithCxxProxy<Alloc = A2, Value = RawUtf16String>,
+    {
+        #[cfg(feature = "iterator-debug")]
+        unsafe {
+            let res = (
+                self.inner.value_as_ref().res,
+                other.inner.value_as_ref().res,
+            );
+            super::swap_proxies::<u16, _, _>(&mut self.inner, &mut other.inner, res);
+        }
+
         unsafe {
-            CSTL_u16string_clear(self.inner.value_as_mut());
+            CSTL_u16string_swap(self.inner.value_as_mut(), other.inner.value_as_mut());
         }
     }
 
@@ -127,12 +463,50 @@ where
         });
     }
 
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let new_capacity = self
+            .capacity()
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        self.inner.try_with_proxy_mut(|val, alloc| unsafe {
+            try_reserve_raw(
+                &mut val.bx as *mut _ as *mut u16,
+                val.size,
+                &mut val.res,
+                new_capacity,
+                alloc,
+            )
+        })?
+    }
+
     pub fn shrink_to_fit(&mut self) {
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_u16string_shrink_to_fit(val, alloc);
         });
     }
 
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the string,
+    /// its length and capacity and whether it is in large mode.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        let val = self.inner.value_as_ref();
+
+        RawDebug {
+            name: "CxxUtf16String",
+            length: val.size,
+            capacity: val.res,
+            large_mode: val.res > new_val().res,
+        }
+    }
+
+    /// Appends `s` without calling into CSTL, returning whether it fits in the capacity.
+    fn push_within_capacity(&mut self, s: &[u16]) -> bool {
+        let val = self.inner.value_as_mut();
+        unsafe {
+            push_within_capacity_raw(ptr::addr_of_mut!(val.bx).cast(), &mut val.size, val.res, s)
+        }
+    }
+
     fn from_alloc(alloc: A) -> Self {
         Self {
             inner: L::new_in(alloc),
@@ -140,175 +514,827 @@ where
     }
 }
 
-impl<A, L> fmt::Debug for CxxUtf16StringLayout<A, L>
+impl<L> CxxUtf16StringLayout<SysAlloc, L>
 where
-    A: CxxProxy,
-    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+    L: WithCxxProxy<Alloc = SysAlloc, Value = RawUtf16String>,
 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CxxUtf16String")
-            .field("length", &self.inner.value_as_ref().size)
-            .field("capacity", &self.inner.value_as_ref().res)
-            .field("large_mode", &(self.inner.value_as_ref().res > 15))
-            .finish()
+    /// Constructs an empty string in the storage at `ptr`,
+    /// see [`CxxUtf16StringLayout::init_in_place_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxUtf16StringLayout::init_in_place_in`].
+    pub unsafe fn init_at(ptr: *mut Self) {
+        unsafe { Self::init_in_place_in(ptr, SysAlloc) }
+    }
+
+    /// Copies the characters of the counted string `s`,
+    /// see [`CxxUtf16StringLayout::from_unicode_string_in`].
+    pub fn from_unicode_string(s: &UnicodeString<'_>) -> Self {
+        Self::from_unicode_string_in(s, SysAlloc)
+    }
+
+    /// Copies the characters of the WinRT string `s`, see [`CxxUtf16StringLayout::from_hstring_in`].
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn from_hstring(s: &HSTRING) -> Self {
+        Self::from_hstring_in(s, SysAlloc)
+    }
+
+    /// Copies the characters of the COM automation string `bstr`,
+    /// see [`CxxUtf16StringLayout::from_bstr_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxUtf16StringLayout::from_bstr_in`].
+    #[cfg(windows)]
+    pub unsafe fn from_bstr(bstr: Bstr) -> Self {
+        unsafe { Self::from_bstr_in(bstr, SysAlloc) }
+    }
+
+    /// Constructs an empty string in `slot`, see [`CxxUtf16StringLayout::emplace_in`].
+    pub fn emplace(slot: &mut MaybeUninit<Self>) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        Self::emplace_in(slot, SysAlloc)
     }
 }
 
-impl<A, L> AsRef<[u16]> for CxxUtf16StringLayout<A, L>
+impl<'a, A, L> CxxUtf16StringLayout<ProxyRef<'a, A>, L>
 where
     A: CxxProxy,
-    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+    L: WithCxxProxy<Alloc = ProxyRef<'a, A>, Value = RawUtf16String>,
 {
-    fn as_ref(&self) -> &[u16] {
-        self.as_bytes()
+    pub fn rebound_in(alloc: &'a A) -> Self {
+        Self::from_alloc(alloc.rebind())
     }
 }
 
-impl<A, L> Borrow<[u16]> for CxxUtf16StringLayout<A, L>
+impl<A, L> fmt::Debug for CxxUtf16StringLayout<A, L>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
 {
-    fn borrow(&self) -> &[u16] {
-        self.as_bytes()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_utf16(self.as_bytes(), f)
     }
 }
 
-impl<A, L> Default for CxxUtf16StringLayout<A, L>
+impl<A, L> Diagnose for CxxUtf16StringLayout<A, L>
 where
-    A: CxxProxy + Default,
+    A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
 {
-    fn default() -> Self {
-        Self::from_alloc(A::default())
+    fn report(&self) -> ContainerReport {
+        let val = self.inner.value_as_ref();
+
+        unsafe { string_report("CxxUtf16String", &val.bx.buf, val.bx.ptr, val.size, val.res) }
     }
 }
 
-impl<A, L> Drop for CxxUtf16StringLayout<A, L>
+/// Appends the characters encoded as UTF-16.
+impl<A, L> fmt::Write for CxxUtf16StringLayout<A, L>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
 {
-    fn drop(&mut self) {
-        self.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_u16string_destroy(val, alloc);
-        });
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.extend(s.encode_utf16());
+        Ok(())
     }
 }
 
-impl<A, L> Clone for CxxUtf16StringLayout<A, L>
-where
-    A: CxxProxy + Clone,
-    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
-{
-    fn clone(&self) -> Self {
-        Self::from_bytes_in(self, self.inner.alloc_as_ref().clone())
-    }
+/// Values formatted like MSVC's `std::to_wstring`, into a [`CxxUtf16String`].
+///
+/// Floating-point numbers are formatted like `%f`, with six decimal places,
+/// and the infinities and NaNs as spelled by the MSVC CRT, e.g. `-nan(ind)`.
+pub trait ToU16String {
+    fn to_u16string(&self) -> CxxUtf16String;
 }
 
-impl<A, L> Extend<u16> for CxxUtf16StringLayout<A, L>
-where
-    A: CxxProxy,
-    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
-{
-    fn extend<I: IntoIterator<Item = u16>>(&mut self, iter: I) {
-        let iter = iter.into_iter();
-        self.reserve(iter.size_hint().0);
-        self.inner.with_proxy_mut(|val, alloc| unsafe {
-            for ch in iter {
-                CSTL_u16string_append_char(val, 1, ch, alloc);
+macro_rules! integer_to_u16string {
+    ($($int:ty),*) => {$(
+        impl ToU16String for $int {
+            fn to_u16string(&self) -> CxxUtf16String {
+                CxxUtf16String::from_display(self)
             }
-        });
+        }
+    )*};
+}
+
+integer_to_u16string!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl ToU16String for f32 {
+    fn to_u16string(&self) -> CxxUtf16String {
+        // Promoted to `double` when passed to `swprintf`:
+        f64::from(*self).to_u16string()
     }
 }
 
-const fn new_val() -> RawUtf16String {
-    RawUtf16String {
-        bx: cstl_sys::CSTL_UTF16StringUnion { buf: [0; 8] },
-        size: 0,
-        res: 7,
+impl ToU16String for f64 {
+    fn to_u16string(&self) -> CxxUtf16String {
+        CxxUtf16String::from_display(MsvcFloat(*self))
     }
 }
 
-impl<A: CxxProxy> WithCxxProxy for Layout<A> {
-    type Value = RawUtf16String;
-    type Alloc = A;
+/// `%f` formatting of the MSVC CRT.
+struct MsvcFloat(f64);
 
-    fn value_as_ref(&self) -> &Self::Value {
-        &self.val
-    }
+impl fmt::Display for MsvcFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const QUIET: u64 = 1 << 51;
+        const INDEFINITE: u64 = 0xfff8_0000_0000_0000;
 
-    fn value_as_mut(&mut self) -> &mut Self::Value {
-        &mut self.val
-    }
+        let value = self.0;
+        let sign = if value.is_sign_negative() { "-" } else { "" };
 
-    fn alloc_as_ref(&self) -> &Self::Alloc {
-        &self.alloc
-    }
+        if value.is_nan() {
+            let bits = value.to_bits();
 
-    fn new_in(alloc: Self::Alloc) -> Self {
-        Self {
-            alloc,
-            val: new_val(),
+            let suffix = if bits & QUIET == 0 {
+                "(snan)"
+            } else if bits == INDEFINITE {
+                "(ind)"
+            } else {
+                ""
+            };
+
+            write!(f, "{sign}nan{suffix}")
+        } else if value.is_infinite() {
+            write!(f, "{sign}inf")
+        } else {
+            write!(f, "{value:.6}")
         }
     }
 }
 
-#[cfg(feature = "msvc2012")]
-pub mod msvc2012 {
-    use cstl_sys::CSTL_UTF16StringVal as RawUtf16String;
+impl<A, L> MemoryFootprint for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn allocated_bytes(&self) -> usize {
+        let val = self.inner.value_as_ref();
 
-    use crate::alloc::{CxxProxy, WithCxxProxy};
+        unsafe { string_bytes(&val.bx.buf, val.res) + proxy_bytes::<L>() }
+    }
+}
 
-    use super::{new_val, CxxUtf16StringLayout, SysAlloc};
+/// Borrowed characters of a UTF-16 string, which [`ToOwned`] copies into a
+/// [`CxxUtf16String`], e.g. for a `Cow<'_, CxxUtf16Str>`.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CxxUtf16Str([u16]);
 
-    pub type CxxUtf16String<A = SysAlloc> = CxxUtf16StringLayout<A, Layout<A>>;
+impl CxxUtf16Str {
+    pub fn from_bytes(s: &[u16]) -> &Self {
+        // `CxxUtf16Str` is transparent:
+        unsafe { &*(s as *const [u16] as *const Self) }
+    }
 
-    #[repr(C)]
-    pub struct Layout<A: CxxProxy> {
-        val: RawUtf16String,
-        alloc: A,
+    pub fn as_bytes(&self) -> &[u16] {
+        &self.0
     }
 
-    impl<A: CxxProxy> Layout<A> {
-        pub const fn new_in(alloc: A) -> Self {
-            Self {
-                alloc,
-                val: new_val(),
-            }
-        }
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
-    impl CxxUtf16String<SysAlloc> {
-        pub const fn new() -> Self {
-            Self {
-                inner: Layout::new_in(SysAlloc),
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
-    impl<A: CxxProxy> CxxUtf16String<A> {
-        pub const fn new_in(alloc: A) -> Self {
-            Self {
-                inner: Layout::new_in(alloc),
-            }
-        }
+    /// Returns a copy with each character mapped to its uppercase counterpart
+    /// under the full Unicode case mapping, restricted to single-character results.
+    ///
+    /// Characters whose full mapping has several characters are copied unchanged,
+    /// even if their simple mapping differs, e.g. U+1F80 isn't mapped to U+1F88.
+    /// Unpaired surrogates are copied unchanged.
+    pub fn to_uppercase(&self) -> CxxUtf16String {
+        map_single_char_case(&self.0, char::to_uppercase)
+    }
 
-        pub const fn allocator(&self) -> &A {
-            &self.inner.alloc
-        }
+    /// Returns a copy with each character mapped to its lowercase counterpart
+    /// under the full Unicode case mapping, restricted to single-character results.
+    ///
+    /// Characters whose full mapping has several characters are copied unchanged,
+    /// even if their simple mapping differs, e.g. U+0130 isn't mapped to U+0069.
+    /// Unpaired surrogates are copied unchanged.
+    pub fn to_lowercase(&self) -> CxxUtf16String {
+        map_single_char_case(&self.0, char::to_lowercase)
     }
 
-    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
-        type Value = RawUtf16String;
-        type Alloc = A;
+    /// Returns the characters without leading and trailing whitespace.
+    pub fn trim(&self) -> &Self {
+        // Whitespace is never encoded with surrogates:
+        self.trim_matches(|unit| char::from_u32(unit.into()).is_some_and(char::is_whitespace))
+    }
 
-        fn value_as_ref(&self) -> &Self::Value {
-            &self.val
-        }
+    /// Returns the characters without the leading and trailing code units
+    /// that match `pat`.
+    pub fn trim_matches(&self, mut pat: impl FnMut(u16) -> bool) -> &Self {
+        let start = self.0.iter().position(|&unit| !pat(unit));
+        let range = start.map_or(0..0, |start| {
+            let end = self.0.iter().rposition(|&unit| !pat(unit)).unwrap_or(start);
+            start..end + 1
+        });
 
-        fn value_as_mut(&mut self) -> &mut Self::Value {
-            &mut self.val
+        Self::from_bytes(&self.0[range])
+    }
+
+    /// Parses the characters into a `T`, e.g. a number,
+    /// with invalid UTF-16 replaced by U+FFFD.
+    pub fn parse<T: FromStr>(&self) -> Result<T, T::Err> {
+        String::from_utf16_lossy(&self.0).parse()
+    }
+
+    /// Whether the characters equal those of `other`, ignoring the case of ASCII letters.
+    pub fn eq_ignore_ascii_case(&self, other: impl AsRef<[u16]>) -> bool {
+        let other = other.as_ref();
+        let to_lower = |unit: u16| match u8::try_from(unit) {
+            Ok(byte) => byte.to_ascii_lowercase().into(),
+            Err(_) => unit,
+        };
+
+        self.0.len() == other.len()
+            && self
+                .0
+                .iter()
+                .zip(other)
+                .all(|(&a, &b)| to_lower(a) == to_lower(b))
+    }
+}
+
+/// Copies `units` into a new string, mapping every character that `f`
+/// maps to a single character.
+fn map_single_char_case<M>(units: &[u16], f: impl Fn(char) -> M) -> CxxUtf16String
+where
+    M: Iterator<Item = char>,
+{
+    let mut mapped = CxxUtf16String::new();
+    mapped.reserve(units.len());
+
+    for c in char::decode_utf16(units.iter().copied()) {
+        let mut buf = [0; 2];
+
+        let encoded: &[u16] = match c {
+            Ok(c) => {
+                let mut chars = f(c);
+
+                match (chars.next(), chars.next()) {
+                    (Some(single), None) => single.encode_utf16(&mut buf),
+                    _ => c.encode_utf16(&mut buf),
+                }
+            }
+            Err(e) => {
+                buf[0] = e.unpaired_surrogate();
+                &buf[..1]
+            }
+        };
+
+        mapped.push(encoded);
+    }
+
+    mapped
+}
+
+impl Deref for CxxUtf16Str {
+    type Target = [u16];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[u16]> for CxxUtf16Str {
+    fn as_ref(&self) -> &[u16] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for CxxUtf16Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_utf16(&self.0, f)
+    }
+}
+
+impl ToOwned for CxxUtf16Str {
+    type Owned = CxxUtf16String;
+
+    fn to_owned(&self) -> Self::Owned {
+        CxxUtf16String::from_bytes_in(self, SysAlloc)
+    }
+}
+
+impl<A, L> Borrow<CxxUtf16Str> for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn borrow(&self) -> &CxxUtf16Str {
+        self.as_str()
+    }
+}
+
+/// UTF-16 string borrowed from C++ code until it is first mutated, when its characters
+/// are copied into an owned [`CxxUtf16String`].
+///
+/// Like a `Cow<'_, CxxUtf16Str>`, but borrows the C++ string itself,
+/// so hooks can pass it on unchanged. Only an owned string is dropped with the wrapper.
+pub enum CxxCowUtf16String<'a, A = SysAlloc, L = Layout<A>>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    Borrowed(&'a CxxUtf16StringLayout<A, L>),
+    Owned(CxxUtf16String),
+}
+
+impl<'a, A, L> CxxCowUtf16String<'a, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    /// Borrows the string at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid string, which isn't mutated for `'a`.
+    pub unsafe fn from_raw(ptr: *const CxxUtf16StringLayout<A, L>) -> Self {
+        Self::Borrowed(unsafe { &*ptr })
+    }
+
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, Self::Borrowed(_))
+    }
+
+    pub fn is_owned(&self) -> bool {
+        matches!(self, Self::Owned(_))
+    }
+
+    pub fn as_str(&self) -> &CxxUtf16Str {
+        match self {
+            Self::Borrowed(borrowed) => borrowed.as_str(),
+            Self::Owned(owned) => owned.as_str(),
+        }
+    }
+
+    /// Returns the borrowed C++ string, or `None` if the string is owned.
+    pub fn as_borrowed(&self) -> Option<&'a CxxUtf16StringLayout<A, L>> {
+        match *self {
+            Self::Borrowed(borrowed) => Some(borrowed),
+            Self::Owned(_) => None,
+        }
+    }
+
+    /// Returns the owned string, first copying the characters of a borrowed one.
+    pub fn to_mut(&mut self) -> &mut CxxUtf16String {
+        if let Self::Borrowed(borrowed) = *self {
+            *self = Self::Owned(CxxUtf16String::from_bytes_in(borrowed, SysAlloc));
+        }
+
+        match self {
+            Self::Borrowed(_) => unreachable!(),
+            Self::Owned(owned) => owned,
+        }
+    }
+
+    /// Returns the owned string, copying the characters of a borrowed one.
+    pub fn into_owned(self) -> CxxUtf16String {
+        match self {
+            Self::Borrowed(borrowed) => CxxUtf16String::from_bytes_in(borrowed, SysAlloc),
+            Self::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<A, L> Deref for CxxCowUtf16String<'_, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    type Target = CxxUtf16Str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<A, L> AsRef<[u16]> for CxxCowUtf16String<'_, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn as_ref(&self) -> &[u16] {
+        self.as_str()
+    }
+}
+
+impl<'a, A, L> From<&'a CxxUtf16StringLayout<A, L>> for CxxCowUtf16String<'a, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn from(borrowed: &'a CxxUtf16StringLayout<A, L>) -> Self {
+        Self::Borrowed(borrowed)
+    }
+}
+
+impl<A, L> From<CxxUtf16String> for CxxCowUtf16String<'_, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn from(owned: CxxUtf16String) -> Self {
+        Self::Owned(owned)
+    }
+}
+
+impl<A, L> fmt::Debug for CxxCowUtf16String<'_, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Borrowed(borrowed) => f.debug_tuple("Borrowed").field(borrowed).finish(),
+            Self::Owned(owned) => f.debug_tuple("Owned").field(owned).finish(),
+        }
+    }
+}
+
+impl<I, A, L> Index<I> for CxxUtf16StringLayout<A, L>
+where
+    I: SliceIndex<[u16]>,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.as_bytes(), index)
+    }
+}
+
+impl<A, L> AsRef<[u16]> for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn as_ref(&self) -> &[u16] {
+        self.as_bytes()
+    }
+}
+
+impl<A, L> Borrow<[u16]> for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn borrow(&self) -> &[u16] {
+        self.as_bytes()
+    }
+}
+
+impl<A1, A2, L1, L2> PartialEq<CxxUtf16StringLayout<A2, L2>> for CxxUtf16StringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawUtf16String>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawUtf16String>,
+{
+    fn eq(&self, other: &CxxUtf16StringLayout<A2, L2>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<A1, A2, L1, L2> PartialOrd<CxxUtf16StringLayout<A2, L2>> for CxxUtf16StringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawUtf16String>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawUtf16String>,
+{
+    fn partial_cmp(&self, other: &CxxUtf16StringLayout<A2, L2>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Eq for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+}
+
+impl<A, L> Ord for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Ord::cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Hash for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_bytes(), state)
+    }
+}
+
+impl<A, L> Default for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy + Default,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn default() -> Self {
+        Self::from_alloc(A::default())
+    }
+}
+
+impl<A, L> Drop for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn drop(&mut self) {
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_u16string_destroy(val, alloc);
+        });
+    }
+}
+
+impl<A, L> Clone for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy + Clone,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn clone(&self) -> Self {
+        Self::from_bytes_in(self, self.inner.alloc_as_ref().clone())
+    }
+
+    /// Copies the characters of `source` into the existing buffer if it fits them,
+    /// like C++ copy assignment. The string keeps its own allocator.
+    fn clone_from(&mut self, source: &Self) {
+        self.replace(source);
+    }
+}
+
+impl<A, L> Extend<u16> for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+    fn extend<I: IntoIterator<Item = u16>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        let spare = self.capacity() - self.len();
+
+        if iter.size_hint().0 > spare {
+            self.reserve(iter.size_hint().0 - spare);
+        }
+
+        // Appends the characters in chunks, rather than one CSTL call per character:
+        let mut chunk = [0; EXTEND_CHUNK_LEN];
+
+        loop {
+            let len = chunk
+                .iter_mut()
+                .zip(&mut iter)
+                .map(|(c, ch)| *c = ch)
+                .count();
+
+            if len == 0 {
+                break;
+            }
+
+            self.push(&chunk[..len]);
+        }
+    }
+}
+
+// The string owns its characters, which are only written through `&mut self`:
+unsafe impl<A, L> Send for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy + Send,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+}
+
+unsafe impl<A, L> Sync for CxxUtf16StringLayout<A, L>
+where
+    A: CxxProxy + Sync,
+    L: WithCxxProxy<Alloc = A, Value = RawUtf16String>,
+{
+}
+
+const fn new_val() -> RawUtf16String {
+    RawUtf16String {
+        bx: cstl_sys::CSTL_UTF16StringUnion { buf: [0; 8] },
+        size: 0,
+        res: 7,
+    }
+}
+
+/// Destroys the string value at `ptr` like `std::u16string::~basic_string`, freeing
+/// its characters with `alloc`, e.g. to destroy a string constructed by C++
+/// before overwriting it.
+///
+/// An empty value is left at `ptr`. With iterator debugging, the proxy
+/// stored before the value isn't freed.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid string value, allocated with an allocator
+/// compatible with `alloc`.
+pub unsafe fn drop_in_place_with<A: CxxProxy>(ptr: *mut RawUtf16String, alloc: &A) {
+    let mut layout = Layout::new_in(alloc.rebind());
+    layout.val = unsafe { ptr.read() };
+
+    layout.with_proxy_mut(|val, alloc| unsafe {
+        CSTL_u16string_destroy(val, alloc);
+    });
+
+    unsafe { ptr.write(new_val()) }
+}
+
+impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+    type Value = RawUtf16String;
+    type Alloc = A;
+
+    const VALUE_OFFSET: usize = mem::offset_of!(Self, val);
+
+    const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+    const FREE_SIZE_UNIT: usize = mem::size_of::<u16>();
+
+    fn value_as_ref(&self) -> &Self::Value {
+        &self.val
+    }
+
+    fn value_as_mut(&mut self) -> &mut Self::Value {
+        &mut self.val
+    }
+
+    fn alloc_as_ref(&self) -> &Self::Alloc {
+        &self.alloc
+    }
+
+    fn new_in(alloc: Self::Alloc) -> Self {
+        Self {
+            alloc,
+            val: new_val(),
+        }
+    }
+}
+
+pub mod msvc2010 {
+    use cstl_sys::CSTL_UTF16StringVal as RawUtf16String;
+
+    use crate::alloc::{AllocMember, CxxProxy, WithCxxProxy};
+
+    use super::{new_val, CxxUtf16StringLayout, SysAlloc};
+
+    pub type CxxUtf16String<A = SysAlloc> = CxxUtf16StringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        val: RawUtf16String,
+        alloc: AllocMember<A>,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxUtf16String<SysAlloc> {
+        pub const fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxUtf16String<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            self.inner.alloc.get()
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxUtf16String<B> {
+            CxxUtf16String::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawUtf16String;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            self.alloc.get()
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+}
+
+pub mod msvc2012 {
+    use cstl_sys::CSTL_UTF16StringVal as RawUtf16String;
+
+    use crate::alloc::{CxxProxy, WithCxxProxy};
+
+    use super::{new_val, CxxUtf16StringLayout, SysAlloc};
+
+    pub type CxxUtf16String<A = SysAlloc> = CxxUtf16StringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        val: RawUtf16String,
+        alloc: A,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                alloc,
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxUtf16String<SysAlloc> {
+        pub const fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxUtf16String<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            &self.inner.alloc
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxUtf16String<B> {
+            CxxUtf16String::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawUtf16String;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            &mut self.val
         }
 
         fn alloc_as_ref(&self) -> &Self::Alloc {
@@ -323,3 +1349,104 @@ pub mod msvc2012 {
         }
     }
 }
+
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug {
+    use cstl_sys::CSTL_UTF16StringVal as RawUtf16String;
+
+    use crate::{
+        alloc::{CxxProxy, WithCxxProxy},
+        iterator_debug::ProxyPtr,
+    };
+
+    use super::{new_val, CxxUtf16StringLayout, SysAlloc};
+
+    pub type CxxUtf16String<A = SysAlloc> = CxxUtf16StringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        alloc: A,
+        proxy: ProxyPtr,
+        val: RawUtf16String,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                proxy: ProxyPtr::new_in(&alloc),
+                alloc,
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxUtf16String<SysAlloc> {
+        pub fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxUtf16String<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            &self.inner.alloc
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxUtf16String<B> {
+            CxxUtf16String::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawUtf16String;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const PROXY_OFFSET: Option<usize> = Some(std::mem::offset_of!(Self, proxy));
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            self.proxy.attach();
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            self.proxy.attach();
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            &self.alloc
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self::new_in(alloc)
+        }
+
+        fn into_parts(self) -> (Self::Alloc, Self::Value) {
+            let this = std::mem::ManuallyDrop::new(self);
+
+            unsafe {
+                this.proxy.free_in(&this.alloc);
+                (std::ptr::read(&this.alloc), std::ptr::read(&this.val))
+            }
+        }
+    }
+
+    impl<A: CxxProxy> Drop for Layout<A> {
+        fn drop(&mut self) {
+            unsafe { self.proxy.free_in(&self.alloc) }
+        }
+    }
+}
@@ -1,13 +1,37 @@
-use std::{alloc::System as SysAlloc, borrow::Borrow, fmt, slice};
+use std::{
+    alloc::System as SysAlloc,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    mem::{self, MaybeUninit},
+    ops::Index,
+    pin::Pin,
+    ptr,
+    slice::{self, SliceIndex},
+};
 
 pub use cstl_sys::CSTL_WideStringVal as RawWideString;
-use cstl_sys::{
-    CSTL_wstring_append_char, CSTL_wstring_append_n, CSTL_wstring_assign_n,
-    CSTL_wstring_c_str, CSTL_wstring_clear, CSTL_wstring_destroy, CSTL_wstring_reserve,
-    CSTL_wstring_shrink_to_fit,
+
+#[cfg(windows)]
+use super::{alloc_bstr, bstr_as_slice, Bstr};
+use super::{
+    chars_ptr_raw, debug_utf16, push_within_capacity_raw, small_capacity, try_reserve_raw,
+    RawDebug, UnicodeString, EXTEND_CHUNK_LEN,
+};
+use crate::{
+    alloc::{growth_policy, CxxProxy, GrowthPolicy, ProxyRef, TryReserveError, WithCxxProxy},
+    cstl::{
+        CSTL_wstring_append_n, CSTL_wstring_assign_n, CSTL_wstring_destroy, CSTL_wstring_reserve,
+        CSTL_wstring_shrink_to_fit, CSTL_wstring_swap,
+    },
+    diagnostics::{
+        proxy_bytes, string_bytes, string_report, ContainerReport, Diagnose, MemoryFootprint,
+    },
+    ffi::CxxIter,
 };
 
-use crate::alloc::{CxxProxy, WithCxxProxy};
+#[cfg(all(windows, feature = "windows"))]
+use windows_strings::HSTRING;
 
 pub type CxxWideString<A = SysAlloc> = CxxWideStringLayout<A, Layout<A>>;
 
@@ -53,6 +77,10 @@ impl<A: CxxProxy> CxxWideString<A> {
     pub const fn allocator(&self) -> &A {
         &self.inner.alloc
     }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxWideString<B> {
+        CxxWideString::from_bytes_in(&self, alloc)
+    }
 }
 
 impl<A, L> CxxWideStringLayout<A, L>
@@ -60,20 +88,126 @@ where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawWideString>,
 {
+    /// Size of the string in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the string in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Offset in bytes of the small string buffer, or of the pointer
+    /// to the characters in large mode, `_Bx`.
+    pub const OFFSET_BUF: usize = L::VALUE_OFFSET + mem::offset_of!(RawWideString, bx);
+
+    /// Offset in bytes of the length in characters, `_Mysize`.
+    pub const OFFSET_SIZE: usize = L::VALUE_OFFSET + mem::offset_of!(RawWideString, size);
+
+    /// Offset in bytes of the capacity in characters, `_Myres`.
+    pub const OFFSET_CAPACITY: usize = L::VALUE_OFFSET + mem::offset_of!(RawWideString, res);
+
+    /// Capacity in characters of the small string buffer, `_BUF_SIZE - 1`.
+    /// Shorter strings are stored in the string itself.
+    pub const SMALL_CAPACITY: usize = small_capacity::<u16>();
+
+    /// Constructs an empty string with `alloc` in the storage at `ptr`,
+    /// e.g. the `this` pointer passed to a hooked C++ constructor.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned to [`Self::ALIGN`].
+    /// A value previously stored there is overwritten without being dropped.
+    /// The string must not be moved afterwards, as the container proxy of iterator
+    /// debugging layouts points to it at `ptr` for C++ iterators.
+    pub unsafe fn init_in_place_in(ptr: *mut Self, alloc: A) {
+        unsafe {
+            ptr.write(Self::from_alloc(alloc));
+            // Attaches the container proxy at the final address:
+            (*ptr).inner.value_as_mut();
+        }
+    }
+
+    /// Constructs an empty string with `alloc` in `slot`, pinned as C++ code
+    /// may refer to it there.
+    ///
+    /// `slot` doesn't drop the string, it must be dropped in place,
+    /// e.g. with [`MaybeUninit::assume_init_drop`], to free its characters.
+    /// It must not be moved out of `slot`, see [`Self::init_in_place_in`].
+    pub fn emplace_in(slot: &mut MaybeUninit<Self>, alloc: A) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        let this = slot.write(Self::from_alloc(alloc));
+        // Attaches the container proxy at the final address:
+        this.inner.value_as_mut();
+
+        Pin::new(this)
+    }
+
     pub fn from_bytes_in<T: AsRef<[u16]>>(s: T, alloc: A) -> Self {
         let mut new = Self::from_alloc(alloc);
 
         let slice = s.as_ref();
 
-        new.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_wstring_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
-        });
+        if !new.push_within_capacity(slice) {
+            new.inner.with_proxy_mut(|val, alloc| unsafe {
+                CSTL_wstring_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+            });
+        }
 
         new
     }
 
+    pub fn try_from_bytes_in<T: AsRef<[u16]>>(s: T, alloc: A) -> Result<Self, TryReserveError> {
+        let mut new = Self::from_alloc(alloc);
+        new.try_push(s)?;
+        Ok(new)
+    }
+
+    /// Copies the characters of the counted string `s`.
+    pub fn from_unicode_string_in(s: &UnicodeString<'_>, alloc: A) -> Self {
+        Self::from_bytes_in(s.as_slice(), alloc)
+    }
+
+    /// Borrows the string as a counted string, e.g. to pass it to NT functions.
+    ///
+    /// Returns `None` if the string is over `u16::MAX / 2` characters long,
+    /// which counted strings can't represent.
+    pub fn as_unicode_string(&self) -> Option<UnicodeString<'_>> {
+        UnicodeString::with_nul(self.as_bytes_with_nul())
+    }
+
+    /// Copies the characters of the WinRT string `s`.
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn from_hstring_in(s: &HSTRING, alloc: A) -> Self {
+        Self::from_bytes_in(&**s, alloc)
+    }
+
+    /// Creates a WinRT string with the characters of this string.
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn to_hstring(&self) -> HSTRING {
+        HSTRING::from_wide(self.as_bytes())
+    }
+
+    /// Copies the characters of the COM automation string `bstr`.
+    ///
+    /// # Safety
+    ///
+    /// `bstr` must be null or a valid `BSTR`.
+    #[cfg(windows)]
+    pub unsafe fn from_bstr_in(bstr: Bstr, alloc: A) -> Self {
+        Self::from_bytes_in(unsafe { bstr_as_slice(bstr) }, alloc)
+    }
+
+    /// Allocates a COM automation string with the characters of this string.
+    ///
+    /// The caller owns the returned `BSTR`, and frees it with `SysFreeString`.
+    #[cfg(windows)]
+    pub fn to_bstr(&self) -> Bstr {
+        alloc_bstr(self.as_bytes())
+    }
+
     pub fn as_ptr(&self) -> *const u16 {
-        unsafe { CSTL_wstring_c_str(self.inner.value_as_ref()) as _ }
+        let val = self.inner.value_as_ref();
+        unsafe { chars_ptr_raw(ptr::addr_of!(val.bx).cast_mut().cast(), val.res) }
     }
 
     pub fn as_bytes(&self) -> &[u16] {
@@ -84,6 +218,12 @@ where
         unsafe { slice::from_raw_parts(self.as_ptr(), self.len() + 1) }
     }
 
+    /// Returns the character or characters at `index`,
+    /// or `None` if it is out of bounds.
+    pub fn get<I: SliceIndex<[u16]>>(&self, index: I) -> Option<&I::Output> {
+        self.as_bytes().get(index)
+    }
+
     pub fn len(&self) -> usize {
         self.inner.value_as_ref().size
     }
@@ -99,19 +239,170 @@ where
     pub fn push<T: AsRef<[u16]>>(&mut self, s: T) {
         let slice = s.as_ref();
 
+        if self.push_within_capacity(slice) {
+            return;
+        }
+
+        let capacity = self.capacity();
+        let required = self.len().saturating_add(slice.len());
+        let new_capacity = growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, required);
+        self.reserve(new_capacity - capacity);
+
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_wstring_append_n(val, slice.as_ptr() as _, slice.len(), alloc);
         });
     }
 
-    pub fn replace<T: AsRef<[u16]>>(&mut self, s: T) {
+    pub fn try_push<T: AsRef<[u16]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let spare = self.capacity() - self.len();
+
+        if slice.len() > spare {
+            self.try_reserve(slice.len() - spare)?;
+        }
+
+        self.push(slice);
+
+        Ok(())
+    }
+
+    /// Replaces the characters with those of `s`, reusing the buffer if it fits them,
+    /// like `std::basic_string::assign`. The old characters aren't copied when growing.
+    pub fn assign<T: AsRef<[u16]>>(&mut self, s: T) {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
         self.clear();
-        self.push(s);
+
+        if slice.len() > capacity {
+            let new_capacity =
+                growth_policy().grown_capacity(GrowthPolicy::Msvc, capacity, slice.len());
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_wstring_assign_n(val, slice.as_ptr() as _, slice.len(), alloc);
+        });
+    }
+
+    /// Replaces the characters with those of `iter`, reusing the buffer if it fits them.
+    pub fn assign_from_iter<I: IntoIterator<Item = u16>>(&mut self, iter: I) {
+        self.clear();
+        self.extend(iter);
     }
 
+    pub fn replace<T: AsRef<[u16]>>(&mut self, s: T) {
+        self.assign(s);
+    }
+
+    pub fn try_replace<T: AsRef<[u16]>>(&mut self, s: T) -> Result<(), TryReserveError> {
+        let slice = s.as_ref();
+        let capacity = self.capacity();
+
+        if slice.len() > capacity {
+            self.try_reserve(slice.len() - capacity)?;
+        }
+
+        self.replace(slice);
+
+        Ok(())
+    }
+
+    /// Removes all characters, keeping the buffer to reuse it for new characters.
     pub fn clear(&mut self) {
+        let val = self.inner.value_as_mut();
+
         unsafe {
-            CSTL_wstring_clear(self.inner.value_as_mut());
+            chars_ptr_raw::<u16>(ptr::addr_of_mut!(val.bx).cast(), val.res).write(0);
+        }
+
+        val.size = 0;
+    }
+
+    /// Returns a copy of the string with its allocator, and clears this one while
+    /// keeping its buffer, e.g. to reuse it every frame.
+    pub fn take_reuse(&mut self) -> Self
+    where
+        A: Clone,
+    {
+        let taken = Self::from_bytes_in(self.as_bytes(), self.inner.alloc_as_ref().clone());
+        self.clear();
+
+        taken
+    }
+
+    /// Returns a C++ iterator to the first character, see [`CxxIter`].
+    pub fn begin(&self) -> CxxIter<'_, u16> {
+        self.iter_at(0)
+    }
+
+    /// Returns a C++ iterator past the last character, see [`CxxIter`].
+    pub fn end(&self) -> CxxIter<'_, u16> {
+        self.iter_at(self.len())
+    }
+
+    /// Returns a C++ iterator to the character at `index`,
+    /// or past the last character if `index == len`, see [`CxxIter`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn iter_at(&self, index: usize) -> CxxIter<'_, u16> {
+        let len = self.len();
+
+        if index > len {
+            panic!("iterator index (is {index}) should be <= len (is {len})");
+        }
+
+        let val = self.inner.value_as_ref();
+        CxxIter::new(unsafe {
+            chars_ptr_raw::<u16>(ptr::addr_of!(val.bx).cast_mut().cast(), val.res).add(index)
+        })
+    }
+
+    /// Swaps two strings along with their allocators,
+    /// like `std::swap` with allocators that propagate on container swap.
+    ///
+    /// The string values are exchanged without allocating, copying the small string
+    /// buffers. With iterator debugging, C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    pub fn swap(&mut self, other: &mut Self) {
+        #[cfg(feature = "iterator-debug")]
+        {
+            super::orphan_small_iterators::<u16, _>(&self.inner, self.inner.value_as_ref().res);
+            super::orphan_small_iterators::<u16, _>(&other.inner, other.inner.value_as_ref().res);
+        }
+
+        mem::swap(self, other);
+    }
+
+    /// Swaps the contents of two strings while keeping their allocators,
+    /// like `std::swap` with allocators that don't propagate on container swap.
+    ///
+    /// Use [`mem::swap`] to swap the allocators as well. With iterator debugging,
+    /// C++ iterators follow the swapped contents,
+    /// except for iterators into small strings, which are orphaned.
+    ///
+    /// # Safety
+    ///
+    /// Each allocator must be able to free memory allocated by the other,
+    /// as required of C++ allocators that compare equal.
+    pub unsafe fn swap_contents<A2, L2>(&mut self, other: &mut CxxWideStringLayout<A2, L2>)
+    where
+        A2: CxxProxy,
+        L2: WithCxxProxy<Alloc = A2, Value = RawWideString>,
+    {
+        #[cfg(feature = "iterator-debug")]
+        unsafe {
+            let res = (
+                self.inner.value_as_ref().res,
+                other.inner.value_as_ref().res,
+            );
+            super::swap_proxies::<u16, _, _>(&mut self.inner, &mut other.inner, res);
+        }
+
+        unsafe {
+            CSTL_wstring_swap(self.inner.value_as_mut(), other.inner.value_as_mut());
         }
     }
 
@@ -127,12 +418,50 @@ where
         });
     }
 
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let new_capacity = self
+            .capacity()
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        self.inner.try_with_proxy_mut(|val, alloc| unsafe {
+            try_reserve_raw(
+                &mut val.bx as *mut _ as *mut u16,
+                val.size,
+                &mut val.res,
+                new_capacity,
+                alloc,
+            )
+        })?
+    }
+
     pub fn shrink_to_fit(&mut self) {
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             CSTL_wstring_shrink_to_fit(val, alloc);
         });
     }
 
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the string,
+    /// its length and capacity and whether it is in large mode.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        let val = self.inner.value_as_ref();
+
+        RawDebug {
+            name: "CxxWideString",
+            length: val.size,
+            capacity: val.res,
+            large_mode: val.res > new_val().res,
+        }
+    }
+
+    /// Appends `s` without calling into CSTL, returning whether it fits in the capacity.
+    fn push_within_capacity(&mut self, s: &[u16]) -> bool {
+        let val = self.inner.value_as_mut();
+        unsafe {
+            push_within_capacity_raw(ptr::addr_of_mut!(val.bx).cast(), &mut val.size, val.res, s)
+        }
+    }
+
     fn from_alloc(alloc: A) -> Self {
         Self {
             inner: L::new_in(alloc),
@@ -140,17 +469,106 @@ where
     }
 }
 
+impl<L> CxxWideStringLayout<SysAlloc, L>
+where
+    L: WithCxxProxy<Alloc = SysAlloc, Value = RawWideString>,
+{
+    /// Constructs an empty string in the storage at `ptr`,
+    /// see [`CxxWideStringLayout::init_in_place_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxWideStringLayout::init_in_place_in`].
+    pub unsafe fn init_at(ptr: *mut Self) {
+        unsafe { Self::init_in_place_in(ptr, SysAlloc) }
+    }
+
+    /// Copies the characters of the counted string `s`,
+    /// see [`CxxWideStringLayout::from_unicode_string_in`].
+    pub fn from_unicode_string(s: &UnicodeString<'_>) -> Self {
+        Self::from_unicode_string_in(s, SysAlloc)
+    }
+
+    /// Copies the characters of the WinRT string `s`, see [`CxxWideStringLayout::from_hstring_in`].
+    #[cfg(all(windows, feature = "windows"))]
+    pub fn from_hstring(s: &HSTRING) -> Self {
+        Self::from_hstring_in(s, SysAlloc)
+    }
+
+    /// Copies the characters of the COM automation string `bstr`,
+    /// see [`CxxWideStringLayout::from_bstr_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxWideStringLayout::from_bstr_in`].
+    #[cfg(windows)]
+    pub unsafe fn from_bstr(bstr: Bstr) -> Self {
+        unsafe { Self::from_bstr_in(bstr, SysAlloc) }
+    }
+
+    /// Constructs an empty string in `slot`, see [`CxxWideStringLayout::emplace_in`].
+    pub fn emplace(slot: &mut MaybeUninit<Self>) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        Self::emplace_in(slot, SysAlloc)
+    }
+}
+
+impl<'a, A, L> CxxWideStringLayout<ProxyRef<'a, A>, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = ProxyRef<'a, A>, Value = RawWideString>,
+{
+    pub fn rebound_in(alloc: &'a A) -> Self {
+        Self::from_alloc(alloc.rebind())
+    }
+}
+
 impl<A, L> fmt::Debug for CxxWideStringLayout<A, L>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawWideString>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CxxWideString")
-            .field("length", &self.inner.value_as_ref().size)
-            .field("capacity", &self.inner.value_as_ref().res)
-            .field("large_mode", &(self.inner.value_as_ref().res > 15))
-            .finish()
+        debug_utf16(self.as_bytes(), f)
+    }
+}
+
+impl<A, L> Diagnose for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+    fn report(&self) -> ContainerReport {
+        let val = self.inner.value_as_ref();
+
+        unsafe { string_report("CxxWideString", &val.bx.buf, val.bx.ptr, val.size, val.res) }
+    }
+}
+
+impl<A, L> MemoryFootprint for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+    fn allocated_bytes(&self) -> usize {
+        let val = self.inner.value_as_ref();
+
+        unsafe { string_bytes(&val.bx.buf, val.res) + proxy_bytes::<L>() }
+    }
+}
+
+impl<I, A, L> Index<I> for CxxWideStringLayout<A, L>
+where
+    I: SliceIndex<[u16]>,
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.as_bytes(), index)
     }
 }
 
@@ -174,6 +592,57 @@ where
     }
 }
 
+impl<A1, A2, L1, L2> PartialEq<CxxWideStringLayout<A2, L2>> for CxxWideStringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawWideString>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawWideString>,
+{
+    fn eq(&self, other: &CxxWideStringLayout<A2, L2>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<A1, A2, L1, L2> PartialOrd<CxxWideStringLayout<A2, L2>> for CxxWideStringLayout<A1, L1>
+where
+    A1: CxxProxy,
+    A2: CxxProxy,
+    L1: WithCxxProxy<Alloc = A1, Value = RawWideString>,
+    L2: WithCxxProxy<Alloc = A2, Value = RawWideString>,
+{
+    fn partial_cmp(&self, other: &CxxWideStringLayout<A2, L2>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Eq for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+}
+
+impl<A, L> Ord for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Ord::cmp(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<A, L> Hash for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_bytes(), state)
+    }
+}
+
 impl<A, L> Default for CxxWideStringLayout<A, L>
 where
     A: CxxProxy + Default,
@@ -204,6 +673,12 @@ where
     fn clone(&self) -> Self {
         Self::from_bytes_in(self, self.inner.alloc_as_ref().clone())
     }
+
+    /// Copies the characters of `source` into the existing buffer if it fits them,
+    /// like C++ copy assignment. The string keeps its own allocator.
+    fn clone_from(&mut self, source: &Self) {
+        self.replace(source);
+    }
 }
 
 impl<A, L> Extend<u16> for CxxWideStringLayout<A, L>
@@ -212,16 +687,47 @@ where
     L: WithCxxProxy<Alloc = A, Value = RawWideString>,
 {
     fn extend<I: IntoIterator<Item = u16>>(&mut self, iter: I) {
-        let iter = iter.into_iter();
-        self.reserve(iter.size_hint().0);
-        self.inner.with_proxy_mut(|val, alloc| unsafe {
-            for ch in iter {
-                CSTL_wstring_append_char(val, 1, ch, alloc);
+        let mut iter = iter.into_iter();
+        let spare = self.capacity() - self.len();
+
+        if iter.size_hint().0 > spare {
+            self.reserve(iter.size_hint().0 - spare);
+        }
+
+        // Appends the characters in chunks, rather than one CSTL call per character:
+        let mut chunk = [0; EXTEND_CHUNK_LEN];
+
+        loop {
+            let len = chunk
+                .iter_mut()
+                .zip(&mut iter)
+                .map(|(c, ch)| *c = ch)
+                .count();
+
+            if len == 0 {
+                break;
             }
-        });
+
+            self.push(&chunk[..len]);
+        }
     }
 }
 
+// The string owns its characters, which are only written through `&mut self`:
+unsafe impl<A, L> Send for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy + Send,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+}
+
+unsafe impl<A, L> Sync for CxxWideStringLayout<A, L>
+where
+    A: CxxProxy + Sync,
+    L: WithCxxProxy<Alloc = A, Value = RawWideString>,
+{
+}
+
 const fn new_val() -> RawWideString {
     RawWideString {
         bx: cstl_sys::CSTL_WideStringUnion { buf: [0; 8] },
@@ -230,10 +736,38 @@ const fn new_val() -> RawWideString {
     }
 }
 
+/// Destroys the string value at `ptr` like `std::wstring::~basic_string`, freeing
+/// its characters with `alloc`, e.g. to destroy a string constructed by C++
+/// before overwriting it.
+///
+/// An empty value is left at `ptr`. With iterator debugging, the proxy
+/// stored before the value isn't freed.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid string value, allocated with an allocator
+/// compatible with `alloc`.
+pub unsafe fn drop_in_place_with<A: CxxProxy>(ptr: *mut RawWideString, alloc: &A) {
+    let mut layout = Layout::new_in(alloc.rebind());
+    layout.val = unsafe { ptr.read() };
+
+    layout.with_proxy_mut(|val, alloc| unsafe {
+        CSTL_wstring_destroy(val, alloc);
+    });
+
+    unsafe { ptr.write(new_val()) }
+}
+
 impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     type Value = RawWideString;
     type Alloc = A;
 
+    const VALUE_OFFSET: usize = mem::offset_of!(Self, val);
+
+    const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+    const FREE_SIZE_UNIT: usize = mem::size_of::<u16>();
+
     fn value_as_ref(&self) -> &Self::Value {
         &self.val
     }
@@ -254,7 +788,85 @@ impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     }
 }
 
-#[cfg(feature = "msvc2012")]
+pub mod msvc2010 {
+    use cstl_sys::CSTL_WideStringVal as RawWideString;
+
+    use crate::alloc::{AllocMember, CxxProxy, WithCxxProxy};
+
+    use super::{new_val, CxxWideStringLayout, SysAlloc};
+
+    pub type CxxWideString<A = SysAlloc> = CxxWideStringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        val: RawWideString,
+        alloc: AllocMember<A>,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxWideString<SysAlloc> {
+        pub const fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxWideString<A> {
+        pub const fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            self.inner.alloc.get()
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxWideString<B> {
+            CxxWideString::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawWideString;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            self.alloc.get()
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self {
+                alloc: AllocMember::new(alloc),
+                val: new_val(),
+            }
+        }
+    }
+}
+
 pub mod msvc2012 {
     use cstl_sys::CSTL_WideStringVal as RawWideString;
 
@@ -297,12 +909,22 @@ pub mod msvc2012 {
         pub const fn allocator(&self) -> &A {
             &self.inner.alloc
         }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxWideString<B> {
+            CxxWideString::from_bytes_in(&self, alloc)
+        }
     }
 
     impl<A: CxxProxy> WithCxxProxy for Layout<A> {
         type Value = RawWideString;
         type Alloc = A;
 
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
         fn value_as_ref(&self) -> &Self::Value {
             &self.val
         }
@@ -323,3 +945,104 @@ pub mod msvc2012 {
         }
     }
 }
+
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug {
+    use cstl_sys::CSTL_WideStringVal as RawWideString;
+
+    use crate::{
+        alloc::{CxxProxy, WithCxxProxy},
+        iterator_debug::ProxyPtr,
+    };
+
+    use super::{new_val, CxxWideStringLayout, SysAlloc};
+
+    pub type CxxWideString<A = SysAlloc> = CxxWideStringLayout<A, Layout<A>>;
+
+    #[repr(C)]
+    pub struct Layout<A: CxxProxy> {
+        alloc: A,
+        proxy: ProxyPtr,
+        val: RawWideString,
+    }
+
+    impl<A: CxxProxy> Layout<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                proxy: ProxyPtr::new_in(&alloc),
+                alloc,
+                val: new_val(),
+            }
+        }
+    }
+
+    impl CxxWideString<SysAlloc> {
+        pub fn new() -> Self {
+            Self {
+                inner: Layout::new_in(SysAlloc),
+            }
+        }
+    }
+
+    impl<A: CxxProxy> CxxWideString<A> {
+        pub fn new_in(alloc: A) -> Self {
+            Self {
+                inner: Layout::new_in(alloc),
+            }
+        }
+
+        pub const fn allocator(&self) -> &A {
+            &self.inner.alloc
+        }
+
+        pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxWideString<B> {
+            CxxWideString::from_bytes_in(&self, alloc)
+        }
+    }
+
+    impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+        type Value = RawWideString;
+        type Alloc = A;
+
+        const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+        const PROXY_OFFSET: Option<usize> = Some(std::mem::offset_of!(Self, proxy));
+
+        const RECOVERS_FROM_ALLOC_ERROR: bool = false;
+
+        const FREE_SIZE_UNIT: usize = std::mem::size_of::<u16>();
+
+        fn value_as_ref(&self) -> &Self::Value {
+            self.proxy.attach();
+            &self.val
+        }
+
+        fn value_as_mut(&mut self) -> &mut Self::Value {
+            self.proxy.attach();
+            &mut self.val
+        }
+
+        fn alloc_as_ref(&self) -> &Self::Alloc {
+            &self.alloc
+        }
+
+        fn new_in(alloc: Self::Alloc) -> Self {
+            Self::new_in(alloc)
+        }
+
+        fn into_parts(self) -> (Self::Alloc, Self::Value) {
+            let this = std::mem::ManuallyDrop::new(self);
+
+            unsafe {
+                this.proxy.free_in(&this.alloc);
+                (std::ptr::read(&this.alloc), std::ptr::read(&this.val))
+            }
+        }
+    }
+
+    impl<A: CxxProxy> Drop for Layout<A> {
+        fn drop(&mut self) {
+            unsafe { self.proxy.free_in(&self.alloc) }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Verification of the container layouts against ones compiled by a C++ compiler.
+//!
+//! [`LAYOUT_SOURCE`] is a C++ translation unit exporting the size and alignment of
+//! `std::string` and `std::vector<int>`, and a sample object of each. A build script
+//! can write it out with [`write_layout_source`] and compile it with the toolset
+//! and flags of the program the containers are shared with, e.g. with the `cc` crate.
+//! [`layout_check!`](crate::layout_check) then compares the containers against it.
+
+use std::{
+    error::Error,
+    ffi::c_void,
+    fmt, fs, io, mem,
+    path::{Path, PathBuf},
+};
+
+use cstl_sys::{CSTL_StringVal as RawString, CSTL_VectorVal as RawVec};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    probe::{self, AbiDescriptor, Toolset},
+    string::narrow::CxxNarrowStringLayout,
+    vec::CxxVecLayout,
+};
+
+/// C++ translation unit exporting the layouts [`layout_check!`](crate::layout_check) checks.
+pub const LAYOUT_SOURCE: &str = r#"#include <cstddef>
+#include <string>
+#include <vector>
+
+struct cxx_stl_layout {
+    std::size_t size;
+    std::size_t align;
+    const void* sample;
+};
+
+static const std::string narrow_string_sample("probe");
+static const std::vector<int> vector_sample{1, 2, 3};
+
+extern "C" void cxx_stl_narrow_string_layout(cxx_stl_layout* out) {
+    *out = {sizeof(std::string), alignof(std::string), &narrow_string_sample};
+}
+
+extern "C" void cxx_stl_vector_layout(cxx_stl_layout* out) {
+    *out = {sizeof(std::vector<int>), alignof(std::vector<int>), &vector_sample};
+}
+"#;
+
+/// Writes [`LAYOUT_SOURCE`] to `cxx_stl_layout.cpp` in `dir`, returning the path of the file.
+pub fn write_layout_source(dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let path = dir.as_ref().join("cxx_stl_layout.cpp");
+    fs::write(&path, LAYOUT_SOURCE)?;
+    Ok(path)
+}
+
+/// Layout of a C++ container exported by [`LAYOUT_SOURCE`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct CxxLayout {
+    pub size: usize,
+    pub align: usize,
+    /// Object of the container holding the sample content.
+    pub sample: *const c_void,
+}
+
+/// Containers that can be checked against a [`CxxLayout`].
+pub trait LayoutSample: Sized {
+    /// Whether the layout starts with a `std::_Container_proxy` pointer.
+    const ITERATOR_DEBUG: bool;
+
+    /// Probes the layout of `sample`, a C++ object holding the sample content.
+    ///
+    /// # Safety
+    ///
+    /// See [`probe::probe_string`] and [`probe::probe_vector`].
+    unsafe fn probe_sample(sample: *const u8) -> Option<AbiDescriptor>;
+}
+
+impl<A, L> LayoutSample for CxxNarrowStringLayout<A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawString>,
+{
+    const ITERATOR_DEBUG: bool =
+        mem::size_of::<L>() > mem::size_of::<A>() + mem::size_of::<RawString>();
+
+    unsafe fn probe_sample(sample: *const u8) -> Option<AbiDescriptor> {
+        unsafe { probe::probe_string(sample, b"probe") }
+    }
+}
+
+impl<A, L> LayoutSample for CxxVecLayout<i32, A, L>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+{
+    const ITERATOR_DEBUG: bool =
+        mem::size_of::<L>() > mem::size_of::<A>() + mem::size_of::<RawVec>();
+
+    unsafe fn probe_sample(sample: *const u8) -> Option<AbiDescriptor> {
+        unsafe { probe::probe_vector(sample, &[1, 2, 3]) }
+    }
+}
+
+/// Difference between a container and its C++ counterpart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    /// The sizes differ.
+    Size { rust: usize, cxx: usize },
+    /// The alignments differ.
+    Align { rust: usize, cxx: usize },
+    /// The sample object isn't laid out like the container,
+    /// `found` is the layout it was probed to have, if any.
+    Layout { found: Option<AbiDescriptor> },
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Size { rust, cxx } => {
+                write!(
+                    f,
+                    "size of the container (is {rust}) differs from C++ (is {cxx})"
+                )
+            }
+            Self::Align { rust, cxx } => {
+                write!(
+                    f,
+                    "alignment of the container (is {rust}) differs from C++ (is {cxx})"
+                )
+            }
+            Self::Layout { found: Some(found) } => {
+                write!(f, "C++ object has a different layout, probed as {found:?}")
+            }
+            Self::Layout { found: None } => f.write_str("C++ object has an unknown layout"),
+        }
+    }
+}
+
+impl Error for LayoutMismatch {}
+
+/// Checks that `T` is laid out like the C++ container described by `cxx`.
+///
+/// # Safety
+///
+/// `cxx` must have been exported by [`LAYOUT_SOURCE`] for the counterpart of `T`.
+pub unsafe fn verify_layout<T: LayoutSample>(cxx: &CxxLayout) -> Result<(), LayoutMismatch> {
+    let (size, align) = (mem::size_of::<T>(), mem::align_of::<T>());
+
+    if size != cxx.size {
+        return Err(LayoutMismatch::Size {
+            rust: size,
+            cxx: cxx.size,
+        });
+    }
+
+    if align != cxx.align {
+        return Err(LayoutMismatch::Align {
+            rust: align,
+            cxx: cxx.align,
+        });
+    }
+
+    let found = unsafe { T::probe_sample(cxx.sample as _) };
+
+    // MSVC layouts are the only ones implemented by the containers:
+    match found {
+        Some(AbiDescriptor {
+            toolset: None | Some(Toolset::Msvc2010 | Toolset::Msvc2012 | Toolset::Msvc2015),
+            iterator_debug,
+        }) if iterator_debug == T::ITERATOR_DEBUG => Ok(()),
+        _ => Err(LayoutMismatch::Layout { found }),
+    }
+}
+
+/// Checks a string and a vector type against the layouts exported
+/// by [`LAYOUT_SOURCE`], which must be linked into the program,
+/// e.g. `layout_check!(CxxNarrowString, CxxVec<i32>)`.
+///
+/// The vector must have `i32` elements.
+///
+/// # Panics
+///
+/// Panics with the [`LayoutMismatch`] if a layout differs.
+#[macro_export]
+macro_rules! layout_check {
+    ($string:ty, $vector:ty) => {{
+        use $crate::testing::layout::{verify_layout, CxxLayout};
+
+        extern "C" {
+            fn cxx_stl_narrow_string_layout(out: *mut CxxLayout);
+            fn cxx_stl_vector_layout(out: *mut CxxLayout);
+        }
+
+        unsafe {
+            let mut layout = ::std::mem::MaybeUninit::<CxxLayout>::uninit();
+
+            cxx_stl_narrow_string_layout(layout.as_mut_ptr());
+            if let Err(e) = verify_layout::<$string>(layout.assume_init_ref()) {
+                panic!("`{}` doesn't match `std::string`: {e}", stringify!($string));
+            }
+
+            cxx_stl_vector_layout(layout.as_mut_ptr());
+            if let Err(e) = verify_layout::<$vector>(layout.assume_init_ref()) {
+                panic!(
+                    "`{}` doesn't match `std::vector<int>`: {e}",
+                    stringify!($vector)
+                );
+            }
+        }
+    }};
+}
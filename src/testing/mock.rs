@@ -0,0 +1,169 @@
+//! Instrumented allocator for tests.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Byte written over newly allocated memory, as by the MSVC debug heap.
+pub const CLEAN_BYTE: u8 = 0xCD;
+
+/// Byte written over freed memory, as by the MSVC debug heap.
+pub const DEAD_BYTE: u8 = 0xDD;
+
+/// Allocator that tracks every block it hands out.
+///
+/// Freed memory is poisoned with [`DEAD_BYTE`], and allocations can be made
+/// to fail on demand. Misuse is recorded as a [`Violation`] instead of panicking,
+/// as the allocator is called from CSTL where unwinding isn't possible.
+///
+/// Clones share their state, so a container's allocator can be inspected
+/// through the `MockAlloc` it was created from.
+#[derive(Clone, Debug, Default)]
+pub struct MockAlloc {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    live: HashMap<usize, Layout>,
+    allocations: usize,
+    deallocations: usize,
+    fail_in: Option<usize>,
+    violations: Vec<Violation>,
+}
+
+/// Incorrect use of a [`MockAlloc`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// A pointer that isn't alive was freed, e.g. due to a double free.
+    UnknownFree { ptr: usize, layout: Layout },
+    /// A block was freed with a different layout than it was allocated with.
+    LayoutMismatch {
+        ptr: usize,
+        allocated: Layout,
+        freed: Layout,
+    },
+    /// A block was never freed, reported by [`MockAlloc::assert_clean`].
+    Leak { ptr: usize, layout: Layout },
+}
+
+impl MockAlloc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the allocation after the next `n` successful ones fail.
+    pub fn fail_after(&self, n: usize) {
+        self.state().fail_in = Some(n);
+    }
+
+    /// Stops injecting allocation failures.
+    pub fn fail_never(&self) {
+        self.state().fail_in = None;
+    }
+
+    /// Number of successful allocations.
+    pub fn allocations(&self) -> usize {
+        self.state().allocations
+    }
+
+    /// Number of successful deallocations.
+    pub fn deallocations(&self) -> usize {
+        self.state().deallocations
+    }
+
+    /// Number of blocks currently alive.
+    pub fn live_blocks(&self) -> usize {
+        self.state().live.len()
+    }
+
+    /// Total size of the blocks currently alive.
+    pub fn live_bytes(&self) -> usize {
+        self.state().live.values().map(Layout::size).sum()
+    }
+
+    /// Violations recorded so far.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.state().violations.clone()
+    }
+
+    /// Asserts that no violations occurred and that every block was freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the list of violations, including leaks, if there are any.
+    pub fn assert_clean(&self) {
+        let state = self.state();
+
+        let mut violations = state.violations.clone();
+        violations.extend(
+            state
+                .live
+                .iter()
+                .map(|(&ptr, &layout)| Violation::Leak { ptr, layout }),
+        );
+
+        assert!(violations.is_empty(), "allocator misused: {violations:#x?}");
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+unsafe impl GlobalAlloc for MockAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut state = self.state();
+
+        match state.fail_in {
+            Some(0) => {
+                state.fail_in = None;
+                return std::ptr::null_mut();
+            }
+            Some(ref mut n) => *n -= 1,
+            None => {}
+        }
+
+        unsafe {
+            let ptr = System.alloc(layout);
+
+            if !ptr.is_null() {
+                ptr.write_bytes(CLEAN_BYTE, layout.size());
+
+                state.live.insert(ptr as usize, layout);
+                state.allocations += 1;
+            }
+
+            ptr
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut state = self.state();
+
+        let Some(allocated) = state.live.remove(&(ptr as usize)) else {
+            state.violations.push(Violation::UnknownFree {
+                ptr: ptr as usize,
+                layout,
+            });
+            return;
+        };
+
+        if allocated != layout {
+            state.violations.push(Violation::LayoutMismatch {
+                ptr: ptr as usize,
+                allocated,
+                freed: layout,
+            });
+        }
+
+        state.deallocations += 1;
+
+        unsafe {
+            ptr.write_bytes(DEAD_BYTE, allocated.size());
+            System.dealloc(ptr, allocated);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Utilities for testing allocators and code built on top of the containers.
+//!
+//! Under Miri, or with the `rust-cstl` feature, the containers call Rust ports
+//! of the CSTL functions instead of the C library, so tests using them
+//! can run under Miri and sanitizers.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    slice,
+};
+
+use crate::{alloc::CxxProxy, string::CxxUtf16String, vec::CxxVec};
+
+pub mod layout;
+pub mod mock;
+pub use mock::{MockAlloc, Violation};
+
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+/// Layouts the conformance harness allocates, as `(size, alignment)` pairs.
+const LAYOUTS: &[(usize, usize)] = &[
+    (1, 1),
+    (2, 2),
+    (7, 1),
+    (16, 8),
+    (24, 8),
+    (100, 4),
+    (64, 32),
+    (256, 64),
+    (4096, 16),
+    (4096, 4096),
+    (1 << 20, 16),
+];
+
+/// Checks that `alloc` behaves like a well-formed C++ compatible allocator.
+///
+/// Every block must be non-null, aligned, writable in its entirety,
+/// and must not overlap others that are alive at the same time.
+/// The allocator is then used to back vectors and strings.
+///
+/// # Panics
+///
+/// Panics if any of the checks fail.
+pub fn assert_allocator_conformance<A: CxxProxy + Clone>(alloc: &A) {
+    let proxy = alloc.proxy();
+
+    let blocks: Vec<_> = LAYOUTS
+        .iter()
+        .map(|&(size, align)| {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let ptr = unsafe { proxy.alloc(layout) };
+
+            assert!(!ptr.is_null(), "allocation of {layout:?} failed");
+            assert_eq!(
+                ptr.align_offset(align),
+                0,
+                "{ptr:p} isn't aligned to {align}"
+            );
+
+            unsafe { ptr.write_bytes(size as u8, size) };
+
+            (ptr, layout)
+        })
+        .collect();
+
+    let mut ranges: Vec<_> = blocks
+        .iter()
+        .map(|&(ptr, layout)| (ptr as usize, ptr as usize + layout.size()))
+        .collect();
+
+    ranges.sort_unstable();
+
+    for pair in ranges.windows(2) {
+        assert!(
+            pair[0].1 <= pair[1].0,
+            "blocks {:#x?} and {:#x?} overlap",
+            pair[0],
+            pair[1]
+        );
+    }
+
+    for &(ptr, layout) in blocks.iter().rev() {
+        let block = unsafe { slice::from_raw_parts(ptr, layout.size()) };
+
+        assert!(
+            block.iter().all(|&b| b == layout.size() as u8),
+            "block {ptr:p} was overwritten"
+        );
+
+        unsafe { proxy.dealloc(ptr, layout) };
+    }
+
+    let mut vec = CxxVec::<u64, A>::new_in(alloc.clone());
+    vec.extend(0..1000);
+    vec.shrink_to_fit();
+    assert!(vec.iter().copied().eq(0..1000), "vector contents corrupted");
+
+    let mut strings = CxxVec::<CxxUtf16String<A>, A>::new_in(alloc.clone());
+
+    for len in [0, 7, 8, 100] {
+        let units: Vec<u16> = (0..len).collect();
+        strings.push(CxxUtf16String::from_bytes_in(&units, alloc.clone()));
+    }
+
+    for (s, len) in strings.iter().zip([0, 7, 8, 100]) {
+        assert!(
+            s.as_bytes().iter().copied().eq(0..len),
+            "string contents corrupted"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! [proptest](https://docs.rs/proptest) strategies generating containers,
+//! weighted towards the edge cases of their representations.
+
+use std::alloc::System as SysAlloc;
+
+use proptest::{collection, prelude::*};
+
+use crate::{string::CxxUtf16String, vec::CxxVec};
+
+/// Capacity of a UTF-16 string in small mode, in characters.
+const UTF16_SMALL_CAPACITY: usize = 7;
+
+/// Generates UTF-16 strings that are empty, right around the small string capacity,
+/// of typical lengths or huge, with arbitrary characters including unpaired surrogates.
+pub fn utf16_string() -> impl Strategy<Value = CxxUtf16String> {
+    prop_oneof![
+        1 => Just(0..=0),
+        3 => Just(UTF16_SMALL_CAPACITY - 1..=UTF16_SMALL_CAPACITY + 1),
+        4 => Just(0..=64),
+        1 => Just(4096..=16384),
+    ]
+    .prop_flat_map(|len| collection::vec(any::<u16>(), len))
+    .prop_map(|chars| CxxUtf16String::from_bytes_in(chars, SysAlloc))
+}
+
+/// Generates vectors of elements generated by `element`,
+/// that are empty, of typical lengths or huge.
+pub fn vec<S>(element: S) -> impl Strategy<Value = CxxVec<S::Value>>
+where
+    S: Strategy + Clone,
+{
+    prop_oneof![
+        1 => Just(0..=0),
+        4 => Just(1..=64),
+        1 => Just(1024..=4096),
+    ]
+    .prop_flat_map(move |len| collection::vec(element.clone(), len))
+    .prop_map(|elements| {
+        let mut vec = CxxVec::new();
+        vec.extend(elements);
+        vec
+    })
+}
@@ -0,0 +1,174 @@
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    slice,
+};
+
+use cstl_sys::CSTL_Alloc;
+
+use super::RawVec;
+use crate::{
+    alloc::{growth_policy, GrowthPolicy},
+    cstl::{
+        CSTL_vector_copy_insert_range, CSTL_vector_end, CSTL_vector_move_push_back,
+        CSTL_vector_reserve,
+    },
+    semantics::{self, BaseType, CopySemantics, DefaultUninit, Semantics, TrivialRelocate},
+};
+
+/// Vector borrowed by [`CxxVecLayout::batch`](super::CxxVecLayout::batch), which calls
+/// into CSTL with a single allocator proxy for all of its operations.
+///
+/// Allocation failures are reported as configured by the
+/// [`AllocErrorPolicy`](crate::alloc::AllocErrorPolicy) once the batch ends,
+/// until then the operations that fail to allocate leave the vector unchanged.
+pub struct Batch<'a, T, S: Semantics<T>> {
+    val: &'a mut RawVec,
+    alloc: &'a mut CSTL_Alloc,
+    _marker: PhantomData<(&'a mut [T], S)>,
+}
+
+impl<'a, T, S: Semantics<T>> Batch<'a, T, S> {
+    pub(super) fn new(val: &'a mut RawVec, alloc: &'a mut CSTL_Alloc) -> Self {
+        Self {
+            val,
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { (self.val.last as *const T).offset_from(self.val.first as *const T) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.val.first == self.val.last
+    }
+
+    pub fn capacity(&self) -> usize {
+        unsafe { (self.val.end as *const T).offset_from(self.val.first as *const T) as usize }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if mem::size_of::<T>() != 0 && self.val.last != self.val.end {
+            unsafe {
+                let last = self.val.last as *mut T;
+                last.write(value);
+                self.val.last = last.add(1) as _;
+            }
+
+            return;
+        }
+
+        if !self.grow_for(1) {
+            return;
+        }
+
+        unsafe {
+            let mut value = DefaultUninit::new(value);
+
+            let pushed = CSTL_vector_move_push_back(
+                self.val,
+                <T as BaseType>::TYPE,
+                &<T as TrivialRelocate>::RELOCATE,
+                value.as_mut_ptr() as _,
+                self.alloc,
+            );
+
+            if !pushed {
+                let _ = value.assume_init();
+            }
+        }
+    }
+
+    /// Appends clones of all elements in `other`.
+    pub fn extend_from_slice(&mut self, other: &[T])
+    where
+        S: CopySemantics<T>,
+    {
+        if !self.grow_for(other.len()) {
+            return;
+        }
+
+        // See `CopySemantics::CLONE`:
+        if let Some(clone) = S::CLONE {
+            for value in other {
+                self.push(clone(value));
+            }
+
+            return;
+        }
+
+        unsafe {
+            CSTL_vector_copy_insert_range(
+                self.val,
+                &S::COPY,
+                CSTL_vector_end(self.val, <T as BaseType>::TYPE),
+                other.as_ptr() as _,
+                other.as_ptr_range().end as _,
+                self.alloc,
+            );
+        }
+
+        semantics::resume_pending_panic();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.capacity();
+
+        if isize::MAX as usize - capacity < additional {
+            panic!("requested capacity ({capacity} + {additional}) overflowed `isize::MAX`");
+        }
+
+        unsafe {
+            CSTL_vector_reserve(
+                self.val,
+                <T as BaseType>::TYPE,
+                &S::MOVE,
+                capacity + additional,
+                self.alloc,
+            );
+        }
+    }
+
+    /// Grows like `CxxVecLayout::grow_for`, returning whether the vector can fit the elements.
+    fn grow_for(&mut self, additional: usize) -> bool {
+        let len = self.len();
+        let capacity = self.capacity();
+
+        if capacity - len < additional {
+            let new_capacity = growth_policy().grown_capacity(
+                GrowthPolicy::Double,
+                capacity,
+                len.saturating_add(additional),
+            );
+
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.capacity() - self.len() >= additional
+    }
+}
+
+impl<T, S: Semantics<T>> Deref for Batch<'_, T, S> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        if self.val.first.is_null() {
+            return &[];
+        }
+
+        unsafe { slice::from_raw_parts(self.val.first as *const T, self.len()) }
+    }
+}
+
+impl<T, S: Semantics<T>> DerefMut for Batch<'_, T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.val.first.is_null() {
+            return &mut [];
+        }
+
+        unsafe { slice::from_raw_parts_mut(self.val.first as *mut T, self.len()) }
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    alloc::System as SysAlloc,
+    ffi::c_void,
+    fmt, mem,
+    ops::Range,
+    ptr,
+    slice::{self, ChunksExact, ChunksExactMut},
+};
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    cstl::{
+        CSTL_vector_begin, CSTL_vector_clear, CSTL_vector_copy_assign,
+        CSTL_vector_copy_insert_range, CSTL_vector_copy_push_back, CSTL_vector_destroy,
+        CSTL_vector_end, CSTL_vector_erase, CSTL_vector_iterator_add, CSTL_vector_reserve,
+        CSTL_vector_shrink_to_fit, CSTL_vector_truncate,
+    },
+    diagnostics::MemoryFootprint,
+    semantics::DynType,
+};
+
+use super::Layout;
+
+/// Vector of elements described by a [`DynType`] at runtime.
+///
+/// Elements are accessed as byte slices of [`DynType::size`] bytes.
+/// The vector is neither [`Send`] nor [`Sync`], as the types it describes at runtime
+/// may not be either.
+pub struct CxxVecDyn<A: CxxProxy = SysAlloc> {
+    ty: DynType,
+    inner: Layout<A>,
+}
+
+impl CxxVecDyn<SysAlloc> {
+    pub const fn new(ty: DynType) -> Self {
+        Self::new_in(ty, SysAlloc)
+    }
+}
+
+impl<A: CxxProxy> CxxVecDyn<A> {
+    pub const fn new_in(ty: DynType, alloc: A) -> Self {
+        Self {
+            ty,
+            inner: Layout::new_in(alloc),
+        }
+    }
+
+    pub const fn dyn_type(&self) -> &DynType {
+        &self.ty
+    }
+
+    pub const fn allocator(&self) -> &A {
+        &self.inner.alloc
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        if !self.inner.val.first.is_null() {
+            self.inner.val.first as _
+        } else {
+            ptr::dangling()
+        }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        if !self.inner.val.first.is_null() {
+            self.inner.val.first as _
+        } else {
+            ptr::dangling_mut()
+        }
+    }
+
+    /// Returns the bytes of all elements.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len() * self.ty.size()) }
+    }
+
+    /// Returns the bytes of all elements.
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        let len = self.len() * self.ty.size();
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), len) }
+    }
+
+    pub fn len(&self) -> usize {
+        byte_len(self.inner.val.first, self.inner.val.last) / self.ty.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.val.first == self.inner.val.last
+    }
+
+    pub fn capacity(&self) -> usize {
+        byte_len(self.inner.val.first, self.inner.val.end) / self.ty.size()
+    }
+
+    /// Returns the bytes of the element at `index`, or `None` if out of bounds.
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.iter().nth(index)
+    }
+
+    /// Returns the bytes of the element at `index`, or `None` if out of bounds.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        self.iter_mut().nth(index)
+    }
+
+    /// Returns an iterator over the bytes of each element.
+    pub fn iter(&self) -> ChunksExact<'_, u8> {
+        self.as_bytes().chunks_exact(self.ty.size())
+    }
+
+    /// Returns an iterator over the bytes of each element.
+    pub fn iter_mut(&mut self) -> ChunksExactMut<'_, u8> {
+        let size = self.ty.size();
+        self.as_mut_bytes().chunks_exact_mut(size)
+    }
+
+    /// Appends a copy of the element in `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `value` isn't equal to [`DynType::size`].
+    pub fn push(&mut self, value: &[u8]) {
+        let len = self.len();
+        self.insert(len, value);
+    }
+
+    /// Inserts a copy of the element in `value` at `index`.
+    ///
+    /// If the vector can't grow under
+    /// [`AllocErrorPolicy::ReturnError`](crate::alloc::AllocErrorPolicy::ReturnError),
+    /// nothing is inserted, which only shows in the unchanged [`CxxVecDyn::len`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`, or if the length of `value`
+    /// isn't equal to [`DynType::size`].
+    pub fn insert(&mut self, index: usize, value: &[u8]) {
+        let len = self.len();
+
+        if index > len {
+            panic!("insertion index (is {index}) should be <= len (is {len})");
+        }
+
+        self.assert_element(value);
+
+        if !self.grow_for(1) {
+            return;
+        }
+
+        let ty = &self.ty;
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_copy_push_back(
+                val,
+                ty.cstl_type(),
+                ty.copy_type(),
+                value.as_ptr() as _,
+                alloc,
+            );
+        });
+
+        // `CSTL_vector_copy_insert` doesn't shift the elements after `index` correctly,
+        // relocate them after appending instead:
+        let size = self.ty.size();
+        self.as_mut_bytes()[index * size..].rotate_right(size);
+    }
+
+    /// Appends copies of the elements in `other`.
+    ///
+    /// Like [`CxxVecDyn::insert`], appends none of them if the vector can't grow
+    /// under [`AllocErrorPolicy::ReturnError`](crate::alloc::AllocErrorPolicy::ReturnError).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `other` isn't a multiple of [`DynType::size`].
+    pub fn extend_from_bytes(&mut self, other: &[u8]) {
+        let size = self.ty.size();
+
+        if !other.len().is_multiple_of(size) {
+            panic!(
+                "length of the elements (is {}) should be a multiple of the type size (is {size})",
+                other.len()
+            );
+        }
+
+        if !self.grow_for(other.len() / size) {
+            return;
+        }
+
+        let ty = &self.ty;
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            let Range { start, end } = other.as_ptr_range();
+
+            CSTL_vector_copy_insert_range(
+                val,
+                ty.copy_type(),
+                CSTL_vector_end(val, ty.cstl_type()),
+                start as _,
+                end as _,
+                alloc,
+            );
+        });
+    }
+
+    /// Drops the element at `index` and shifts all elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) {
+        let len = self.len();
+
+        if index >= len {
+            panic!("removal index (is {index}) should be < len (is {len})");
+        }
+
+        let size = self.ty.size();
+
+        unsafe {
+            let removed = self.as_mut_ptr().add(index * size);
+            (self.ty.drop_type().drop.unwrap_unchecked())(removed as _, removed.add(size) as _);
+
+            let pos = CSTL_vector_iterator_add(
+                CSTL_vector_begin(&self.inner.val, self.ty.cstl_type()),
+                index as isize,
+            );
+
+            CSTL_vector_erase(&mut self.inner.val, self.ty.move_type(), pos);
+        }
+    }
+
+    /// Removes all elements, keeping the storage to reuse it for new elements.
+    pub fn clear(&mut self) {
+        unsafe {
+            CSTL_vector_clear(&mut self.inner.val, self.ty.drop_type());
+        }
+    }
+
+    /// Swaps two vectors along with their element types and allocators,
+    /// like `std::swap` with allocators that propagate on container swap.
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(self, other);
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            unsafe {
+                CSTL_vector_truncate(
+                    &mut self.inner.val,
+                    self.ty.cstl_type(),
+                    self.ty.drop_type(),
+                    new_len,
+                );
+            }
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.capacity();
+
+        if isize::MAX as usize - capacity < additional {
+            panic!("requested capacity ({capacity} + {additional}) overflowed `isize::MAX`");
+        }
+
+        let ty = &self.ty;
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_reserve(
+                val,
+                ty.cstl_type(),
+                ty.move_type(),
+                capacity + additional,
+                alloc,
+            );
+        });
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        let ty = &self.ty;
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_shrink_to_fit(val, ty.cstl_type(), ty.move_type(), alloc);
+        });
+    }
+
+    /// Geometrically grows the vector to fit `additional` more elements, as CSTL
+    /// operations that insert into a full vector allocate with an alignment
+    /// derived from the element size instead of [`DynType::cstl_type`].
+    ///
+    /// Returns whether the vector can fit the elements.
+    fn grow_for(&mut self, additional: usize) -> bool {
+        let len = self.len();
+        let capacity = self.capacity();
+
+        if capacity - len < additional {
+            let new_capacity = (len + additional)
+                .max(capacity * 2)
+                .min(isize::MAX as usize);
+
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.capacity() - self.len() >= additional
+    }
+
+    fn assert_element(&self, value: &[u8]) {
+        let size = self.ty.size();
+
+        if value.len() != size {
+            panic!(
+                "length of the element (is {}) should be equal to the type size (is {size})",
+                value.len()
+            );
+        }
+    }
+}
+
+impl<A: CxxProxy> fmt::Debug for CxxVecDyn<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<A: CxxProxy> MemoryFootprint for CxxVecDyn<A> {
+    fn allocated_bytes(&self) -> usize {
+        byte_len(self.inner.val.first, self.inner.val.end)
+    }
+}
+
+impl<A: CxxProxy> Drop for CxxVecDyn<A> {
+    fn drop(&mut self) {
+        let ty = &self.ty;
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_destroy(val, ty.cstl_type(), ty.drop_type(), alloc);
+        });
+    }
+}
+
+impl<A: CxxProxy + Clone> Clone for CxxVecDyn<A> {
+    fn clone(&self) -> Self {
+        let mut new = Self::new_in(self.ty, self.inner.alloc_as_ref().clone());
+        let ty = &self.ty;
+
+        self.inner.with_proxy(|old_val, old_alloc| {
+            new.inner.with_proxy_mut(|new_val, new_alloc| unsafe {
+                CSTL_vector_copy_assign(
+                    new_val,
+                    ty.cstl_type(),
+                    ty.copy_type(),
+                    old_val,
+                    new_alloc,
+                    old_alloc,
+                    false,
+                );
+            });
+        });
+
+        new
+    }
+}
+
+fn byte_len(first: *mut c_void, last: *mut c_void) -> usize {
+    last as usize - first as usize
+}
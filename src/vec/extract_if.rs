@@ -0,0 +1,119 @@
+use std::{fmt, iter::FusedIterator, marker::PhantomData, thread};
+
+use cstl_sys::CSTL_VectorVal as RawVec;
+
+use crate::semantics;
+
+/// Iterator removing the elements of a vector that match a filter,
+/// see [`CxxVecLayout::extract_if`](super::CxxVecLayout::extract_if).
+///
+/// Elements that don't match are kept in order. Dropping the iterator before it
+/// is exhausted keeps the unvisited elements, and leaking it leaks all elements.
+pub struct ExtractIf<'a, T, F: FnMut(&mut T) -> bool> {
+    val: &'a mut RawVec,
+    first: *mut T,
+    /// Index of the next element to visit.
+    index: usize,
+    /// Number of elements removed so far.
+    removed: usize,
+    old_len: usize,
+    /// Shifts kept elements with the move table of the vector's semantics.
+    shift: unsafe fn(*mut T, *mut T, usize),
+    filter: F,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T, F: FnMut(&mut T) -> bool> ExtractIf<'a, T, F> {
+    /// Takes the elements of the vector value `val` of `len` elements.
+    pub(super) fn new(
+        val: &'a mut RawVec,
+        len: usize,
+        shift: unsafe fn(*mut T, *mut T, usize),
+        filter: F,
+    ) -> Self {
+        let first = val.first as *mut T;
+
+        // The vector is empty until the iterator is dropped:
+        val.last = val.first;
+
+        Self {
+            val,
+            first,
+            index: 0,
+            removed: 0,
+            old_len: len,
+            shift,
+            filter,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Iterator for ExtractIf<'_, T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.old_len {
+            unsafe {
+                let current = self.first.add(self.index);
+                let matched = (self.filter)(&mut *current);
+                self.index += 1;
+
+                if matched {
+                    self.removed += 1;
+                    return Some(current.read());
+                }
+
+                if self.removed > 0 {
+                    (self.shift)(current, current.sub(self.removed), 1);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.index))
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> FusedIterator for ExtractIf<'_, T, F> {}
+
+// The iterator borrows the vector mutably, like `&mut [T]`:
+unsafe impl<T: Send, F: FnMut(&mut T) -> bool + Send> Send for ExtractIf<'_, T, F> {}
+
+unsafe impl<T: Sync, F: FnMut(&mut T) -> bool + Sync> Sync for ExtractIf<'_, T, F> {}
+
+impl<T, F: FnMut(&mut T) -> bool> Drop for ExtractIf<'_, T, F> {
+    fn drop(&mut self) {
+        unsafe {
+            // Unvisited elements, including one whose filter panicked, are kept:
+            if self.removed > 0 && self.index < self.old_len {
+                let unvisited = self.first.add(self.index);
+                (self.shift)(
+                    unvisited,
+                    unvisited.sub(self.removed),
+                    self.old_len - self.index,
+                );
+            }
+
+            self.val.last = self.first.add(self.old_len - self.removed) as _;
+        }
+
+        // Moved-from elements that panicked while dropping are only resumed
+        // when that doesn't abort a panicking filter:
+        if !thread::panicking() {
+            semantics::resume_pending_panic();
+        }
+    }
+}
+
+impl<T: fmt::Debug, F: FnMut(&mut T) -> bool> fmt::Debug for ExtractIf<'_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("index", &self.index)
+            .field("removed", &self.removed)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,28 +1,25 @@
 use std::{
-    alloc::System as SysAlloc,
-    iter::FusedIterator,
-    marker::PhantomData,
-    mem::ManuallyDrop,
-    ptr::{self, NonNull},
-    slice,
+    alloc::System as SysAlloc, iter::FusedIterator, marker::PhantomData, mem::ManuallyDrop,
+    ptr::NonNull, slice,
 };
 
 use cstl_sys::CSTL_VectorVal as RawVec;
 
 use crate::{
     alloc::CxxProxy,
-    vec::{CxxVec, Layout},
+    semantics::{CopySemantics, RustSemantics, Semantics},
+    vec::{CxxVecLayout, Layout},
 };
 
-pub struct IntoIter<T, A: CxxProxy = SysAlloc> {
+pub struct IntoIter<T, A: CxxProxy = SysAlloc, S: Semantics<T> = RustSemantics> {
     pub(super) alloc: ManuallyDrop<A>,
     pub(super) val: RawVec,
-    pub(super) _marker: PhantomData<T>,
+    pub(super) _marker: PhantomData<(T, S)>,
     pub(super) ptr: NonNull<T>,
     pub(super) end: NonNull<T>,
 }
 
-impl<T, A: CxxProxy> IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> IntoIter<T, A, S> {
     pub fn allocator(&self) -> &A {
         &self.alloc
     }
@@ -34,25 +31,21 @@ impl<T, A: CxxProxy> IntoIter<T, A> {
     pub fn as_mut_slice(&mut self) -> &mut [T] {
         unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len()) }
     }
-
-    fn as_raw_mut_slice(&mut self) -> *mut [T] {
-        ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len())
-    }
 }
 
-impl<T, A: CxxProxy> AsRef<[T]> for IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> AsRef<[T]> for IntoIter<T, A, S> {
     fn as_ref(&self) -> &[T] {
         self.as_slice()
     }
 }
 
-impl<T, A: CxxProxy> AsMut<[T]> for IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> AsMut<[T]> for IntoIter<T, A, S> {
     fn as_mut(&mut self) -> &mut [T] {
         self.as_mut_slice()
     }
 }
 
-impl<T, A: CxxProxy> Iterator for IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> Iterator for IntoIter<T, A, S> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -77,7 +70,7 @@ impl<T, A: CxxProxy> Iterator for IntoIter<T, A> {
     }
 }
 
-impl<T, A: CxxProxy> DoubleEndedIterator for IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> DoubleEndedIterator for IntoIter<T, A, S> {
     fn next_back(&mut self) -> Option<Self::Item> {
         if self.ptr != self.end {
             unsafe {
@@ -90,48 +83,45 @@ impl<T, A: CxxProxy> DoubleEndedIterator for IntoIter<T, A> {
     }
 }
 
-impl<T, A: CxxProxy> ExactSizeIterator for IntoIter<T, A> {}
+impl<T, A: CxxProxy, S: Semantics<T>> ExactSizeIterator for IntoIter<T, A, S> {}
 
-impl<T, A: CxxProxy> FusedIterator for IntoIter<T, A> {}
+impl<T, A: CxxProxy, S: Semantics<T>> FusedIterator for IntoIter<T, A, S> {}
 
-impl<T, A: CxxProxy + Default> Default for IntoIter<T, A> {
+impl<T, A: CxxProxy + Default, S: Semantics<T>> Default for IntoIter<T, A, S> {
     fn default() -> Self {
-        super::CxxVec::default().into_iter()
+        CxxVecLayout::<T, A, Layout<A>, S>::default().into_iter()
     }
 }
 
-impl<T: Clone, A: CxxProxy + Clone> Clone for IntoIter<T, A> {
+impl<T, A: CxxProxy + Clone, S: CopySemantics<T>> Clone for IntoIter<T, A, S> {
     fn clone(&self) -> Self {
-        super::CxxVec::from_slice_in(self.as_slice(), A::clone(&self.alloc)).into_iter()
+        CxxVecLayout::<T, A, Layout<A>, S>::from_slice_in(self.as_slice(), A::clone(&self.alloc))
+            .into_iter()
     }
 }
 
-impl<T, A: CxxProxy> Drop for IntoIter<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> Drop for IntoIter<T, A, S> {
     fn drop(&mut self) {
-        struct DropGuard<'a, T, A: CxxProxy>(&'a mut IntoIter<T, A>);
-
-        impl<T, A: CxxProxy> Drop for DropGuard<'_, T, A> {
-            fn drop(&mut self) {
-                unsafe {
-                    let _ = CxxVec::<T, _> {
-                        inner: Layout {
-                            alloc: ManuallyDrop::take(&mut self.0.alloc),
-                            val: self.0.val,
-                        },
-                        _marker: PhantomData,
-                    };
-                }
+        unsafe {
+            if let Some(drop) = S::DROP.drop {
+                drop(self.ptr.as_ptr() as _, self.end.as_ptr() as _);
             }
-        }
-
-        let guard = DropGuard(self);
 
-        unsafe {
-            ptr::drop_in_place(guard.0.as_raw_mut_slice());
+            // Only frees the buffer, the elements were either moved out or dropped:
+            let _ = CxxVecLayout::<T, A, _, S> {
+                inner: Layout {
+                    alloc: ManuallyDrop::take(&mut self.alloc),
+                    val: RawVec {
+                        last: self.val.first,
+                        ..self.val
+                    },
+                },
+                _marker: PhantomData,
+            };
         }
     }
 }
 
-unsafe impl<T: Send, A: CxxProxy + Send> Send for IntoIter<T, A> {}
+unsafe impl<T: Send, A: CxxProxy + Send, S: Semantics<T>> Send for IntoIter<T, A, S> {}
 
-unsafe impl<T: Sync, A: CxxProxy + Sync> Sync for IntoIter<T, A> {}
+unsafe impl<T: Sync, A: CxxProxy + Sync, S: Semantics<T>> Sync for IntoIter<T, A, S> {}
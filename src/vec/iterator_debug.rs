@@ -0,0 +1,123 @@
+use std::marker::PhantomData;
+
+use cstl_sys::CSTL_VectorVal as RawVec;
+
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    iterator_debug::{CheckedIter, ProxyPtr},
+    semantics::{Custom, Semantics, Trivial},
+};
+
+use super::{new_val, CxxVecLayout, SysAlloc};
+
+pub type CxxVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>>;
+
+/// Vector using the [`CustomSemantics`](crate::semantics::CustomSemantics) tables of its elements.
+pub type CxxCustomVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Custom>;
+
+/// Vector of trivially copyable elements, moved and copied in whole ranges
+/// with the [`TrivialType`](crate::semantics::TrivialType) tables.
+pub type CxxTrivialVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Trivial>;
+
+#[repr(C)]
+pub struct Layout<A: CxxProxy> {
+    alloc: A,
+    proxy: ProxyPtr,
+    val: RawVec,
+}
+
+impl<A: CxxProxy> Layout<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            proxy: ProxyPtr::new_in(&alloc),
+            alloc,
+            val: new_val(),
+        }
+    }
+}
+
+impl<T, S: Semantics<T>> CxxVecLayout<T, SysAlloc, Layout<SysAlloc>, S> {
+    pub fn new() -> Self {
+        Self {
+            inner: Layout::new_in(SysAlloc),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A: CxxProxy, S: Semantics<T>> CxxVecLayout<T, A, Layout<A>, S> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            inner: Layout::new_in(alloc),
+            _marker: PhantomData,
+        }
+    }
+
+    pub const fn allocator(&self) -> &A {
+        &self.inner.alloc
+    }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxVecLayout<T, B, Layout<B>, S> {
+        self.into_vec_in(alloc)
+    }
+
+    /// Returns a checked iterator to the element at `index`,
+    /// or to the end of the vector if `index == len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn checked_iter(&mut self, index: usize) -> CheckedIter<'_, T> {
+        let len = self.len();
+
+        if index > len {
+            panic!("iterator index (is {index}) should be <= len (is {len})");
+        }
+
+        // C++ iterators of an empty vector are null rather than dangling:
+        let first = self.inner.value_as_mut().first as *mut T;
+        CheckedIter::new(&self.inner.proxy, unsafe { first.add(index) })
+    }
+}
+
+impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+    type Value = RawVec;
+    type Alloc = A;
+
+    const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+    const PROXY_OFFSET: Option<usize> = Some(std::mem::offset_of!(Self, proxy));
+
+    fn value_as_ref(&self) -> &Self::Value {
+        self.proxy.attach();
+        &self.val
+    }
+
+    fn value_as_mut(&mut self) -> &mut Self::Value {
+        self.proxy.attach();
+        &mut self.val
+    }
+
+    fn alloc_as_ref(&self) -> &Self::Alloc {
+        &self.alloc
+    }
+
+    fn new_in(alloc: Self::Alloc) -> Self {
+        Self::new_in(alloc)
+    }
+
+    fn into_parts(self) -> (Self::Alloc, Self::Value) {
+        let this = std::mem::ManuallyDrop::new(self);
+
+        unsafe {
+            this.proxy.free_in(&this.alloc);
+            (std::ptr::read(&this.alloc), std::ptr::read(&this.val))
+        }
+    }
+}
+
+impl<A: CxxProxy> Drop for Layout<A> {
+    fn drop(&mut self) {
+        unsafe { self.proxy.free_in(&self.alloc) }
+    }
+}
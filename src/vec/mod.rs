@@ -3,34 +3,63 @@ use std::{
     borrow::{Borrow, BorrowMut},
     fmt,
     hash::{Hash, Hasher},
+    io,
     marker::PhantomData,
-    mem::{self, ManuallyDrop},
+    mem::{self, ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut, Index, IndexMut, Range},
+    pin::Pin,
     ptr::{self, NonNull},
     slice::{self, SliceIndex},
 };
 
+pub use batch::Batch;
 pub use cstl_sys::CSTL_VectorVal as RawVec;
-use cstl_sys::{
-    CSTL_vector_begin, CSTL_vector_clear, CSTL_vector_copy_assign, CSTL_vector_copy_assign_range,
-    CSTL_vector_destroy, CSTL_vector_end, CSTL_vector_erase, CSTL_vector_iterator_add,
-    CSTL_vector_iterator_eq, CSTL_vector_move_assign, CSTL_vector_move_assign_range,
-    CSTL_vector_move_insert, CSTL_vector_move_push_back, CSTL_vector_pop_back, CSTL_vector_reserve,
-    CSTL_vector_resize, CSTL_vector_shrink_to_fit, CSTL_vector_truncate,
-};
+pub use dynamic::CxxVecDyn;
+pub use extract_if::ExtractIf;
 use into_iter::IntoIter;
+pub use view::CxxSlice;
 
 use crate::{
-    alloc::{CxxProxy, WithCxxProxy},
-    semantics::{BaseType, CopyMoveType, CopyOnlyType, DefaultUninit, MoveType},
+    alloc::{
+        alloc_error_policy, growth_policy, AllocErrorPolicy, CxxProxy, GrowthPolicy, ProxyRef,
+        TryReserveError, WithCxxProxy,
+    },
+    cstl::{
+        CSTL_vector_copy_assign, CSTL_vector_copy_assign_range, CSTL_vector_copy_insert_range,
+        CSTL_vector_destroy, CSTL_vector_end, CSTL_vector_move_assign,
+        CSTL_vector_move_assign_range, CSTL_vector_move_push_back, CSTL_vector_reserve,
+        CSTL_vector_resize, CSTL_vector_shrink_to_fit, CSTL_vector_swap,
+    },
+    diagnostics::{proxy_bytes, vector_report, ContainerReport, Diagnose, MemoryFootprint},
+    ffi::CxxIter,
+    semantics::{
+        self, BaseType, CopySemantics, Custom, DefaultConstructType, DefaultUninit, RustSemantics,
+        Semantics, Trivial, TrivialRelocate, TrivialType,
+    },
 };
 
+pub mod batch;
+pub mod dynamic;
+pub mod extract_if;
 pub mod into_iter;
-#[cfg(feature = "msvc2012")]
+#[cfg(feature = "iterator-debug")]
+pub mod iterator_debug;
+pub mod msvc2010;
 pub mod msvc2012;
+pub mod view;
+
+/// A vector given back with the reason it couldn't be moved to another allocator.
+type Refused<T, A, L, S> = (CxxVecLayout<T, A, L, S>, TryReserveError);
 
 pub type CxxVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>>;
 
+/// Vector using the [`CustomSemantics`](crate::semantics::CustomSemantics) tables of its elements.
+pub type CxxCustomVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Custom>;
+
+/// Vector of trivially copyable elements, moved and copied in whole ranges
+/// with the [`TrivialType`] tables.
+pub type CxxTrivialVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Trivial>;
+
 #[repr(C)]
 pub struct Layout<A: CxxProxy> {
     alloc: A,
@@ -38,13 +67,14 @@ pub struct Layout<A: CxxProxy> {
 }
 
 #[repr(C)]
-pub struct CxxVecLayout<T, A, L>
+pub struct CxxVecLayout<T, A, L, S = RustSemantics>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     inner: L,
-    _marker: PhantomData<(T, A)>,
+    _marker: PhantomData<(T, A, S)>,
 }
 
 impl<A: CxxProxy> Layout<A> {
@@ -56,7 +86,7 @@ impl<A: CxxProxy> Layout<A> {
     }
 }
 
-impl<T> CxxVec<T, SysAlloc> {
+impl<T, S: Semantics<T>> CxxVecLayout<T, SysAlloc, Layout<SysAlloc>, S> {
     pub const fn new() -> Self {
         Self {
             inner: Layout::new_in(SysAlloc),
@@ -65,7 +95,7 @@ impl<T> CxxVec<T, SysAlloc> {
     }
 }
 
-impl<T, A: CxxProxy> CxxVec<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> CxxVecLayout<T, A, Layout<A>, S> {
     pub const fn new_in(alloc: A) -> Self {
         Self {
             inner: Layout::new_in(alloc),
@@ -76,14 +106,97 @@ impl<T, A: CxxProxy> CxxVec<T, A> {
     pub const fn allocator(&self) -> &A {
         &self.inner.alloc
     }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxVecLayout<T, B, Layout<B>, S> {
+        self.into_vec_in(alloc)
+    }
 }
 
-impl<T, A, L> CxxVecLayout<T, A, L>
+impl<T, A, L, S> CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
-    pub fn from_vec_in<L2, A2>(vec: CxxVecLayout<T, A2, L2>, alloc: A) -> Self
+    /// Size of the vector in bytes.
+    pub const SIZE: usize = mem::size_of::<Self>();
+
+    /// Alignment of the vector in bytes.
+    pub const ALIGN: usize = mem::align_of::<Self>();
+
+    /// Offset in bytes of the pointer to the first element, `_Myfirst`.
+    pub const OFFSET_FIRST: usize = L::VALUE_OFFSET + mem::offset_of!(RawVec, first);
+
+    /// Offset in bytes of the pointer past the last element, `_Mylast`.
+    pub const OFFSET_LAST: usize = L::VALUE_OFFSET + mem::offset_of!(RawVec, last);
+
+    /// Offset in bytes of the pointer past the end of the storage, `_Myend`.
+    pub const OFFSET_END: usize = L::VALUE_OFFSET + mem::offset_of!(RawVec, end);
+
+    /// Constructs an empty vector with `alloc` in the storage at `ptr`,
+    /// e.g. the `this` pointer passed to a hooked C++ constructor.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and aligned to [`Self::ALIGN`].
+    /// A value previously stored there is overwritten without being dropped.
+    /// The vector must not be moved afterwards, as the container proxy of iterator
+    /// debugging layouts points to it at `ptr` for C++ iterators.
+    pub unsafe fn init_in_place_in(ptr: *mut Self, alloc: A) {
+        unsafe {
+            ptr.write(Self::from_alloc(alloc));
+            // Attaches the container proxy at the final address:
+            (*ptr).inner.value_as_mut();
+        }
+    }
+
+    /// Constructs an empty vector with `alloc` in `slot`, pinned as C++ code
+    /// may refer to it there.
+    ///
+    /// `slot` doesn't drop the vector, it must be dropped in place,
+    /// e.g. with [`MaybeUninit::assume_init_drop`], to free its elements.
+    /// It must not be moved out of `slot`, see [`Self::init_in_place_in`].
+    pub fn emplace_in(slot: &mut MaybeUninit<Self>, alloc: A) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        let this = slot.write(Self::from_alloc(alloc));
+        // Attaches the container proxy at the final address:
+        this.inner.value_as_mut();
+
+        Pin::new(this)
+    }
+
+    /// Moves the elements of `vec` into a new vector that uses `alloc`.
+    ///
+    /// If the storage for them can't be allocated, this reports it as
+    /// [`AllocErrorPolicy::Abort`] or [`AllocErrorPolicy::Panic`] would,
+    /// even under [`AllocErrorPolicy::ReturnError`], since there is no way
+    /// to give `vec` back. Use [`Self::try_from_vec_in`] to recover it.
+    pub fn from_vec_in<L2, A2>(vec: CxxVecLayout<T, A2, L2, S>, alloc: A) -> Self
+    where
+        L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
+        A2: CxxProxy,
+    {
+        match Self::try_from_vec_in(vec, alloc) {
+            Ok(new) => new,
+            Err((_, TryReserveError::CapacityOverflow)) => panic!("capacity overflow"),
+            Err((_, TryReserveError::AllocError { layout })) => {
+                if alloc_error_policy() == AllocErrorPolicy::Panic {
+                    panic!("memory allocation of {} bytes failed", layout.size());
+                }
+
+                std::alloc::handle_alloc_error(layout)
+            }
+        }
+    }
+
+    /// Like [`Self::from_vec_in`], but gives `vec` back untouched along with
+    /// the error if the storage for its elements can't be allocated.
+    pub fn try_from_vec_in<L2, A2>(
+        vec: CxxVecLayout<T, A2, L2, S>,
+        alloc: A,
+    ) -> Result<Self, Refused<T, A2, L2, S>>
     where
         L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
         A2: CxxProxy,
@@ -91,28 +204,40 @@ where
         let mut new = Self::from_alloc(alloc);
         let mut drained = vec;
 
-        drained.inner.with_proxy_mut(|old_val, old_alloc| {
-            new.inner.with_proxy_mut(|new_val, new_alloc| unsafe {
+        let moved = drained.inner.with_proxy_mut(|old_val, old_alloc| {
+            new.inner.try_with_proxy_mut(|new_val, new_alloc| unsafe {
                 let moved = CSTL_vector_move_assign(
                     new_val,
                     <T as BaseType>::TYPE,
-                    &<DefaultUninit<T> as MoveType>::MOVE,
+                    &S::MOVE,
                     old_val,
                     old_alloc,
                     new_alloc,
                     false,
                 );
 
+                // With unequal allocators the elements are moved one by one, leaving
+                // moved-from elements behind, which are dropped with the table for them:
                 if moved {
-                    old_val.last = old_val.first;
+                    let old_last = mem::replace(&mut old_val.last, old_val.first);
+
+                    if let Some(drop) = S::MOVE.drop_type.drop {
+                        drop(old_val.first, old_last);
+                    }
                 }
+
+                moved
             })
         });
 
-        new
+        match moved {
+            Ok(true) => Ok(new),
+            Ok(false) => Err((drained, TryReserveError::CapacityOverflow)),
+            Err(err) => Err((drained, err)),
+        }
     }
 
-    pub fn into_vec_in<A2, L2>(self, alloc: A2) -> CxxVecLayout<T, A2, L2>
+    pub fn into_vec_in<A2, L2>(self, alloc: A2) -> CxxVecLayout<T, A2, L2, S>
     where
         L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
         A2: CxxProxy,
@@ -120,6 +245,19 @@ where
         CxxVecLayout::from_vec_in(self, alloc)
     }
 
+    /// Like [`Self::into_vec_in`], but gives `self` back untouched along with
+    /// the error if the storage for its elements can't be allocated.
+    pub fn try_into_vec_in<A2, L2>(
+        self,
+        alloc: A2,
+    ) -> Result<CxxVecLayout<T, A2, L2, S>, (Self, TryReserveError)>
+    where
+        L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
+        A2: CxxProxy,
+    {
+        CxxVecLayout::try_from_vec_in(self, alloc)
+    }
+
     pub fn from_rust_vec_in(vec: Vec<T>, alloc: A) -> Self {
         let mut new = Self::from_alloc(alloc);
         let mut drained = vec;
@@ -130,7 +268,7 @@ where
             let moved = CSTL_vector_move_assign_range(
                 val,
                 <T as BaseType>::TYPE,
-                &<DefaultUninit<T> as MoveType>::MOVE,
+                &<T as TrivialRelocate>::RELOCATE,
                 start as _,
                 end as _,
                 alloc,
@@ -164,19 +302,23 @@ where
 
     pub fn from_slice_in(slice: &[T], alloc: A) -> Self
     where
-        T: Clone,
+        S: CopySemantics<T>,
     {
         let mut new = Self::from_alloc(alloc);
 
         new.reserve(slice.len());
 
+        if new.extend_cloned(slice) {
+            return new;
+        }
+
         new.inner.with_proxy_mut(|val, alloc| unsafe {
             let Range { start, end } = slice.as_ptr_range();
 
             CSTL_vector_copy_assign_range(
                 val,
                 <T as BaseType>::TYPE,
-                &<T as CopyOnlyType>::COPY,
+                &S::COPY,
                 start as _,
                 end as _,
                 alloc,
@@ -186,6 +328,98 @@ where
         new
     }
 
+    pub fn try_from_slice_in(slice: &[T], alloc: A) -> Result<Self, TryReserveError>
+    where
+        S: CopySemantics<T>,
+    {
+        let mut new = Self::from_alloc(alloc);
+
+        new.try_reserve(slice.len())?;
+
+        if new.extend_cloned(slice) {
+            return Ok(new);
+        }
+
+        new.inner.with_proxy_mut(|val, alloc| unsafe {
+            let Range { start, end } = slice.as_ptr_range();
+
+            CSTL_vector_copy_assign_range(
+                val,
+                <T as BaseType>::TYPE,
+                &S::COPY,
+                start as _,
+                end as _,
+                alloc,
+            );
+        });
+
+        Ok(new)
+    }
+
+    /// Like [`CxxVecLayout::from_slice_in`], but copies all elements at once.
+    pub fn from_copied_slice_in(slice: &[T], alloc: A) -> Self
+    where
+        T: TrivialType,
+    {
+        let mut new = Self::from_alloc(alloc);
+
+        new.extend_from_copied_slice(slice);
+
+        new
+    }
+
+    /// Appends clones of all elements in `other`.
+    ///
+    /// If the vector can't grow under [`AllocErrorPolicy::ReturnError`], none of them
+    /// are appended, which [`Self::try_reserve`] beforehand can report instead.
+    pub fn extend_from_slice(&mut self, other: &[T])
+    where
+        S: CopySemantics<T>,
+    {
+        if self.extend_cloned(other) || !self.grow_for(other.len()) {
+            return;
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            let Range { start, end } = other.as_ptr_range();
+
+            CSTL_vector_copy_insert_range(
+                val,
+                &S::COPY,
+                CSTL_vector_end(val, <T as BaseType>::TYPE),
+                start as _,
+                end as _,
+                alloc,
+            );
+        });
+    }
+
+    /// Appends copies of all elements in `other`, copying them all at once.
+    ///
+    /// Like [`Self::extend_from_slice`], appends none of them if the vector
+    /// can't grow under [`AllocErrorPolicy::ReturnError`].
+    pub fn extend_from_copied_slice(&mut self, other: &[T])
+    where
+        T: TrivialType,
+    {
+        if !self.grow_for(other.len()) {
+            return;
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            let Range { start, end } = other.as_ptr_range();
+
+            CSTL_vector_copy_insert_range(
+                val,
+                &<T as TrivialType>::TRIVIAL_COPY,
+                CSTL_vector_end(val, <T as BaseType>::TYPE),
+                start as _,
+                end as _,
+                alloc,
+            );
+        });
+    }
+
     pub fn as_ptr(&self) -> *const T {
         if !self.first_ptr().is_null() {
             self.first_ptr()
@@ -215,21 +449,33 @@ where
     }
 
     pub fn is_empty(&self) -> bool {
-        self.first_ptr() == self.end_ptr()
+        self.first_ptr() == self.last_ptr()
     }
 
     pub fn capacity(&self) -> usize {
         unsafe { self.end_ptr().offset_from(self.first_ptr()) as usize }
     }
 
+    /// Appends `value`.
+    ///
+    /// If the vector can't grow under [`AllocErrorPolicy::ReturnError`], it's left
+    /// unchanged and `value` is dropped. [`Self::try_push`] reports the failure.
     pub fn push(&mut self, value: T) {
+        let Err(value) = self.push_within_capacity(value) else {
+            return;
+        };
+
+        if !self.grow_for(1) {
+            return;
+        }
+
         self.inner.with_proxy_mut(|val, alloc| unsafe {
             let mut value = DefaultUninit::new(value);
 
             let pushed = CSTL_vector_move_push_back(
                 val,
                 <T as BaseType>::TYPE,
-                &<DefaultUninit<T> as MoveType>::MOVE,
+                &<T as TrivialRelocate>::RELOCATE,
                 value.as_mut_ptr() as _,
                 alloc,
             );
@@ -240,24 +486,34 @@ where
         });
     }
 
+    /// Like [`CxxVecLayout::push`], but returns an error if the vector can't grow
+    /// regardless of the [`AllocErrorPolicy`].
+    pub fn try_push(&mut self, value: T) -> Result<(), TryReserveError> {
+        self.try_grow_for(1)?;
+        self.push(value);
+
+        Ok(())
+    }
+
     pub fn pop(&mut self) -> Option<T> {
-        if !self.is_empty() {
-            unsafe {
-                let last = self.last_ptr().offset(-1).read();
+        if self.is_empty() {
+            return None;
+        }
 
-                CSTL_vector_pop_back(
-                    self.inner.value_as_mut(),
-                    <T as BaseType>::TYPE,
-                    &<DefaultUninit<T> as BaseType>::DROP,
-                );
+        unsafe {
+            let val = self.inner.value_as_mut();
+            let last = (val.last as *mut T).sub(1);
+            val.last = last as _;
 
-                Some(last)
-            }
-        } else {
-            None
+            Some(last.read())
         }
     }
 
+    /// Inserts `value` at `index`, shifting the elements after it
+    /// with the move table of `S`, or a single copy if it relocates bitwise.
+    ///
+    /// If the vector can't grow under [`AllocErrorPolicy::ReturnError`], it's left
+    /// unchanged and `value` is dropped. [`Self::try_insert`] reports the failure.
     pub fn insert(&mut self, index: usize, value: T) {
         let len = self.len();
 
@@ -265,156 +521,785 @@ where
             panic!("insertion index (is {index}) should be <= len (is {len})");
         }
 
+        if !self.grow_for(1) {
+            return;
+        }
+
+        unsafe {
+            let pos = self.first_ptr_mut().add(index);
+            shift_elements::<T, S>(pos, pos.add(1), len - index);
+            pos.write(value);
+
+            let val = self.inner.value_as_mut();
+            val.last = (val.last as *mut T).add(1) as _;
+        }
+
+        semantics::resume_pending_panic();
+    }
+
+    /// Like [`CxxVecLayout::insert`], but returns an error if the vector can't grow
+    /// regardless of the [`AllocErrorPolicy`].
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), TryReserveError> {
+        self.try_grow_for(1)?;
+        self.insert(index, value);
+
+        Ok(())
+    }
+
+    /// Removes and returns the element at `index`, shifting the elements
+    /// after it like [`CxxVecLayout::insert`].
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+
+        if index >= len {
+            panic!("removal index (is {index}) should be < len (is {len})");
+        }
+
+        unsafe {
+            let pos = self.first_ptr_mut().add(index);
+            let removed = pos.read();
+            shift_elements::<T, S>(pos.add(1), pos, len - index - 1);
+
+            let val = self.inner.value_as_mut();
+            val.last = (val.last as *mut T).sub(1) as _;
+
+            semantics::resume_pending_panic();
+            removed
+        }
+    }
+
+    /// Removes all elements, keeping the storage to reuse it for new elements.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Moves the elements into a new vector with the same allocator, which is returned,
+    /// leaving this one empty with its storage kept, e.g. to reuse it every frame.
+    ///
+    /// If the new vector can't be allocated and the
+    /// [`AllocErrorPolicy`] returns errors,
+    /// the elements are kept and the returned vector is empty.
+    pub fn take_reuse(&mut self) -> Self
+    where
+        A: Clone,
+    {
+        let mut taken = Self::from_alloc(self.inner.alloc_as_ref().clone());
+        let val = self.inner.value_as_mut();
+
+        let moved = taken.inner.with_proxy_mut(|taken_val, alloc| unsafe {
+            CSTL_vector_move_assign_range(
+                taken_val,
+                <T as BaseType>::TYPE,
+                &S::MOVE,
+                val.first,
+                val.last,
+                alloc,
+            )
+        });
+
+        if moved {
+            let old_last = mem::replace(&mut val.last, val.first);
+
+            // Moved-from elements are dropped with the table for them, which forgets relocated ones:
+            if let Some(drop) = S::MOVE.drop_type.drop {
+                unsafe { drop(val.first, old_last) };
+            }
+
+            semantics::resume_pending_panic();
+        }
+
+        taken
+    }
+
+    /// Replaces the elements with copies of those in `src`, reusing the storage
+    /// if it fits them, like `std::vector::assign`.
+    pub fn assign<U: AsRef<[T]>>(&mut self, src: U)
+    where
+        S: CopySemantics<T>,
+    {
+        // CSTL drops the replaced elements with the table for moved-from elements,
+        // which doesn't drop relocated ones:
+        self.clear();
+
+        let slice = src.as_ref();
+
+        if self.extend_cloned(slice) {
+            return;
+        }
+
         self.inner.with_proxy_mut(|val, alloc| unsafe {
-            let pos = CSTL_vector_iterator_add(
-                CSTL_vector_begin(val, <T as BaseType>::TYPE),
-                index as isize,
+            let Range { start, end } = slice.as_ptr_range();
+
+            CSTL_vector_copy_assign_range(
+                val,
+                <T as BaseType>::TYPE,
+                &S::COPY,
+                start as _,
+                end as _,
+                alloc,
             );
+        });
+    }
 
-            let mut value = DefaultUninit::new(value);
+    /// Like [`CxxVecLayout::assign`], but returns an error if the vector can't grow
+    /// regardless of the [`AllocErrorPolicy`].
+    pub fn try_assign<U: AsRef<[T]>>(&mut self, src: U) -> Result<(), TryReserveError>
+    where
+        S: CopySemantics<T>,
+    {
+        let slice = src.as_ref();
+
+        // Grows before clearing, so the elements are kept if it fails:
+        if slice.len() > self.capacity() {
+            self.try_reserve(slice.len() - self.len())?;
+        }
+
+        self.assign(slice);
+
+        Ok(())
+    }
+
+    /// Replaces the elements with those of `iter`, reusing the storage if it fits them.
+    pub fn assign_from_iter<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.clear();
+        self.extend(iter);
+    }
+
+    /// Returns a C++ iterator to the first element, see [`CxxIter`].
+    pub fn begin(&self) -> CxxIter<'_, T> {
+        self.iter_at(0)
+    }
+
+    /// Returns a C++ iterator past the last element, see [`CxxIter`].
+    pub fn end(&self) -> CxxIter<'_, T> {
+        self.iter_at(self.len())
+    }
+
+    /// Returns a C++ iterator to the element at `index`,
+    /// or past the last element if `index == len`, see [`CxxIter`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn iter_at(&self, index: usize) -> CxxIter<'_, T> {
+        let len = self.len();
+
+        if index > len {
+            panic!("iterator index (is {index}) should be <= len (is {len})");
+        }
+
+        // C++ iterators of an empty vector are null rather than dangling:
+        CxxIter::new(unsafe { self.first_ptr().cast_mut().add(index) })
+    }
+
+    /// Returns an iterator removing and yielding the elements for which `filter`
+    /// returns `true`, in one pass that also lets `filter` modify the kept elements.
+    pub fn extract_if<F>(&mut self, filter: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let len = self.len();
+        ExtractIf::new(
+            self.inner.value_as_mut(),
+            len,
+            shift_elements::<T, S>,
+            filter,
+        )
+    }
+
+    /// Swaps the elements at indices `a` and `b`, like [`slice::swap`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` are out of bounds.
+    pub fn swap_elements(&mut self, a: usize, b: usize) {
+        self.as_mut_slice().swap(a, b);
+    }
+
+    /// Swaps two vectors along with their allocators,
+    /// like `std::swap` with allocators that propagate on container swap.
+    ///
+    /// Only the pointers are exchanged, the elements aren't moved.
+    /// With iterator debugging, C++ iterators follow the swapped contents.
+    ///
+    /// Shadows [`slice::swap`], see [`CxxVecLayout::swap_elements`].
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(self, other);
+    }
+
+    /// Swaps the contents of two vectors while keeping their allocators,
+    /// like `std::swap` with allocators that don't propagate on container swap.
+    ///
+    /// Use [`mem::swap`] to swap the allocators as well. With iterator debugging,
+    /// C++ iterators follow the swapped contents.
+    ///
+    /// # Safety
+    ///
+    /// Each allocator must be able to free memory allocated by the other,
+    /// as required of C++ allocators that compare equal.
+    pub unsafe fn swap_contents<A2, L2>(&mut self, other: &mut CxxVecLayout<T, A2, L2, S>)
+    where
+        A2: CxxProxy,
+        L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
+    {
+        #[cfg(feature = "iterator-debug")]
+        unsafe {
+            crate::iterator_debug::swap_proxies(&mut self.inner, &mut other.inner);
+        }
+
+        unsafe {
+            CSTL_vector_swap(self.inner.value_as_mut(), other.inner.value_as_mut());
+        }
+    }
+
+    /// Resizes the vector to `new_len`, filling new slots with clones of `value`.
+    ///
+    /// If the vector can't grow under [`AllocErrorPolicy::ReturnError`], its length
+    /// is left unchanged and `value` is dropped. [`Self::try_resize`] reports the failure.
+    pub fn resize(&mut self, new_len: usize, value: T)
+    where
+        S: CopySemantics<T>,
+    {
+        if new_len > isize::MAX as usize {
+            panic!("requested length ({new_len}) exceeded `isize::MAX`");
+        }
 
-            let inserted = CSTL_vector_move_insert(
+        let len = self.len();
+
+        if new_len > len {
+            if !self.grow_for(new_len - len) {
+                return;
+            }
+
+            if let Some(clone) = S::CLONE {
+                // See `CxxVecLayout::extend_cloned`:
+                for _ in len + 1..new_len {
+                    self.push(clone(&value));
+                }
+
+                self.push(value);
+                return;
+            }
+
+            self.inner.with_proxy_mut(|val, alloc| unsafe {
+                CSTL_vector_resize(
+                    val,
+                    <T as BaseType>::TYPE,
+                    &S::COPY,
+                    new_len,
+                    &value as *const T as _,
+                    alloc,
+                );
+            });
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Like [`CxxVecLayout::resize`], but returns an error if the vector can't grow
+    /// regardless of the [`AllocErrorPolicy`].
+    pub fn try_resize(&mut self, new_len: usize, value: T) -> Result<(), TryReserveError>
+    where
+        S: CopySemantics<T>,
+    {
+        self.try_grow_for(new_len.saturating_sub(self.len()))?;
+        self.resize(new_len, value);
+
+        Ok(())
+    }
+
+    /// Resizes the vector to `new_len`, filling new slots with [`Default::default`] values,
+    /// like `std::vector::resize(n)`.
+    ///
+    /// Leaves the length unchanged if the vector can't grow
+    /// under [`AllocErrorPolicy::ReturnError`].
+    pub fn resize_default(&mut self, new_len: usize)
+    where
+        T: Default,
+    {
+        if new_len > isize::MAX as usize {
+            panic!("requested length ({new_len}) exceeded `isize::MAX`");
+        }
+
+        let len = self.len();
+
+        if new_len > len {
+            if !self.grow_for(new_len - len) {
+                return;
+            }
+
+            self.fill_default(new_len);
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Appends a [`Default::default`] value constructed in place and returns it,
+    /// like `std::vector::emplace_back()`.
+    pub fn emplace_default(&mut self) -> &mut T
+    where
+        T: Default,
+    {
+        let len = self.len();
+
+        // Unlike `push`, there is no value to give back, so failing to grow can't be ignored:
+        if !self.grow_for(1) {
+            let layout = std::alloc::Layout::array::<T>(len + 1).expect("capacity overflow");
+            std::alloc::handle_alloc_error(layout);
+        }
+
+        self.fill_default(len + 1);
+
+        &mut self.as_mut_slice()[len]
+    }
+
+    pub fn resize_with<F>(&mut self, new_len: usize, mut f: F)
+    where
+        F: FnMut() -> T,
+    {
+        let len = self.len();
+
+        if new_len > len {
+            let additional = new_len - len;
+
+            self.reserve(additional);
+
+            for _ in 0..additional {
+                self.push(f());
+            }
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Resizes the vector to `new_len`, leaving the added elements uninitialized
+    /// without calling into CSTL for them.
+    ///
+    /// Leaves the length unchanged if the vector can't grow
+    /// under [`AllocErrorPolicy::ReturnError`], so it must be checked before
+    /// the added elements are initialized.
+    ///
+    /// # Safety
+    ///
+    /// The added elements must be initialized before they are read.
+    pub unsafe fn resize_uninit(&mut self, new_len: usize)
+    where
+        T: TrivialType,
+    {
+        if new_len > isize::MAX as usize {
+            panic!("requested length ({new_len}) exceeded `isize::MAX`");
+        }
+
+        let len = self.len();
+
+        if new_len > len {
+            if !self.grow_for(new_len - len) {
+                return;
+            }
+
+            let val = self.inner.value_as_mut();
+            val.last = unsafe { (val.first as *mut T).add(new_len) as _ };
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Resizes the vector to `new_len`, zeroing the bytes of the added elements
+    /// all at once.
+    ///
+    /// # Safety
+    ///
+    /// A value of `T` with all bytes zero must be valid.
+    pub unsafe fn resize_zeroed(&mut self, new_len: usize)
+    where
+        T: TrivialType,
+    {
+        let len = self.len();
+
+        unsafe {
+            self.resize_uninit(new_len);
+
+            if self.len() > len {
+                self.first_ptr_mut()
+                    .add(len)
+                    .write_bytes(0, self.len() - len);
+            }
+        }
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            unsafe {
+                let val = self.inner.value_as_mut();
+                let new_last = (val.first as *mut T).add(new_len);
+                let old_last = mem::replace(&mut val.last, new_last as _);
+
+                // The elements are removed before dropping them, in case a drop panics:
+                if let Some(drop) = S::DROP.drop {
+                    drop(new_last as _, old_last);
+                }
+            }
+
+            semantics::resume_pending_panic();
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.capacity();
+
+        if isize::MAX as usize - capacity < additional {
+            panic!("requested capacity ({capacity} + {additional}) overflowed `isize::MAX`");
+        }
+
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_reserve(
                 val,
-                &<DefaultUninit<T> as MoveType>::MOVE,
-                pos,
-                value.as_mut_ptr() as _,
+                <T as BaseType>::TYPE,
+                &S::MOVE,
+                capacity + additional,
                 alloc,
             );
+        });
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let new_capacity = self
+            .capacity()
+            .checked_add(additional)
+            .filter(|&capacity| capacity <= isize::MAX as usize)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+
+        let reserved = self.inner.try_with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_reserve(val, <T as BaseType>::TYPE, &S::MOVE, new_capacity, alloc)
+        })?;
+
+        if reserved {
+            Ok(())
+        } else {
+            Err(TryReserveError::CapacityOverflow)
+        }
+    }
 
-            if CSTL_vector_iterator_eq(inserted, CSTL_vector_end(val, <T as BaseType>::TYPE)) {
-                drop(value.assume_init());
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            CSTL_vector_shrink_to_fit(val, <T as BaseType>::TYPE, &S::MOVE, alloc);
+        });
+    }
+
+    /// Shrinks the capacity of the vector to the larger of `min_capacity` and its length.
+    ///
+    /// Does nothing if the capacity is already at most that.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let len = self.len();
+        let capacity = self.capacity();
+        let new_capacity = min_capacity.max(len);
+
+        if new_capacity >= capacity {
+            return;
+        }
+
+        if new_capacity == len {
+            return self.shrink_to_fit();
+        }
+
+        // CSTL only shrinks to the length, so the storage is reallocated here like it would:
+        self.inner.with_proxy_mut(|val, alloc| unsafe {
+            let aligned_alloc = alloc.aligned_alloc.expect("null allocation function");
+            let aligned_free = alloc.aligned_free.expect("null deallocation function");
+
+            let align = <T as BaseType>::ALIGN;
+            let new_first = aligned_alloc(alloc.opaque, new_capacity * mem::size_of::<T>(), align);
+
+            if new_first.is_null() {
+                return;
+            }
+
+            if let Some(move_) = S::MOVE.move_ {
+                move_(val.first, val.last, new_first);
+            }
+
+            if let Some(drop) = S::MOVE.drop_type.drop {
+                drop(val.first, val.last);
             }
+
+            aligned_free(
+                alloc.opaque,
+                val.first,
+                capacity * mem::size_of::<T>(),
+                align,
+            );
+
+            val.first = new_first;
+            val.last = new_first.cast::<T>().add(len) as _;
+            val.end = new_first.cast::<T>().add(new_capacity) as _;
         });
     }
 
-    pub fn remove(&mut self, index: usize) -> T {
+    /// Calls `f` with a [`Batch`] of operations on the vector,
+    /// which share one allocator proxy instead of making one each.
+    pub fn batch<R>(&mut self, f: impl FnOnce(&mut Batch<'_, T, S>) -> R) -> R {
+        self.inner
+            .with_proxy_mut(|val, alloc| f(&mut Batch::new(val, alloc)))
+    }
+
+    /// Returns a [`Debug`](fmt::Debug) view of the vector that prints at most
+    /// `max_elements` of its elements, followed by the number of the others.
+    pub fn debug_capped(&self, max_elements: usize) -> impl fmt::Debug + '_
+    where
+        T: fmt::Debug,
+    {
+        CappedDebug {
+            elements: self.as_slice(),
+            max_elements,
+        }
+    }
+
+    /// Returns a [`Debug`](fmt::Debug) view of the internals of the vector,
+    /// its length and capacity and the pointers to its storage.
+    pub fn raw_debug(&self) -> impl fmt::Debug {
+        RawDebug {
+            length: self.len(),
+            capacity: self.capacity(),
+            first: self.first_ptr().cast(),
+            last: self.last_ptr().cast(),
+            end: self.end_ptr().cast(),
+        }
+    }
+}
+
+impl<T, L, S> CxxVecLayout<T, SysAlloc, L, S>
+where
+    L: WithCxxProxy<Alloc = SysAlloc, Value = RawVec>,
+    S: Semantics<T>,
+{
+    /// Constructs an empty vector in the storage at `ptr`,
+    /// see [`CxxVecLayout::init_in_place_in`].
+    ///
+    /// # Safety
+    ///
+    /// See [`CxxVecLayout::init_in_place_in`].
+    pub unsafe fn init_at(ptr: *mut Self) {
+        unsafe { Self::init_in_place_in(ptr, SysAlloc) }
+    }
+
+    /// Constructs an empty vector in `slot`, see [`CxxVecLayout::emplace_in`].
+    pub fn emplace(slot: &mut MaybeUninit<Self>) -> Pin<&mut Self>
+    where
+        Self: Unpin,
+    {
+        Self::emplace_in(slot, SysAlloc)
+    }
+}
+
+impl<'a, T, A, L, S> CxxVecLayout<T, ProxyRef<'a, A>, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = ProxyRef<'a, A>, Value = RawVec>,
+    S: Semantics<T>,
+{
+    pub fn rebound_in(alloc: &'a A) -> Self {
+        Self::from_alloc(alloc.rebind())
+    }
+}
+
+impl<T, A, L, S> CxxVecLayout<NonNull<T>, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<NonNull<T>>,
+{
+    /// Returns an iterator over references to the objects the elements point to,
+    /// e.g. for a `std::vector<T*>` registry of engine objects.
+    ///
+    /// # Safety
+    ///
+    /// Every element must point to a valid `T` that isn't mutated
+    /// while the references are alive.
+    pub unsafe fn iter_deref(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Returns an iterator over mutable references to the objects the elements point to.
+    ///
+    /// # Safety
+    ///
+    /// Every element must point to a valid `T` that isn't accessed through other
+    /// pointers while the references are alive, and no two elements may be equal.
+    pub unsafe fn iter_deref_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.iter_mut().map(|ptr| unsafe { ptr.as_mut() })
+    }
+}
+
+impl<T, A, L, S> CxxVecLayout<Option<NonNull<T>>, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<Option<NonNull<T>>>,
+{
+    /// Returns an iterator over references to the objects the non-null elements
+    /// point to, skipping null pointers, e.g. the free slots of a registry.
+    ///
+    /// # Safety
+    ///
+    /// Every non-null element must point to a valid `T` that isn't mutated
+    /// while the references are alive.
+    pub unsafe fn iter_deref(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().flatten().map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Returns an iterator over mutable references to the objects the non-null
+    /// elements point to, skipping null pointers.
+    ///
+    /// # Safety
+    ///
+    /// Every non-null element must point to a valid `T` that isn't accessed through
+    /// other pointers while the references are alive, and no two non-null elements
+    /// may be equal.
+    pub unsafe fn iter_deref_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.iter_mut().flatten().map(|ptr| unsafe { ptr.as_mut() })
+    }
+}
+
+impl<T, A, L, S> CxxVecLayout<T, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn from_alloc(alloc: A) -> Self {
+        Self {
+            inner: L::new_in(alloc),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Grows the vector following the [`GrowthPolicy`] with [`Semantics::MOVE`] to fit
+    /// `additional` more elements,
+    /// as CSTL would otherwise relocate the elements with the table of the growing operation.
+    ///
+    /// CSTL operations that insert into a full vector also allocate with an alignment
+    /// derived from the element size instead of [`BaseType::TYPE`], so growing first
+    /// keeps the alignments of allocations and deallocations consistent.
+    ///
+    /// Returns whether the vector can fit the elements.
+    fn grow_for(&mut self, additional: usize) -> bool {
+        let len = self.len();
+        let capacity = self.capacity();
+
+        if capacity - len < additional {
+            let new_capacity = growth_policy().grown_capacity(
+                GrowthPolicy::Double,
+                capacity,
+                len.saturating_add(additional),
+            );
+
+            self.reserve(new_capacity - capacity);
+        }
+
+        self.capacity() - self.len() >= additional
+    }
+
+    /// Like [`CxxVecLayout::grow_for`], but reports allocation failures
+    /// regardless of the [`AllocErrorPolicy`].
+    fn try_grow_for(&mut self, additional: usize) -> Result<(), TryReserveError> {
         let len = self.len();
+        let capacity = self.capacity();
 
-        if index > len {
-            panic!("removal index (is {index}) should be <= len (is {len})");
+        if capacity - len >= additional {
+            return Ok(());
         }
 
-        unsafe {
-            let removed = self.first_ptr().offset(index as isize).read();
+        let required = len
+            .checked_add(additional)
+            .filter(|&required| required <= isize::MAX as usize)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        let new_capacity = growth_policy().grown_capacity(GrowthPolicy::Double, capacity, required);
 
-            let pos = CSTL_vector_iterator_add(
-                CSTL_vector_begin(self.inner.value_as_ref(), <T as BaseType>::TYPE),
-                index as isize,
-            );
+        self.try_reserve(new_capacity - capacity)
+    }
 
-            CSTL_vector_erase(
-                self.inner.value_as_mut(),
-                &<DefaultUninit<T> as MoveType>::MOVE,
-                pos,
-            );
+    /// Moves `value` into the spare capacity without calling into CSTL,
+    /// or returns it if the vector is full.
+    #[inline]
+    fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        let val = self.inner.value_as_mut();
 
-            removed
+        // Zero-sized elements can't be counted by their pointers:
+        if mem::size_of::<T>() == 0 || val.last == val.end {
+            return Err(value);
         }
-    }
 
-    pub fn clear(&mut self) {
         unsafe {
-            CSTL_vector_clear(self.inner.value_as_mut(), &<T as BaseType>::DROP);
-        }
-    }
-
-    pub fn resize(&mut self, new_len: usize, value: T)
-    where
-        T: Clone,
-    {
-        if new_len > isize::MAX as usize {
-            panic!("requested length ({new_len}) exceeded `isize::MAX`");
+            let last = val.last as *mut T;
+            last.write(value);
+            val.last = last.add(1) as _;
         }
 
-        if new_len > self.len() {
-            self.inner.with_proxy_mut(|val, alloc| unsafe {
-                CSTL_vector_resize(
-                    val,
-                    <T as BaseType>::TYPE,
-                    &<DefaultUninit<T> as CopyMoveType>::COPY,
-                    new_len,
-                    &value as *const T as _,
-                    alloc,
-                );
-            });
-        } else {
-            self.truncate(new_len);
-        }
+        Ok(())
     }
 
-    pub fn resize_with<F>(&mut self, new_len: usize, mut f: F)
+    /// Appends clones of the elements in `other` made with [`CopySemantics::CLONE`],
+    /// returning whether `S` has it.
+    ///
+    /// The length is updated after every clone, so a panicking clone leaves
+    /// the vector holding the elements cloned so far.
+    fn extend_cloned(&mut self, other: &[T]) -> bool
     where
-        F: FnMut() -> T,
+        S: CopySemantics<T>,
     {
-        let len = self.len();
-
-        if new_len > len {
-            let additional = new_len - len;
-
-            self.reserve(additional);
+        let Some(clone) = S::CLONE else {
+            return false;
+        };
 
-            for _ in 0..additional {
-                self.push(f());
+        if self.grow_for(other.len()) {
+            for value in other {
+                self.push(clone(value));
             }
-        } else {
-            self.truncate(new_len);
         }
+
+        true
     }
 
-    pub fn truncate(&mut self, new_len: usize) {
-        if new_len < self.len() {
-            unsafe {
-                CSTL_vector_truncate(
-                    self.inner.value_as_mut(),
-                    <T as BaseType>::TYPE,
-                    &<T as BaseType>::DROP,
-                    new_len,
-                );
-            }
+    /// Moves elements from `iter` into the spare capacity until it is full,
+    /// without calling into CSTL for each of them.
+    fn fill_spare(&mut self, iter: &mut impl Iterator<Item = T>) {
+        // Zero-sized elements can't be counted by their pointers:
+        if mem::size_of::<T>() == 0 {
+            return;
         }
-    }
 
-    pub fn reserve(&mut self, additional: usize) {
-        let capacity = self.capacity();
+        let val = self.inner.value_as_mut();
 
-        if isize::MAX as usize - capacity < additional {
-            panic!("requested capacity ({capacity} + {additional}) overflowed `isize::MAX`");
-        }
+        while val.last != val.end {
+            let Some(value) = iter.next() else {
+                break;
+            };
 
-        self.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_vector_reserve(
-                val,
-                <T as BaseType>::TYPE,
-                &<DefaultUninit<T> as MoveType>::MOVE,
-                capacity + additional,
-                alloc,
-            );
-        });
+            unsafe {
+                let last = val.last as *mut T;
+                last.write(value);
+                val.last = last.add(1) as _;
+            }
+        }
     }
 
-    pub fn shrink_to_fit(&mut self) {
+    /// Grows the vector to `new_len` with default values, which must fit
+    /// in its capacity, as [`DefaultConstructType::DEFAULT`] can't reallocate it.
+    fn fill_default(&mut self, new_len: usize)
+    where
+        T: Default,
+    {
         self.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_vector_shrink_to_fit(
+            CSTL_vector_resize(
                 val,
                 <T as BaseType>::TYPE,
-                &<DefaultUninit<T> as MoveType>::MOVE,
+                &<T as DefaultConstructType>::DEFAULT,
+                new_len,
+                ptr::null(),
                 alloc,
             );
         });
     }
-}
-
-impl<T, A, L> CxxVecLayout<T, A, L>
-where
-    A: CxxProxy,
-    L: WithCxxProxy<Alloc = A, Value = RawVec>,
-{
-    fn from_alloc(alloc: A) -> Self {
-        Self {
-            inner: L::new_in(alloc),
-            _marker: PhantomData,
-        }
-    }
 
     fn first_ptr(&self) -> *const T {
         self.inner.value_as_ref().first as _
@@ -433,91 +1318,167 @@ where
     }
 }
 
-impl<T, A, L> AsRef<CxxVecLayout<T, A, L>> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> AsRef<CxxVecLayout<T, A, L, S>> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn as_ref(&self) -> &Self {
         self
     }
 }
 
-impl<T, A, L> AsRef<[T]> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> AsRef<[T]> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn as_ref(&self) -> &[T] {
         self
     }
 }
 
-impl<T, A, L> AsMut<CxxVecLayout<T, A, L>> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> AsMut<CxxVecLayout<T, A, L, S>> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn as_mut(&mut self) -> &mut Self {
         self
     }
 }
 
-impl<T, A, L> AsMut<[T]> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> AsMut<[T]> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn as_mut(&mut self) -> &mut [T] {
         self
     }
 }
 
-impl<T, A, L> Borrow<[T]> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Borrow<[T]> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn borrow(&self) -> &[T] {
         &self[..]
     }
 }
 
-impl<T, A, L> BorrowMut<[T]> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> BorrowMut<[T]> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn borrow_mut(&mut self) -> &mut [T] {
         &mut self[..]
     }
 }
 
-impl<T, A, L> fmt::Debug for CxxVecLayout<T, A, L>
+impl<T, A, L, S> fmt::Debug for CxxVecLayout<T, A, L, S>
 where
     T: fmt::Debug,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(&**self, f)
     }
 }
 
-impl<T, A, L> Default for CxxVecLayout<T, A, L>
+/// Elements of a vector, see [`CxxVecLayout::debug_capped`].
+struct CappedDebug<'a, T> {
+    elements: &'a [T],
+    max_elements: usize,
+}
+
+impl<T: fmt::Debug> fmt::Debug for CappedDebug<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = self.elements.len().min(self.max_elements);
+        let mut list = f.debug_list();
+        list.entries(&self.elements[..shown]);
+
+        if shown < self.elements.len() {
+            list.entry(&format_args!("..{} more", self.elements.len() - shown));
+        }
+
+        list.finish()
+    }
+}
+
+/// Internals of a vector, see [`CxxVecLayout::raw_debug`].
+struct RawDebug {
+    length: usize,
+    capacity: usize,
+    first: *const (),
+    last: *const (),
+    end: *const (),
+}
+
+impl fmt::Debug for RawDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CxxVec")
+            .field("length", &self.length)
+            .field("capacity", &self.capacity)
+            .field("first", &self.first)
+            .field("last", &self.last)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+impl<T, A, L, S> Diagnose for CxxVecLayout<T, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn report(&self) -> ContainerReport {
+        let val = self.inner.value_as_ref();
+
+        vector_report::<T>("CxxVec", val.first, val.last, val.end)
+    }
+}
+
+impl<T, A, L, S> MemoryFootprint for CxxVecLayout<T, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn allocated_bytes(&self) -> usize {
+        let val = self.inner.value_as_ref();
+
+        (val.end as usize - val.first as usize) + proxy_bytes::<L>()
+    }
+}
+
+impl<T, A, L, S> Default for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy + Default,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn default() -> Self {
         Self::from_alloc(A::default())
     }
 }
 
-impl<T, A, L> Deref for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Deref for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     type Target = [T];
 
@@ -526,43 +1487,49 @@ where
     }
 }
 
-impl<T, A, L> DerefMut for CxxVecLayout<T, A, L>
+impl<T, A, L, S> DerefMut for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.as_mut_slice()
     }
 }
 
-impl<T, A, L> Drop for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Drop for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn drop(&mut self) {
         self.inner.with_proxy_mut(|val, alloc| unsafe {
-            CSTL_vector_destroy(val, <T as BaseType>::TYPE, &<T as BaseType>::DROP, alloc);
+            CSTL_vector_destroy(val, <T as BaseType>::TYPE, &S::DROP, alloc);
         });
     }
 }
 
-impl<T, A, L> Clone for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Clone for CxxVecLayout<T, A, L, S>
 where
-    T: Clone + Sized,
     A: CxxProxy + Clone,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: CopySemantics<T>,
 {
     fn clone(&self) -> Self {
         let mut new = Self::from_alloc(self.inner.alloc_as_ref().clone());
 
+        if new.extend_cloned(self) {
+            return new;
+        }
+
         self.inner.with_proxy(|old_val, old_alloc| {
             new.inner.with_proxy_mut(|new_val, new_alloc| unsafe {
                 CSTL_vector_copy_assign(
                     new_val,
                     <T as BaseType>::TYPE,
-                    &<T as CopyOnlyType>::COPY,
+                    &S::COPY,
                     old_val,
                     new_alloc,
                     old_alloc,
@@ -573,13 +1540,39 @@ where
 
         new
     }
+
+    /// Copies the elements of `source` into the existing storage if it fits them,
+    /// like C++ copy assignment. The vector keeps its own allocator.
+    fn clone_from(&mut self, source: &Self) {
+        // See `CxxVecLayout::assign`:
+        self.clear();
+
+        if self.extend_cloned(source) {
+            return;
+        }
+
+        source.inner.with_proxy(|source_val, source_alloc| {
+            self.inner.with_proxy_mut(|val, alloc| unsafe {
+                CSTL_vector_copy_assign(
+                    val,
+                    <T as BaseType>::TYPE,
+                    &S::COPY,
+                    source_val,
+                    alloc,
+                    source_alloc,
+                    false,
+                );
+            });
+        });
+    }
 }
 
-impl<T, I, A, L> Index<I> for CxxVecLayout<T, A, L>
+impl<T, I, A, L, S> Index<I> for CxxVecLayout<T, A, L, S>
 where
     I: SliceIndex<[T]>,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     type Output = I::Output;
 
@@ -588,118 +1581,169 @@ where
     }
 }
 
-impl<T, I, A, L> IndexMut<I> for CxxVecLayout<T, A, L>
+impl<T, I, A, L, S> IndexMut<I> for CxxVecLayout<T, A, L, S>
 where
     I: SliceIndex<[T]>,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn index_mut(&mut self, index: I) -> &mut Self::Output {
         IndexMut::index_mut(&mut **self, index)
     }
 }
 
-impl<T, A, L> Extend<T> for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Extend<T> for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
+    /// Drops the elements that don't fit if the vector can't grow
+    /// under [`AllocErrorPolicy::ReturnError`].
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        let iter = iter.into_iter();
-        self.reserve(iter.size_hint().0);
+        let mut iter = iter.into_iter();
+        self.grow_for(iter.size_hint().0);
+        self.fill_spare(&mut iter);
         iter.for_each(|e| self.push(e));
     }
 }
 
-impl<'a, T, A, L> Extend<&'a T> for CxxVecLayout<T, A, L>
+impl<'a, T, A, L, S> Extend<&'a T> for CxxVecLayout<T, A, L, S>
 where
     T: Copy + 'a,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
         self.extend(iter.into_iter().copied())
     }
 }
 
-impl<T, A1, A2, L1, L2> PartialEq<CxxVecLayout<T, A2, L2>> for CxxVecLayout<T, A1, L1>
+/// Appends the written bytes, growing the vector like [`CxxVecLayout::push`].
+///
+/// Allocation failures are reported as [`io::ErrorKind::OutOfMemory`] errors
+/// regardless of the [`AllocErrorPolicy`].
+impl<A, L, S> io::Write for CxxVecLayout<u8, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<u8>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.try_grow_for(buf.len())
+            .map_err(|err| io::Error::new(io::ErrorKind::OutOfMemory, err))?;
+        self.extend_from_copied_slice(buf);
+
+        Ok(())
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let len = bufs
+            .iter()
+            .map(|buf| buf.len())
+            .fold(0, usize::saturating_add);
+
+        self.try_grow_for(len)
+            .map_err(|err| io::Error::new(io::ErrorKind::OutOfMemory, err))?;
+        bufs.iter()
+            .for_each(|buf| self.extend_from_copied_slice(buf));
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T, A1, A2, L1, L2, S> PartialEq<CxxVecLayout<T, A2, L2, S>> for CxxVecLayout<T, A1, L1, S>
 where
     T: PartialEq,
     A1: CxxProxy,
     A2: CxxProxy,
     L1: WithCxxProxy<Alloc = A1, Value = RawVec>,
     L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
+    S: Semantics<T>,
 {
-    fn eq(&self, other: &CxxVecLayout<T, A2, L2>) -> bool {
+    fn eq(&self, other: &CxxVecLayout<T, A2, L2, S>) -> bool {
         PartialEq::eq(&**self, &**other)
     }
 }
 
-impl<T, A1, A2, L1, L2> PartialOrd<CxxVecLayout<T, A2, L2>> for CxxVecLayout<T, A1, L1>
+impl<T, A1, A2, L1, L2, S> PartialOrd<CxxVecLayout<T, A2, L2, S>> for CxxVecLayout<T, A1, L1, S>
 where
     T: PartialOrd,
     A1: CxxProxy,
     A2: CxxProxy,
     L1: WithCxxProxy<Alloc = A1, Value = RawVec>,
     L2: WithCxxProxy<Alloc = A2, Value = RawVec>,
+    S: Semantics<T>,
 {
-    fn partial_cmp(&self, other: &CxxVecLayout<T, A2, L2>) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &CxxVecLayout<T, A2, L2, S>) -> Option<std::cmp::Ordering> {
         PartialOrd::partial_cmp(&**self, &**other)
     }
 }
 
-impl<T, A, L> Eq for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Eq for CxxVecLayout<T, A, L, S>
 where
     T: Eq,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
 }
 
-impl<T, A, L> Ord for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Ord for CxxVecLayout<T, A, L, S>
 where
     T: Ord,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         Ord::cmp(&**self, &**other)
     }
 }
 
-impl<T, A, L> Hash for CxxVecLayout<T, A, L>
+impl<T, A, L, S> Hash for CxxVecLayout<T, A, L, S>
 where
     T: Hash,
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     fn hash<H: Hasher>(&self, state: &mut H) {
         Hash::hash(&**self, state)
     }
 }
 
-impl<T, A, L> IntoIterator for CxxVecLayout<T, A, L>
+impl<T, A, L, S> IntoIterator for CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     type Item = T;
-    type IntoIter = IntoIter<T, A>;
+    type IntoIter = IntoIter<T, A, S>;
 
     fn into_iter(self) -> Self::IntoIter {
         unsafe {
             // Dropped by IntoIter:
             let mut vec = ManuallyDrop::new(self);
-            let alloc = ManuallyDrop::new(ptr::read(vec.inner.alloc_as_ref()));
 
             let ptr = NonNull::new_unchecked(vec.as_mut_ptr());
             let end = ptr.add(vec.len());
 
-            let val = RawVec {
-                first: vec.first_ptr() as _,
-                last: vec.last_ptr() as _,
-                end: vec.end_ptr() as _,
-            };
+            let (alloc, val) = ptr::read(&vec.inner).into_parts();
+            let alloc = ManuallyDrop::new(alloc);
 
             IntoIter {
                 alloc,
@@ -712,10 +1756,11 @@ where
     }
 }
 
-impl<'a, T, A, L> IntoIterator for &'a CxxVecLayout<T, A, L>
+impl<'a, T, A, L, S> IntoIterator for &'a CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
@@ -725,10 +1770,11 @@ where
     }
 }
 
-impl<'a, T, A, L> IntoIterator for &'a mut CxxVecLayout<T, A, L>
+impl<'a, T, A, L, S> IntoIterator for &'a mut CxxVecLayout<T, A, L, S>
 where
     A: CxxProxy,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
     type Item = &'a mut T;
     type IntoIter = slice::IterMut<'a, T>;
@@ -738,22 +1784,59 @@ where
     }
 }
 
-unsafe impl<T, A, L> Send for CxxVecLayout<T, A, L>
+// The vector owns its elements like a `Vec<T, A>`:
+unsafe impl<T, A, L, S> Send for CxxVecLayout<T, A, L, S>
 where
     T: Send,
     A: CxxProxy + Send,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
 }
 
-unsafe impl<T, A, L> Sync for CxxVecLayout<T, A, L>
+unsafe impl<T, A, L, S> Sync for CxxVecLayout<T, A, L, S>
 where
     T: Sync,
     A: CxxProxy + Sync,
     L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
 {
 }
 
+// `std::vector` is three pointers on both 32 and 64-bit targets,
+// preceded by the `std::_Container_proxy` pointer with iterator debugging,
+// and followed by a padded allocator member in Visual Studio 2010:
+const _: () = {
+    assert!(mem::size_of::<CxxVec<u8>>() == 3 * mem::size_of::<usize>());
+    assert!(mem::align_of::<CxxVec<u8>>() == mem::align_of::<usize>());
+    assert!(mem::size_of::<msvc2010::CxxVec<u8>>() == 4 * mem::size_of::<usize>());
+
+    #[cfg(feature = "iterator-debug")]
+    assert!(mem::size_of::<iterator_debug::CxxVec<u8>>() == 4 * mem::size_of::<usize>());
+};
+
+/// Destroys the vector value at `ptr` like `std::vector<T>::~vector`, dropping its
+/// elements with the [`Semantics::DROP`] table of `S` and freeing them with `alloc`,
+/// e.g. to destroy a vector constructed by C++ before overwriting it.
+///
+/// An empty value is left at `ptr`. With iterator debugging, the proxy
+/// stored before the value isn't freed.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid vector value of `T` elements,
+/// allocated with an allocator compatible with `alloc`.
+pub unsafe fn drop_in_place_with<T, S: Semantics<T>, A: CxxProxy>(ptr: *mut RawVec, alloc: &A) {
+    let mut layout = Layout::new_in(alloc.rebind());
+    layout.val = unsafe { ptr.read() };
+
+    layout.with_proxy_mut(|val, alloc| unsafe {
+        CSTL_vector_destroy(val, <T as BaseType>::TYPE, &S::DROP, alloc);
+    });
+
+    unsafe { ptr.write(new_val()) }
+}
+
 const fn new_val() -> RawVec {
     RawVec {
         first: ptr::null_mut(),
@@ -762,10 +1845,52 @@ const fn new_val() -> RawVec {
     }
 }
 
+/// Moves `count` elements from `src` to `dest` within the same storage,
+/// leaving the vacated slots uninitialized.
+///
+/// Elements are moved one at a time with the move table of `S`, starting from
+/// the end nearest to `dest` so each one lands in a vacated or free slot, and
+/// their moved-from sources are dropped right after. Pending panics of the drops
+/// are left for the caller to resume once the vector is consistent again.
+///
+/// # Safety
+///
+/// Both ranges must lie in the storage of one vector, `src` must hold `count`
+/// initialized elements and the slots of `dest` outside of it must be uninitialized.
+unsafe fn shift_elements<T, S: Semantics<T>>(src: *mut T, dest: *mut T, count: usize) {
+    if count == 0 || src == dest {
+        return;
+    }
+
+    if S::RELOCATE_BITWISE {
+        return ptr::copy(src, dest, count);
+    }
+
+    let shift = |i: usize| {
+        let (from, to) = (src.add(i), dest.add(i));
+
+        if let Some(move_) = S::MOVE.move_ {
+            move_(from.cast(), from.add(1).cast(), to.cast());
+        }
+
+        if let Some(drop) = S::MOVE.drop_type.drop {
+            drop(from.cast(), from.add(1).cast());
+        }
+    };
+
+    if dest > src {
+        (0..count).rev().for_each(shift);
+    } else {
+        (0..count).for_each(shift);
+    }
+}
+
 impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     type Value = RawVec;
     type Alloc = A;
 
+    const VALUE_OFFSET: usize = mem::offset_of!(Self, val);
+
     fn value_as_ref(&self) -> &Self::Value {
         &self.val
     }
@@ -785,3 +1910,288 @@ impl<A: CxxProxy> WithCxxProxy for Layout<A> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        mem,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use cstl_sys::CSTL_MoveType;
+
+    use super::{CxxCustomVec, CxxTrivialVec, CxxVec};
+    use crate::{
+        alloc::{CxxProxy, TryReserveError},
+        semantics::{self, CustomSemantics, CxxMove, UnwindPolicy},
+        testing::MockAlloc,
+    };
+
+    thread_local! {
+        static LIVE: Cell<usize> = const { Cell::new(0) };
+        static CLONES_LEFT: Cell<usize> = const { Cell::new(usize::MAX) };
+        static MOVES: Cell<usize> = const { Cell::new(0) };
+        static ALIGNED_BLOCKS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Element counting its live instances, whose clone panics once
+    /// [`panic_on_clone`] clones were made and whose drop panics if `panics`.
+    #[derive(Debug)]
+    struct Counted {
+        panics: bool,
+    }
+
+    impl Counted {
+        fn new() -> Self {
+            LIVE.set(LIVE.get() + 1);
+            Self { panics: false }
+        }
+    }
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            match CLONES_LEFT.get() {
+                0 => panic!("clone panicked"),
+                left => CLONES_LEFT.set(left - 1),
+            }
+
+            Self::new()
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            LIVE.set(LIVE.get() - 1);
+
+            if self.panics {
+                panic!("drop panicked");
+            }
+        }
+    }
+
+    /// Element counting its moves through its move table.
+    #[derive(Debug, PartialEq)]
+    struct Moved(Box<u32>);
+
+    impl CxxMove for Moved {
+        fn cxx_move(&mut self) -> Self {
+            MOVES.set(MOVES.get() + 1);
+            Moved(mem::replace(&mut self.0, Box::new(0)))
+        }
+    }
+
+    unsafe impl CustomSemantics for Moved {
+        const MOVE: CSTL_MoveType = <Self as CxxMove>::MOVE;
+    }
+
+    /// Allocator counting its live over-aligned blocks.
+    struct CountingAligned;
+
+    impl CxxProxy for CountingAligned {
+        fn proxy<'a>(&self) -> impl GlobalAlloc + 'a
+        where
+            Self: 'a,
+        {
+            System
+        }
+
+        unsafe fn alloc_aligned(proxy: &impl GlobalAlloc, layout: Layout) -> *mut u8 {
+            ALIGNED_BLOCKS.set(ALIGNED_BLOCKS.get() + 1);
+            unsafe { proxy.alloc(layout) }
+        }
+
+        unsafe fn dealloc_aligned(proxy: &impl GlobalAlloc, ptr: *mut u8, layout: Layout) {
+            ALIGNED_BLOCKS.set(ALIGNED_BLOCKS.get() - 1);
+            unsafe { proxy.dealloc(ptr, layout) }
+        }
+    }
+
+    #[repr(align(64))]
+    struct Simd(u8);
+
+    /// Makes the clone after the next `clones` ones panic.
+    fn panic_on_clone(clones: usize) {
+        CLONES_LEFT.set(clones);
+    }
+
+    fn counted(len: usize) -> CxxVec<Counted> {
+        let mut vec = CxxVec::new();
+        vec.extend((0..len).map(|_| Counted::new()));
+        vec
+    }
+
+    fn assert_panics(f: impl FnOnce()) {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        assert!(result.is_err(), "expected a panic");
+        panic_on_clone(usize::MAX);
+    }
+
+    #[test]
+    fn extend_from_slice_keeps_clones_made_before_panic() {
+        let mut vec = counted(2);
+        let source = counted(4);
+
+        panic_on_clone(3);
+        assert_panics(|| vec.extend_from_slice(&source));
+
+        assert_eq!(vec.len(), 5);
+        assert_eq!(LIVE.get(), 9);
+
+        drop((vec, source));
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[test]
+    fn resize_keeps_clones_made_before_panic() {
+        let mut vec = counted(1);
+
+        panic_on_clone(2);
+        assert_panics(|| vec.resize(8, Counted::new()));
+
+        assert_eq!(vec.len(), 3);
+        assert_eq!(LIVE.get(), 3);
+
+        drop(vec);
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[test]
+    fn clone_drops_partial_clone_on_panic() {
+        let vec = counted(4);
+
+        panic_on_clone(2);
+        assert_panics(|| drop(vec.clone()));
+
+        assert_eq!(vec.len(), 4);
+        assert_eq!(LIVE.get(), 4);
+
+        let mut target = counted(3);
+
+        panic_on_clone(1);
+        assert_panics(|| target.clone_from(&vec));
+
+        assert_eq!(target.len(), 1);
+        assert_eq!(LIVE.get(), 5);
+
+        drop((vec, target));
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[test]
+    fn panicking_drop_drops_remaining_elements() {
+        semantics::set_unwind_policy(UnwindPolicy::Rethrow);
+
+        let mut vec = counted(5);
+        vec[2].panics = true;
+
+        assert_panics(|| vec.truncate(1));
+
+        assert_eq!(vec.len(), 1);
+        assert_eq!(LIVE.get(), 1);
+
+        vec[0].panics = true;
+        assert_panics(|| drop(vec));
+
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[test]
+    fn custom_semantics_shift_with_move_table() {
+        let mut vec = CxxCustomVec::new();
+        vec.reserve(8);
+        vec.extend((1..6).map(|i| Moved(Box::new(i))));
+        MOVES.set(0);
+
+        vec.insert(1, Moved(Box::new(9)));
+        assert_eq!(MOVES.replace(0), 4);
+
+        assert_eq!(vec.remove(0), Moved(Box::new(1)));
+        assert_eq!(MOVES.replace(0), 5);
+
+        let extracted: Vec<_> = vec.extract_if(|m| *m.0 % 2 == 0).collect();
+        assert_eq!(extracted, [Moved(Box::new(2)), Moved(Box::new(4))]);
+        assert_eq!(MOVES.replace(0), 2);
+
+        let mut extract = vec.extract_if(|m| *m.0 == 9);
+        assert_eq!(extract.next(), Some(Moved(Box::new(9))));
+        drop(extract);
+        assert_eq!(MOVES.replace(0), 2);
+
+        assert_eq!(vec.as_slice(), [Moved(Box::new(3)), Moved(Box::new(5))]);
+    }
+
+    #[test]
+    fn over_aligned_storage_uses_alloc_aligned() {
+        let mut vec = CxxVec::new_in(CountingAligned);
+        vec.extend((0..10).map(Simd));
+
+        assert_eq!(ALIGNED_BLOCKS.get(), 1);
+        assert_eq!(vec.as_ptr().align_offset(64), 0);
+        assert_eq!(vec[9].0, 9);
+
+        vec.shrink_to_fit();
+        assert_eq!(ALIGNED_BLOCKS.get(), 1);
+
+        drop(vec);
+        assert_eq!(ALIGNED_BLOCKS.get(), 0);
+
+        let mut bytes = CxxVec::new_in(CountingAligned);
+        bytes.extend(0..10u8);
+        assert_eq!(ALIGNED_BLOCKS.get(), 0);
+    }
+
+    #[test]
+    fn try_assign_keeps_elements_on_failure() {
+        let alloc = MockAlloc::new();
+        let mut vec = CxxVec::new_in(alloc.clone());
+        vec.extend([1, 2, 3]);
+
+        alloc.fail_after(0);
+        assert!(vec.try_assign([4; 64]).is_err());
+        assert_eq!(vec.as_slice(), [1, 2, 3]);
+
+        alloc.fail_never();
+        assert!(vec.try_assign([4; 64]).is_ok());
+        assert_eq!(vec.as_slice(), [4; 64]);
+
+        drop(vec);
+        alloc.assert_clean();
+    }
+
+    #[test]
+    fn try_from_vec_in_gives_back_the_source_on_failure() {
+        let source = MockAlloc::new();
+        let target = MockAlloc::new();
+        let mut vec = CxxVec::new_in(source.clone());
+        vec.extend(["a", "b", "c"].map(String::from));
+
+        target.fail_after(0);
+        let (vec, err) = CxxVec::try_from_vec_in(vec, target.clone()).unwrap_err();
+        assert!(matches!(err, TryReserveError::AllocError { .. }));
+        assert_eq!(vec.as_slice(), ["a", "b", "c"]);
+
+        target.fail_never();
+        let moved: CxxVec<_, _> = vec.try_into_vec_in(target.clone()).unwrap();
+        assert_eq!(moved.as_slice(), ["a", "b", "c"]);
+        source.assert_clean();
+
+        drop(moved);
+        target.assert_clean();
+    }
+
+    #[test]
+    fn trivial_vectors_copy_in_bulk() {
+        let mut vec = CxxTrivialVec::from_slice_in(&[1u32, 2, 3], System);
+        vec.extend_from_slice(&[4, 5]);
+        vec.resize(7, 6);
+        vec.insert(0, 0);
+
+        let clone = vec.clone();
+        assert_eq!(clone.as_slice(), [0, 1, 2, 3, 4, 5, 6, 6]);
+
+        vec.assign([9; 3]);
+        assert_eq!(vec.as_slice(), [9; 3]);
+    }
+}
@@ -0,0 +1,86 @@
+use std::marker::PhantomData;
+
+use cstl_sys::CSTL_VectorVal as RawVec;
+
+use crate::{
+    alloc::{AllocMember, CxxProxy, WithCxxProxy},
+    semantics::{Custom, Semantics, Trivial},
+};
+
+use super::{new_val, CxxVecLayout, SysAlloc};
+
+pub type CxxVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>>;
+
+/// Vector using the [`CustomSemantics`](crate::semantics::CustomSemantics) tables of its elements.
+pub type CxxCustomVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Custom>;
+
+/// Vector of trivially copyable elements, moved and copied in whole ranges
+/// with the [`TrivialType`](crate::semantics::TrivialType) tables.
+pub type CxxTrivialVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Trivial>;
+
+#[repr(C)]
+pub struct Layout<A: CxxProxy> {
+    val: RawVec,
+    alloc: AllocMember<A>,
+}
+
+impl<A: CxxProxy> Layout<A> {
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            alloc: AllocMember::new(alloc),
+            val: new_val(),
+        }
+    }
+}
+
+impl<T, S: Semantics<T>> CxxVecLayout<T, SysAlloc, Layout<SysAlloc>, S> {
+    pub const fn new() -> Self {
+        Self {
+            inner: Layout::new_in(SysAlloc),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A: CxxProxy, S: Semantics<T>> CxxVecLayout<T, A, Layout<A>, S> {
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            inner: Layout::new_in(alloc),
+            _marker: PhantomData,
+        }
+    }
+
+    pub const fn allocator(&self) -> &A {
+        self.inner.alloc.get()
+    }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxVecLayout<T, B, Layout<B>, S> {
+        self.into_vec_in(alloc)
+    }
+}
+
+impl<A: CxxProxy> WithCxxProxy for Layout<A> {
+    type Value = RawVec;
+    type Alloc = A;
+
+    const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
+    fn value_as_ref(&self) -> &Self::Value {
+        &self.val
+    }
+
+    fn value_as_mut(&mut self) -> &mut Self::Value {
+        &mut self.val
+    }
+
+    fn alloc_as_ref(&self) -> &Self::Alloc {
+        self.alloc.get()
+    }
+
+    fn new_in(alloc: Self::Alloc) -> Self {
+        Self {
+            alloc: AllocMember::new(alloc),
+            val: new_val(),
+        }
+    }
+}
@@ -2,12 +2,22 @@ use std::marker::PhantomData;
 
 use cstl_sys::CSTL_VectorVal as RawVec;
 
-use crate::alloc::{CxxProxy, WithCxxProxy};
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::{Custom, Semantics, Trivial},
+};
 
 use super::{new_val, CxxVecLayout, SysAlloc};
 
 pub type CxxVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>>;
 
+/// Vector using the [`CustomSemantics`](crate::semantics::CustomSemantics) tables of its elements.
+pub type CxxCustomVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Custom>;
+
+/// Vector of trivially copyable elements, moved and copied in whole ranges
+/// with the [`TrivialType`](crate::semantics::TrivialType) tables.
+pub type CxxTrivialVec<T, A = SysAlloc> = CxxVecLayout<T, A, Layout<A>, Trivial>;
+
 #[repr(C)]
 pub struct Layout<A: CxxProxy> {
     val: RawVec,
@@ -23,7 +33,7 @@ impl<A: CxxProxy> Layout<A> {
     }
 }
 
-impl<T> CxxVec<T, SysAlloc> {
+impl<T, S: Semantics<T>> CxxVecLayout<T, SysAlloc, Layout<SysAlloc>, S> {
     pub const fn new() -> Self {
         Self {
             inner: Layout::new_in(SysAlloc),
@@ -32,7 +42,7 @@ impl<T> CxxVec<T, SysAlloc> {
     }
 }
 
-impl<T, A: CxxProxy> CxxVec<T, A> {
+impl<T, A: CxxProxy, S: Semantics<T>> CxxVecLayout<T, A, Layout<A>, S> {
     pub const fn new_in(alloc: A) -> Self {
         Self {
             inner: Layout::new_in(alloc),
@@ -43,12 +53,18 @@ impl<T, A: CxxProxy> CxxVec<T, A> {
     pub const fn allocator(&self) -> &A {
         &self.inner.alloc
     }
+
+    pub fn replace_allocator<B: CxxProxy>(self, alloc: B) -> CxxVecLayout<T, B, Layout<B>, S> {
+        self.into_vec_in(alloc)
+    }
 }
 
 impl<A: CxxProxy> WithCxxProxy for Layout<A> {
     type Value = RawVec;
     type Alloc = A;
 
+    const VALUE_OFFSET: usize = std::mem::offset_of!(Self, val);
+
     fn value_as_ref(&self) -> &Self::Value {
         &self.val
     }
@@ -0,0 +1,83 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use super::{CxxVec, CxxVecLayout, RawVec};
+use crate::{
+    alloc::{CxxProxy, WithCxxProxy},
+    semantics::Semantics,
+};
+
+/// Borrowed elements of a vector, which [`ToOwned`] clones into a [`CxxVec`],
+/// e.g. for a `Cow<'_, CxxSlice<T>>`.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CxxSlice<T>([T]);
+
+impl<T> CxxSlice<T> {
+    pub fn from_slice(slice: &[T]) -> &Self {
+        // `CxxSlice` is transparent:
+        unsafe { &*(slice as *const [T] as *const Self) }
+    }
+
+    pub fn from_mut_slice(slice: &mut [T]) -> &mut Self {
+        unsafe { &mut *(slice as *mut [T] as *mut Self) }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.0
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<T> Deref for CxxSlice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CxxSlice<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<[T]> for CxxSlice<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CxxSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl<T: Clone> ToOwned for CxxSlice<T> {
+    type Owned = CxxVec<T>;
+
+    fn to_owned(&self) -> Self::Owned {
+        let mut vec = CxxVec::new();
+        vec.extend(self.0.iter().cloned());
+        vec
+    }
+}
+
+impl<T, A, L, S> Borrow<CxxSlice<T>> for CxxVecLayout<T, A, L, S>
+where
+    A: CxxProxy,
+    L: WithCxxProxy<Alloc = A, Value = RawVec>,
+    S: Semantics<T>,
+{
+    fn borrow(&self) -> &CxxSlice<T> {
+        CxxSlice::from_slice(self)
+    }
+}
@@ -0,0 +1,605 @@
+//! Derive macros for [`cxx-stl`](https://crates.io/crates/cxx-stl).
+//!
+//! Use them through the re-exports in `cxx_stl::semantics` with the `derive` feature.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Derives the C++ move semantics of a type.
+///
+/// C++ moves leave a valid "moved-from" value behind. This derive implements
+/// `CxxMove` by leaving that value in place of the moved value, and `CustomSemantics`
+/// with the resulting move table, so containers with `Custom` semantics such as
+/// `CxxCustomVec<T>` move their elements with it. With `#[cxx(clone)]` on the type,
+/// `CustomCopySemantics` is implemented as well, copying elements with [`Clone`].
+///
+/// Fields are moved-from as their own [`Default`] value, unless overridden
+/// with `#[cxx(moved_from = <expr>)]`, which lifts the [`Default`] requirement
+/// for that field. Enums must mark a unit variant with `#[cxx(moved_from)]`.
+///
+/// ```ignore
+/// #[derive(Clone, CxxSemantics)]
+/// #[cxx(clone)]
+/// struct Handle {
+///     #[cxx(moved_from = RawHandle::INVALID)]
+///     raw: RawHandle,
+///     name: String,
+/// }
+///
+/// #[derive(CxxSemantics)]
+/// enum State {
+///     #[cxx(moved_from)]
+///     Empty,
+///     Loaded(Box<[u8]>),
+/// }
+/// ```
+///
+/// Types that are [`Default`] already implement `CxxMove` by leaving their default
+/// value behind, so they can't use this derive, and implement `CustomSemantics`
+/// with `<Self as CxxMove>::MOVE` instead.
+///
+/// Unions, enums without a moved-from variant and fields that are neither
+/// [`Default`] nor overridden are rejected at compile time.
+#[proc_macro_derive(CxxSemantics, attributes(cxx))]
+pub fn derive_cxx_semantics(input: TokenStream) -> TokenStream {
+    expand(input).unwrap_or_else(Error::into_compile_error)
+}
+
+struct Error {
+    span: Span,
+    message: String,
+}
+
+impl Error {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    fn into_compile_error(self) -> TokenStream {
+        let message = TokenTree::Literal(Literal::string(&self.message));
+        let group = Group::new(Delimiter::Brace, message.into());
+
+        let mut tokens: Vec<TokenTree> = vec![
+            Punct::new(':', Spacing::Joint).into(),
+            Punct::new(':', Spacing::Alone).into(),
+            Ident::new("core", self.span).into(),
+            Punct::new(':', Spacing::Joint).into(),
+            Punct::new(':', Spacing::Alone).into(),
+            Ident::new("compile_error", self.span).into(),
+            Punct::new('!', Spacing::Alone).into(),
+            group.into(),
+        ];
+
+        for token in &mut tokens {
+            token.set_span(self.span);
+        }
+
+        tokens.into_iter().collect()
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A field, named or not, and its moved-from value if overridden.
+struct Field {
+    name: Option<Ident>,
+    ty: Vec<TokenTree>,
+    moved_from: Option<Vec<TokenTree>>,
+}
+
+/// An enum variant.
+struct Variant {
+    name: Ident,
+    has_fields: bool,
+    moved_from: bool,
+}
+
+enum Data {
+    Named(Vec<Field>),
+    Tuple(Vec<Field>),
+    Unit,
+    Enum(Vec<Variant>),
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut rest = &tokens[..];
+
+    let mut clone = false;
+
+    for args in parse_attributes(&mut rest)? {
+        match &args[..] {
+            [TokenTree::Ident(key)] if key.to_string() == "clone" => clone = true,
+            [token, ..] => {
+                return Err(Error::new(
+                    token.span(),
+                    "expected `#[cxx(clone)]` on a struct or an enum",
+                ))
+            }
+            [] => return Err(Error::new(Span::call_site(), "expected `clone`")),
+        }
+    }
+
+    skip_visibility(&mut rest);
+
+    let keyword = match rest.first() {
+        Some(TokenTree::Ident(ident)) => ident.clone(),
+        _ => return Err(Error::new(Span::call_site(), "expected an item")),
+    };
+    rest = &rest[1..];
+
+    let name = match rest.first() {
+        Some(TokenTree::Ident(ident)) => ident.clone(),
+        _ => return Err(Error::new(keyword.span(), "expected an identifier")),
+    };
+    rest = &rest[1..];
+
+    let generics = parse_generics(&mut rest);
+    let mut where_clause = Vec::new();
+
+    let data = match keyword.to_string().as_str() {
+        "struct" => {
+            take_where_clause(&mut rest, &mut where_clause);
+
+            match rest.first() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                    Data::Named(parse_fields(group.stream(), true)?)
+                }
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    rest = &rest[1..];
+                    take_where_clause(&mut rest, &mut where_clause);
+                    Data::Tuple(parse_fields(group.stream(), false)?)
+                }
+                _ => Data::Unit,
+            }
+        }
+        "enum" => {
+            take_where_clause(&mut rest, &mut where_clause);
+
+            match rest.first() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                    Data::Enum(parse_variants(group.stream())?)
+                }
+                _ => return Err(Error::new(name.span(), "expected enum variants")),
+            }
+        }
+        "union" => {
+            return Err(Error::new(
+                keyword.span(),
+                "unions can't be moved with C++ semantics, as the active field is unknown",
+            ))
+        }
+        _ => return Err(Error::new(keyword.span(), "expected a struct or an enum")),
+    };
+
+    let body = match &data {
+        Data::Named(fields) => {
+            let mut inits = Vec::new();
+
+            for field in fields {
+                inits.push(TokenTree::Ident(field.name.clone().unwrap()));
+                inits.push(Punct::new(':', Spacing::Alone).into());
+                inits.extend(field_init(field));
+                inits.push(Punct::new(',', Spacing::Alone).into());
+            }
+
+            let mut body = parse("Self");
+            body.push(Group::new(Delimiter::Brace, inits.into_iter().collect()).into());
+            body
+        }
+        Data::Tuple(fields) => {
+            let mut inits = Vec::new();
+
+            for field in fields {
+                inits.extend(field_init(field));
+                inits.push(Punct::new(',', Spacing::Alone).into());
+            }
+
+            let mut body = parse("Self");
+            body.push(Group::new(Delimiter::Parenthesis, inits.into_iter().collect()).into());
+            body
+        }
+        Data::Unit => parse("Self"),
+        Data::Enum(variants) => {
+            let mut moved_from = variants.iter().filter(|v| v.moved_from);
+
+            let variant = match (moved_from.next(), moved_from.next()) {
+                (Some(variant), None) => variant,
+                (None, _) => {
+                    return Err(Error::new(
+                        name.span(),
+                        "enums must mark the variant representing the moved-from state \
+                         with `#[cxx(moved_from)]`",
+                    ))
+                }
+                (Some(_), Some(second)) => {
+                    return Err(Error::new(
+                        second.name.span(),
+                        "only one variant can be marked with `#[cxx(moved_from)]`",
+                    ))
+                }
+            };
+
+            if variant.has_fields {
+                return Err(Error::new(
+                    variant.name.span(),
+                    "the moved-from variant must not have fields",
+                ));
+            }
+
+            let mut body = parse("Self::");
+            body.push(TokenTree::Ident(variant.name.clone()));
+            body
+        }
+    };
+
+    let fields: &[Field] = match &data {
+        Data::Named(fields) | Data::Tuple(fields) => fields,
+        _ => &[],
+    };
+
+    // Bounds on generic field types, which must be `Default` unless overridden:
+    if !generics.is_empty() {
+        if !where_clause.is_empty() && !ends_with_comma(&where_clause) {
+            where_clause.push(Punct::new(',', Spacing::Alone).into());
+        }
+
+        for field in fields.iter().filter(|f| f.moved_from.is_none()) {
+            where_clause.extend(field.ty.iter().cloned());
+            where_clause.push(Punct::new(':', Spacing::Alone).into());
+            where_clause.extend(parse("::core::default::Default,"));
+        }
+    }
+
+    let (impl_params, type_args) = split_generics(&generics);
+
+    let header = |unsafety: &str, trait_path: &str, extra_bounds: &str| {
+        let mut header = parse(&format!("#[automatically_derived] {unsafety} impl"));
+        header.extend(angle_bracketed(impl_params.clone()));
+        header.extend(parse(trait_path));
+        header.extend(parse("for"));
+        header.push(TokenTree::Ident(name.clone()));
+        header.extend(angle_bracketed(type_args.clone()));
+
+        if !where_clause.is_empty() || !extra_bounds.is_empty() {
+            header.extend(parse("where"));
+            header.extend(where_clause.iter().cloned());
+
+            if !where_clause.is_empty() && !ends_with_comma(&where_clause) {
+                header.push(Punct::new(',', Spacing::Alone).into());
+            }
+
+            header.extend(parse(extra_bounds));
+        }
+
+        header
+    };
+
+    let mut replace = parse("::core::mem::replace");
+    let mut args = parse("self,");
+    args.extend(body);
+    replace.push(Group::new(Delimiter::Parenthesis, args.into_iter().collect()).into());
+
+    let mut function = parse("#[inline] fn cxx_move(&mut self) -> Self");
+    function.push(Group::new(Delimiter::Brace, replace.into_iter().collect()).into());
+
+    let mut output = header("", "::cxx_stl::semantics::CxxMove", "");
+    output.push(Group::new(Delimiter::Brace, function.into_iter().collect()).into());
+
+    output.extend(header(
+        "unsafe",
+        "::cxx_stl::semantics::CustomSemantics",
+        "",
+    ));
+    output.push(TokenTree::Group(Group::new(
+        Delimiter::Brace,
+        "const MOVE: ::cxx_stl::semantics::CSTL_MoveType = \
+         <Self as ::cxx_stl::semantics::CxxMove>::MOVE;"
+            .parse()
+            .unwrap(),
+    )));
+
+    if clone {
+        output.extend(header(
+            "unsafe",
+            "::cxx_stl::semantics::CustomCopySemantics",
+            "Self: ::core::clone::Clone",
+        ));
+        output.push(TokenTree::Group(Group::new(
+            Delimiter::Brace,
+            "const COPY: ::cxx_stl::semantics::CSTL_CopyType = \
+             ::cxx_stl::semantics::CSTL_CopyType {
+                 move_type: <Self as ::cxx_stl::semantics::CxxMove>::MOVE,
+                 ..<Self as ::cxx_stl::semantics::CopyOnlyType>::COPY
+             };"
+            .parse()
+            .unwrap(),
+        )));
+    }
+
+    Ok(output.into_iter().collect())
+}
+
+/// `<T as ::core::default::Default>::default()`, or the overridden moved-from value.
+fn field_init(field: &Field) -> Vec<TokenTree> {
+    if let Some(moved_from) = &field.moved_from {
+        let group = Group::new(Delimiter::Parenthesis, moved_from.iter().cloned().collect());
+        return vec![group.into()];
+    }
+
+    let mut init: Vec<TokenTree> = vec![Punct::new('<', Spacing::Alone).into()];
+    init.extend(field.ty.iter().cloned());
+    init.extend(parse("as ::core::default::Default>::default()"));
+    init
+}
+
+fn parse(source: &str) -> Vec<TokenTree> {
+    source.parse::<TokenStream>().unwrap().into_iter().collect()
+}
+
+fn angle_bracketed(params: Vec<Vec<TokenTree>>) -> Vec<TokenTree> {
+    if params.is_empty() {
+        return Vec::new();
+    }
+
+    let mut tokens: Vec<TokenTree> = vec![Punct::new('<', Spacing::Alone).into()];
+
+    for param in params {
+        tokens.extend(param);
+        tokens.push(Punct::new(',', Spacing::Alone).into());
+    }
+
+    tokens.push(Punct::new('>', Spacing::Alone).into());
+    tokens
+}
+
+fn is_punct(token: &TokenTree, ch: char) -> bool {
+    matches!(token, TokenTree::Punct(punct) if punct.as_char() == ch)
+}
+
+fn ends_with_comma(tokens: &[TokenTree]) -> bool {
+    tokens.last().is_some_and(|t| is_punct(t, ','))
+}
+
+/// Splits `tokens` at commas outside of angle brackets.
+fn split_top_level(tokens: &[TokenTree], separator: char) -> Vec<&[TokenTree]> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '<' => depth += 1,
+            // Not a closing bracket if part of `->`:
+            TokenTree::Punct(punct)
+                if punct.as_char() == '>' && !(i > 0 && is_punct(&tokens[i - 1], '-')) =>
+            {
+                depth = depth.saturating_sub(1)
+            }
+            TokenTree::Punct(punct) if punct.as_char() == separator && depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if start < tokens.len() {
+        parts.push(&tokens[start..]);
+    }
+
+    parts
+}
+
+/// Skips outer attributes, returning the arguments of `#[cxx(...)]` attributes.
+fn parse_attributes(rest: &mut &[TokenTree]) -> Result<Vec<Vec<TokenTree>>> {
+    let mut cxx_args = Vec::new();
+
+    while let [TokenTree::Punct(pound), TokenTree::Group(group), tail @ ..] = *rest {
+        if pound.as_char() != '#' || group.delimiter() != Delimiter::Bracket {
+            break;
+        }
+
+        let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+
+        if let [TokenTree::Ident(ident), args @ ..] = &inner[..] {
+            if ident.to_string() == "cxx" {
+                match args {
+                    [TokenTree::Group(args)] if args.delimiter() == Delimiter::Parenthesis => {
+                        cxx_args.push(args.stream().into_iter().collect());
+                    }
+                    _ => return Err(Error::new(ident.span(), "expected `#[cxx(...)]`")),
+                }
+            }
+        }
+
+        *rest = tail;
+    }
+
+    Ok(cxx_args)
+}
+
+fn skip_visibility(rest: &mut &[TokenTree]) {
+    if let [TokenTree::Ident(ident), tail @ ..] = *rest {
+        if ident.to_string() == "pub" {
+            *rest = match tail {
+                [TokenTree::Group(group), tail @ ..]
+                    if group.delimiter() == Delimiter::Parenthesis =>
+                {
+                    tail
+                }
+                _ => tail,
+            };
+        }
+    }
+}
+
+/// Takes the tokens between the angle brackets following the item name.
+fn parse_generics(rest: &mut &[TokenTree]) -> Vec<TokenTree> {
+    if !rest.first().is_some_and(|t| is_punct(t, '<')) {
+        return Vec::new();
+    }
+
+    let mut depth = 0usize;
+
+    for (i, token) in rest.iter().enumerate() {
+        if is_punct(token, '<') {
+            depth += 1;
+        } else if is_punct(token, '>') && !is_punct(&rest[i - 1], '-') {
+            depth -= 1;
+
+            if depth == 0 {
+                let generics = rest[1..i].to_vec();
+                *rest = &rest[i + 1..];
+                return generics;
+            }
+        }
+    }
+
+    Vec::new()
+}
+
+/// Takes a `where` clause up to the item body or the terminating `;`.
+fn take_where_clause(rest: &mut &[TokenTree], where_clause: &mut Vec<TokenTree>) {
+    match rest.first() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "where" => {}
+        _ => return,
+    }
+
+    let end = rest
+        .iter()
+        .position(|token| match token {
+            TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+            token => is_punct(token, ';'),
+        })
+        .unwrap_or(rest.len());
+
+    where_clause.extend(rest[1..end].iter().cloned());
+    *rest = &rest[end..];
+}
+
+/// Splits generic parameters into those of the `impl` and the arguments of the type.
+fn split_generics(generics: &[TokenTree]) -> (Vec<Vec<TokenTree>>, Vec<Vec<TokenTree>>) {
+    let mut impl_params = Vec::new();
+    let mut type_args = Vec::new();
+
+    for param in split_top_level(generics, ',') {
+        let param = split_top_level(param, '=')
+            .first()
+            .copied()
+            .unwrap_or(param);
+
+        let arg = match param {
+            [TokenTree::Punct(quote), lifetime @ TokenTree::Ident(_), ..]
+                if quote.as_char() == '\'' =>
+            {
+                vec![TokenTree::Punct(quote.clone()), lifetime.clone()]
+            }
+            [TokenTree::Ident(konst), name @ TokenTree::Ident(_), ..]
+                if konst.to_string() == "const" =>
+            {
+                vec![name.clone()]
+            }
+            [name @ TokenTree::Ident(_), ..] => vec![name.clone()],
+            _ => continue,
+        };
+
+        impl_params.push(param.to_vec());
+        type_args.push(arg);
+    }
+
+    (impl_params, type_args)
+}
+
+fn parse_fields(stream: TokenStream, named: bool) -> Result<Vec<Field>> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    let mut fields = Vec::new();
+
+    for mut rest in split_top_level(&tokens, ',') {
+        let cxx_args = parse_attributes(&mut rest)?;
+        skip_visibility(&mut rest);
+
+        let name = if named {
+            let [TokenTree::Ident(name), TokenTree::Punct(colon), tail @ ..] = rest else {
+                return Err(Error::new(Span::call_site(), "expected a named field"));
+            };
+
+            if colon.as_char() != ':' {
+                return Err(Error::new(colon.span(), "expected `:`"));
+            }
+
+            rest = tail;
+            Some(name.clone())
+        } else {
+            None
+        };
+
+        let mut moved_from = None;
+
+        for args in cxx_args {
+            match &args[..] {
+                [TokenTree::Ident(key), TokenTree::Punct(eq), value @ ..]
+                    if key.to_string() == "moved_from"
+                        && eq.as_char() == '='
+                        && !value.is_empty() =>
+                {
+                    moved_from = Some(value.to_vec());
+                }
+                [token, ..] => {
+                    return Err(Error::new(
+                        token.span(),
+                        "expected `#[cxx(moved_from = <expr>)]` on a field",
+                    ))
+                }
+                [] => return Err(Error::new(Span::call_site(), "expected `moved_from`")),
+            }
+        }
+
+        fields.push(Field {
+            name,
+            ty: rest.to_vec(),
+            moved_from,
+        });
+    }
+
+    Ok(fields)
+}
+
+fn parse_variants(stream: TokenStream) -> Result<Vec<Variant>> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    let mut variants = Vec::new();
+
+    for mut rest in split_top_level(&tokens, ',') {
+        let cxx_args = parse_attributes(&mut rest)?;
+
+        let [TokenTree::Ident(name), tail @ ..] = rest else {
+            return Err(Error::new(Span::call_site(), "expected a variant"));
+        };
+
+        let has_fields = matches!(tail.first(), Some(TokenTree::Group(_)));
+        let mut moved_from = false;
+
+        for args in cxx_args {
+            match &args[..] {
+                [TokenTree::Ident(key)] if key.to_string() == "moved_from" => moved_from = true,
+                [token, ..] => {
+                    return Err(Error::new(
+                        token.span(),
+                        "expected `#[cxx(moved_from)]` on a variant",
+                    ))
+                }
+                [] => return Err(Error::new(name.span(), "expected `moved_from`")),
+            }
+        }
+
+        variants.push(Variant {
+            name: name.clone(),
+            has_fields,
+            moved_from,
+        });
+    }
+
+    Ok(variants)
+}
@@ -0,0 +1,123 @@
+use std::cell::Cell;
+
+use cxx_stl::{
+    semantics::{CustomCopySemantics, CustomSemantics, CxxMove},
+    vec::CxxCustomVec,
+};
+use stl_rs_derive::CxxSemantics;
+
+thread_local! {
+    static MOVES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Raw handle value, which isn't `Default`, counting the moved-from values made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Raw(u32);
+
+impl Raw {
+    const INVALID: Raw = Raw(u32::MAX);
+
+    fn moved_from() -> Self {
+        MOVES.set(MOVES.get() + 1);
+        Self::INVALID
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CxxSemantics)]
+#[cxx(clone)]
+struct Handle {
+    #[cxx(moved_from = Raw::moved_from())]
+    raw: Raw,
+    name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CxxSemantics)]
+struct Wrapper<T>(#[cxx(moved_from = None)] Option<T>, Vec<T>);
+
+#[derive(Clone, Debug, PartialEq, Eq, CxxSemantics)]
+#[cxx(clone)]
+enum State {
+    Loaded(Box<[u8]>),
+    #[cxx(moved_from)]
+    Empty,
+}
+
+fn assert_custom_semantics<T: CustomSemantics>() {}
+
+fn assert_custom_copy_semantics<T: CustomCopySemantics>() {}
+
+fn handle(raw: u32) -> Handle {
+    Handle {
+        raw: Raw(raw),
+        name: raw.to_string(),
+    }
+}
+
+#[test]
+fn struct_moved_from_state() {
+    assert_custom_copy_semantics::<Handle>();
+
+    let mut handle = handle(7);
+    let moved = handle.cxx_move();
+
+    assert_eq!(moved.raw, Raw(7));
+    assert_eq!(moved.name, "7");
+    assert_eq!(
+        handle,
+        Handle {
+            raw: Raw::INVALID,
+            name: String::new(),
+        }
+    );
+}
+
+#[test]
+fn generic_tuple_struct_moved_from_state() {
+    assert_custom_semantics::<Wrapper<String>>();
+
+    let mut wrapper = Wrapper(Some(1u8), vec![2, 3]);
+    let moved = wrapper.cxx_move();
+
+    assert_eq!(moved, Wrapper(Some(1), vec![2, 3]));
+    assert_eq!(wrapper, Wrapper(None, Vec::new()));
+}
+
+#[test]
+fn enum_moved_from_state() {
+    assert_custom_copy_semantics::<State>();
+
+    let mut state = State::Loaded(Box::new([1, 2, 3]));
+    let moved = state.cxx_move();
+
+    assert_eq!(moved, State::Loaded(Box::new([1, 2, 3])));
+    assert_eq!(state, State::Empty);
+}
+
+#[test]
+fn custom_vectors_move_with_derived_semantics() {
+    let mut handles = CxxCustomVec::new();
+    handles.reserve(16);
+    handles.extend((1..16).map(handle));
+
+    MOVES.set(0);
+    handles.insert(0, handle(0));
+    assert_eq!(MOVES.get(), 15);
+
+    let handles = handles.clone();
+    assert!(handles
+        .iter()
+        .enumerate()
+        .all(|(i, handle)| handle.raw == Raw(i as u32) && handle.name == i.to_string()));
+
+    let mut states = CxxCustomVec::new();
+    states.extend([State::Empty, State::Loaded(Box::new([4]))]);
+    states.remove(0);
+
+    let states = states.clone();
+    assert_eq!(states[..], [State::Loaded(Box::new([4]))]);
+}
+
+#[test]
+fn rejected_shapes() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
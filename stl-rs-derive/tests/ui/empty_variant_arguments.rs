@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+enum State {
+    #[cxx()]
+    Empty,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+enum State {
+    #[cxx(moved_from)]
+    Empty,
+    #[cxx(moved_from)]
+    Closed,
+}
+
+fn main() {}
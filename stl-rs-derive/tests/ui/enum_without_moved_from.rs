@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+enum State {
+    Empty,
+    Loaded(Vec<u8>),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use stl_rs_derive::CxxSemantics;
+
+struct RawHandle(u32);
+
+#[derive(CxxSemantics)]
+struct Handle {
+    raw: RawHandle,
+}
+
+fn main() {}
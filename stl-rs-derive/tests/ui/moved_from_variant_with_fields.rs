@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+enum State {
+    #[cxx(moved_from)]
+    Loaded(Vec<u8>),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+struct Handle {
+    #[cxx(moved_to = 0)]
+    raw: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+#[cxx(copy)]
+struct Handle {
+    raw: u32,
+}
+
+fn main() {}
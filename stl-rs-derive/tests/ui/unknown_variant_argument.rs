@@ -0,0 +1,9 @@
+use stl_rs_derive::CxxSemantics;
+
+#[derive(CxxSemantics)]
+enum State {
+    #[cxx(moved_from = 0)]
+    Empty,
+}
+
+fn main() {}